@@ -11,6 +11,8 @@ use uuid::Uuid;
 pub struct SerialisableAttestationCa {
     pub(crate) ca: Base64UrlSafeData,
     pub(crate) aaguids: BTreeSet<Uuid>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) intermediates: Vec<Base64UrlSafeData>,
 }
 
 /// A structure representing an Attestation CA and other options associated to this CA.
@@ -29,6 +31,10 @@ pub struct AttestationCa {
     /// attested as trusted by this CA. AAGUIDS that are not in this set, but signed by
     /// this CA will NOT be trusted.
     pub aaguids: BTreeSet<Uuid>,
+    /// Intermediate certificates that chain to this CA. These are added to the chain of an
+    /// attestation when it is verified, so that authenticators that only send their leaf
+    /// certificate can still chain to this CA. These are NOT trusted themselves.
+    pub intermediates: Vec<x509::X509>,
 }
 
 #[allow(clippy::from_over_into)]
@@ -37,6 +43,11 @@ impl Into<SerialisableAttestationCa> for AttestationCa {
         SerialisableAttestationCa {
             ca: Base64UrlSafeData(self.ca.to_der().expect("Invalid DER")),
             aaguids: self.aaguids,
+            intermediates: self
+                .intermediates
+                .iter()
+                .map(|crt| Base64UrlSafeData(crt.to_der().expect("Invalid DER")))
+                .collect(),
         }
     }
 }
@@ -48,6 +59,11 @@ impl TryFrom<SerialisableAttestationCa> for AttestationCa {
         Ok(AttestationCa {
             ca: x509::X509::from_der(&data.ca.0)?,
             aaguids: data.aaguids,
+            intermediates: data
+                .intermediates
+                .iter()
+                .map(|der| x509::X509::from_der(&der.0))
+                .collect::<Result<_, _>>()?,
         })
     }
 }
@@ -59,6 +75,7 @@ impl TryFrom<&[u8]> for AttestationCa {
         Ok(AttestationCa {
             ca: x509::X509::from_pem(data)?,
             aaguids: Default::default(),
+            intermediates: Vec::new(),
         })
     }
 }
@@ -83,20 +100,90 @@ impl AttestationCa {
         self.aaguids.insert(aaguid);
     }
 
+    /// Add an intermediate certificate that chains to this Attestation CA, for authenticators
+    /// that do not send it in their attestation.
+    pub fn insert_intermediate(&mut self, intermediate: x509::X509) {
+        self.intermediates.push(intermediate);
+    }
+
     /// Create a customised attestation CA from a DER public key.
     pub fn new_from_der(data: &[u8]) -> Result<Self, OpenSSLErrorStack> {
         Ok(AttestationCa {
             ca: x509::X509::from_der(data)?,
             aaguids: BTreeSet::default(),
+            intermediates: Vec::new(),
         })
     }
 }
 
+/// The options a model of authenticator reports in its CTAP2 `authenticatorGetInfo`, such as
+/// from the FIDO Metadata Service. These describe a "factory new" device, so options that can be
+/// configured by the user (such as `clientPin`) may differ on the device that is in use.
+///
+/// <https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-20210615.html#option-id>
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthenticatorOptions {
+    /// `plat` - the authenticator is attached to the client, and can not be removed.
+    pub platform_device: bool,
+    /// `rk` - the authenticator can create discoverable (resident) credentials.
+    pub resident_key: bool,
+    /// `clientPin` - `Some(true)` if a PIN is set, `Some(false)` if a PIN is supported but not
+    /// yet set, and `None` if a PIN is not supported.
+    pub client_pin: Option<bool>,
+    /// `up` - the authenticator can test for user presence.
+    pub user_presence: bool,
+    /// `uv` - `Some(true)` if built in user verification (such as a biometric) is configured,
+    /// `Some(false)` if it is supported but not yet configured, and `None` if it is not supported.
+    pub user_verification: Option<bool>,
+    /// `alwaysUv` - `Some(true)` if user verification is required for every operation,
+    /// `Some(false)` if this is supported but disabled, and `None` if it is not supported.
+    pub always_uv: Option<bool>,
+    /// `credMgmt` - the authenticator supports credential management.
+    pub credential_management: bool,
+    /// `bioEnroll` - `Some(true)` if biometric enrollments exist, `Some(false)` if biometric
+    /// enrollment is supported but none exist, and `None` if it is not supported.
+    pub bio_enroll: Option<bool>,
+    /// `largeBlobs` - the authenticator supports the largeBlobs command.
+    pub large_blobs: bool,
+    /// `makeCredUvNotRqd` - the authenticator can create non-discoverable credentials without
+    /// user verification.
+    pub make_cred_uv_not_required: bool,
+}
+
+impl From<&BTreeMap<String, bool>> for AuthenticatorOptions {
+    /// Interpret the options map of `authenticatorGetInfo`, applying the CTAP defaults for
+    /// any options that are absent.
+    fn from(options: &BTreeMap<String, bool>) -> Self {
+        let get = |id: &str| options.get(id).copied();
+        AuthenticatorOptions {
+            platform_device: get("plat").unwrap_or(false),
+            resident_key: get("rk").unwrap_or(false),
+            client_pin: get("clientPin"),
+            user_presence: get("up").unwrap_or(true),
+            user_verification: get("uv"),
+            always_uv: get("alwaysUv"),
+            credential_management: get("credMgmt").unwrap_or(false),
+            bio_enroll: get("bioEnroll"),
+            large_blobs: get("largeBlobs").unwrap_or(false),
+            make_cred_uv_not_required: get("makeCredUvNotRqd").unwrap_or(false),
+        }
+    }
+}
+
 /// A list of AttestationCas and associated options.
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct AttestationCaList {
     /// The set of CA's that we trust in this Operation
     pub cas: BTreeMap<Base64UrlSafeData, AttestationCa>,
+    /// The set of AAGUIDS (Device Ids) that are known to have security issues, such as from the
+    /// status reports of the FIDO Metadata Service. These are still trusted by their CA, but may
+    /// be rejected at registration.
+    #[serde(default)]
+    pub vulnerable_aaguids: BTreeSet<Uuid>,
+    /// The options reported by the device models of these CAs, such as from the FIDO Metadata
+    /// Service. These are added to the attestation metadata of a credential at registration.
+    #[serde(default)]
+    pub authenticator_options: BTreeMap<Uuid, AuthenticatorOptions>,
 }
 
 impl TryFrom<AttestationCa> for AttestationCaList {
@@ -134,7 +221,11 @@ impl TryFrom<&[(&[u8], Uuid)]> for AttestationCaList {
             if !cas.contains_key(kid.as_ref()) {
                 let mut aaguids = BTreeSet::default();
                 aaguids.insert(*aaguid);
-                let att_ca = AttestationCa { ca, aaguids };
+                let att_ca = AttestationCa {
+                    ca,
+                    aaguids,
+                    intermediates: Vec::new(),
+                };
                 cas.insert(kid.to_vec().into(), att_ca);
             } else {
                 let att_ca = cas.get_mut(kid.as_ref()).expect("Can not fail!");
@@ -143,7 +234,11 @@ impl TryFrom<&[(&[u8], Uuid)]> for AttestationCaList {
             };
         }
 
-        Ok(AttestationCaList { cas })
+        Ok(AttestationCaList {
+            cas,
+            vulnerable_aaguids: BTreeSet::default(),
+            authenticator_options: BTreeMap::default(),
+        })
     }
 }
 
@@ -159,7 +254,11 @@ impl AttestationCaList {
             if !cas.contains_key(kid.as_ref()) {
                 let mut aaguids = BTreeSet::default();
                 aaguids.insert(aaguid);
-                let att_ca = AttestationCa { ca, aaguids };
+                let att_ca = AttestationCa {
+                    ca,
+                    aaguids,
+                    intermediates: Vec::new(),
+                };
                 cas.insert(kid.to_vec().into(), att_ca);
             } else {
                 let att_ca = cas.get_mut(kid.as_ref()).expect("Can not fail!");
@@ -168,7 +267,11 @@ impl AttestationCaList {
             };
         }
 
-        Ok(AttestationCaList { cas })
+        Ok(AttestationCaList {
+            cas,
+            vulnerable_aaguids: BTreeSet::default(),
+            authenticator_options: BTreeMap::default(),
+        })
     }
 }
 
@@ -187,4 +290,24 @@ impl AttestationCaList {
         let att_ca_dgst = att_ca.get_kid()?;
         Ok(self.cas.insert(att_ca_dgst.into(), att_ca))
     }
+
+    /// Mark this AAGUID as belonging to a device model with known security issues.
+    pub fn insert_vulnerable_aaguid(&mut self, aaguid: Uuid) {
+        self.vulnerable_aaguids.insert(aaguid);
+    }
+
+    /// Determine if this AAGUID belongs to a device model with known security issues.
+    pub fn is_vulnerable_aaguid(&self, aaguid: &Uuid) -> bool {
+        self.vulnerable_aaguids.contains(aaguid)
+    }
+
+    /// Set the options that the device model of this AAGUID reports.
+    pub fn insert_authenticator_options(&mut self, aaguid: Uuid, options: AuthenticatorOptions) {
+        self.authenticator_options.insert(aaguid, options);
+    }
+
+    /// Retrieve the options that the device model of this AAGUID reports, if known.
+    pub fn get_authenticator_options(&self, aaguid: &Uuid) -> Option<&AuthenticatorOptions> {
+        self.authenticator_options.get(aaguid)
+    }
 }
@@ -519,7 +519,7 @@ impl From<WebauthnError> for ResponseError {
             WebauthnError::MismatchedChallenge => Self::MismatchedChallenge,
             WebauthnError::ChallengeNotFound => Self::ChallengeNotFound,
             WebauthnError::InvalidRPOrigin => Self::InvalidRPOrigin,
-            WebauthnError::InvalidRPIDHash => Self::InvalidRPIDHash,
+            WebauthnError::RpIdHashMismatch { .. } => Self::InvalidRPIDHash,
             WebauthnError::UserNotPresent => Self::UserNotPresent,
             WebauthnError::UserNotVerified => Self::UserNotVerified,
             WebauthnError::InvalidExtensions => Self::InvalidExtensions,
@@ -579,7 +579,7 @@ impl From<WebauthnError> for ResponseError {
             WebauthnError::OpenSSLErrorNoCurveName => Self::OpenSSLErrorNoCurveName,
             WebauthnError::COSEKeyInvalidCBORValue => Self::COSEKeyInvalidCBORValue,
             WebauthnError::COSEKeyInvalidType => Self::COSEKeyInvalidType,
-            WebauthnError::COSEKeyEDUnsupported => Self::COSEKeyEDUnsupported,
+            WebauthnError::COSEKeyEd448Unsupported => Self::COSEKeyEDUnsupported,
             WebauthnError::COSEKeyECDSAXYInvalid => Self::COSEKeyECDSAXYInvalid,
             WebauthnError::COSEKeyRSANEInvalid => Self::COSEKeyRSANEInvalid,
             WebauthnError::COSEKeyECDSAInvalidCurve => Self::COSEKeyECDSAInvalidCurve,
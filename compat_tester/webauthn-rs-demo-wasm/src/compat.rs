@@ -820,7 +820,7 @@ impl Component for CompatTest {
                                 self.results.fallback_alg.set_warn(err)
                             }
                             ResponseError::COSEKeyEDUnsupported => {
-                                // Means that ED keys are the fallback and we don't support it.
+                                // Means that Ed448 keys are the fallback, which we don't support.
                                 self.results.fallback_alg.set_warn(err)
                             }
                             _ => self.results.fallback_alg.set_err(err),
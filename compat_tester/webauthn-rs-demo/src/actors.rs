@@ -74,6 +74,8 @@ impl WebauthnActor {
         let swan = WebauthnBuilder::new(&rp_id, &rp_origin)
             .expect("Invalid rp id or origin")
             .rp_name(&rp_name)
+            // The demo defaults to a plain http origin on localhost.
+            .allow_insecure_localhost(true)
             .build()
             .expect("Failed to build swan");
 
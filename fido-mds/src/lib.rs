@@ -41,7 +41,7 @@ use crate::mds::{
 
 use crate::query::{AttrValueAssertion, Query};
 
-use webauthn_attestation_ca::AttestationCaList;
+use webauthn_attestation_ca::{AttestationCaList, AuthenticatorOptions};
 
 use base64::{engine::general_purpose::STANDARD, Engine};
 use compact_jwt::JwtError;
@@ -587,6 +587,20 @@ impl StatusReport {
     fn gte(&self, level: &AuthenticatorStatus) -> bool {
         self.numeric() >= level.numeric()
     }
+
+    /// If this report indicates that the security of the device is compromised, such as a user
+    /// verification bypass, key compromise or revocation. An available update alone is not
+    /// considered a compromise.
+    pub fn is_security_compromise(&self) -> bool {
+        matches!(
+            self,
+            StatusReport::UserVerificationBypass { .. }
+                | StatusReport::AttestationKeyCompromise { .. }
+                | StatusReport::UserKeyRemoteCompromise { .. }
+                | StatusReport::UserKeyPhysicalCompromise { .. }
+                | StatusReport::Revoked { .. }
+        )
+    }
 }
 
 impl PartialOrd for StatusReport {
@@ -982,6 +996,15 @@ impl fmt::Display for FIDO2 {
 }
 
 impl FIDO2 {
+    /// If any status report of this device indicates that its security is compromised. As the
+    /// firmware version of a device is generally not known at registration, this is true even
+    /// if a later firmware version resolved the issue.
+    pub fn is_security_compromised(&self) -> bool {
+        self.status_reports
+            .iter()
+            .any(StatusReport::is_security_compromise)
+    }
+
     fn query_attr(&self, ava: &AttrValueAssertion) -> bool {
         match ava {
             AttrValueAssertion::AaguidEq(u) => self.aaguid == *u,
@@ -1433,10 +1456,25 @@ impl FidoMds {
             })
             .collect();
 
-        AttestationCaList::try_from(data.as_slice())
+        let mut att_ca_list = AttestationCaList::try_from(data.as_slice())
             .map_err(|e| {
                 error!(err = ?e, "Failed to process attestation ca list");
             })
-            .ok()
+            .ok()?;
+
+        fds.iter()
+            .filter(|fd| fd.is_security_compromised())
+            .for_each(|fd| att_ca_list.insert_vulnerable_aaguid(fd.aaguid));
+
+        for fd in fds {
+            if let Some(agi) = fd.authenticator_get_info.as_ref() {
+                att_ca_list.insert_authenticator_options(
+                    fd.aaguid,
+                    AuthenticatorOptions::from(&agi.options),
+                );
+            }
+        }
+
+        Some(att_ca_list)
     }
 }
@@ -124,6 +124,7 @@ pub fn verify_fido_sk_ssh_attestation(
         data: ParsedAttestationData::Basic(att_x509),
         metadata: AttestationMetadata::Packed {
             aaguid: Uuid::from_bytes(acd.aaguid),
+            authenticator_options: None,
         },
     };
 
@@ -137,10 +138,10 @@ pub fn verify_fido_sk_ssh_attestation(
     // but in this case because we have the ca_list and none was the result (which happens)
     // in some cases, we need to map that through. But we need verify_attesation_ca_chain
     // to still return these option types due to re-attestation in the future.
-    let ca_crt = ca_crt.ok_or(WebauthnError::AttestationNotVerifiable)?;
+    let ca_crt = ca_crt.ok_or(WebauthnError::AttestationStatementX5CMissing)?;
 
     match &attestation.metadata {
-        AttestationMetadata::Packed { aaguid } | AttestationMetadata::Tpm { aaguid, .. } => {
+        AttestationMetadata::Packed { aaguid, .. } | AttestationMetadata::Tpm { aaguid, .. } => {
             // If not present, fail.
             if !ca_crt.aaguids.contains(aaguid) {
                 error!(?aaguid, "aaguid not trusted by this CA");
@@ -181,7 +182,7 @@ pub fn verify_fido_sk_ssh_attestation(
     //
     // https://github.com/openssh/openssh-portable/blob/c46f6fed419167c1671e4227459e108036c760f8/ssh-sk.c#L291
     let ck = COSEKey::try_from(&acd.credential_pk).map_err(|e| {
-        if matches!(e, WebauthnError::COSEKeyEDUnsupported) {
+        if matches!(e, WebauthnError::COSEKeyEd448Unsupported) {
             WebauthnError::SshPublicKeyEDUnsupported
         } else {
             e
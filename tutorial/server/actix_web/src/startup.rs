@@ -33,6 +33,10 @@ pub(crate) fn startup() -> (Data<Webauthn>, Data<Mutex<UserData>>) {
     // may be changed in the future.
     let builder = builder.rp_name("Actix-web webauthn-rs");
 
+    // This tutorial is served over plain http on localhost, which must be explicitly
+    // allowed. Do NOT do this in production!
+    let builder = builder.allow_insecure_localhost(true);
+
     // Consume the builder and create our webauthn instance.
     // Webauthn has no mutable inner state, so Arc (Data) and read only is sufficient.
     let webauthn = Data::new(builder.build().expect("Invalid configuration"));
@@ -42,6 +42,10 @@ impl AppState {
         // may be changed in the future.
         let builder = builder.rp_name("Axum Webauthn-rs");
 
+        // This tutorial is served over plain http on localhost, which must be explicitly
+        // allowed. Do NOT do this in production!
+        let builder = builder.allow_insecure_localhost(true);
+
         // Consume the builder and create our webauthn instance.
         let webauthn = Arc::new(builder.build().expect("Invalid configuration"));
 
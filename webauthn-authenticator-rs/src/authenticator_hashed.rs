@@ -153,6 +153,7 @@ pub fn perform_register_with_request(
         // TODO
         attestation: None,
         authenticator_selection: None,
+        hints: None,
         extensions: None,
     };
     let client_data_hash = request.client_data_hash;
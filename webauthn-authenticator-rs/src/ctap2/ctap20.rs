@@ -629,6 +629,7 @@ impl<'a, T: Token, U: UiCallback> AuthenticatorBackendHashedClientData
                 // transport which was actually used.
                 transports: self.info.get_transports(),
             },
+            authenticator_attachment: None,
         })
     }
 
@@ -693,6 +694,7 @@ impl<'a, T: Token, U: UiCallback> AuthenticatorBackendHashedClientData
             // TODO
             extensions: AuthenticationExtensionsClientOutputs::default(),
             type_,
+            authenticator_attachment: None,
         })
     }
 }
@@ -238,6 +238,7 @@ impl AuthenticatorBackend for MozillaAuthenticator {
             extensions: RegistrationExtensionsClientOutputs {
                 ..Default::default()
             },
+            authenticator_attachment: None,
         })
     }
 
@@ -347,6 +348,7 @@ impl AuthenticatorBackend for MozillaAuthenticator {
             extensions: AuthenticationExtensionsClientOutputs {
                 ..Default::default()
             },
+            authenticator_attachment: None,
         })
     }
 }
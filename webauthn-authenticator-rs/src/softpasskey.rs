@@ -367,6 +367,7 @@ impl AuthenticatorBackendHashedClientData for SoftPasskey {
             },
             type_: "public-key".to_string(),
             extensions: RegistrationExtensionsClientOutputs::default(),
+            authenticator_attachment: None,
         };
 
         trace!("rego  -> {:x?}", rego);
@@ -426,6 +427,7 @@ impl AuthenticatorBackendHashedClientData for SoftPasskey {
             },
             type_: "public-key".to_string(),
             extensions: AuthenticationExtensionsClientOutputs::default(),
+            authenticator_attachment: None,
         })
     }
 }
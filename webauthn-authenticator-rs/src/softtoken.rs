@@ -600,6 +600,7 @@ impl AuthenticatorBackendHashedClientData for SoftToken {
             },
             type_: "public-key".to_string(),
             extensions: RegistrationExtensionsClientOutputs::default(),
+            authenticator_attachment: None,
         };
 
         trace!("rego  -> {:x?}", rego);
@@ -659,6 +660,7 @@ impl AuthenticatorBackendHashedClientData for SoftToken {
             },
             type_: "public-key".to_string(),
             extensions: AuthenticationExtensionsClientOutputs::default(),
+            authenticator_attachment: None,
         })
     }
 }
@@ -928,6 +930,7 @@ mod tests {
         let att_ca_list: AttestationCaList = AttestationCa {
             ca: ca_root,
             aaguids,
+            intermediates: Vec::new(),
         }
         .try_into()
         .expect("Failed to build attestation ca list");
@@ -1010,6 +1013,7 @@ mod tests {
         let att_ca_list: AttestationCaList = AttestationCa {
             ca: ca_root,
             aaguids,
+            intermediates: Vec::new(),
         }
         .try_into()
         .expect("Failed to build attestation ca list");
@@ -205,6 +205,7 @@ impl AuthenticatorBackend for Win10 {
                     ),
                     transports: Some(native_to_transports(a.dwUsedTransport)),
                 },
+                authenticator_attachment: None,
             })
         }
     }
@@ -341,6 +342,7 @@ impl AuthenticatorBackend for Win10 {
                 },
                 type_,
                 extensions,
+                authenticator_attachment: None,
             })
         }
     }
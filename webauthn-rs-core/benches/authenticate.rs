@@ -0,0 +1,97 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use url::Url;
+use webauthn_rs_core::proto::*;
+use webauthn_rs_core::{AttestationFormat, WebauthnCore};
+
+// An ES256 assertion over the challenge 0..32 for the origin http://localhost:8080.
+const ASSERTION: &str = r#"
+{
+    "id":"OeAKNr2HF331abjY1RMWM8J1y3dh-stQ5icbY-XwVn8",
+    "rawId":"OeAKNr2HF331abjY1RMWM8J1y3dh-stQ5icbY-XwVn8",
+    "response":{
+        "authenticatorData":"SZYN5YgOjGh0NBcPZHZgW4_krrmihjLHmVzzuoMdl2MFAAAAAg",
+        "clientDataJSON":"eyJ0eXBlIjoid2ViYXV0aG4uZ2V0IiwiY2hhbGxlbmdlIjoiQUFFQ0F3UUZCZ2NJQ1FvTERBME9EeEFSRWhNVUZSWVhHQmthR3h3ZEhoOCIsIm9yaWdpbiI6Imh0dHA6Ly9sb2NhbGhvc3Q6ODA4MCIsImNyb3NzT3JpZ2luIjpmYWxzZX0",
+        "signature":"MEUCIAWAoPMdgthOkCBVh25Hq_Ic_W4qYmTfUOTUcUT8_95TAiEAwqr_Srgenz5R-1hlw_xYEWrrHo-ntljqHu-0gXrHinc",
+        "userHandle":null
+    },
+    "type":"public-key"
+}
+"#;
+
+fn credential() -> Credential {
+    Credential {
+        cred_id: Base64UrlSafeData(vec![
+            57, 224, 10, 54, 189, 135, 23, 125, 245, 105, 184, 216, 213, 19, 22, 51, 194, 117, 203,
+            119, 97, 250, 203, 80, 230, 39, 27, 99, 229, 240, 86, 127,
+        ]),
+        cred: COSEKey {
+            type_: COSEAlgorithm::ES256,
+            key: COSEKeyType::EC_EC2(COSEEC2Key {
+                curve: ECDSACurve::SECP256R1,
+                x: vec![
+                    102, 237, 80, 46, 83, 145, 17, 231, 230, 221, 119, 213, 190, 141, 203, 45, 188,
+                    226, 222, 242, 81, 212, 156, 225, 161, 142, 214, 181, 8, 39, 4, 68,
+                ]
+                .into(),
+                y: vec![
+                    71, 31, 63, 29, 97, 203, 213, 54, 16, 51, 120, 47, 23, 35, 135, 248, 40, 152,
+                    38, 73, 61, 197, 124, 225, 22, 162, 134, 242, 218, 156, 70, 21,
+                ]
+                .into(),
+            }),
+        },
+        counter: 1,
+        transports: None,
+        user_verified: false,
+        backup_eligible: false,
+        backup_state: false,
+        registration_policy: UserVerificationPolicy::Discouraged_DO_NOT_USE,
+        extensions: RegisteredExtensions::none(),
+        attestation: ParsedAttestation::default(),
+        attestation_format: AttestationFormat::None,
+        registered_at: None,
+    }
+}
+
+fn authenticate(c: &mut Criterion) {
+    let rsp: PublicKeyCredential = serde_json::from_str(ASSERTION).expect("Invalid assertion");
+    let cred = credential();
+    let challenge: Vec<u8> = (0..32).collect();
+
+    let mut group = c.benchmark_group("authenticate_credential");
+    // The matching origin is always last, so every configured origin is compared.
+    for size in [1, 8, 32] {
+        let mut origins: Vec<Url> = (1..size)
+            .map(|i| Url::parse(&format!("https://idm{i}.example.com")).expect("Invalid URL"))
+            .collect();
+        origins.push(Url::parse("http://localhost:8080").expect("Invalid URL"));
+
+        let wan = WebauthnCore::new_unsafe_experts_only(
+            "http://localhost:8080/auth",
+            "localhost",
+            origins,
+            None,
+            None,
+            None,
+        );
+
+        group.bench_with_input(BenchmarkId::new("origins", size), &wan, |b, wan| {
+            b.iter(|| {
+                black_box(
+                    wan.authenticate_credential_with_challenge(
+                        &rsp,
+                        &cred,
+                        UserVerificationPolicy::Discouraged_DO_NOT_USE,
+                        &challenge,
+                        false,
+                    )
+                    .expect("Authentication failed"),
+                )
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, authenticate);
+criterion_main!(benches);
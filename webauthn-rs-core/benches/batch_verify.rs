@@ -0,0 +1,58 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use webauthn_rs_core::proto::AttestationCaList;
+use webauthn_rs_core::AttestationCaListBatchVerify;
+use webauthn_rs_device_catalog::data::yubico::YUBICO_U2F_ROOT_CA_SERIAL_457200631_PEM;
+
+// The packed attestation of a Yubikey 5Ci.
+const ATTESTATION_OBJECT: &str = "o2NmbXRmcGFja2VkZ2F0dFN0bXSjY2FsZyZjc2lnWEcwRQIhAKAZODmj+uF5qXsDY2NFol3apRjld544KRUpHzwfk5cbAiBnp2gHmamr2xr46ilQuhzIR9BwMlwtxWd6IT2QEYeo7WN4NWOBWQLBMIICvTCCAaWgAwIBAgIEK/F8eDANBgkqhkiG9w0BAQsFADAuMSwwKgYDVQQDEyNZdWJpY28gVTJGIFJvb3QgQ0EgU2VyaWFsIDQ1NzIwMDYzMTAgFw0xNDA4MDEwMDAwMDBaGA8yMDUwMDkwNDAwMDAwMFowbjELMAkGA1UEBhMCU0UxEjAQBgNVBAoMCVl1YmljbyBBQjEiMCAGA1UECwwZQXV0aGVudGljYXRvciBBdHRlc3RhdGlvbjEnMCUGA1UEAwweWXViaWNvIFUyRiBFRSBTZXJpYWwgNzM3MjQ2MzI4MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEdMLHhCPIcS6bSPJZWGb8cECuTN8H13fVha8Ek5nt+pI8vrSflxb59Vp4bDQlH8jzXj3oW1ZwUDjHC6EnGWB5i6NsMGowIgYJKwYBBAGCxAoCBBUxLjMuNi4xLjQuMS40MTQ4Mi4xLjcwEwYLKwYBBAGC5RwCAQEEBAMCAiQwIQYLKwYBBAGC5RwBAQQEEgQQxe9V/62aS5+1gK3rr+Am0DAMBgNVHRMBAf8EAjAAMA0GCSqGSIb3DQEBCwUAA4IBAQCLbpN2nXhNbunZANJxAn/Cd+S4JuZsObnUiLnLLS0FPWa01TY8F7oJ8bE+aFa4kTe6NQQfi8+yiZrQ8N+JL4f7gNdQPSrH+r3iFd4SvroDe1jaJO4J9LeiFjmRdcVa+5cqNF4G1fPCofvw9W4lKnObuPakr0x/icdVq1MXhYdUtQk6Zr5mBnc4FhN9qi7DXqLHD5G7ZFUmGwfIcD2+0m1f1mwQS8yRD5+/aDCf3vutwddoi3crtivzyromwbKklR4qHunJ75LGZLZA8pJ/mXnUQ6TTsgRqPvPXgQPbSyGMf2z/DIPbQqCD/Bmc4dj9o6LozheBdDtcZCAjSPTAd/uiaGF1dGhEYXRhWMS3tF916xTswLEZrAO3fy8EzMmvvR8f5wWM7F5+4KJ0ikEAAAACxe9V/62aS5+1gK3rr+Am0ABAuZcVDBVS68E/MtAgeQpElJxldF/6cY9sSvbWqx/qRh8wiu42lyRBRmh5yFeD/r9k130dMbFHBHI9RTFgdJQIzaUBAgMmIAEhWCDCfn9t/BeDFfwG32Ms/owb5hFeBYUcaCmQRauVoRrI8yJYII97t5wYshX4dZ+iRas0vPwaOwYvZ1wTOnVn+QDbCF/E";
+const CLIENT_DATA_JSON: &str = "eyJ0eXBlIjoid2ViYXV0aG4uY3JlYXRlIiwib3JpZ2luIjoiaHR0cHM6XC9cLzE3Mi4yMC4wLjE0MTo4NDQzIiwiY2hhbGxlbmdlIjoidHZSMW0tZF9vaFhyd1Z4UWpNZ0g4S25vdkhaN0JSV2habURONFRWTXBOVSJ9";
+
+fn batch_verify(c: &mut Criterion) {
+    let attestation_object = STANDARD
+        .decode(ATTESTATION_OBJECT)
+        .expect("Invalid attestation object");
+    let client_data_json = STANDARD
+        .decode(CLIENT_DATA_JSON)
+        .expect("Invalid client data");
+
+    let att_ca_list: AttestationCaList = YUBICO_U2F_ROOT_CA_SERIAL_457200631_PEM
+        .try_into()
+        .expect("Failed to build att ca list");
+
+    let mut group = c.benchmark_group("batch_verify_attestation");
+    for size in [10, 100, 1000] {
+        let items: Vec<_> = (0..size)
+            .map(|_| (attestation_object.as_slice(), client_data_json.as_slice()))
+            .collect();
+
+        group.bench_with_input(BenchmarkId::new("batch", size), &items, |b, items| {
+            b.iter(|| {
+                att_ca_list
+                    .batch_verify(items.iter().copied())
+                    .expect("Failed to prepare attestation ca list")
+                    .for_each(|res| {
+                        black_box(res.expect("Attestation failed to verify"));
+                    })
+            })
+        });
+
+        // The same work, but rebuilding the ca store for every credential.
+        group.bench_with_input(BenchmarkId::new("individual", size), &items, |b, items| {
+            b.iter(|| {
+                for item in items.iter().copied() {
+                    att_ca_list
+                        .batch_verify([item])
+                        .expect("Failed to prepare attestation ca list")
+                        .for_each(|res| {
+                            black_box(res.expect("Attestation failed to verify"));
+                        })
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, batch_verify);
+criterion_main!(benches);
@@ -4,9 +4,10 @@
 //! for more details.
 
 use std::convert::TryFrom;
+use std::sync::Arc;
 
 use crate::crypto::{
-    assert_packed_attest_req, assert_tpm_attest_req, compute_sha256, only_hash_from_type,
+    assert_tpm_attest_req, check_packed_attest_req, compute_sha256, only_hash_from_type,
     verify_signature,
 };
 use crate::error::WebauthnError;
@@ -274,7 +275,7 @@ impl AttestationX509Extension for AndroidKeyAttestationExtensionData {
 
     const IS_REQUIRED: bool = true;
 
-    const VALIDATION_ERROR: WebauthnError = WebauthnError::AttestationCertificateNonceMismatch;
+    const VALIDATION_ERROR: WebauthnError = WebauthnError::AttestationStatementAndroidKeyInvalid;
 }
 
 impl AttestationX509Extension for AppleAnonymousNonce {
@@ -360,6 +361,9 @@ pub enum AttestationFormat {
     AppleAnonymous,
     /// No attestation
     None,
+    /// An attestation format that is not supported by this library, which was verified by the
+    /// registered [AttestationFormatVerifier] for this `fmt` value.
+    Custom(String),
 }
 
 impl TryFrom<&str> for AttestationFormat {
@@ -379,12 +383,56 @@ impl TryFrom<&str> for AttestationFormat {
     }
 }
 
+/// A verifier for an attestation statement format that is not supported by this library, such
+/// as a proprietary format used to import bring-your-own-key credentials.
+///
+/// Verifiers are only consulted when the `fmt` of an attestation object is not one of the
+/// formats that are built in. A verifier for a built in format, such as `packed`, is never used.
+///
+/// # Safety
+///
+/// The verifier is responsible for the entire verification procedure of the format, and for the
+/// trust path that it returns. An incorrect verifier can allow forged attestations to be accepted.
+pub trait AttestationFormatVerifier: std::fmt::Debug + Send + Sync {
+    /// The attestation statement format identifier (the `fmt` value) that this verifies.
+    fn format(&self) -> &str;
+
+    /// Verify that `att_stmt` is a correct attestation statement, conveying a valid attestation
+    /// signature over `auth_data` (the raw authenticator data) and `client_data_hash`.
+    ///
+    /// On success this returns the attestation trust path, which is verified against the
+    /// attestation CA list if one is in use, and any metadata about the authenticator.
+    fn verify(
+        &self,
+        acd: &AttestedCredentialData,
+        att_stmt: &serde_cbor_2::Value,
+        auth_data: &[u8],
+        client_data_hash: &[u8],
+    ) -> Result<(ParsedAttestationData, AttestationMetadata), WebauthnError>;
+}
+
+/// Determine the attestation format of `fmt`, falling back to the registered verifiers when it
+/// is not one of the built in formats.
+pub(crate) fn resolve_attestation_format(
+    fmt: &str,
+    verifiers: &[Arc<dyn AttestationFormatVerifier>],
+) -> Result<AttestationFormat, WebauthnError> {
+    AttestationFormat::try_from(fmt).or_else(|err| {
+        if verifiers.iter().any(|verifier| verifier.format() == fmt) {
+            Ok(AttestationFormat::Custom(fmt.to_string()))
+        } else {
+            Err(err)
+        }
+    })
+}
+
 // Perform the Verification procedure for 8.2. Packed Attestation Statement Format
 // https://w3c.github.io/webauthn/#sctn-packed-attestation
 pub(crate) fn verify_packed_attestation(
     acd: &AttestedCredentialData,
     att_obj: &AttestationObject<Registration>,
     client_data_hash: &[u8],
+    strict_subject: bool,
 ) -> Result<(ParsedAttestationData, AttestationMetadata), WebauthnError> {
     let att_stmt = &att_obj.att_stmt;
     let auth_data_bytes = &att_obj.auth_data_bytes;
@@ -458,7 +506,7 @@ pub(crate) fn verify_packed_attestation(
             // Statement Certificate Requirements.
             // https://w3c.github.io/webauthn/#sctn-packed-attestation-cert-requirements
 
-            assert_packed_attest_req(attestn_cert)?;
+            check_packed_attest_req(attestn_cert, strict_subject)?;
 
             // If attestnCert contains an extension with OID 1.3.6.1.4.1.45724.1.1.4
             // (id-fido-gen-ce-aaguid) verify that the value of this extension matches the aaguid
@@ -476,6 +524,7 @@ pub(crate) fn verify_packed_attestation(
                 ParsedAttestationData::Basic(arr_x509),
                 AttestationMetadata::Packed {
                     aaguid: Uuid::from_bytes(acd.aaguid),
+                    authenticator_options: None,
                 },
             ))
         }
@@ -615,6 +664,70 @@ pub(crate) fn verify_fidou2f_attestation(
     Ok(attestation)
 }
 
+/// Verify that the public key specified by the parameters and unique fields of pubArea is
+/// identical to the credential public key.
+fn verify_tpm_pubarea_key(
+    credential_public_key: &COSEKey,
+    pubarea: &TpmtPublic,
+) -> Result<(), WebauthnError> {
+    match (
+        &credential_public_key.key,
+        &pubarea.parameters,
+        &pubarea.unique,
+    ) {
+        (
+            COSEKeyType::RSA(cose_rsa),
+            TpmuPublicParms::Rsa(tpm_parms),
+            TpmuPublicId::Rsa(tpm_modulus),
+        ) => {
+            // The TPM encodes the default exponent as zero, which is handled by exponent().
+            let cose_exponent =
+                u32::from_be_bytes([0, cose_rsa.e[0], cose_rsa.e[1], cose_rsa.e[2]]);
+            if cose_exponent != tpm_parms.exponent() {
+                debug!(
+                    cose_exponent,
+                    tpm_exponent = tpm_parms.exponent(),
+                    "Rsa exponent mismatch in TpmsRsaParms"
+                );
+                return Err(WebauthnError::AttestationTpmPubAreaMismatch);
+            }
+
+            // check the pkey is the same.
+            if cose_rsa.n.as_ref() != tpm_modulus {
+                return Err(WebauthnError::AttestationTpmPubAreaMismatch);
+            }
+        }
+        (
+            COSEKeyType::EC_EC2(COSEEC2Key { curve, x, y }),
+            TpmuPublicParms::Ecc(ecc_parms),
+            TpmuPublicId::Ecc(ecc_points),
+        ) => {
+            match (curve, ecc_parms.curve_id) {
+                (ECDSACurve::SECP256R1, TpmiEccCurve::NistP256)
+                | (ECDSACurve::SECP384R1, TpmiEccCurve::NistP384)
+                | (ECDSACurve::SECP521R1, TpmiEccCurve::NistP521) => {
+                    // Ok!
+                }
+                c_mismatch => {
+                    debug!(?c_mismatch, "TpmiEccCurve ID mismatch");
+                    return Err(WebauthnError::AttestationTpmPubAreaMismatch);
+                }
+            }
+
+            if x.0 != ecc_points.x || y.0 != ecc_points.y {
+                debug!("Invalid X or Y coords in TpmuPublicId");
+                return Err(WebauthnError::AttestationTpmPubAreaMismatch);
+            }
+        }
+        ex => {
+            debug!(?ex, "Unrecognised combination");
+            return Err(WebauthnError::AttestationTpmPubAreaMismatch);
+        }
+    }
+
+    Ok(())
+}
+
 // https://w3c.github.io/webauthn/#sctn-tpm-attestation
 pub(crate) fn verify_tpm_attestation(
     acd: &AttestedCredentialData,
@@ -725,53 +838,7 @@ pub(crate) fn verify_tpm_attestation(
     // identical to the credentialPublicKey in the attestedCredentialData in authenticatorData.
     let credential_public_key = COSEKey::try_from(&acd.credential_pk)?;
 
-    // Check the algo is the same
-    match (
-        &credential_public_key.key,
-        &pubarea.parameters,
-        &pubarea.unique,
-    ) {
-        (
-            COSEKeyType::RSA(cose_rsa),
-            TpmuPublicParms::Rsa(_tpm_parms),
-            TpmuPublicId::Rsa(tpm_modulus),
-        ) => {
-            // Is it possible to check the exponent? I think it's not ... as the tpm_parms and the
-            // cose rse disagree in my test vectors.
-            // cose_rsa.e != tpm_parms.exponent ||
-
-            // check the pkey is the same.
-            if cose_rsa.n.as_ref() != tpm_modulus {
-                return Err(WebauthnError::AttestationTpmPubAreaMismatch);
-            }
-        }
-        (
-            COSEKeyType::EC_EC2(COSEEC2Key { curve, x, y }),
-            TpmuPublicParms::Ecc(ecc_parms),
-            TpmuPublicId::Ecc(ecc_points),
-        ) => {
-            match (curve, ecc_parms.curve_id) {
-                (ECDSACurve::SECP256R1, TpmiEccCurve::NistP256)
-                | (ECDSACurve::SECP384R1, TpmiEccCurve::NistP384)
-                | (ECDSACurve::SECP521R1, TpmiEccCurve::NistP521) => {
-                    // Ok!
-                }
-                c_mismatch => {
-                    debug!(?c_mismatch, "TpmiEccCurve ID mismatch");
-                    return Err(WebauthnError::AttestationTpmPubAreaMismatch);
-                }
-            }
-
-            if x.0 != ecc_points.x || y.0 != ecc_points.y {
-                debug!("Invalid X or Y coords in TpmuPublicId");
-                return Err(WebauthnError::AttestationTpmPubAreaMismatch);
-            }
-        }
-        ex => {
-            debug!(?ex, "Unrecognised combination");
-            return Err(WebauthnError::AttestationTpmPubAreaMismatch);
-        }
-    }
+    verify_tpm_pubarea_key(&credential_public_key, &pubarea)?;
 
     // Concatenate authenticatorData and clientDataHash to form attToBeSigned.
     let verification_data: Vec<u8> = auth_data_bytes
@@ -878,6 +945,7 @@ pub(crate) fn verify_tpm_attestation(
         AttestationMetadata::Tpm {
             aaguid: Uuid::from_bytes(acd.aaguid),
             firmware_version: certinfo.firmware_version,
+            authenticator_options: None,
         },
     ))
 }
@@ -1041,10 +1109,25 @@ pub(crate) fn verify_android_key_attestation(
     // let pem = attestn_cert.to_pem()?;
     // dbg!(std::str::from_utf8(&pem).unwrap());
 
+    //
+    // The key description must also show that the key was generated on the device for signing.
+    // This is enforced while the extension is parsed, so a description that fails to parse is
+    // reported as invalid, rather than as a malformed certificate.
     let meta = validate_extension::<AndroidKeyAttestationExtensionData>(
         attestn_cert,
         &client_data_hash.to_vec(),
-    )?;
+    )
+    .map_err(|e| match e {
+        WebauthnError::AttestationStatementX5CInvalid => {
+            error!("android-key attestation key description is invalid");
+            WebauthnError::AttestationStatementAndroidKeyInvalid
+        }
+        WebauthnError::AttestationStatementAndroidKeyInvalid => {
+            error!("android-key attestation challenge does not match the clientDataHash");
+            e
+        }
+        e => e,
+    })?;
 
     // arr_x509.iter().for_each(|c| {
     //     let pem = c.to_pem().unwrap();
@@ -1227,17 +1310,73 @@ pub(crate) fn verify_android_safetynet_attestation(
     Ok((ParsedAttestationData::Basic(x5c), metadata))
 }
 
+// Verification results from openssl/x509_vfy.h that indicate the chain was otherwise valid, but
+// violated the name or path length constraints of one of the certificate authorities.
+fn is_chain_constraint_violation(res: x509::X509VerifyResult) -> bool {
+    matches!(
+        res.as_raw(),
+        openssl_sys::X509_V_ERR_PATH_LENGTH_EXCEEDED
+            | openssl_sys::X509_V_ERR_PROXY_PATH_LENGTH_EXCEEDED
+            | openssl_sys::X509_V_ERR_PERMITTED_VIOLATION
+            | openssl_sys::X509_V_ERR_EXCLUDED_VIOLATION
+            | openssl_sys::X509_V_ERR_SUBTREE_MINMAX
+            | openssl_sys::X509_V_ERR_UNSUPPORTED_CONSTRAINT_TYPE
+            | openssl_sys::X509_V_ERR_UNSUPPORTED_CONSTRAINT_SYNTAX
+            | openssl_sys::X509_V_ERR_UNSUPPORTED_NAME_SYNTAX
+    )
+}
+
 /// Verify the attestation chain
+///
+/// Name constraints and path length constraints of the certificate authorities in the chain are
+/// enforced. A chain that violates these returns
+/// [WebauthnError::AttestationChainConstraintViolation].
+///
+/// Unless `danger_disable_certificate_time_checks` is set, every certificate of the chain must
+/// be within its validity period, otherwise [WebauthnError::AttestationCertificateExpired] or
+/// [WebauthnError::AttestationCertificateNotYetValid] is returned.
 pub fn verify_attestation_ca_chain<'a>(
     att_data: &'_ ParsedAttestationData,
     ca_list: &'a AttestationCaList,
     danger_disable_certificate_time_checks: bool,
 ) -> Result<Option<&'a AttestationCa>, WebauthnError> {
+    let ca_store = build_attestation_ca_store(ca_list, danger_disable_certificate_time_checks)?;
+    verify_attestation_ca_chain_with_store(att_data, ca_list, &ca_store)
+}
+
+/// Create the x509 store of the trusted attestation CAs that chains are validated against.
+fn build_attestation_ca_store(
+    ca_list: &AttestationCaList,
+    danger_disable_certificate_time_checks: bool,
+) -> Result<store::X509Store, WebauthnError> {
     // If the ca_list is empty, Immediately fail since no valid attestation can be created.
     if ca_list.cas.is_empty() {
         return Err(WebauthnError::AttestationCertificateTrustStoreEmpty);
     }
 
+    let mut ca_store = store::X509StoreBuilder::new().map_err(WebauthnError::OpenSSLError)?;
+
+    // In tests we may need to allow disabling time window validity.
+    if danger_disable_certificate_time_checks {
+        ca_store
+            .set_flags(verify::X509VerifyFlags::NO_CHECK_TIME)
+            .map_err(WebauthnError::OpenSSLError)?;
+    }
+
+    for ca_crt in ca_list.cas.values() {
+        ca_store
+            .add_cert(ca_crt.ca.clone())
+            .map_err(WebauthnError::OpenSSLError)?;
+    }
+
+    Ok(ca_store.build())
+}
+
+fn verify_attestation_ca_chain_with_store<'a>(
+    att_data: &'_ ParsedAttestationData,
+    ca_list: &'a AttestationCaList,
+    ca_store: &store::X509StoreRef,
+) -> Result<Option<&'a AttestationCa>, WebauthnError> {
     // Do we have a format we can actually check?
     let fullchain = match att_data {
         ParsedAttestationData::Basic(chain) => chain,
@@ -1264,37 +1403,26 @@ pub fn verify_attestation_ca_chain<'a>(
     // Convert the chain to a stackref so that openssl can use it.
     let mut chain_stack = stack::Stack::new().map_err(WebauthnError::OpenSSLError)?;
 
-    for crt in chain.iter() {
+    // Intermediates configured with our CAs may be absent from the x5c, so they are provided
+    // as untrusted certificates that the chain may be built through.
+    let intermediates = ca_list
+        .cas
+        .values()
+        .flat_map(|ca_crt| ca_crt.intermediates.iter());
+
+    for crt in chain.iter().chain(intermediates) {
         chain_stack
             .push(crt.clone())
             .map_err(WebauthnError::OpenSSLError)?;
     }
 
-    // Create the x509 store that we will validate against.
-    let mut ca_store = store::X509StoreBuilder::new().map_err(WebauthnError::OpenSSLError)?;
-
-    // In tests we may need to allow disabling time window validity.
-    if danger_disable_certificate_time_checks {
-        ca_store
-            .set_flags(verify::X509VerifyFlags::NO_CHECK_TIME)
-            .map_err(WebauthnError::OpenSSLError)?;
-    }
-
-    for ca_crt in ca_list.cas.values() {
-        ca_store
-            .add_cert(ca_crt.ca.clone())
-            .map_err(WebauthnError::OpenSSLError)?;
-    }
-
-    let ca_store = ca_store.build();
-
     let mut ca_ctx = x509::X509StoreContext::new().map_err(WebauthnError::OpenSSLError)?;
 
     // Providing the cert and chain, validate we have a ref to our store.
     // Note this is a result<result ... because the inner .init must return an errorstack
     // for openssl.
     let res: Result<_, _> = ca_ctx
-        .init(&ca_store, leaf, &chain_stack, |ca_ctx_ref| {
+        .init(ca_store, leaf, &chain_stack, |ca_ctx_ref| {
             ca_ctx_ref.verify_cert().map(|_| {
                 // The value as passed in is a boolean that we ignore in favour of the richer error type.
                 let res = ca_ctx_ref.error();
@@ -1320,7 +1448,17 @@ pub fn verify_attestation_ca_chain<'a>(
                         ca_ctx_ref.error_depth(),
                         ca_ctx_ref.current_cert().map(|crt| crt.subject_name())
                     );
-                    Err(WebauthnError::AttestationChainNotTrusted(res.to_string()))
+                    if is_chain_constraint_violation(res) {
+                        Err(WebauthnError::AttestationChainConstraintViolation(
+                            res.to_string(),
+                        ))
+                    } else if res.as_raw() == openssl_sys::X509_V_ERR_CERT_HAS_EXPIRED {
+                        Err(WebauthnError::AttestationCertificateExpired)
+                    } else if res.as_raw() == openssl_sys::X509_V_ERR_CERT_NOT_YET_VALID {
+                        Err(WebauthnError::AttestationCertificateNotYetValid)
+                    } else {
+                        Err(WebauthnError::AttestationChainNotTrusted(res.to_string()))
+                    }
                 }
             })
         })
@@ -1344,3 +1482,508 @@ pub fn verify_attestation_ca_chain<'a>(
             .map(Some)
     })
 }
+
+/// Assert that the aaguid of the device is within the authority of the attestation CA that
+/// it chained to, if that CA limits the set of aaguids it may attest.
+pub(crate) fn verify_attestation_ca_aaguid(
+    att_ca_crt: &AttestationCa,
+    attestation_metadata: &AttestationMetadata,
+) -> Result<(), WebauthnError> {
+    if att_ca_crt.aaguids.is_empty() {
+        trace!("No aaguids set present, allowing all associated keys.");
+        return Ok(());
+    }
+
+    match attestation_metadata {
+        AttestationMetadata::Packed { aaguid, .. } | AttestationMetadata::Tpm { aaguid, .. } => {
+            // If not present, fail.
+            if att_ca_crt.aaguids.contains(aaguid) {
+                Ok(())
+            } else {
+                Err(WebauthnError::AttestationUntrustedAaguid)
+            }
+        }
+        // Fail
+        _ => Err(WebauthnError::AttestationFormatMissingAaguid),
+    }
+}
+
+/// Verify that an attestation statement is correct, conveying a valid attestation signature
+/// over the authenticator data and the hash of the serialised client data, using the
+/// verification procedure of the attestation format.
+pub(crate) fn verify_attestation_statement(
+    attest_format: &AttestationFormat,
+    acd: &AttestedCredentialData,
+    att_obj: &AttestationObject<Registration>,
+    client_data_hash: &[u8],
+    danger_disable_certificate_time_checks: bool,
+    strict_packed_subject: bool,
+    verifiers: &[Arc<dyn AttestationFormatVerifier>],
+) -> Result<(ParsedAttestationData, AttestationMetadata), WebauthnError> {
+    match attest_format {
+        AttestationFormat::FIDOU2F => Ok((
+            verify_fidou2f_attestation(acd, att_obj, client_data_hash)?,
+            AttestationMetadata::None,
+        )),
+        AttestationFormat::Packed => {
+            verify_packed_attestation(acd, att_obj, client_data_hash, strict_packed_subject)
+        }
+        AttestationFormat::Tpm => verify_tpm_attestation(acd, att_obj, client_data_hash),
+        AttestationFormat::AppleAnonymous => {
+            verify_apple_anonymous_attestation(acd, att_obj, client_data_hash)
+        }
+        AttestationFormat::AndroidKey => {
+            verify_android_key_attestation(acd, att_obj, client_data_hash)
+        }
+        AttestationFormat::AndroidSafetyNet => verify_android_safetynet_attestation(
+            acd,
+            att_obj,
+            client_data_hash,
+            danger_disable_certificate_time_checks,
+        ),
+        AttestationFormat::None => Ok((ParsedAttestationData::None, AttestationMetadata::None)),
+        AttestationFormat::Custom(fmt) => verifiers
+            .iter()
+            .find(|verifier| verifier.format() == fmt)
+            .ok_or(WebauthnError::AttestationNotSupported)?
+            .verify(
+                acd,
+                &att_obj.att_stmt,
+                &att_obj.auth_data_bytes,
+                client_data_hash,
+            ),
+    }
+}
+
+/// Batch verification of previously registered attestations against an [AttestationCaList].
+pub trait AttestationCaListBatchVerify {
+    /// Verify a batch of previously registered attestations against this trusted attestation
+    /// CA list. Each item is the pair of the raw `attestationObject` and `clientDataJSON` bytes
+    /// that were supplied at registration.
+    ///
+    /// The x509 store of the ca list is only built once and is reused for every item of the
+    /// batch, making this suitable for re-checking a large number of stored credentials when
+    /// the set of trusted CAs changes. Items are verified lazily as the returned iterator is
+    /// consumed, and each item yields the [AttestationMetadata] of the device, or the reason
+    /// the attestation failed to verify.
+    ///
+    /// # Safety
+    /// As with [Credential::verify_attestation], the CA expiry time is NOT verified, since
+    /// some vendors issue attestation intermediates that are only valid for minutes.
+    fn batch_verify<I, A, C>(
+        &self,
+        items: I,
+    ) -> Result<AttestationBatchVerify<'_, I::IntoIter>, WebauthnError>
+    where
+        I: IntoIterator<Item = (A, C)>,
+        A: AsRef<[u8]>,
+        C: AsRef<[u8]>;
+}
+
+impl AttestationCaListBatchVerify for AttestationCaList {
+    fn batch_verify<I, A, C>(
+        &self,
+        items: I,
+    ) -> Result<AttestationBatchVerify<'_, I::IntoIter>, WebauthnError>
+    where
+        I: IntoIterator<Item = (A, C)>,
+        A: AsRef<[u8]>,
+        C: AsRef<[u8]>,
+    {
+        // As with Credential::verify_attestation, time checks are disabled.
+        let danger_disable_certificate_time_checks = true;
+        let ca_store = build_attestation_ca_store(self, danger_disable_certificate_time_checks)?;
+
+        Ok(AttestationBatchVerify {
+            ca_list: self,
+            ca_store,
+            items: items.into_iter(),
+        })
+    }
+}
+
+/// The iterator of results returned by [AttestationCaListBatchVerify::batch_verify].
+pub struct AttestationBatchVerify<'a, I> {
+    ca_list: &'a AttestationCaList,
+    ca_store: store::X509Store,
+    items: I,
+}
+
+impl<I, A, C> Iterator for AttestationBatchVerify<'_, I>
+where
+    I: Iterator<Item = (A, C)>,
+    A: AsRef<[u8]>,
+    C: AsRef<[u8]>,
+{
+    type Item = Result<AttestationMetadata, WebauthnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.items
+            .next()
+            .map(|(attestation_object, client_data_json)| {
+                self.verify_item(attestation_object.as_ref(), client_data_json.as_ref())
+            })
+    }
+}
+
+impl<I> AttestationBatchVerify<'_, I> {
+    fn verify_item(
+        &self,
+        attestation_object: &[u8],
+        client_data_json: &[u8],
+    ) -> Result<AttestationMetadata, WebauthnError> {
+        let danger_disable_certificate_time_checks = true;
+        let att_obj = AttestationObject::<Registration>::try_from(attestation_object)?;
+        let client_data_json_hash = compute_sha256(client_data_json);
+
+        let attest_format = AttestationFormat::try_from(att_obj.fmt.as_str())?;
+        let acd = att_obj
+            .auth_data
+            .acd
+            .as_ref()
+            .ok_or(WebauthnError::MissingAttestationCredentialData)?;
+
+        let (attestation_data, attestation_metadata) = verify_attestation_statement(
+            &attest_format,
+            acd,
+            &att_obj,
+            &client_data_json_hash,
+            danger_disable_certificate_time_checks,
+            true,
+            &[],
+        )?;
+
+        let att_ca_crt = verify_attestation_ca_chain_with_store(
+            &attestation_data,
+            self.ca_list,
+            &self.ca_store,
+        )?
+        .ok_or(WebauthnError::AttestationStatementX5CMissing)?;
+        verify_attestation_ca_aaguid(att_ca_crt, &attestation_metadata)?;
+
+        Ok(attestation_metadata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::panic)]
+
+    use super::*;
+
+    /// Issue a certificate for a new P-256 key with the common name `cn`, valid between the unix
+    /// times `not_before` and `not_after`. The certificate is self signed, unless it is issued
+    /// by the certificate and key in `issuer`.
+    fn issue_certificate(
+        cn: &str,
+        serial: u32,
+        issuer: Option<(&x509::X509, &openssl::pkey::PKey<openssl::pkey::Private>)>,
+        is_ca: bool,
+        (not_before, not_after): (i64, i64),
+    ) -> (x509::X509, openssl::pkey::PKey<openssl::pkey::Private>) {
+        use openssl::{asn1, bn, ec, nid, pkey};
+
+        let group = ec::EcGroup::from_curve_name(nid::Nid::X9_62_PRIME256V1).unwrap();
+        let key = pkey::PKey::from_ec_key(ec::EcKey::generate(&group).unwrap()).unwrap();
+        let mut name = x509::X509NameBuilder::new().unwrap();
+        name.append_entry_by_nid(nid::Nid::COMMONNAME, cn).unwrap();
+        let name = name.build();
+
+        let mut cert = x509::X509Builder::new().unwrap();
+        cert.set_version(2).unwrap();
+        cert.set_serial_number(
+            &bn::BigNum::from_u32(serial)
+                .unwrap()
+                .to_asn1_integer()
+                .unwrap(),
+        )
+        .unwrap();
+        cert.set_subject_name(&name).unwrap();
+        cert.set_issuer_name(issuer.map_or(&*name, |(crt, _)| crt.subject_name()))
+            .unwrap();
+        cert.set_pubkey(&key).unwrap();
+        cert.set_not_before(&asn1::Asn1Time::from_unix(not_before).unwrap())
+            .unwrap();
+        cert.set_not_after(&asn1::Asn1Time::from_unix(not_after).unwrap())
+            .unwrap();
+        let mut constraints = x509::extension::BasicConstraints::new();
+        if is_ca {
+            constraints.critical().ca();
+        }
+        cert.append_extension(constraints.build().unwrap()).unwrap();
+        cert.sign(issuer.map_or(&key, |(_, key)| key), MessageDigest::sha256())
+            .unwrap();
+        (cert.build(), key)
+    }
+
+    /// The current unix time in seconds.
+    fn unix_now() -> i64 {
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64
+    }
+
+    #[test]
+    fn tpm_pubarea_default_rsa_exponent() {
+        // A TPMT_PUBLIC from a Windows Hello TPM, which encodes the default RSA exponent as zero.
+        let data: Vec<u8> = vec![
+            0, 1, 0, 11, 0, 6, 4, 114, 0, 32, 157, 255, 203, 243, 108, 56, 58, 230, 153, 251, 152,
+            104, 220, 109, 203, 137, 215, 21, 56, 132, 190, 40, 3, 146, 44, 18, 65, 88, 191, 173,
+            34, 174, 0, 16, 0, 16, 8, 0, 0, 0, 0, 0, 1, 0, 220, 20, 243, 114, 251, 142, 90, 236,
+            17, 204, 181, 223, 8, 72, 230, 209, 122, 44, 90, 55, 96, 134, 69, 16, 125, 139, 112,
+            81, 154, 230, 133, 211, 129, 37, 75, 208, 222, 70, 210, 239, 209, 188, 152, 93, 222,
+            222, 154, 169, 217, 160, 90, 243, 135, 151, 25, 87, 240, 178, 106, 119, 150, 89, 23,
+            223, 158, 88, 107, 72, 101, 61, 184, 132, 19, 110, 144, 107, 22, 178, 252, 206, 50,
+            207, 11, 177, 137, 35, 139, 68, 212, 148, 121, 249, 50, 35, 89, 52, 47, 26, 23, 6, 15,
+            115, 155, 127, 59, 168, 208, 196, 78, 125, 205, 0, 98, 43, 223, 233, 65, 137, 103, 2,
+            227, 35, 81, 107, 247, 230, 186, 111, 27, 4, 57, 42, 220, 32, 29, 181, 159, 6, 176,
+            182, 94, 191, 222, 212, 235, 60, 101, 83, 86, 217, 203, 151, 251, 254, 219, 204, 195,
+            10, 74, 147, 5, 27, 167, 127, 117, 149, 245, 157, 92, 124, 2, 196, 214, 107, 246, 228,
+            171, 229, 100, 212, 67, 88, 215, 75, 33, 183, 199, 51, 171, 210, 213, 65, 45, 96, 96,
+            226, 29, 130, 254, 58, 92, 252, 133, 207, 105, 63, 156, 208, 149, 142, 9, 83, 1, 193,
+            217, 244, 35, 137, 43, 138, 137, 140, 82, 231, 195, 145, 213, 230, 185, 245, 104, 105,
+            62, 142, 124, 34, 9, 157, 167, 188, 243, 112, 104, 248, 63, 50, 19, 53, 173, 69, 12,
+            39, 252, 9, 69, 223,
+        ];
+        let pubarea = TpmtPublic::try_from(data.as_slice()).unwrap();
+
+        let modulus = match (&pubarea.parameters, &pubarea.unique) {
+            (TpmuPublicParms::Rsa(tpm_parms), TpmuPublicId::Rsa(tpm_modulus)) => {
+                assert_eq!(tpm_parms.exponent(), 65537);
+                tpm_modulus.clone()
+            }
+            _ => panic!("pubArea should be an Rsa key"),
+        };
+
+        let cose_key = |e: [u8; 3]| COSEKey {
+            type_: COSEAlgorithm::RS256,
+            key: COSEKeyType::RSA(COSERSAKey {
+                n: modulus.clone().into(),
+                e,
+            }),
+        };
+
+        // The zero exponent in the pubArea matches a COSEKey with the explicit exponent 65537.
+        assert!(verify_tpm_pubarea_key(&cose_key([0x01, 0x00, 0x01]), &pubarea).is_ok());
+
+        assert!(matches!(
+            verify_tpm_pubarea_key(&cose_key([0x00, 0x00, 0x03]), &pubarea),
+            Err(WebauthnError::AttestationTpmPubAreaMismatch)
+        ));
+    }
+
+    #[test]
+    fn attestation_ca_configured_intermediate() {
+        let now = unix_now();
+        let validity = (now, now + 86400);
+
+        let (root, root_key) = issue_certificate("Example Root", 1, None, true, validity);
+        let (intermediate, intermediate_key) = issue_certificate(
+            "Example Intermediate",
+            2,
+            Some((&root, &root_key)),
+            true,
+            validity,
+        );
+        let (leaf, _) = issue_certificate(
+            "Example Attestation",
+            3,
+            Some((&intermediate, &intermediate_key)),
+            false,
+            validity,
+        );
+
+        // The authenticator only sends its leaf.
+        let att_data = ParsedAttestationData::Basic(vec![leaf]);
+
+        let mut att_ca = AttestationCa::new_from_der(&root.to_der().unwrap()).unwrap();
+        let ca_list = AttestationCaList::try_from(att_ca.clone()).unwrap();
+        assert!(matches!(
+            verify_attestation_ca_chain(&att_data, &ca_list, false),
+            Err(WebauthnError::AttestationChainNotTrusted(_))
+        ));
+
+        att_ca.insert_intermediate(intermediate);
+        let ca_list = AttestationCaList::try_from(att_ca).unwrap();
+        let trusted = verify_attestation_ca_chain(&att_data, &ca_list, false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            trusted.get_kid().unwrap(),
+            root.digest(MessageDigest::sha256()).unwrap().to_vec()
+        );
+
+        // The intermediates survive serialisation with their CA.
+        let ca_list: AttestationCaList =
+            serde_json::from_str(&serde_json::to_string(&ca_list).unwrap()).unwrap();
+        assert!(verify_attestation_ca_chain(&att_data, &ca_list, false).is_ok());
+    }
+
+    #[test]
+    fn packed_attestation_p384_leaf() {
+        use openssl::{asn1, bn, ec, nid, pkey, sign};
+        use serde_cbor_2::Value;
+        use std::collections::BTreeMap;
+
+        // The credential is P-256, but the vendor attests with a stronger P-384 certificate.
+        let p256 = ec::EcGroup::from_curve_name(nid::Nid::X9_62_PRIME256V1).unwrap();
+        let cred_key = ec::EcKey::generate(&p256).unwrap();
+        let mut ctx = bn::BigNumContext::new().unwrap();
+        let mut x = bn::BigNum::new().unwrap();
+        let mut y = bn::BigNum::new().unwrap();
+        cred_key
+            .public_key()
+            .affine_coordinates_gfp(&p256, &mut x, &mut y, &mut ctx)
+            .unwrap();
+        let cose_key = serde_cbor_2::to_vec(&Value::Map(BTreeMap::from([
+            (Value::Integer(1), Value::Integer(2)),
+            (Value::Integer(3), Value::Integer(-7)),
+            (Value::Integer(-1), Value::Integer(1)),
+            (
+                Value::Integer(-2),
+                Value::Bytes(x.to_vec_padded(32).unwrap()),
+            ),
+            (
+                Value::Integer(-3),
+                Value::Bytes(y.to_vec_padded(32).unwrap()),
+            ),
+        ])))
+        .unwrap();
+
+        let p384 = ec::EcGroup::from_curve_name(nid::Nid::SECP384R1).unwrap();
+        let leaf_key = pkey::PKey::from_ec_key(ec::EcKey::generate(&p384).unwrap()).unwrap();
+        let mut name = x509::X509NameBuilder::new().unwrap();
+        name.append_entry_by_nid(nid::Nid::COUNTRYNAME, "AU")
+            .unwrap();
+        name.append_entry_by_nid(nid::Nid::ORGANIZATIONNAME, "Example Vendor")
+            .unwrap();
+        name.append_entry_by_nid(
+            nid::Nid::ORGANIZATIONALUNITNAME,
+            "Authenticator Attestation",
+        )
+        .unwrap();
+        name.append_entry_by_nid(nid::Nid::COMMONNAME, "Example Attestation")
+            .unwrap();
+        let name = name.build();
+        let mut cert = x509::X509Builder::new().unwrap();
+        cert.set_version(2).unwrap();
+        cert.set_serial_number(&bn::BigNum::from_u32(1).unwrap().to_asn1_integer().unwrap())
+            .unwrap();
+        cert.set_subject_name(&name).unwrap();
+        cert.set_issuer_name(&name).unwrap();
+        cert.set_pubkey(&leaf_key).unwrap();
+        cert.set_not_before(&asn1::Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        cert.set_not_after(&asn1::Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        cert.append_extension(x509::extension::BasicConstraints::new().build().unwrap())
+            .unwrap();
+        cert.sign(&leaf_key, MessageDigest::sha384()).unwrap();
+        let leaf = cert.build();
+
+        // rpIdHash, flags (UP | AT), counter, aaguid, credential id and public key
+        let cred_id = [0, 1, 2, 3];
+        let mut auth_data = sha256(b"example.com").to_vec();
+        auth_data.push(0x41);
+        auth_data.extend_from_slice(&0u32.to_be_bytes());
+        auth_data.extend_from_slice(&[0; 16]);
+        auth_data.extend_from_slice(&(cred_id.len() as u16).to_be_bytes());
+        auth_data.extend_from_slice(&cred_id);
+        auth_data.extend_from_slice(&cose_key);
+
+        let client_data_hash = sha256(b"client data");
+        let mut signer = sign::Signer::new(MessageDigest::sha384(), &leaf_key).unwrap();
+        signer.update(&auth_data).unwrap();
+        signer.update(&client_data_hash).unwrap();
+        let sig = signer.sign_to_vec().unwrap();
+
+        let verify = |alg: COSEAlgorithm| {
+            let att_obj = serde_cbor_2::to_vec(&Value::Map(BTreeMap::from([
+                (
+                    Value::Text("fmt".to_string()),
+                    Value::Text("packed".to_string()),
+                ),
+                (
+                    Value::Text("attStmt".to_string()),
+                    Value::Map(BTreeMap::from([
+                        (Value::Text("alg".to_string()), Value::Integer(alg as i128)),
+                        (Value::Text("sig".to_string()), Value::Bytes(sig.clone())),
+                        (
+                            Value::Text("x5c".to_string()),
+                            Value::Array(vec![Value::Bytes(leaf.to_der().unwrap())]),
+                        ),
+                    ])),
+                ),
+                (
+                    Value::Text("authData".to_string()),
+                    Value::Bytes(auth_data.clone()),
+                ),
+            ])))
+            .unwrap();
+            let att_obj = AttestationObject::<Registration>::try_from(att_obj.as_slice()).unwrap();
+            let acd = att_obj.auth_data.acd.as_ref().unwrap();
+            verify_packed_attestation(acd, &att_obj, &client_data_hash, true)
+        };
+
+        // The digest is selected by the alg of the statement, not the curve of the credential.
+        let (att_data, _) = verify(COSEAlgorithm::ES384).unwrap();
+        match att_data {
+            ParsedAttestationData::Basic(x5c) => {
+                assert_eq!(x5c.len(), 1);
+                assert_eq!(x5c[0].to_der().unwrap(), leaf.to_der().unwrap());
+            }
+            _ => panic!("unexpected attestation type"),
+        }
+
+        assert!(matches!(
+            verify(COSEAlgorithm::ES256),
+            Err(WebauthnError::AttestationStatementSigInvalid)
+        ));
+    }
+
+    #[test]
+    fn attestation_ca_chain_validity_period() {
+        let now = unix_now();
+        let day = 86400;
+
+        let (root, root_key) =
+            issue_certificate("Example Root", 1, None, true, (now - day, now + 10 * day));
+        let ca_list = AttestationCaList::try_from(
+            AttestationCa::new_from_der(&root.to_der().unwrap()).unwrap(),
+        )
+        .unwrap();
+        let verify = |validity: (i64, i64), danger_disable_certificate_time_checks: bool| {
+            let (leaf, _) = issue_certificate(
+                "Example Attestation",
+                2,
+                Some((&root, &root_key)),
+                false,
+                validity,
+            );
+            let att_data = ParsedAttestationData::Basic(vec![leaf]);
+            verify_attestation_ca_chain(&att_data, &ca_list, danger_disable_certificate_time_checks)
+                .map(|att_ca| att_ca.is_some())
+        };
+
+        assert!(verify((now - day, now + day), false).unwrap());
+
+        let expired = (now - 2 * day, now - day);
+        assert!(matches!(
+            verify(expired, false),
+            Err(WebauthnError::AttestationCertificateExpired)
+        ));
+
+        let not_yet_valid = (now + day, now + 2 * day);
+        assert!(matches!(
+            verify(not_yet_valid, false),
+            Err(WebauthnError::AttestationCertificateNotYetValid)
+        ));
+
+        // The validity period is not checked when the time checks are disabled.
+        assert!(verify(expired, true).unwrap());
+        assert!(verify(not_yet_valid, true).unwrap());
+    }
+}
@@ -18,15 +18,15 @@
 use rand::prelude::*;
 use std::collections::BTreeSet;
 use std::convert::TryFrom;
+use std::sync::Arc;
 use url::Url;
 
 use crate::attestation::{
-    verify_android_key_attestation, verify_android_safetynet_attestation,
-    verify_apple_anonymous_attestation, verify_attestation_ca_chain, verify_fidou2f_attestation,
-    verify_packed_attestation, verify_tpm_attestation, AttestationFormat,
+    resolve_attestation_format, verify_attestation_ca_aaguid, verify_attestation_ca_chain,
+    verify_attestation_statement, AttestationFormat, AttestationFormatVerifier,
 };
 use crate::constants::{AUTHENTICATOR_TIMEOUT, CHALLENGE_SIZE_BYTES};
-use crate::crypto::compute_sha256;
+use crate::crypto::{compute_sha256, relabel_cose_key};
 use crate::error::WebauthnError;
 use crate::internals::*;
 use crate::proto::*;
@@ -54,6 +54,9 @@ pub struct WebauthnCore {
     rp_id: String,
     rp_id_hash: [u8; 32],
     allowed_origins: Vec<Url>,
+    // The origins of allowed_origins, computed once so they are not rebuilt on every
+    // verification.
+    allowed_origin_tuples: Vec<url::Origin>,
     authenticator_timeout: u32,
     require_valid_counter_value: bool,
     #[allow(unused)]
@@ -61,6 +64,11 @@ pub struct WebauthnCore {
     allow_cross_origin: bool,
     allow_subdomains_origin: bool,
     allow_any_port: bool,
+    lenient_cose_parsing: bool,
+    strict_packed_subject: bool,
+    store_transports: bool,
+    warn_uv_downgrade: bool,
+    attestation_format_verifiers: Vec<Arc<dyn AttestationFormatVerifier>>,
 }
 
 impl WebauthnCore {
@@ -89,25 +97,106 @@ impl WebauthnCore {
         allow_any_port: Option<bool>,
     ) -> Self {
         let rp_id_hash = compute_sha256(rp_id.as_bytes());
+        let allowed_origin_tuples = allowed_origins.iter().map(Url::origin).collect();
         WebauthnCore {
             rp_name: rp_name.to_string(),
             rp_id: rp_id.to_string(),
             rp_id_hash,
             allowed_origins,
+            allowed_origin_tuples,
             authenticator_timeout: authenticator_timeout.unwrap_or(AUTHENTICATOR_TIMEOUT),
             require_valid_counter_value: true,
             ignore_unsupported_attestation_formats: true,
             allow_cross_origin: false,
             allow_subdomains_origin: allow_subdomains_origin.unwrap_or(false),
             allow_any_port: allow_any_port.unwrap_or(false),
+            lenient_cose_parsing: false,
+            strict_packed_subject: true,
+            store_transports: false,
+            warn_uv_downgrade: false,
+            attestation_format_verifiers: Vec::new(),
         }
     }
 
+    /// Accept credential public keys that use the string labels of a COSE key ("kty", "alg")
+    /// instead of the integer labels that the specification requires. A few non-conformant
+    /// authenticators emit these. This defaults to false.
+    pub fn set_lenient_cose_parsing(&mut self, lenient: bool) {
+        self.lenient_cose_parsing = lenient;
+    }
+
+    /// Require that the subject of a packed attestation certificate has the organisational
+    /// unit "Authenticator Attestation", as the specification requires. If set to false, a
+    /// different organisational unit is only warned about. This defaults to true.
+    pub fn set_strict_packed_subject(&mut self, strict: bool) {
+        self.strict_packed_subject = strict;
+    }
+
+    /// Store the transports that the client reported at registration on the credential, so
+    /// that they are provided in allowCredentials of future authentications. Some clients
+    /// report incorrect transports, which can prevent the credential from being found when
+    /// authenticating, so this defaults to false.
+    pub fn set_store_transports(&mut self, store: bool) {
+        self.store_transports = store;
+    }
+
+    /// Log a warning when user verification was preferred for an authentication, but was not
+    /// performed by the authenticator. This is the normal case for many security keys, so it is
+    /// only logged at debug level by default. The result reports this regardless, with
+    /// [AuthenticationResult::uv_downgraded]. This defaults to false.
+    pub fn set_warn_uv_downgrade(&mut self, warn: bool) {
+        self.warn_uv_downgrade = warn;
+    }
+
+    /// Register a verifier for an attestation statement format that is not supported by this
+    /// library. This is only consulted when the `fmt` of a registration is not a built in format.
+    /// If more than one verifier is registered for the same format, the first is used.
+    pub fn register_attestation_format_verifier(
+        &mut self,
+        verifier: Arc<dyn AttestationFormatVerifier>,
+    ) {
+        self.attestation_format_verifiers.push(verifier);
+    }
+
     /// Get the currently configured origins
     pub fn get_allowed_origins(&self) -> &[Url] {
         &self.allowed_origins
     }
 
+    /// The SHA-256 hash of the relying party id, as it is expected to appear in the
+    /// rpIdHash of authenticator data. This is computed once when the instance is created.
+    pub fn rp_id_hash(&self) -> &[u8; 32] {
+        &self.rp_id_hash
+    }
+
+    /// Create the span that a ceremony is verified within, so that failures can be correlated
+    /// in logs. Only non-sensitive fields are recorded. The credential id is a persistent
+    /// identifier of the user, so only a truncated hash of it is recorded.
+    fn verify_span(&self, ceremony: &'static str, credential_id: &[u8]) -> tracing::Span {
+        let credential_id_hash = Base64UrlSafeData(compute_sha256(credential_id)[..8].to_vec());
+        info_span!(
+            "verify",
+            ceremony,
+            rp_id = %self.rp_id,
+            %credential_id_hash,
+            attestation_format = tracing::field::Empty,
+            outcome = tracing::field::Empty,
+            reason = tracing::field::Empty,
+        )
+    }
+
+    fn record_verify_outcome<T>(span: &tracing::Span, result: &Result<T, WebauthnError>) {
+        match result {
+            Ok(_) => {
+                span.record("outcome", "success");
+            }
+            Err(e) => {
+                span.record("outcome", "failure");
+                span.record("reason", tracing::field::display(e));
+            }
+        }
+    }
+
     fn generate_challenge(&self) -> Challenge {
         let mut rng = rand::thread_rng();
         Challenge::new(rng.gen::<[u8; CHALLENGE_SIZE_BYTES]>().to_vec())
@@ -251,6 +340,7 @@ impl WebauthnCore {
                     require_resident_key,
                     user_verification: policy,
                 }),
+                hints: None,
                 extensions: extensions.clone(),
             },
         };
@@ -265,12 +355,56 @@ impl WebauthnCore {
             authenticator_attachment,
             extensions: extensions.unwrap_or_default(),
             experimental_allow_passkeys: !experimental_reject_passkeys,
+            platform_authenticator: None,
+            ignore_attestation: false,
         };
 
         // This should have an opaque type of username + chal + policy
         Ok((c, wr))
     }
 
+    /// Parse a credential registration response and report its structural properties, without
+    /// the registration state of the ceremony. This is intended to help debug client payloads.
+    ///
+    /// ⚠️  THIS IS NOT A SECURITY CHECK. The challenge, origin, rp id hash and attestation are
+    /// NOT verified, so the response may be forged or replayed. Use
+    /// [`register_credential`](Self::register_credential) to register credentials.
+    pub fn inspect_registration(
+        &self,
+        reg: &RegisterPublicKeyCredential,
+    ) -> Result<RegistrationInspection, WebauthnError> {
+        let mut data = AuthenticatorAttestationResponse::<Registration>::try_from(&reg.response)?;
+
+        if self.lenient_cose_parsing {
+            if let Some(acd) = data.attestation_object.auth_data.acd.as_mut() {
+                acd.credential_pk = relabel_cose_key(&acd.credential_pk);
+            }
+        }
+
+        let auth_data = &data.attestation_object.auth_data;
+        let acd = auth_data
+            .acd
+            .as_ref()
+            .ok_or(WebauthnError::MissingAttestationCredentialData)?;
+        let credential_key = COSEKey::try_from(&acd.credential_pk)?;
+
+        let attestation_format = resolve_attestation_format(
+            data.attestation_object.fmt.as_str(),
+            &self.attestation_format_verifiers,
+        )?;
+
+        Ok(RegistrationInspection {
+            aaguid: uuid::Uuid::from_bytes(acd.aaguid),
+            algorithm: credential_key.type_,
+            attestation_format,
+            user_present: auth_data.user_present,
+            user_verified: auth_data.user_verified,
+            backup_eligible: auth_data.backup_eligible,
+            backup_state: auth_data.backup_state,
+            transports: data.transports.clone(),
+        })
+    }
+
     /// Process a credential registration response. This is the output of
     /// `navigator.credentials.create()` which is sent to the webserver from the client.
     ///
@@ -302,9 +436,14 @@ impl WebauthnCore {
             authenticator_attachment: _,
             extensions,
             experimental_allow_passkeys,
+            platform_authenticator: _,
+            ignore_attestation,
         } = state;
         let chal: &ChallengeRef = challenge.into();
 
+        let span = self.verify_span("registration", reg.raw_id.as_ref());
+        let _enter = span.enter();
+
         // send to register_credential_internal
         let credential = self.register_credential_internal(
             reg,
@@ -316,7 +455,17 @@ impl WebauthnCore {
             false,
             extensions,
             *experimental_allow_passkeys,
-        )?;
+            // Attestation can only be ignored when there is no CA list to assert it against.
+            *ignore_attestation && attestation_cas.is_none(),
+        );
+        if let Ok(credential) = &credential {
+            span.record(
+                "attestation_format",
+                tracing::field::debug(&credential.attestation_format),
+            );
+        }
+        Self::record_verify_outcome(&span, &credential);
+        let credential = credential?;
 
         // Check that the credentialId is not yet registered to any other user. If registration is
         // requested for a credential that is already registered to a different user, the Relying
@@ -347,6 +496,7 @@ impl WebauthnCore {
         danger_disable_certificate_time_checks: bool,
         req_extn: &RequestRegistrationExtensions,
         experimental_allow_passkeys: bool,
+        ignore_attestation: bool,
     ) -> Result<Credential, WebauthnError> {
         // Internal management - if the attestation ca list is some, but is empty, we need to fail!
         if attestation_cas
@@ -373,10 +523,19 @@ impl WebauthnCore {
         // Now, we actually do a much larger conversion in one shot
         // here, where we get the AuthenticatorAttestationResponse
 
-        let data = AuthenticatorAttestationResponse::try_from(&reg.response)?;
+        let mut data = AuthenticatorAttestationResponse::try_from(&reg.response)?;
 
         // trace!("data: {:?}", data);
 
+        // OUT OF SPEC - Some non-conformant authenticators label the credential public key
+        // parameters with strings rather than integers. If allowed, relabel them now so that
+        // attestation verification and the credential both use the standard form.
+        if self.lenient_cose_parsing {
+            if let Some(acd) = data.attestation_object.auth_data.acd.as_mut() {
+                acd.credential_pk = relabel_cose_key(&acd.credential_pk);
+            }
+        }
+
         // Verify that the value of C.type is webauthn.create.
         if data.client_data_json.type_ != "webauthn.create" {
             return Err(WebauthnError::InvalidClientDataType);
@@ -389,14 +548,7 @@ impl WebauthnCore {
         }
 
         // Verify that the client's origin matches one of our allowed origins..
-        if !self.allowed_origins.iter().any(|origin| {
-            Self::origins_match(
-                self.allow_subdomains_origin,
-                self.allow_any_port,
-                &data.client_data_json.origin,
-                origin,
-            )
-        }) {
+        if !self.client_origin_allowed(&data.client_data_json.origin) {
             return Err(WebauthnError::InvalidRPOrigin);
         }
 
@@ -436,8 +588,14 @@ impl WebauthnCore {
         //
         //  NOW: Remember that RP ID https://w3c.github.io/webauthn/#rp-id is NOT THE SAME as the RP name
         // it's actually derived from the RP origin.
+        //
+        // Subdomain origins (allow_subdomains_origin) do NOT affect this check, as the
+        // credential is always bound to the hash of the RP ID.
         if data.attestation_object.auth_data.rp_id_hash != self.rp_id_hash {
-            return Err(WebauthnError::InvalidRPIDHash);
+            error!(rp_id = %self.rp_id, "rpIdHash does not match the configured rp_id");
+            return Err(WebauthnError::RpIdHashMismatch {
+                expected_rp_id: self.rp_id.clone(),
+            });
         }
 
         // Verify that the User Present bit of the flags in authData is set.
@@ -492,7 +650,26 @@ impl WebauthnCore {
         //  https://w3c.github.io/webauthn-3/#none-attestation
         //  https://www.w3.org/TR/webauthn-3/#sctn-apple-anonymous-attestation
         //
-        let attest_format = AttestationFormat::try_from(data.attestation_object.fmt.as_str())?;
+        //
+        // Formats that are not built in may be handled by a registered AttestationFormatVerifier.
+        //
+        // If attestation was not requested, the RP may ignore whatever attestation the client
+        // returned and proceed as though the format was none. The authenticator data and
+        // credential public key are still verified below.
+        let attest_format = if ignore_attestation {
+            if data.attestation_object.fmt != "none" {
+                debug!(
+                    fmt = %data.attestation_object.fmt,
+                    "ignoring attestation statement that was not requested"
+                );
+            }
+            AttestationFormat::None
+        } else {
+            resolve_attestation_format(
+                data.attestation_object.fmt.as_str(),
+                &self.attestation_format_verifiers,
+            )?
+        };
 
         // Verify that attStmt is a correct attestation statement, conveying a valid attestation
         // signature, by using the attestation statement format fmt’s verification procedure given
@@ -505,47 +682,45 @@ impl WebauthnCore {
             .as_ref()
             .ok_or(WebauthnError::MissingAttestationCredentialData)?;
 
+        // The credential id embedded in the signed authenticator data must be the same
+        // credential that the client reported in rawId, else the client is confused or lying
+        // about which credential was created.
+        if acd.credential_id != reg.raw_id {
+            error!("attested credential id does not match the response rawId");
+            return Err(WebauthnError::CredentialIdMismatch);
+        }
+
+        // Parse the credential public key before the attestation is verified, so that a credential
+        // using an algorithm we recognise but do not support is reported as such, naming the
+        // algorithm, rather than as a failure of the attestation.
+        let credential_key = COSEKey::try_from(&acd.credential_pk).map_err(|e| match e {
+            WebauthnError::COSEKeyEd448Unsupported => {
+                error!("credential uses the unsupported algorithm Ed448");
+                WebauthnError::CredentialAlgorithmNotSupported {
+                    alg: "Ed448".to_string(),
+                }
+            }
+            e => e,
+        })?;
+
         // Now, match based on the attest_format
         debug!("attestation is: {:?}", &attest_format);
         debug!("attested credential data is: {:?}", &acd);
 
-        let (attestation_data, attestation_metadata) = match attest_format {
-            AttestationFormat::FIDOU2F => (
-                verify_fidou2f_attestation(acd, &data.attestation_object, &client_data_json_hash)?,
-                AttestationMetadata::None,
-            ),
-            AttestationFormat::Packed => {
-                verify_packed_attestation(acd, &data.attestation_object, &client_data_json_hash)?
-            }
-            // AttestationMetadata::None,
-            AttestationFormat::Tpm => {
-                verify_tpm_attestation(acd, &data.attestation_object, &client_data_json_hash)?
-            }
-            // AttestationMetadata::None,
-            AttestationFormat::AppleAnonymous => verify_apple_anonymous_attestation(
-                acd,
-                &data.attestation_object,
-                &client_data_json_hash,
-            )?,
-            // AttestationMetadata::None,
-            AttestationFormat::AndroidKey => verify_android_key_attestation(
-                acd,
-                &data.attestation_object,
-                &client_data_json_hash,
-            )?,
-            AttestationFormat::AndroidSafetyNet => verify_android_safetynet_attestation(
-                acd,
-                &data.attestation_object,
-                &client_data_json_hash,
-                danger_disable_certificate_time_checks,
-            )?,
-            AttestationFormat::None => (ParsedAttestationData::None, AttestationMetadata::None),
-        };
+        let (attestation_data, attestation_metadata) = verify_attestation_statement(
+            &attest_format,
+            acd,
+            &data.attestation_object,
+            &client_data_json_hash,
+            danger_disable_certificate_time_checks,
+            self.strict_packed_subject,
+            &self.attestation_format_verifiers,
+        )?;
 
-        let credential: Credential = Credential::new(
+        let mut credential: Credential = Credential::new(
             acd,
             &data.attestation_object.auth_data,
-            COSEKey::try_from(&acd.credential_pk)?,
+            credential_key,
             policy,
             ParsedAttestation {
                 data: attestation_data,
@@ -554,7 +729,11 @@ impl WebauthnCore {
             req_extn,
             &reg.extensions,
             attest_format,
-            &data.transports,
+            if self.store_transports {
+                &data.transports
+            } else {
+                &None
+            },
         );
 
         // Now based on result ...
@@ -589,7 +768,9 @@ impl WebauthnCore {
             // but in this case because we have the ca_list and none was the result (which happens)
             // in some cases, we need to map that through. But we need verify_attesation_ca_chain
             // to still return these option types due to re-attestation in the future.
-            let ca_crt = ca_crt.ok_or(WebauthnError::AttestationNotVerifiable)?;
+            //
+            // None is only returned for self and none attestations, which carry no x5c.
+            let ca_crt = ca_crt.ok_or(WebauthnError::AttestationStatementX5CMissing)?;
             Some(ca_crt)
         } else {
             None
@@ -600,25 +781,18 @@ impl WebauthnCore {
         // Assert that the aaguid of the device, is within the authority of this CA (if
         // a list of aaguids was provided).
         if let Some(att_ca_crt) = attested_ca_crt {
-            if att_ca_crt.aaguids.is_empty() {
-                trace!("No aaguids set present, allowing all associated keys.");
-            } else {
-                match &credential.attestation.metadata {
-                    AttestationMetadata::Packed { aaguid }
-                    | AttestationMetadata::Tpm { aaguid, .. } => {
-                        // If not present, fail.
-                        if !att_ca_crt.aaguids.contains(aaguid) {
-                            return Err(WebauthnError::AttestationUntrustedAaguid);
-                        }
-                    }
-                    _ => {
-                        // Fail
-                        return Err(WebauthnError::AttestationFormatMissingAaguid);
-                    }
-                }
-            }
+            verify_attestation_ca_aaguid(att_ca_crt, &credential.attestation.metadata)?;
         };
 
+        // The chain was verified against this list, so the options it has for this model of
+        // device apply to it.
+        if let Some(ca_list) = attestation_cas {
+            credential
+                .attestation
+                .metadata
+                .set_authenticator_options(ca_list);
+        }
+
         // Verify that the credential public key alg is one of the allowed algorithms.
         let alg_valid = credential_algorithms
             .iter()
@@ -645,13 +819,15 @@ impl WebauthnCore {
             return Err(WebauthnError::CredentialMayNotBeHardwareBound);
         }
 
-        // OUT OF SPEC - exclude any credential that is in our exclude list.
+        // OUT OF SPEC - exclude any credential that is in our exclude list. The client should have
+        // prevented this, but not all clients honour excludeCredentials, so it is enforced here.
         let excluded = exclude_credentials
             .iter()
             .any(|credid| credid.0.as_slice() == credential.cred_id.0.as_slice());
 
         if excluded {
-            return Err(WebauthnError::CredentialAlteredAlgFromRequest);
+            error!("credential is in the excludeCredentials of this ceremony");
+            return Err(WebauthnError::CredentialExcludedFromRequest);
         }
 
         // If the attestation statement attStmt verified successfully and is found to be trustworthy,
@@ -660,11 +836,10 @@ impl WebauthnCore {
         // For us, we return the credential for the caller to persist.
         // If trust failed, we have already returned an Err before this point.
 
-        // TODO: Associate the credentialId with the transport hints returned by calling
-        // credential.response.getTransports(). This value SHOULD NOT be modified before or after
-        // storing it. It is RECOMMENDED to use this value to populate the transports of the
-        // allowCredentials option in future get() calls to help the client know how to find a
-        // suitable authenticator.
+        // If store_transports is set, the credentialId is associated with the transport hints
+        // returned by calling credential.response.getTransports() in Credential::new. This value
+        // SHOULD NOT be modified before or after storing it, and is used to populate the
+        // transports of the allowCredentials option in future get() calls.
 
         Ok(credential)
     }
@@ -711,14 +886,7 @@ impl WebauthnCore {
         }
 
         // Verify that the value of C.origin matches one of our allowed origins.
-        if !self.allowed_origins.iter().any(|origin| {
-            Self::origins_match(
-                self.allow_subdomains_origin,
-                self.allow_any_port,
-                &c.origin,
-                origin,
-            )
-        }) {
+        if !self.client_origin_allowed(&c.origin) {
             return Err(WebauthnError::InvalidRPOrigin);
         }
 
@@ -730,18 +898,27 @@ impl WebauthnCore {
         // Verify that the rpIdHash in authData is the SHA-256 hash of the RP ID expected by the Relying Party.
         // Note that if we have an appid stored in the state and the client indicates it has used the appid extension,
         // we also check the hash against this appid in addition to the Relying Party
+        //
+        // A client may echo the appid extension result even though it was not requested. As no
+        // appid was requested there is nothing to verify it against, so the result is ignored.
         let has_appid_enabled = rsp.extensions.appid.unwrap_or(false);
 
-        let appid_hash = if has_appid_enabled {
-            appid.as_ref().map(|id| compute_sha256(id.as_bytes()))
-        } else {
-            None
+        let appid_hash = match (has_appid_enabled, appid) {
+            (true, Some(id)) => Some(compute_sha256(id.as_bytes())),
+            (true, None) => {
+                debug!("ignoring appid extension result that was not requested");
+                None
+            }
+            (false, _) => None,
         };
 
         if !(data.authenticator_data.rp_id_hash == self.rp_id_hash
             || Some(&data.authenticator_data.rp_id_hash) == appid_hash.as_ref())
         {
-            return Err(WebauthnError::InvalidRPIDHash);
+            error!(rp_id = %self.rp_id, "rpIdHash does not match the configured rp_id");
+            return Err(WebauthnError::RpIdHashMismatch {
+                expected_rp_id: self.rp_id.clone(),
+            });
         }
 
         // Verify that the User Present bit of the flags in authData is set.
@@ -771,7 +948,8 @@ impl WebauthnCore {
             }
             // Pass - we can not know if verification was requested to the client in the past correctly.
             // This means we can't know what it's behaviour is at the moment.
-            // We must allow unverified tokens now.
+            // We must allow unverified tokens now. Equally, newer authenticators may perform UV
+            // even when it is discouraged, which is accepted and reported in the result.
             _ => {}
         }
 
@@ -780,6 +958,13 @@ impl WebauthnCore {
         // risk profile from when it was originally enrolled. Reject the authentication if this
         // situation occurs.
 
+        // The backup eligibility of a credential is fixed when it is created, so a credential
+        // that was backup eligible can never become device bound.
+        if cred.backup_eligible && !data.authenticator_data.backup_eligible {
+            error!("Credential was backup eligible, but the authenticator reports it is not!");
+            return Err(WebauthnError::BackupEligibilityChanged);
+        }
+
         if cred.backup_eligible != data.authenticator_data.backup_eligible {
             if allow_backup_eligible_upgrade
                 && !cred.backup_eligible
@@ -860,7 +1045,7 @@ impl WebauthnCore {
             .pop_first()
             .ok_or(WebauthnError::CredentialNotFound)?;
 
-        self.generate_challenge_authenticate_inner(creds, policy, extensions, false)
+        self.generate_challenge_authenticate_inner(creds, None, policy, extensions, false)
     }
 
     /// Authenticate a single credential, with the ability to override the userVerification
@@ -879,7 +1064,7 @@ impl WebauthnCore {
         extensions: Option<RequestAuthenticationExtensions>,
     ) -> Result<(RequestChallengeResponse, AuthenticationState), WebauthnError> {
         let policy = policy.unwrap_or(cred.registration_policy);
-        self.generate_challenge_authenticate_inner(vec![cred], policy, extensions, false)
+        self.generate_challenge_authenticate_inner(vec![cred], None, policy, extensions, false)
     }
 
     /// Authenticate a set of credentials allowing the user verification policy to be set.
@@ -898,6 +1083,7 @@ impl WebauthnCore {
     ) -> Result<(RequestChallengeResponse, AuthenticationState), WebauthnError> {
         self.generate_challenge_authenticate_inner(
             creds,
+            None,
             policy,
             extensions,
             allow_backup_eligible_upgrade,
@@ -910,12 +1096,43 @@ impl WebauthnCore {
         policy: UserVerificationPolicy,
         extensions: Option<RequestAuthenticationExtensions>,
     ) -> Result<(RequestChallengeResponse, AuthenticationState), WebauthnError> {
-        self.generate_challenge_authenticate_inner(vec![], policy, extensions, false)
+        self.generate_challenge_authenticate_inner(vec![], None, policy, extensions, false)
+    }
+
+    /// Authenticate a set of credentials that are only identified by their allowCredentials
+    /// descriptors. This allows the ceremony to be started without loading the full credentials,
+    /// which must then be provided with `AuthenticationState::set_allowed_credentials` before
+    /// the response is processed. Only credentials listed in `allow_credentials` are accepted.
+    ///
+    /// NOTE: Over-riding the UserVerificationPolicy may have SECURITY consequences. You should
+    /// understand how this interacts with the credentials in use, and how that may impact
+    /// your system security.
+    ///
+    /// If in doubt, do NOT use this function!
+    pub fn generate_challenge_authenticate_allow_list(
+        &self,
+        allow_credentials: Vec<AllowCredentials>,
+        policy: UserVerificationPolicy,
+        extensions: Option<RequestAuthenticationExtensions>,
+        allow_backup_eligible_upgrade: bool,
+    ) -> Result<(RequestChallengeResponse, AuthenticationState), WebauthnError> {
+        if allow_credentials.is_empty() {
+            return Err(WebauthnError::CredentialNotFound);
+        }
+
+        self.generate_challenge_authenticate_inner(
+            vec![],
+            Some(allow_credentials),
+            policy,
+            extensions,
+            allow_backup_eligible_upgrade,
+        )
     }
 
     fn generate_challenge_authenticate_inner(
         &self,
         creds: Vec<Credential>,
+        allow_credentials: Option<Vec<AllowCredentials>>,
         policy: UserVerificationPolicy,
         extensions: Option<RequestAuthenticationExtensions>,
         allow_backup_eligible_upgrade: bool,
@@ -923,14 +1140,20 @@ impl WebauthnCore {
         let chal = self.generate_challenge();
 
         // Get the user's existing creds if any.
-        let ac = creds
-            .iter()
-            .map(|cred| AllowCredentials {
-                type_: "public-key".to_string(),
-                id: cred.cred_id.clone(),
-                transports: cred.transports.clone(),
-            })
-            .collect();
+        let allowed_credential_ids = allow_credentials
+            .as_ref()
+            .map(|ac| ac.iter().map(|c| c.id.clone()).collect());
+
+        let ac = allow_credentials.unwrap_or_else(|| {
+            creds
+                .iter()
+                .map(|cred| AllowCredentials {
+                    type_: "public-key".to_string(),
+                    id: cred.cred_id.clone(),
+                    transports: cred.transports.clone(),
+                })
+                .collect()
+        });
 
         // Extract the appid from the extensions to store it in the AuthenticationState
         let appid = extensions.as_ref().and_then(|e| e.appid.clone());
@@ -954,10 +1177,91 @@ impl WebauthnCore {
             challenge: chal.into(),
             appid,
             allow_backup_eligible_upgrade,
+            platform_authenticator: None,
+            allowed_credential_ids,
+            user_verification_fallback: false,
         };
         Ok((r, st))
     }
 
+    /// Process an authenticate response for a single credential, where the challenge was
+    /// supplied by the caller rather than generated by `generate_challenge_authenticate`.
+    /// This allows an assertion to be used as a signature over application data, such as
+    /// the digest of a document.
+    ///
+    /// ⚠️  Since no state is stored, this can not detect that the same assertion has been
+    /// submitted more than once. The caller is responsible for ensuring the challenge is
+    /// unique to the data being signed, and for rejecting replays if they matter.
+    pub fn authenticate_credential_with_challenge(
+        &self,
+        rsp: &PublicKeyCredential,
+        cred: &Credential,
+        policy: UserVerificationPolicy,
+        challenge: &[u8],
+        allow_backup_eligible_upgrade: bool,
+    ) -> Result<AuthenticationResult, WebauthnError> {
+        let state = AuthenticationState {
+            credentials: vec![cred.clone()],
+            policy,
+            challenge: challenge.to_vec().into(),
+            appid: None,
+            allow_backup_eligible_upgrade,
+            platform_authenticator: None,
+            allowed_credential_ids: None,
+            user_verification_fallback: false,
+        };
+        self.authenticate_credential(rsp, &state)
+    }
+
+    /// Verify a signed assertion using only a credential public key and the expected challenge.
+    /// This is a minimal verification primitive for storage models that retain only the public
+    /// key of a credential rather than a full [Credential], such as key escrow services.
+    ///
+    /// ⚠️  Since neither the credential nor any state is stored, the caller assumes the
+    /// responsibilities that are otherwise handled by the authentication state:
+    ///
+    /// * The challenge must be unique and single use. This can not detect that the same
+    ///   assertion has been submitted more than once, so replays must be rejected by the caller.
+    /// * The signature counter is not compared to a stored value, so cloned authenticators can
+    ///   not be detected. The counter is returned in the [AuthenticationResult] to be compared
+    ///   and persisted by the caller.
+    /// * The backup eligibility and state of the authenticator are accepted as reported, as
+    ///   there is no registered state to compare them against.
+    /// * The credential id of the response is not checked. The caller must ensure that `key`
+    ///   belongs to the credential identified by the response's `rawId`.
+    /// * User verification is only enforced as requested by `policy`.
+    pub fn verify_assertion_with_key(
+        &self,
+        rsp: &PublicKeyCredential,
+        key: &COSEKey,
+        challenge: &[u8],
+        policy: UserVerificationPolicy,
+    ) -> Result<AuthenticationResult, WebauthnError> {
+        // Take the backup flags from the assertion itself, since there are no registered
+        // values to hold it to. Only the authenticator data is needed for this, the full
+        // response (including the clientDataJSON) is parsed once during verification.
+        let data = AuthenticatorData::<Authentication>::try_from(
+            rsp.response.authenticator_data.as_ref(),
+        )?;
+
+        let cred = Credential {
+            cred_id: rsp.raw_id.clone(),
+            cred: key.clone(),
+            counter: 0,
+            transports: None,
+            user_verified: false,
+            backup_eligible: data.backup_eligible,
+            backup_state: data.backup_state,
+            registration_policy: policy,
+            extensions: RegisteredExtensions::none(),
+            attestation: ParsedAttestation::default(),
+            attestation_format: AttestationFormat::None,
+            registered_at: None,
+        };
+
+        self.authenticate_credential_with_challenge(rsp, &cred, policy, challenge, false)
+    }
+
     /// Process an authenticate response from the authenticator and browser. This
     /// is the output of `navigator.credentials.get()`, which is processed by this
     /// function. If the authentication fails, appropriate errors will be returned.
@@ -972,6 +1276,19 @@ impl WebauthnCore {
         &self,
         rsp: &PublicKeyCredential,
         state: &AuthenticationState,
+    ) -> Result<AuthenticationResult, WebauthnError> {
+        let span = self.verify_span("authentication", rsp.raw_id.as_ref());
+        let _enter = span.enter();
+
+        let result = self.authenticate_credential_internal(rsp, state);
+        Self::record_verify_outcome(&span, &result);
+        result
+    }
+
+    fn authenticate_credential_internal(
+        &self,
+        rsp: &PublicKeyCredential,
+        state: &AuthenticationState,
     ) -> Result<AuthenticationResult, WebauthnError> {
         // Steps 1 through 4 are client side.
 
@@ -984,6 +1301,9 @@ impl WebauthnCore {
             challenge: chal,
             appid,
             allow_backup_eligible_upgrade,
+            platform_authenticator: _,
+            allowed_credential_ids,
+            user_verification_fallback,
         } = state;
         let chal: &ChallengeRef = chal.into();
 
@@ -1024,6 +1344,15 @@ impl WebauthnCore {
             found_cred.ok_or(WebauthnError::CredentialNotFound)?
         };
 
+        // If the credentials were supplied after the ceremony was started, they must still be
+        // one of the credentials that were listed in allowCredentials.
+        if let Some(allowed_credential_ids) = allowed_credential_ids {
+            if !allowed_credential_ids.contains(&cred.cred_id) {
+                error!("credential was not in the allowCredentials of this ceremony");
+                return Err(WebauthnError::CredentialNotFound);
+            }
+        }
+
         // Identify the user being authenticated and verify that this user is the owner of the public
         // key credential source credentialSource identified by credential.id:
 
@@ -1048,11 +1377,19 @@ impl WebauthnCore {
         // being basicly non-existant, that there is no point. As a result, we have already enforced
         // these conditions.
 
+        // For an accessibility fallback, the credential is not held to the user verification it
+        // performed at registration. The caller is responsible for compensating controls.
+        let fallback_cred = user_verification_fallback.then(|| Credential {
+            registration_policy: UserVerificationPolicy::Preferred,
+            user_verified: false,
+            ..cred.clone()
+        });
+
         let auth_data = self.verify_credential_internal(
             rsp,
             *policy,
             chal,
-            cred,
+            fallback_cred.as_ref().unwrap_or(cred),
             appid,
             *allow_backup_eligible_upgrade,
         )?;
@@ -1062,7 +1399,15 @@ impl WebauthnCore {
         let backup_state = auth_data.backup_state;
         let backup_eligible = auth_data.backup_eligible;
 
-        let extensions = process_authentication_extensions(&auth_data.extensions);
+        let extensions = process_authentication_extensions(&auth_data.extensions, &rsp.extensions);
+
+        // The largeBlob write confirmation is a client extension output. It is not signed by the
+        // authenticator, so it only informs the caller if their write was persisted.
+        let large_blob_written = rsp
+            .extensions
+            .large_blob
+            .as_ref()
+            .and_then(|large_blob| large_blob.written);
 
         if backup_state != cred.backup_state {
             needs_update = true;
@@ -1072,6 +1417,32 @@ impl WebauthnCore {
             needs_update = true;
         }
 
+        // verify_credential_internal only permits the backup eligibility to change from false
+        // to true, so any change here is the credential becoming backup eligible.
+        let backup_state_changed = backup_eligible && !cred.backup_eligible;
+        if backup_state_changed {
+            debug!("Credential has become backup eligible");
+        }
+
+        let backup_state_transition = if backup_eligible || cred.backup_eligible {
+            Some(match (cred.backup_state, backup_state) {
+                (false, true) => BackupTransition::BackedUp,
+                (true, false) => BackupTransition::Restored,
+                _ => BackupTransition::NoChange,
+            })
+        } else {
+            None
+        };
+
+        let uv_downgraded = *policy == UserVerificationPolicy::Preferred && !user_verified;
+        if uv_downgraded {
+            if self.warn_uv_downgrade {
+                warn!("User verification was preferred, but not performed by the authenticator");
+            } else {
+                debug!("User verification was preferred, but not performed by the authenticator");
+            }
+        }
+
         // If the signature counter value authData.signCount is nonzero or the value stored in
         // conjunction with credential’s id attribute is nonzero, then run the following sub-step:
         if counter > 0 || cred.counter > 0 {
@@ -1101,22 +1472,76 @@ impl WebauthnCore {
             user_verified,
             backup_eligible,
             backup_state,
+            backup_state_changed,
             counter,
             extensions,
+            large_blob_written,
+            authenticator_data: rsp.response.authenticator_data.clone().into(),
+            uv_downgraded,
+            // The authenticator selects the hmac-secret by the UV flag of this signed assertion.
+            hmac_secret_user_verified: rsp
+                .extensions
+                .hmac_get_secret
+                .is_some()
+                .then_some(user_verified),
+            authenticator_display_name: rsp
+                .extensions
+                .cred_props
+                .as_ref()
+                .and_then(CredProps::authenticator_display_name)
+                .map(str::to_string),
+
+            backup_state_transition,
         })
     }
 
+    fn client_origin_allowed(&self, ccd_url: &url::Url) -> bool {
+        let ccd_origin = ccd_url.origin();
+        self.allowed_origins
+            .iter()
+            .zip(self.allowed_origin_tuples.iter())
+            .any(|(cnf_url, cnf_origin)| {
+                Self::origins_match_precomputed(
+                    self.allow_subdomains_origin,
+                    self.allow_any_port,
+                    ccd_url,
+                    &ccd_origin,
+                    cnf_url,
+                    cnf_origin,
+                )
+            })
+    }
+
+    #[cfg(test)]
     fn origins_match(
         allow_subdomains_origin: bool,
         allow_any_port: bool,
         ccd_url: &url::Url,
         cnf_url: &url::Url,
+    ) -> bool {
+        Self::origins_match_precomputed(
+            allow_subdomains_origin,
+            allow_any_port,
+            ccd_url,
+            &ccd_url.origin(),
+            cnf_url,
+            &cnf_url.origin(),
+        )
+    }
+
+    fn origins_match_precomputed(
+        allow_subdomains_origin: bool,
+        allow_any_port: bool,
+        ccd_url: &url::Url,
+        ccd_origin: &url::Origin,
+        cnf_url: &url::Url,
+        cnf_origin: &url::Origin,
     ) -> bool {
         if ccd_url == cnf_url {
             return true;
         }
         if allow_subdomains_origin {
-            match (ccd_url.origin(), cnf_url.origin()) {
+            match (ccd_origin, cnf_origin) {
                 (
                     url::Origin::Tuple(ccd_scheme, ccd_host, ccd_port),
                     url::Origin::Tuple(cnf_scheme, cnf_host, cnf_port),
@@ -1133,7 +1558,7 @@ impl WebauthnCore {
 
                     let valid = match (ccd_host, cnf_host) {
                         (url::Host::Domain(ccd_domain), url::Host::Domain(cnf_domain)) => {
-                            ccd_domain.ends_with(&cnf_domain)
+                            ccd_domain.ends_with(cnf_domain.as_str())
                         }
                         (a, b) => a == b,
                     };
@@ -1150,7 +1575,7 @@ impl WebauthnCore {
                     false
                 }
             }
-        } else if ccd_url.origin() != cnf_url.origin() || !ccd_url.origin().is_tuple() {
+        } else if ccd_origin != cnf_origin || !ccd_origin.is_tuple() {
             if ccd_url.host() == cnf_url.host()
                 && ccd_url.scheme() == cnf_url.scheme()
                 && allow_any_port
@@ -1169,6 +1594,11 @@ impl WebauthnCore {
     pub fn rp_name(&self) -> &str {
         self.rp_name.as_str()
     }
+
+    /// Returns the RP id
+    pub fn rp_id(&self) -> &str {
+        self.rp_id.as_str()
+    }
 }
 
 /*
@@ -1338,6 +1768,7 @@ mod tests {
     use crate::{internals::*, AttestationFormat};
     use base64::{engine::general_purpose::STANDARD, Engine};
     use base64urlsafedata::Base64UrlSafeData;
+    use openssl::pkey::{PKey, Private};
     use url::Url;
 
     use webauthn_rs_device_catalog::data::{
@@ -1395,9 +1826,24 @@ mod tests {
             false,
             &RequestRegistrationExtensions::default(),
             true,
+            false,
         );
         trace!("{:?}", result);
         assert!(result.is_ok());
+        // fido-u2f is always basic attestation.
+        let cred = result.unwrap();
+        assert_eq!(cred.attestation.attestation_type(), AttestationType::Basic);
+        assert!(cred.attestation.attestation_type().is_certificate_based());
+        assert_eq!(
+            cred.attestation.attestation_cert_serial(),
+            Some(vec![0x18, 0xac, 0x46, 0xc0])
+        );
+        assert_eq!(
+            cred.attestation.attestation_cert_issuer().as_deref(),
+            Some("CN=Yubico U2F Root CA Serial 457200631")
+        );
+        assert_eq!(ParsedAttestation::default().attestation_cert_serial(), None);
+        assert_eq!(ParsedAttestation::default().attestation_cert_issuer(), None);
     }
 
     // These are vectors from https://github.com/duo-labs/webauthn
@@ -1441,6 +1887,7 @@ mod tests {
             false,
             &RequestRegistrationExtensions::default(),
             true,
+            false,
         );
         trace!("{:?}", result);
         assert!(result.is_ok());
@@ -1487,8 +1934,67 @@ mod tests {
             false,
             &RequestRegistrationExtensions::default(),
             false,
+            false,
         );
         assert!(result.is_ok());
+        // This touchid credential has no x5c, so it is self attested. The signature is
+        // valid, but there is no guarantee of hardware backing.
+        let cred = result.unwrap();
+        assert_eq!(cred.attestation.attestation_type(), AttestationType::Self_);
+        assert!(!cred.attestation.attestation_type().is_certificate_based());
+    }
+
+    #[test]
+    fn test_registration_rp_id_hash_mismatch() {
+        let _ = tracing_subscriber::fmt::try_init();
+        // The credential was created for rp_id "localhost", but we are configured with a
+        // subdomain specific rp_id. Even though the origin is allowed, the rpIdHash must
+        // not match.
+        let wan = Webauthn::new_unsafe_experts_only(
+            "localhost:8443/auth",
+            "auth.localhost",
+            vec![Url::parse("https://localhost:8443").unwrap()],
+            None,
+            Some(true),
+            None,
+        );
+
+        let chal = Challenge::new(
+            STANDARD
+                .decode("lP6mWNAtG+/Vv15iM7lb/XRkdWMvVQ+lTyKwZuOg1Vo=")
+                .unwrap(),
+        );
+
+        let rsp = r#"{
+                        "id":"ATk_7QKbi_ntSdp16LXeU6RDf9YnRLIDTCqEjJFzc6rKBhbqoSYccxNa",
+                        "rawId":"ATk_7QKbi_ntSdp16LXeU6RDf9YnRLIDTCqEjJFzc6rKBhbqoSYccxNa",
+                        "response":{
+                            "attestationObject":"o2NmbXRmcGFja2VkZ2F0dFN0bXSiY2FsZyZjc2lnWEcwRQIgLXPjBtVEhBH3KdUDFFk3LAd9EtHogllIf48vjX4wgfECIQCXOymmfg12FPMXEdwpSjjtmrvki4K8y0uYxqWN5Bw6DGhhdXRoRGF0YViuSZYN5YgOjGh0NBcPZHZgW4_krrmihjLHmVzzuoMdl2NFXaqejq3OAAI1vMYKZIsLJfHwVQMAKgE5P-0Cm4v57Unadei13lOkQ3_WJ0SyA0wqhIyRc3OqygYW6qEmHHMTWqUBAgMmIAEhWCDNRS_Gw52ow5PNrC9OdFTFNudDmZO6Y3wmM9N8e0tJICJYIC09iIH5_RrT5tbS0PIw3srdAxYDMGao7yWgu0JFIEzT",
+                            "clientDataJSON":"eyJjaGFsbGVuZ2UiOiJsUDZtV05BdEctX1Z2MTVpTTdsYl9YUmtkV012VlEtbFR5S3dadU9nMVZvIiwiZXh0cmFfa2V5c19tYXlfYmVfYWRkZWRfaGVyZSI6ImRvIG5vdCBjb21wYXJlIGNsaWVudERhdGFKU09OIGFnYWluc3QgYSB0ZW1wbGF0ZS4gU2VlIGh0dHBzOi8vZ29vLmdsL3lhYlBleCIsIm9yaWdpbiI6Imh0dHBzOi8vbG9jYWxob3N0Ojg0NDMiLCJ0eXBlIjoid2ViYXV0aG4uY3JlYXRlIn0"
+                            },
+                        "type":"public-key"
+                      }
+        "#;
+        let rsp_d: RegisterPublicKeyCredential = serde_json::from_str(rsp).unwrap();
+        let result = wan.register_credential_internal(
+            &rsp_d,
+            UserVerificationPolicy::Preferred,
+            &chal,
+            &[],
+            &[COSEAlgorithm::ES256],
+            None,
+            false,
+            &RequestRegistrationExtensions::default(),
+            false,
+            false,
+        );
+
+        match result {
+            Err(WebauthnError::RpIdHashMismatch { expected_rp_id }) => {
+                assert_eq!(expected_rp_id, "auth.localhost");
+            }
+            r => panic!("Unexpected result {:?}", r),
+        }
     }
 
     #[test]
@@ -1531,6 +2037,7 @@ mod tests {
             false,
             &RequestRegistrationExtensions::default(),
             false,
+            false,
         );
         trace!("{:?}", result);
         assert!(result.is_ok());
@@ -1576,13 +2083,16 @@ mod tests {
             false,
             &RequestRegistrationExtensions::default(),
             false,
+            false,
         );
         trace!("{:?}", result);
         assert!(result.is_ok());
     }
 
     #[test]
-    fn test_registration_packed_attestaion_fails_with_invalid_fido_aaguid_extension() {
+    fn test_registration_packed_full_attestation_signed_data() {
+        use crate::crypto::{compute_sha256, verify_signature};
+
         let _ = tracing_subscriber::fmt::try_init();
         let wan = Webauthn::new_unsafe_experts_only(
             "webauthn.firstyear.id.au",
@@ -1597,11 +2107,12 @@ mod tests {
             serde_json::from_str("\"qabSCYW_PPKKBAW5_qEsPF3Q3prQeYBORfDMArsoKdg\"").unwrap();
         let chal = Challenge::from(chal);
 
+        // Captured from a yubikey 5, which provides packed full (basic) attestation.
         let rsp = r#"{
             "id": "eKSmfhLUwwmJpuD2IKaTopbbWKFv-qZAE4LXa2FGmTtRpvioMpeFhI8RqdsOGlBoQxJehEQyWyu7ECwPkVL5Hg",
             "rawId": "eKSmfhLUwwmJpuD2IKaTopbbWKFv-qZAE4LXa2FGmTtRpvioMpeFhI8RqdsOGlBoQxJehEQyWyu7ECwPkVL5Hg",
             "response": {
-            "attestationObject": "o2NmbXRmcGFja2VkZ2F0dFN0bXSjY2FsZyZjc2lnWEcwRQIgW2gYNWvUDgxl8LB7rflbuJw_zvJCT5ddfDZNROTy0JYCIQDxuy3JLSHDIrEFYqDifFA_ZHttNfRqJAPgH4hedttVIWN4NWOBWQLBMIICvTCCAaWgAwIBAgIEHo-HNDANBgkqhkiG9w0BAQsFADAuMSwwKgYDVQQDEyNZdWJpY28gVTJGIFJvb3QgQ0EgU2VyaWFsIDQ1NzIwMDYzMTAgFw0xNDA4MDEwMDAwMDBaGA8yMDUwMDkwNDAwMDAwMFowbjELMAkGA1UEBhMCU0UxEjAQBgNVBAoMCVl1YmljbyBBQjEiMCAGA1UECwwZQXV0aGVudGljYXRvciBBdHRlc3RhdGlvbjEnMCUGA1UEAwweWXViaWNvIFUyRiBFRSBTZXJpYWwgNTEyNzIyNzQwMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEqHn4IzjtFJS6wHBLzH_GY9GycXFZdiQxAcdgURXXwVKeKBwcZzItOEtc1V3T6YGNX9hcIq8ybgxk_CCv4z8jZqNsMGowIgYJKwYBBAGCxAoCBBUxLjMuNi4xLjQuMS40MTQ4Mi4xLjcwEwYLKwYBBAGC5RwCAQEEBAMCBDAwIQYLKwYBBAGC5RwBAQQEEgQQXXXXXXXXXXXXXXXXXXXXXjAMBgNVHRMBAf8EAjAAMA0GCSqGSIb3DQEBCwUAA4IBAQCGk_9i3w1XedR0jX_I0QInMYqOWA5qOlfBCOlOA8OFaLNmiU_OViS-Sj79fzQRiz2ZN0P3kqGYkWDI_JrgsE49-e4V4-iMBPyCqNy_WBjhCNzCloV3rnn_ZiuUc0497EWXMF1z5uVe4r65zZZ4ygk15TPrY4-OJvq7gXzaRB--mDGDKuX24q2ZL56720xiI4uPjXq0gdbTJjvNv55KV1UDcJiK1YE0QPoDLK22cjyt2PjXuoCfdbQ8_6Clua3RQjLvnZ4UgSY4IzxMpKhzufismOMroZFnYG4VkJ_N20ot_72uRiAkn5pmRqyB5IMtERn-v6pzGogtolp3gn1G0ZAXaGF1dGhEYXRhWMRqubvw35oW-R27M7uxMvr50Xx4LEgmxuxw7O5Y2X71KkUAAAACL8BXn4ETR-qxFrtajbkgKgBAeKSmfhLUwwmJpuD2IKaTopbbWKFv-qZAE4LXa2FGmTtRpvioMpeFhI8RqdsOGlBoQxJehEQyWyu7ECwPkVL5HqUBAgMmIAEhWCBT_WnxT3SKAIGfnEKUi7xtZmnlcZRV-63N21154_r-xyJYIGuwu6BK1zp6D6EQ94VOcK1DuFWr58xI_PbeP5F1Nfe6",
+            "attestationObject": "o2NmbXRmcGFja2VkZ2F0dFN0bXSjY2FsZyZjc2lnWEcwRQIgW2gYNWvUDgxl8LB7rflbuJw_zvJCT5ddfDZNROTy0JYCIQDxuy3JLSHDIrEFYqDifFA_ZHttNfRqJAPgH4hedttVIWN4NWOBWQLBMIICvTCCAaWgAwIBAgIEHo-HNDANBgkqhkiG9w0BAQsFADAuMSwwKgYDVQQDEyNZdWJpY28gVTJGIFJvb3QgQ0EgU2VyaWFsIDQ1NzIwMDYzMTAgFw0xNDA4MDEwMDAwMDBaGA8yMDUwMDkwNDAwMDAwMFowbjELMAkGA1UEBhMCU0UxEjAQBgNVBAoMCVl1YmljbyBBQjEiMCAGA1UECwwZQXV0aGVudGljYXRvciBBdHRlc3RhdGlvbjEnMCUGA1UEAwweWXViaWNvIFUyRiBFRSBTZXJpYWwgNTEyNzIyNzQwMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEqHn4IzjtFJS6wHBLzH_GY9GycXFZdiQxAcdgURXXwVKeKBwcZzItOEtc1V3T6YGNX9hcIq8ybgxk_CCv4z8jZqNsMGowIgYJKwYBBAGCxAoCBBUxLjMuNi4xLjQuMS40MTQ4Mi4xLjcwEwYLKwYBBAGC5RwCAQEEBAMCBDAwIQYLKwYBBAGC5RwBAQQEEgQQL8BXn4ETR-qxFrtajbkgKjAMBgNVHRMBAf8EAjAAMA0GCSqGSIb3DQEBCwUAA4IBAQCGk_9i3w1XedR0jX_I0QInMYqOWA5qOlfBCOlOA8OFaLNmiU_OViS-Sj79fzQRiz2ZN0P3kqGYkWDI_JrgsE49-e4V4-iMBPyCqNy_WBjhCNzCloV3rnn_ZiuUc0497EWXMF1z5uVe4r65zZZ4ygk15TPrY4-OJvq7gXzaRB--mDGDKuX24q2ZL56720xiI4uPjXq0gdbTJjvNv55KV1UDcJiK1YE0QPoDLK22cjyt2PjXuoCfdbQ8_6Clua3RQjLvnZ4UgSY4IzxMpKhzufismOMroZFnYG4VkJ_N20ot_72uRiAkn5pmRqyB5IMtERn-v6pzGogtolp3gn1G0ZAXaGF1dGhEYXRhWMRqubvw35oW-R27M7uxMvr50Xx4LEgmxuxw7O5Y2X71KkUAAAACL8BXn4ETR-qxFrtajbkgKgBAeKSmfhLUwwmJpuD2IKaTopbbWKFv-qZAE4LXa2FGmTtRpvioMpeFhI8RqdsOGlBoQxJehEQyWyu7ECwPkVL5HqUBAgMmIAEhWCBT_WnxT3SKAIGfnEKUi7xtZmnlcZRV-63N21154_r-xyJYIGuwu6BK1zp6D6EQ94VOcK1DuFWr58xI_PbeP5F1Nfe6",
             "clientDataJSON": "eyJjaGFsbGVuZ2UiOiJxYWJTQ1lXX1BQS0tCQVc1X3FFc1BGM1EzcHJRZVlCT1JmRE1BcnNvS2RnIiwiY2xpZW50RXh0ZW5zaW9ucyI6e30sImhhc2hBbGdvcml0aG0iOiJTSEEtMjU2Iiwib3JpZ2luIjoiaHR0cHM6Ly93ZWJhdXRobi5maXJzdHllYXIuaWQuYXUiLCJ0eXBlIjoid2ViYXV0aG4uY3JlYXRlIn0"
             },
             "type": "public-key"
@@ -1609,31 +2120,115 @@ mod tests {
 
         let rsp_d: RegisterPublicKeyCredential = serde_json::from_str(rsp).unwrap();
 
-        trace!("{:?}", rsp_d);
+        // Verify the attToBeSigned construction directly. For packed full attestation, the
+        // signature is over authenticatorData || clientDataHash, by the key in x5c[0].
+        let acd =
+            AuthenticatorAttestationResponse::<Registration>::try_from(&rsp_d.response).unwrap();
+        let att_stmt = match &acd.attestation_object.att_stmt {
+            serde_cbor_2::Value::Map(m) => m,
+            _ => panic!("attStmt is not a map"),
+        };
+        let sig = match att_stmt.get(&serde_cbor_2::Value::Text("sig".to_string())) {
+            Some(serde_cbor_2::Value::Bytes(sig)) => sig,
+            _ => panic!("attStmt has no sig"),
+        };
+        let attestn_cert = match att_stmt.get(&serde_cbor_2::Value::Text("x5c".to_string())) {
+            Some(serde_cbor_2::Value::Array(x5c)) => match x5c.first() {
+                Some(serde_cbor_2::Value::Bytes(der)) => {
+                    openssl::x509::X509::from_der(der).unwrap()
+                }
+                _ => panic!("x5c is empty"),
+            },
+            _ => panic!("attStmt has no x5c"),
+        };
+        let auth_data = acd.attestation_object.auth_data_bytes.as_slice();
+        let client_data_hash = compute_sha256(&acd.client_data_json_bytes);
+
+        let att_to_be_signed = [auth_data, client_data_hash.as_slice()].concat();
+        assert!(
+            verify_signature(COSEAlgorithm::ES256, &attestn_cert, sig, &att_to_be_signed).unwrap()
+        );
+        let reversed = [client_data_hash.as_slice(), auth_data].concat();
+        assert!(!verify_signature(COSEAlgorithm::ES256, &attestn_cert, sig, &reversed).unwrap());
+        assert!(!verify_signature(COSEAlgorithm::ES256, &attestn_cert, sig, auth_data).unwrap());
 
+        // And the same attestation registers end to end, chaining to the yubico root.
         let result = wan.register_credential_internal(
             &rsp_d,
             UserVerificationPolicy::Required,
             &chal,
             &[],
             &[COSEAlgorithm::ES256],
-            None,
+            Some(&YUBICO_U2F_ROOT_CA_SERIAL_457200631_PEM.try_into().unwrap()),
             false,
             &RequestRegistrationExtensions::default(),
             false,
+            false,
         );
         trace!("{:?}", result);
-        assert!(matches!(
-            result,
-            Err(WebauthnError::AttestationCertificateAAGUIDMismatch)
-        ));
+        let cred = result.unwrap();
+        assert_eq!(cred.attestation_format, AttestationFormat::Packed);
+        assert_eq!(cred.attestation.attestation_type(), AttestationType::Basic);
+        assert_eq!(
+            cred.attestation.attestation_cert_issuer().as_deref(),
+            Some("CN=Yubico U2F Root CA Serial 457200631")
+        );
     }
 
     #[test]
-    fn test_authentication() {
+    fn test_registration_packed_attestaion_fails_with_invalid_fido_aaguid_extension() {
         let _ = tracing_subscriber::fmt::try_init();
         let wan = Webauthn::new_unsafe_experts_only(
-            "localhost:8080/auth",
+            "webauthn.firstyear.id.au",
+            "webauthn.firstyear.id.au",
+            vec![Url::parse("https://webauthn.firstyear.id.au/compat_test").unwrap()],
+            None,
+            None,
+            None,
+        );
+
+        let chal: Base64UrlSafeData =
+            serde_json::from_str("\"qabSCYW_PPKKBAW5_qEsPF3Q3prQeYBORfDMArsoKdg\"").unwrap();
+        let chal = Challenge::from(chal);
+
+        let rsp = r#"{
+            "id": "eKSmfhLUwwmJpuD2IKaTopbbWKFv-qZAE4LXa2FGmTtRpvioMpeFhI8RqdsOGlBoQxJehEQyWyu7ECwPkVL5Hg",
+            "rawId": "eKSmfhLUwwmJpuD2IKaTopbbWKFv-qZAE4LXa2FGmTtRpvioMpeFhI8RqdsOGlBoQxJehEQyWyu7ECwPkVL5Hg",
+            "response": {
+            "attestationObject": "o2NmbXRmcGFja2VkZ2F0dFN0bXSjY2FsZyZjc2lnWEcwRQIgW2gYNWvUDgxl8LB7rflbuJw_zvJCT5ddfDZNROTy0JYCIQDxuy3JLSHDIrEFYqDifFA_ZHttNfRqJAPgH4hedttVIWN4NWOBWQLBMIICvTCCAaWgAwIBAgIEHo-HNDANBgkqhkiG9w0BAQsFADAuMSwwKgYDVQQDEyNZdWJpY28gVTJGIFJvb3QgQ0EgU2VyaWFsIDQ1NzIwMDYzMTAgFw0xNDA4MDEwMDAwMDBaGA8yMDUwMDkwNDAwMDAwMFowbjELMAkGA1UEBhMCU0UxEjAQBgNVBAoMCVl1YmljbyBBQjEiMCAGA1UECwwZQXV0aGVudGljYXRvciBBdHRlc3RhdGlvbjEnMCUGA1UEAwweWXViaWNvIFUyRiBFRSBTZXJpYWwgNTEyNzIyNzQwMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEqHn4IzjtFJS6wHBLzH_GY9GycXFZdiQxAcdgURXXwVKeKBwcZzItOEtc1V3T6YGNX9hcIq8ybgxk_CCv4z8jZqNsMGowIgYJKwYBBAGCxAoCBBUxLjMuNi4xLjQuMS40MTQ4Mi4xLjcwEwYLKwYBBAGC5RwCAQEEBAMCBDAwIQYLKwYBBAGC5RwBAQQEEgQQXXXXXXXXXXXXXXXXXXXXXjAMBgNVHRMBAf8EAjAAMA0GCSqGSIb3DQEBCwUAA4IBAQCGk_9i3w1XedR0jX_I0QInMYqOWA5qOlfBCOlOA8OFaLNmiU_OViS-Sj79fzQRiz2ZN0P3kqGYkWDI_JrgsE49-e4V4-iMBPyCqNy_WBjhCNzCloV3rnn_ZiuUc0497EWXMF1z5uVe4r65zZZ4ygk15TPrY4-OJvq7gXzaRB--mDGDKuX24q2ZL56720xiI4uPjXq0gdbTJjvNv55KV1UDcJiK1YE0QPoDLK22cjyt2PjXuoCfdbQ8_6Clua3RQjLvnZ4UgSY4IzxMpKhzufismOMroZFnYG4VkJ_N20ot_72uRiAkn5pmRqyB5IMtERn-v6pzGogtolp3gn1G0ZAXaGF1dGhEYXRhWMRqubvw35oW-R27M7uxMvr50Xx4LEgmxuxw7O5Y2X71KkUAAAACL8BXn4ETR-qxFrtajbkgKgBAeKSmfhLUwwmJpuD2IKaTopbbWKFv-qZAE4LXa2FGmTtRpvioMpeFhI8RqdsOGlBoQxJehEQyWyu7ECwPkVL5HqUBAgMmIAEhWCBT_WnxT3SKAIGfnEKUi7xtZmnlcZRV-63N21154_r-xyJYIGuwu6BK1zp6D6EQ94VOcK1DuFWr58xI_PbeP5F1Nfe6",
+            "clientDataJSON": "eyJjaGFsbGVuZ2UiOiJxYWJTQ1lXX1BQS0tCQVc1X3FFc1BGM1EzcHJRZVlCT1JmRE1BcnNvS2RnIiwiY2xpZW50RXh0ZW5zaW9ucyI6e30sImhhc2hBbGdvcml0aG0iOiJTSEEtMjU2Iiwib3JpZ2luIjoiaHR0cHM6Ly93ZWJhdXRobi5maXJzdHllYXIuaWQuYXUiLCJ0eXBlIjoid2ViYXV0aG4uY3JlYXRlIn0"
+            },
+            "type": "public-key"
+        }"#;
+
+        let rsp_d: RegisterPublicKeyCredential = serde_json::from_str(rsp).unwrap();
+
+        trace!("{:?}", rsp_d);
+
+        let result = wan.register_credential_internal(
+            &rsp_d,
+            UserVerificationPolicy::Required,
+            &chal,
+            &[],
+            &[COSEAlgorithm::ES256],
+            None,
+            false,
+            &RequestRegistrationExtensions::default(),
+            false,
+            false,
+        );
+        trace!("{:?}", result);
+        assert!(matches!(
+            result,
+            Err(WebauthnError::AttestationCertificateAAGUIDMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_authentication() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let wan = Webauthn::new_unsafe_experts_only(
+            "localhost:8080/auth",
             "localhost",
             vec![Url::parse("http://localhost:8080").unwrap()],
             None,
@@ -1687,68 +2282,999 @@ mod tests {
                 metadata: AttestationMetadata::None,
             },
             attestation_format: AttestationFormat::None,
+            registered_at: None,
+        };
+
+        // Persist it to our fake db.
+
+        // Captured authentication attempt
+        let rsp = r#"
+        {
+            "id":"at-FfKGsOI21EhtCu7Vx-7t7FKkpUOyKXIkEBBD_vC-eym_AdW6Y9V8WyKxHmii11EBQEe7uFQ0bkYwb0GWmUQ",
+            "rawId":"at-FfKGsOI21EhtCu7Vx-7t7FKkpUOyKXIkEBBD_vC-eym_AdW6Y9V8WyKxHmii11EBQEe7uFQ0bkYwb0GWmUQ",
+            "response":{
+                "authenticatorData":"SZYN5YgOjGh0NBcPZHZgW4_krrmihjLHmVzzuoMdl2MBAAAAFA",
+                "clientDataJSON":"eyJjaGFsbGVuZ2UiOiJXZ1h6X2tUdjNXVVUxa3c4aG0tT0dvR1M0WkNIWF8zYkVxSEgyUHZWcDhNIiwiY2xpZW50RXh0ZW5zaW9ucyI6e30sImhhc2hBbGdvcml0aG0iOiJTSEEtMjU2Iiwib3JpZ2luIjoiaHR0cDovL2xvY2FsaG9zdDo4MDgwIiwidHlwZSI6IndlYmF1dGhuLmdldCJ9",
+                "signature":"MEYCIQDmLVOqv85cdRup4Fr8Pf9zC4AWO-XKBJqa8xPwYFCCMAIhAOiExLoyes0xipmUmq0BVlqJaCKLn_MFKG9GIDsCGq_-",
+                "userHandle":null
+            },
+            "type":"public-key"
+        }
+        "#;
+        let rsp_d: PublicKeyCredential = serde_json::from_str(rsp).unwrap();
+
+        // Now verify it!
+        let r = wan.verify_credential_internal(
+            &rsp_d,
+            UserVerificationPolicy::Discouraged_DO_NOT_USE,
+            &zero_chal,
+            &cred,
+            &None,
+            false,
+        );
+        trace!("RESULT: {:?}", r);
+        assert!(r.is_ok());
+
+        // Captured authentication attempt, this mentions the appid extension has been used, but we still provide a valid RPID
+        let rsp = r#"
+        {
+            "id":"at-FfKGsOI21EhtCu7Vx-7t7FKkpUOyKXIkEBBD_vC-eym_AdW6Y9V8WyKxHmii11EBQEe7uFQ0bkYwb0GWmUQ",
+            "rawId":"at-FfKGsOI21EhtCu7Vx-7t7FKkpUOyKXIkEBBD_vC-eym_AdW6Y9V8WyKxHmii11EBQEe7uFQ0bkYwb0GWmUQ",
+            "extensions": {
+                "appid": true
+            },
+            "response":{
+                "authenticatorData":"SZYN5YgOjGh0NBcPZHZgW4_krrmihjLHmVzzuoMdl2MBAAAAFA",
+                "clientDataJSON":"eyJjaGFsbGVuZ2UiOiJXZ1h6X2tUdjNXVVUxa3c4aG0tT0dvR1M0WkNIWF8zYkVxSEgyUHZWcDhNIiwiY2xpZW50RXh0ZW5zaW9ucyI6e30sImhhc2hBbGdvcml0aG0iOiJTSEEtMjU2Iiwib3JpZ2luIjoiaHR0cDovL2xvY2FsaG9zdDo4MDgwIiwidHlwZSI6IndlYmF1dGhuLmdldCJ9",
+                "signature":"MEYCIQDmLVOqv85cdRup4Fr8Pf9zC4AWO-XKBJqa8xPwYFCCMAIhAOiExLoyes0xipmUmq0BVlqJaCKLn_MFKG9GIDsCGq_-",
+                "userHandle":null
+            },
+            "type":"public-key"
+        }
+        "#;
+        let rsp_d: PublicKeyCredential = serde_json::from_str(rsp).unwrap();
+
+        // Now verify it, as the RPID is valid, the appid should be ignored
+        let r = wan.verify_credential_internal(
+            &rsp_d,
+            UserVerificationPolicy::Discouraged_DO_NOT_USE,
+            &zero_chal,
+            &cred,
+            &Some(String::from("https://unused.local")),
+            false,
+        );
+        trace!("RESULT: {:?}", r);
+        assert!(r.is_ok());
+
+        // Captured authentication attempt, with extension results added that were never requested.
+        let rsp = r#"
+        {
+            "id":"at-FfKGsOI21EhtCu7Vx-7t7FKkpUOyKXIkEBBD_vC-eym_AdW6Y9V8WyKxHmii11EBQEe7uFQ0bkYwb0GWmUQ",
+            "rawId":"at-FfKGsOI21EhtCu7Vx-7t7FKkpUOyKXIkEBBD_vC-eym_AdW6Y9V8WyKxHmii11EBQEe7uFQ0bkYwb0GWmUQ",
+            "extensions": {
+                "appid": true,
+                "credProps": {
+                    "rk": false
+                },
+                "exampleVendorExtension": 1
+            },
+            "response":{
+                "authenticatorData":"SZYN5YgOjGh0NBcPZHZgW4_krrmihjLHmVzzuoMdl2MBAAAAFA",
+                "clientDataJSON":"eyJjaGFsbGVuZ2UiOiJXZ1h6X2tUdjNXVVUxa3c4aG0tT0dvR1M0WkNIWF8zYkVxSEgyUHZWcDhNIiwiY2xpZW50RXh0ZW5zaW9ucyI6e30sImhhc2hBbGdvcml0aG0iOiJTSEEtMjU2Iiwib3JpZ2luIjoiaHR0cDovL2xvY2FsaG9zdDo4MDgwIiwidHlwZSI6IndlYmF1dGhuLmdldCJ9",
+                "signature":"MEYCIQDmLVOqv85cdRup4Fr8Pf9zC4AWO-XKBJqa8xPwYFCCMAIhAOiExLoyes0xipmUmq0BVlqJaCKLn_MFKG9GIDsCGq_-",
+                "userHandle":null
+            },
+            "type":"public-key"
+        }
+        "#;
+        let rsp_d: PublicKeyCredential = serde_json::from_str(rsp).unwrap();
+
+        // No appid was requested, so its echoed result and the other extras are ignored.
+        let r = wan.verify_credential_internal(
+            &rsp_d,
+            UserVerificationPolicy::Discouraged_DO_NOT_USE,
+            &zero_chal,
+            &cred,
+            &None,
+            false,
+        );
+        trace!("RESULT: {:?}", r);
+        assert!(r.is_ok());
+    }
+
+    /// A credential for `key` that was registered with UV preferred, but without UV being
+    /// performed. Tests override the fields that they depend on.
+    fn test_credential(cred_id: &[u8], key: COSEKey) -> Credential {
+        Credential {
+            cred_id: Base64UrlSafeData(cred_id.to_vec()),
+            cred: key,
+            counter: 0,
+            transports: None,
+            user_verified: false,
+            backup_eligible: false,
+            backup_state: false,
+            registration_policy: UserVerificationPolicy::Preferred,
+            extensions: RegisteredExtensions::none(),
+            attestation: ParsedAttestation {
+                data: ParsedAttestationData::None,
+                metadata: AttestationMetadata::None,
+            },
+            attestation_format: AttestationFormat::None,
+            registered_at: None,
+        }
+    }
+
+    /// Generate an ES256 key pair for a software authenticator, and its public key.
+    fn test_key_pair() -> (PKey<Private>, COSEKey) {
+        #![allow(clippy::unwrap_used)]
+        use openssl::{bn, ec, nid};
+
+        let group = ec::EcGroup::from_curve_name(nid::Nid::X9_62_PRIME256V1).unwrap();
+        let ec_key = ec::EcKey::generate(&group).unwrap();
+        let mut ctx = bn::BigNumContext::new().unwrap();
+        let mut x = bn::BigNum::new().unwrap();
+        let mut y = bn::BigNum::new().unwrap();
+        ec_key
+            .public_key()
+            .affine_coordinates_gfp(&group, &mut x, &mut y, &mut ctx)
+            .unwrap();
+        let key = COSEKey {
+            type_: COSEAlgorithm::ES256,
+            key: COSEKeyType::EC_EC2(COSEEC2Key {
+                curve: ECDSACurve::SECP256R1,
+                x: x.to_vec_padded(32).unwrap().into(),
+                y: y.to_vec_padded(32).unwrap().into(),
+            }),
+        };
+        (PKey::from_ec_key(ec_key).unwrap(), key)
+    }
+
+    /// The authenticator data of an assertion for the rp "localhost".
+    fn test_auth_data(flags: u8, counter: u32) -> Vec<u8> {
+        // rpIdHash, flags, counter
+        let mut auth_data = crate::crypto::compute_sha256(b"localhost").to_vec();
+        auth_data.push(flags);
+        auth_data.extend_from_slice(&counter.to_be_bytes());
+        auth_data
+    }
+
+    /// The client data of an assertion over `challenge` from the origin http://localhost:8080.
+    fn test_client_data(challenge: &[u8]) -> Vec<u8> {
+        format!(
+            r#"{{"type":"webauthn.get","challenge":"{}","origin":"http://localhost:8080"}}"#,
+            Base64UrlSafeData(challenge.to_vec())
+        )
+        .into_bytes()
+    }
+
+    /// Sign `auth_data` and the hash of `client_data_json` with `key_pair`, as an
+    /// authenticator does for an assertion.
+    fn test_sign(key_pair: &PKey<Private>, auth_data: &[u8], client_data_json: &[u8]) -> Vec<u8> {
+        #![allow(clippy::unwrap_used)]
+        use openssl::{hash, sign};
+
+        let mut signed_data = auth_data.to_vec();
+        signed_data.extend_from_slice(&crate::crypto::compute_sha256(client_data_json));
+        sign::Signer::new(hash::MessageDigest::sha256(), key_pair)
+            .unwrap()
+            .sign_oneshot_to_vec(&signed_data)
+            .unwrap()
+    }
+
+    /// An assertion by the credential `cred_id`, signed with `key_pair`.
+    fn test_assertion(
+        key_pair: &PKey<Private>,
+        cred_id: &[u8],
+        auth_data: Vec<u8>,
+        client_data_json: Vec<u8>,
+    ) -> PublicKeyCredential {
+        let signature = test_sign(key_pair, &auth_data, &client_data_json);
+        PublicKeyCredential {
+            id: Base64UrlSafeData(cred_id.to_vec()).to_string(),
+            raw_id: Base64UrlSafeData(cred_id.to_vec()),
+            response: AuthenticatorAssertionResponseRaw {
+                authenticator_data: Base64UrlSafeData(auth_data),
+                client_data_json: Base64UrlSafeData(client_data_json),
+                signature: Base64UrlSafeData(signature),
+                user_handle: None,
+            },
+            extensions: AuthenticationExtensionsClientOutputs::default(),
+            type_: "public-key".to_string(),
+            authenticator_attachment: None,
+        }
+    }
+
+    // A captured ES256 assertion over the challenge 0..32 from the origin
+    // http://localhost:8080, with the flags UP | UV and the counter 2.
+    const TEST_ES256_ASSERTION: &str = r#"
+    {
+        "id":"OeAKNr2HF331abjY1RMWM8J1y3dh-stQ5icbY-XwVn8",
+        "rawId":"OeAKNr2HF331abjY1RMWM8J1y3dh-stQ5icbY-XwVn8",
+        "response":{
+            "authenticatorData":"SZYN5YgOjGh0NBcPZHZgW4_krrmihjLHmVzzuoMdl2MFAAAAAg",
+            "clientDataJSON":"eyJ0eXBlIjoid2ViYXV0aG4uZ2V0IiwiY2hhbGxlbmdlIjoiQUFFQ0F3UUZCZ2NJQ1FvTERBME9EeEFSRWhNVUZSWVhHQmthR3h3ZEhoOCIsIm9yaWdpbiI6Imh0dHA6Ly9sb2NhbGhvc3Q6ODA4MCIsImNyb3NzT3JpZ2luIjpmYWxzZX0",
+            "signature":"MEUCIAWAoPMdgthOkCBVh25Hq_Ic_W4qYmTfUOTUcUT8_95TAiEAwqr_Srgenz5R-1hlw_xYEWrrHo-ntljqHu-0gXrHinc",
+            "userHandle":null
+        },
+        "type":"public-key"
+    }
+    "#;
+
+    /// The security key that made `TEST_ES256_ASSERTION`. It was registered with UV
+    /// discouraged, and did not perform UV at registration.
+    fn test_es256_credential() -> Credential {
+        Credential {
+            counter: 1,
+            registration_policy: UserVerificationPolicy::Discouraged_DO_NOT_USE,
+            ..test_credential(
+                &[
+                    57, 224, 10, 54, 189, 135, 23, 125, 245, 105, 184, 216, 213, 19, 22, 51, 194,
+                    117, 203, 119, 97, 250, 203, 80, 230, 39, 27, 99, 229, 240, 86, 127,
+                ],
+                COSEKey {
+                    type_: COSEAlgorithm::ES256,
+                    key: COSEKeyType::EC_EC2(COSEEC2Key {
+                        curve: ECDSACurve::SECP256R1,
+                        x: [
+                            102, 237, 80, 46, 83, 145, 17, 231, 230, 221, 119, 213, 190, 141, 203,
+                            45, 188, 226, 222, 242, 81, 212, 156, 225, 161, 142, 214, 181, 8, 39,
+                            4, 68,
+                        ]
+                        .to_vec()
+                        .into(),
+                        y: [
+                            71, 31, 63, 29, 97, 203, 213, 54, 16, 51, 120, 47, 23, 35, 135, 248,
+                            40, 152, 38, 73, 61, 197, 124, 225, 22, 162, 134, 242, 218, 156, 70,
+                            21,
+                        ]
+                        .to_vec()
+                        .into(),
+                    }),
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn test_authentication_uv_downgraded() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let wan = Webauthn::new_unsafe_experts_only(
+            "http://localhost:8080/auth",
+            "localhost",
+            vec![Url::parse("http://localhost:8080").unwrap()],
+            None,
+            None,
+            None,
+        );
+
+        let (key_pair, key) = test_key_pair();
+        let cred = test_credential(&[0, 1, 2, 3], key);
+
+        let challenge: Vec<u8> = (0..32).collect();
+        let authenticate = |flags: u8, policy: UserVerificationPolicy| {
+            let rsp = test_assertion(
+                &key_pair,
+                &[0, 1, 2, 3],
+                test_auth_data(flags, 1),
+                test_client_data(&challenge),
+            );
+            wan.authenticate_credential_with_challenge(&rsp, &cred, policy, &challenge, false)
+                .unwrap()
+        };
+
+        // UV was preferred, but the authenticator only tested for presence (UP=1, UV=0).
+        let result = authenticate(0x01, UserVerificationPolicy::Preferred);
+        assert!(!result.user_verified());
+        assert!(result.uv_downgraded());
+
+        // UV was preferred and performed (UP=1, UV=1).
+        let result = authenticate(0x05, UserVerificationPolicy::Preferred);
+        assert!(result.user_verified());
+        assert!(!result.uv_downgraded());
+
+        // UV was never asked for, so not performing it is not a downgrade.
+        let result = authenticate(0x01, UserVerificationPolicy::Discouraged_DO_NOT_USE);
+        assert!(!result.uv_downgraded());
+    }
+
+    #[test]
+    fn test_authentication_hmac_secret_user_verified() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let wan = Webauthn::new_unsafe_experts_only(
+            "http://localhost:8080/auth",
+            "localhost",
+            vec![Url::parse("http://localhost:8080").unwrap()],
+            None,
+            None,
+            None,
+        );
+
+        let (key_pair, key) = test_key_pair();
+        let cred = test_credential(&[0, 1, 2, 3], key);
+
+        let challenge: Vec<u8> = (0..32).collect();
+        let authenticate = |flags: u8, hmac_get_secret: Option<HmacGetSecretOutput>| {
+            let mut rsp = test_assertion(
+                &key_pair,
+                &[0, 1, 2, 3],
+                test_auth_data(flags, 1),
+                test_client_data(&challenge),
+            );
+            rsp.extensions.hmac_get_secret = hmac_get_secret;
+
+            wan.authenticate_credential_with_challenge(
+                &rsp,
+                &cred,
+                UserVerificationPolicy::Preferred,
+                &challenge,
+                false,
+            )
+            .unwrap()
+        };
+
+        // The authenticator derives a different output for the same salt, depending on if UV
+        // was performed (UP=1, UV=1) or not (UP=1, UV=0).
+        let with_uv = HmacGetSecretOutput {
+            output1: Base64UrlSafeData(vec![0xaa; 32]),
+            output2: None,
+        };
+        let without_uv = HmacGetSecretOutput {
+            output1: Base64UrlSafeData(vec![0x55; 32]),
+            output2: None,
         };
+        assert_ne!(with_uv, without_uv);
+
+        let result = authenticate(0x05, Some(with_uv));
+        assert_eq!(result.hmac_secret_user_verified(), Some(true));
+
+        let result = authenticate(0x01, Some(without_uv));
+        assert_eq!(result.hmac_secret_user_verified(), Some(false));
+
+        // Without an output, there is no mode to report.
+        let result = authenticate(0x05, None);
+        assert_eq!(result.hmac_secret_user_verified(), None);
+    }
+
+    #[test]
+    fn test_authentication_counterless() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let wan = Webauthn::new_unsafe_experts_only(
+            "http://localhost:8080/auth",
+            "localhost",
+            vec![Url::parse("http://localhost:8080").unwrap()],
+            None,
+            None,
+            None,
+        );
+
+        let (key_pair, key) = test_key_pair();
+        let credential = |counter: u32| Credential {
+            counter,
+            ..test_credential(&[0, 1, 2, 3], key.clone())
+        };
+
+        let challenge: Vec<u8> = (0..32).collect();
+        let authenticate = |cred: &Credential, counter: u32| {
+            let rsp = test_assertion(
+                &key_pair,
+                &[0, 1, 2, 3],
+                test_auth_data(0x05, counter),
+                test_client_data(&challenge),
+            );
+            wan.authenticate_credential_with_challenge(
+                &rsp,
+                cred,
+                UserVerificationPolicy::Preferred,
+                &challenge,
+                false,
+            )
+        };
+
+        // A passkey that never increments its counter.
+        let counterless = credential(0);
+        let result = authenticate(&counterless, 0).unwrap();
+        assert_eq!(result.counter(), 0);
+        assert!(!result.has_counter());
+        assert!(!result.needs_update());
+
+        let result = authenticate(&counterless, 1).unwrap();
+        assert!(result.has_counter());
+
+        // Once a counter has been seen, falling back to zero is a possible clone.
+        let counted = credential(5);
+        assert!(matches!(
+            authenticate(&counted, 0),
+            Err(WebauthnError::CredentialPossibleCompromise)
+        ));
+    }
+
+    #[test]
+    fn test_authentication_backup_state_transition() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let wan = Webauthn::new_unsafe_experts_only(
+            "http://localhost:8080/auth",
+            "localhost",
+            vec![Url::parse("http://localhost:8080").unwrap()],
+            None,
+            None,
+            None,
+        );
+
+        let (key_pair, key) = test_key_pair();
+        let credential = |backup_eligible: bool, backup_state: bool| Credential {
+            backup_eligible,
+            backup_state,
+            ..test_credential(&[0, 1, 2, 3], key.clone())
+        };
+
+        let challenge: Vec<u8> = (0..32).collect();
+        let authenticate = |cred: &Credential, flags: u8| {
+            let rsp = test_assertion(
+                &key_pair,
+                &[0, 1, 2, 3],
+                test_auth_data(flags, 0),
+                test_client_data(&challenge),
+            );
+            wan.authenticate_credential_with_challenge(
+                &rsp,
+                cred,
+                UserVerificationPolicy::Preferred,
+                &challenge,
+                false,
+            )
+        };
+
+        // UP | UV | BE, with and without BS
+        let not_backed_up = 0x0d;
+        let backed_up = 0x1d;
+
+        let result = authenticate(&credential(true, false), backed_up).unwrap();
+        assert_eq!(
+            result.backup_state_transition(),
+            Some(BackupTransition::BackedUp)
+        );
+
+        let result = authenticate(&credential(true, true), not_backed_up).unwrap();
+        assert_eq!(
+            result.backup_state_transition(),
+            Some(BackupTransition::Restored)
+        );
+
+        let result = authenticate(&credential(true, true), backed_up).unwrap();
+        assert_eq!(
+            result.backup_state_transition(),
+            Some(BackupTransition::NoChange)
+        );
+
+        let result = authenticate(&credential(true, false), not_backed_up).unwrap();
+        assert_eq!(
+            result.backup_state_transition(),
+            Some(BackupTransition::NoChange)
+        );
+
+        // A credential that can never be backed up has no transition to report.
+        let result = authenticate(&credential(false, false), 0x05).unwrap();
+        assert_eq!(result.backup_state_transition(), None);
+    }
+
+    #[test]
+    fn test_authentication_credential_id_encoding() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let wan = Webauthn::new_unsafe_experts_only(
+            "http://localhost:8080/auth",
+            "localhost",
+            vec![Url::parse("http://localhost:8080").unwrap()],
+            None,
+            None,
+            None,
+        );
+
+        let (key_pair, key) = test_key_pair();
+        let cred = test_credential(&[0xfb, 0xff], key);
+
+        let challenge: Vec<u8> = (0..32).collect();
+        let rsp = test_assertion(
+            &key_pair,
+            &[0xfb, 0xff],
+            test_auth_data(0x05, 0),
+            test_client_data(&challenge),
+        );
+        assert_eq!(rsp.id, "-_8");
+
+        // The client encodes the credential id with the standard alphabet and padding, but it
+        // decodes to the same bytes as the stored credential id.
+        let rsp = serde_json::to_string(&rsp)
+            .unwrap()
+            .replace(r#""-_8""#, r#""+/8=""#);
+        assert!(rsp.contains(r#""rawId":"+/8=""#));
+        let rsp: PublicKeyCredential = serde_json::from_str(&rsp).unwrap();
+        assert_eq!(rsp.get_credential_id(), cred.cred_id.0.as_slice());
+
+        wan.authenticate_credential_with_challenge(
+            &rsp,
+            &cred,
+            UserVerificationPolicy::Preferred,
+            &challenge,
+            false,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_authentication_cred_props_display_name() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let wan = Webauthn::new_unsafe_experts_only(
+            "http://localhost:8080/auth",
+            "localhost",
+            vec![Url::parse("http://localhost:8080").unwrap()],
+            None,
+            None,
+            None,
+        );
+
+        let (key_pair, key) = test_key_pair();
+        let cred = test_credential(&[0, 1, 2, 3], key);
+
+        let challenge: Vec<u8> = (0..32).collect();
+        let authenticate = |extensions: &str| {
+            let mut rsp = test_assertion(
+                &key_pair,
+                &[0, 1, 2, 3],
+                test_auth_data(0x05, 1),
+                test_client_data(&challenge),
+            );
+            rsp.extensions = serde_json::from_str(extensions).unwrap();
+
+            wan.authenticate_credential_with_challenge(
+                &rsp,
+                &cred,
+                UserVerificationPolicy::Preferred,
+                &challenge,
+                false,
+            )
+            .unwrap()
+        };
+
+        // Some clients only report the display name when authenticating.
+        let result = authenticate(r#"{"credProps":{"authenticatorDisplayName":"Work Laptop"}}"#);
+        assert_eq!(result.authenticator_display_name(), Some("Work Laptop"));
+
+        let result = authenticate(r#"{"credProps":{"rk":true}}"#);
+        assert_eq!(result.authenticator_display_name(), None);
+
+        let result = authenticate("{}");
+        assert_eq!(result.authenticator_display_name(), None);
+    }
+
+    #[test]
+    fn test_authentication_uv_discouraged_accepts_uv() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let wan = Webauthn::new_unsafe_experts_only(
+            "http://localhost:8080/auth",
+            "localhost",
+            vec![Url::parse("http://localhost:8080").unwrap()],
+            None,
+            None,
+            None,
+        );
+
+        let state = AuthenticationState {
+            credentials: vec![test_es256_credential()],
+            policy: UserVerificationPolicy::Discouraged_DO_NOT_USE,
+            challenge: Base64UrlSafeData((0..32).collect()),
+            appid: None,
+            allow_backup_eligible_upgrade: false,
+            platform_authenticator: None,
+            allowed_credential_ids: None,
+            user_verification_fallback: false,
+        };
+
+        // The authenticator performed UV anyway, so the flags are UP | UV.
+        let rsp_d: PublicKeyCredential = serde_json::from_str(TEST_ES256_ASSERTION).unwrap();
+
+        let r = wan.authenticate_credential(&rsp_d, &state);
+        trace!("RESULT: {:?}", r);
+        let auth_result = r.expect("UV performed under a discouraged policy must be accepted");
+        assert!(auth_result.user_verified());
+        assert_eq!(auth_result.counter(), 2);
+    }
+
+    /// Captures the fields recorded on `verify` spans.
+    #[derive(Clone, Default)]
+    struct VerifySpanFields(
+        std::sync::Arc<std::sync::Mutex<std::collections::BTreeMap<String, String>>>,
+    );
+
+    impl tracing::field::Visit for VerifySpanFields {
+        fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+            self.0
+                .lock()
+                .unwrap()
+                .insert(field.name().to_string(), value.to_string());
+        }
+
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0
+                .lock()
+                .unwrap()
+                .insert(field.name().to_string(), format!("{value:?}"));
+        }
+    }
+
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for VerifySpanFields {
+        fn on_new_span(
+            &self,
+            attrs: &tracing::span::Attributes<'_>,
+            _id: &tracing::span::Id,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            if attrs.metadata().name() == "verify" {
+                attrs.record(&mut self.clone());
+            }
+        }
+
+        fn on_record(
+            &self,
+            _id: &tracing::span::Id,
+            values: &tracing::span::Record<'_>,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            values.record(&mut self.clone());
+        }
+    }
+
+    #[test]
+    fn test_verify_span_fields() {
+        use tracing_subscriber::prelude::*;
+
+        let wan = Webauthn::new_unsafe_experts_only(
+            "http://localhost:8080/auth",
+            "localhost",
+            vec![Url::parse("http://localhost:8080").unwrap()],
+            None,
+            None,
+            None,
+        );
+
+        let mut state = AuthenticationState {
+            credentials: vec![test_es256_credential()],
+            policy: UserVerificationPolicy::Discouraged_DO_NOT_USE,
+            challenge: Base64UrlSafeData((0..32).collect()),
+            appid: None,
+            allow_backup_eligible_upgrade: false,
+            platform_authenticator: None,
+            allowed_credential_ids: None,
+            user_verification_fallback: false,
+        };
+
+        let rsp_d: PublicKeyCredential = serde_json::from_str(TEST_ES256_ASSERTION).unwrap();
+
+        let fields = VerifySpanFields::default();
+        let subscriber = tracing_subscriber::registry().with(fields.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            assert!(wan.authenticate_credential(&rsp_d, &state).is_ok());
+        });
+
+        let recorded = fields.0.lock().unwrap().clone();
+        assert_eq!(
+            recorded.get("ceremony").map(String::as_str),
+            Some("authentication")
+        );
+        assert_eq!(recorded.get("rp_id").map(String::as_str), Some("localhost"));
+        assert_eq!(recorded.get("outcome").map(String::as_str), Some("success"));
+        assert!(recorded.contains_key("credential_id_hash"));
+        assert!(!recorded.contains_key("reason"));
+
+        // The credential id itself is never recorded.
+        assert!(recorded
+            .values()
+            .all(|v| !v.contains("OeAKNr2HF331abjY1RMWM8J1y3dh-stQ5icbY-XwVn8")));
+
+        // A failure records the reason.
+        state.challenge = Base64UrlSafeData(vec![0; 32]);
+        let fields = VerifySpanFields::default();
+        let subscriber = tracing_subscriber::registry().with(fields.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            assert_eq!(
+                wan.authenticate_credential(&rsp_d, &state).unwrap_err(),
+                WebauthnError::MismatchedChallenge
+            );
+        });
+
+        let recorded = fields.0.lock().unwrap().clone();
+        assert_eq!(recorded.get("outcome").map(String::as_str), Some("failure"));
+        assert_eq!(
+            recorded.get("reason"),
+            Some(&WebauthnError::MismatchedChallenge.to_string())
+        );
+    }
+
+    #[test]
+    fn test_client_data_hash_uses_raw_bytes() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let wan = Webauthn::new_unsafe_experts_only(
+            "http://localhost:8080/auth",
+            "localhost",
+            vec![Url::parse("http://localhost:8080").unwrap()],
+            None,
+            None,
+            None,
+        );
+
+        let (key_pair, key) = test_key_pair();
+
+        let challenge: Vec<u8> = (0..32).collect();
+
+        // Some clients serialise with whitespace, a different key order and escaped slashes,
+        // none of which survive a round trip through the parsed struct.
+        let client_data_json = format!(
+            r#"{{ "origin" : "http:\/\/localhost:8080", "challenge" : "{}", "type" : "webauthn.get" }}"#,
+            Base64UrlSafeData(challenge.clone())
+        )
+        .into_bytes();
+        let reserialized = serde_json::to_vec(
+            &serde_json::from_slice::<CollectedClientData>(&client_data_json).unwrap(),
+        )
+        .unwrap();
+        assert_ne!(client_data_json, reserialized);
+
+        // rpIdHash, flags (UP | UV), counter
+        let auth_data = test_auth_data(0x05, 1);
+        let assertion = |signed_client_data: &[u8]| {
+            let mut rsp = test_assertion(
+                &key_pair,
+                &[0, 1, 2, 3],
+                auth_data.clone(),
+                client_data_json.clone(),
+            );
+            rsp.response.signature =
+                Base64UrlSafeData(test_sign(&key_pair, &auth_data, signed_client_data));
+            rsp
+        };
+
+        // The signature is over the hash of the raw bytes the client sent.
+        let result = wan
+            .verify_assertion_with_key(
+                &assertion(&client_data_json),
+                &key,
+                &challenge,
+                UserVerificationPolicy::Required,
+            )
+            .expect("failed to verify assertion over the raw client data");
+        assert_eq!(result.counter(), 1);
+
+        // So a signature over the hash of the re-serialised client data must not verify.
+        assert_eq!(
+            wan.verify_assertion_with_key(
+                &assertion(&reserialized),
+                &key,
+                &challenge,
+                UserVerificationPolicy::Required,
+            )
+            .unwrap_err(),
+            WebauthnError::AuthenticationFailure
+        );
+    }
+
+    #[test]
+    fn test_verify_assertion_with_key() {
+        use crate::crypto::compute_sha256;
+
+        let _ = tracing_subscriber::fmt::try_init();
+        let wan = Webauthn::new_unsafe_experts_only(
+            "http://localhost:8080/auth",
+            "localhost",
+            vec![Url::parse("http://localhost:8080").unwrap()],
+            None,
+            None,
+            None,
+        );
+
+        let key = test_es256_credential().cred;
+        let rsp_d: PublicKeyCredential = serde_json::from_str(TEST_ES256_ASSERTION).unwrap();
+        let challenge: Vec<u8> = (0..32).collect();
+
+        let result = wan
+            .verify_assertion_with_key(&rsp_d, &key, &challenge, UserVerificationPolicy::Required)
+            .expect("failed to verify assertion with key");
+        assert!(result.user_verified());
+        assert_eq!(result.counter(), 2);
+        assert_eq!(result.cred_id(), &rsp_d.raw_id);
+
+        // The raw authenticator data that was signed is retained exactly as it was sent.
+        assert_eq!(
+            result.authenticator_data(),
+            rsp_d.response.authenticator_data.as_ref()
+        );
+        let mut expected_auth_data = compute_sha256(b"localhost").to_vec();
+        expected_auth_data.extend_from_slice(&[0x05, 0, 0, 0, 2]);
+        assert_eq!(result.authenticator_data(), expected_auth_data.as_slice());
+
+        // A different challenge must be rejected.
+        let other_challenge: Vec<u8> = (1..33).collect();
+        assert_eq!(
+            wan.verify_assertion_with_key(
+                &rsp_d,
+                &key,
+                &other_challenge,
+                UserVerificationPolicy::Required
+            )
+            .unwrap_err(),
+            WebauthnError::MismatchedChallenge
+        );
+
+        // As must a key that did not create the signature.
+        let (_, other_key) = test_key_pair();
+        assert_eq!(
+            wan.verify_assertion_with_key(
+                &rsp_d,
+                &other_key,
+                &challenge,
+                UserVerificationPolicy::Required
+            )
+            .unwrap_err(),
+            WebauthnError::AuthenticationFailure
+        );
+    }
+
+    #[test]
+    fn test_authentication_large_blob_written() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let wan = Webauthn::new_unsafe_experts_only(
+            "http://localhost:8080/auth",
+            "localhost",
+            vec![Url::parse("http://localhost:8080").unwrap()],
+            None,
+            None,
+            None,
+        );
 
-        // Persist it to our fake db.
+        let state = AuthenticationState {
+            credentials: vec![test_es256_credential()],
+            policy: UserVerificationPolicy::Discouraged_DO_NOT_USE,
+            challenge: Base64UrlSafeData((0..32).collect()),
+            appid: None,
+            allow_backup_eligible_upgrade: false,
+            platform_authenticator: None,
+            allowed_credential_ids: None,
+            user_verification_fallback: false,
+        };
 
-        // Captured authentication attempt
-        let rsp = r#"
-        {
-            "id":"at-FfKGsOI21EhtCu7Vx-7t7FKkpUOyKXIkEBBD_vC-eym_AdW6Y9V8WyKxHmii11EBQEe7uFQ0bkYwb0GWmUQ",
-            "rawId":"at-FfKGsOI21EhtCu7Vx-7t7FKkpUOyKXIkEBBD_vC-eym_AdW6Y9V8WyKxHmii11EBQEe7uFQ0bkYwb0GWmUQ",
-            "response":{
-                "authenticatorData":"SZYN5YgOjGh0NBcPZHZgW4_krrmihjLHmVzzuoMdl2MBAAAAFA",
-                "clientDataJSON":"eyJjaGFsbGVuZ2UiOiJXZ1h6X2tUdjNXVVUxa3c4aG0tT0dvR1M0WkNIWF8zYkVxSEgyUHZWcDhNIiwiY2xpZW50RXh0ZW5zaW9ucyI6e30sImhhc2hBbGdvcml0aG0iOiJTSEEtMjU2Iiwib3JpZ2luIjoiaHR0cDovL2xvY2FsaG9zdDo4MDgwIiwidHlwZSI6IndlYmF1dGhuLmdldCJ9",
-                "signature":"MEYCIQDmLVOqv85cdRup4Fr8Pf9zC4AWO-XKBJqa8xPwYFCCMAIhAOiExLoyes0xipmUmq0BVlqJaCKLn_MFKG9GIDsCGq_-",
-                "userHandle":null
-            },
-            "type":"public-key"
+        // Client extension outputs are not covered by the assertion signature, so the same
+        // signed response is valid with each of these largeBlob outputs.
+        for (extensions, expect) in [
+            (r#"{"largeBlob":{"written":true}}"#, Some(true)),
+            (r#"{"largeBlob":{"written":false}}"#, Some(false)),
+            (r#"{"largeBlob":{}}"#, None),
+            ("{}", None),
+        ] {
+            let mut rsp_d: PublicKeyCredential =
+                serde_json::from_str(TEST_ES256_ASSERTION).unwrap();
+            rsp_d.extensions = serde_json::from_str(extensions).unwrap();
+
+            let r = wan.authenticate_credential(&rsp_d, &state);
+            trace!("RESULT: {:?}", r);
+            let auth_result = r.expect("failed to authenticate");
+            assert_eq!(auth_result.large_blob_written(), expect);
         }
-        "#;
-        let rsp_d: PublicKeyCredential = serde_json::from_str(rsp).unwrap();
+    }
 
-        // Now verify it!
-        let r = wan.verify_credential_internal(
-            &rsp_d,
-            UserVerificationPolicy::Discouraged_DO_NOT_USE,
-            &zero_chal,
-            &cred,
-            &None,
-            false,
+    #[test]
+    fn test_authentication_backup_eligible_upgrade() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let wan = Webauthn::new_unsafe_experts_only(
+            "http://localhost:8080/auth",
+            "localhost",
+            vec![Url::parse("http://localhost:8080").unwrap()],
+            None,
+            None,
+            None,
         );
-        trace!("RESULT: {:?}", r);
-        assert!(r.is_ok());
 
-        // Captured authentication attempt, this mentions the appid extension has been used, but we still provide a valid RPID
-        let rsp = r#"
-        {
-            "id":"at-FfKGsOI21EhtCu7Vx-7t7FKkpUOyKXIkEBBD_vC-eym_AdW6Y9V8WyKxHmii11EBQEe7uFQ0bkYwb0GWmUQ",
-            "rawId":"at-FfKGsOI21EhtCu7Vx-7t7FKkpUOyKXIkEBBD_vC-eym_AdW6Y9V8WyKxHmii11EBQEe7uFQ0bkYwb0GWmUQ",
-            "extensions": {
-                "appid": true
-            },
-            "response":{
-                "authenticatorData":"SZYN5YgOjGh0NBcPZHZgW4_krrmihjLHmVzzuoMdl2MBAAAAFA",
-                "clientDataJSON":"eyJjaGFsbGVuZ2UiOiJXZ1h6X2tUdjNXVVUxa3c4aG0tT0dvR1M0WkNIWF8zYkVxSEgyUHZWcDhNIiwiY2xpZW50RXh0ZW5zaW9ucyI6e30sImhhc2hBbGdvcml0aG0iOiJTSEEtMjU2Iiwib3JpZ2luIjoiaHR0cDovL2xvY2FsaG9zdDo4MDgwIiwidHlwZSI6IndlYmF1dGhuLmdldCJ9",
-                "signature":"MEYCIQDmLVOqv85cdRup4Fr8Pf9zC4AWO-XKBJqa8xPwYFCCMAIhAOiExLoyes0xipmUmq0BVlqJaCKLn_MFKG9GIDsCGq_-",
-                "userHandle":null
-            },
-            "type":"public-key"
-        }
-        "#;
-        let rsp_d: PublicKeyCredential = serde_json::from_str(rsp).unwrap();
+        // A device bound passkey, that is not backup eligible.
+        let mut cred = Credential {
+            user_verified: true,
+            registration_policy: UserVerificationPolicy::Required,
+            ..test_credential(
+                &[
+                    89, 147, 100, 29, 211, 211, 192, 42, 230, 184, 202, 99, 191, 49, 116, 172, 47,
+                    13, 156, 206, 59, 194, 28, 221, 22, 103, 206, 161, 134, 243, 45, 100,
+                ],
+                COSEKey {
+                    type_: COSEAlgorithm::ES256,
+                    key: COSEKeyType::EC_EC2(COSEEC2Key {
+                        curve: ECDSACurve::SECP256R1,
+                        x: [
+                            90, 231, 151, 118, 19, 240, 245, 184, 25, 177, 35, 101, 112, 192, 127,
+                            137, 51, 62, 175, 225, 78, 132, 244, 65, 53, 198, 58, 119, 36, 96, 40,
+                            254,
+                        ]
+                        .to_vec()
+                        .into(),
+                        y: [
+                            193, 128, 210, 120, 156, 225, 73, 72, 76, 223, 170, 85, 86, 18, 58,
+                            213, 193, 200, 80, 162, 150, 160, 115, 235, 87, 13, 199, 154, 21, 93,
+                            96, 226,
+                        ]
+                        .to_vec()
+                        .into(),
+                    }),
+                },
+            )
+        };
 
-        // Now verify it, as the RPID is valid, the appid should be ignored
-        let r = wan.verify_credential_internal(
-            &rsp_d,
-            UserVerificationPolicy::Discouraged_DO_NOT_USE,
-            &zero_chal,
-            &cred,
-            &Some(String::from("https://unused.local")),
-            false,
+        let rsp_fmt = |authenticator_data: &str, signature: &str| {
+            format!(
+                r#"{{
+                "id":"WZNkHdPTwCrmuMpjvzF0rC8NnM47whzdFmfOoYbzLWQ",
+                "rawId":"WZNkHdPTwCrmuMpjvzF0rC8NnM47whzdFmfOoYbzLWQ",
+                "response":{{
+                    "authenticatorData":"{authenticator_data}",
+                    "clientDataJSON":"eyJ0eXBlIjoid2ViYXV0aG4uZ2V0IiwiY2hhbGxlbmdlIjoiSUNFaUl5UWxKaWNvS1NvckxDMHVMekF4TWpNME5UWTNPRGs2T3p3OVBqOCIsIm9yaWdpbiI6Imh0dHA6Ly9sb2NhbGhvc3Q6ODA4MCIsImNyb3NzT3JpZ2luIjpmYWxzZX0",
+                    "signature":"{signature}",
+                    "userHandle":null
+                }},
+                "type":"public-key"
+            }}"#
+            )
+        };
+
+        // The first authentication is device bound, with flags UP | UV.
+        let rsp_device_bound: PublicKeyCredential = serde_json::from_str(&rsp_fmt(
+            "SZYN5YgOjGh0NBcPZHZgW4_krrmihjLHmVzzuoMdl2MFAAAAAA",
+            "MEQCIAxu-zcPyUjSxHDTSpLQVlpEUnhA8D6FNSKMZNtVu4CLAiA3uZZH-FhjeBiXhaP3esxtYrDqHB7BqQAXTd8Qh4W5UA",
+        ))
+        .unwrap();
+        // The second authentication occurs after the passkey begins to sync, with flags
+        // UP | UV | BE | BS.
+        let rsp_synced: PublicKeyCredential = serde_json::from_str(&rsp_fmt(
+            "SZYN5YgOjGh0NBcPZHZgW4_krrmihjLHmVzzuoMdl2MdAAAAAA",
+            "MEQCIBxHLXbTAaTZ6S7KjCnA2Svxxn1r9NyUxYMHHUWfmu7qAiAw6VjPbUJ-2IMJMe058O2GEi52A2EhpnjYDxZK73AokQ",
+        ))
+        .unwrap();
+
+        let state = |cred: &Credential| AuthenticationState {
+            credentials: vec![cred.clone()],
+            policy: UserVerificationPolicy::Required,
+            challenge: Base64UrlSafeData((32..64).collect()),
+            appid: None,
+            allow_backup_eligible_upgrade: true,
+            platform_authenticator: None,
+            allowed_credential_ids: None,
+            user_verification_fallback: false,
+        };
+
+        let r = wan
+            .authenticate_credential(&rsp_device_bound, &state(&cred))
+            .expect("Failed to authenticate");
+        assert!(!r.backup_state_changed());
+        assert!(!r.needs_update());
+
+        let r = wan
+            .authenticate_credential(&rsp_synced, &state(&cred))
+            .expect("Failed to authenticate");
+        assert!(r.backup_state_changed());
+        assert!(r.needs_update());
+        assert!(r.backup_eligible());
+        assert!(r.backup_state());
+
+        // Once the stored credential is updated, the transition is not reported again.
+        cred.backup_eligible = r.backup_eligible();
+        cred.backup_state = r.backup_state();
+
+        let r = wan
+            .authenticate_credential(&rsp_synced, &state(&cred))
+            .expect("Failed to authenticate");
+        assert!(!r.backup_state_changed());
+        assert!(!r.needs_update());
+
+        // The backup eligible credential can not revert to being device bound, even when
+        // eligibility upgrades are allowed.
+        assert_eq!(
+            wan.authenticate_credential(&rsp_device_bound, &state(&cred))
+                .unwrap_err(),
+            WebauthnError::BackupEligibilityChanged
         );
-        trace!("RESULT: {:?}", r);
-        assert!(r.is_ok());
     }
 
     #[test]
@@ -1807,6 +3333,7 @@ mod tests {
                 metadata: AttestationMetadata::None,
             },
             attestation_format: AttestationFormat::None,
+            registered_at: None,
         };
 
         // Persist it to our fake db.
@@ -1908,6 +3435,7 @@ mod tests {
             },
             type_: "public-key".to_string(),
             extensions: RegistrationExtensionsClientOutputs::default(),
+            authenticator_attachment: None,
         };
 
         // Assert this fails when the attestaion is missing.
@@ -1922,6 +3450,7 @@ mod tests {
             false,
             &RequestRegistrationExtensions::default(),
             false,
+            false,
         );
         trace!("{:?}", result);
         assert!(matches!(
@@ -1940,6 +3469,7 @@ mod tests {
             false,
             &RequestRegistrationExtensions::default(),
             false,
+            false,
         );
         trace!("{:?}", result);
         assert!(matches!(
@@ -1963,6 +3493,7 @@ mod tests {
             false,
             &RequestRegistrationExtensions::default(),
             false,
+            false,
         );
         trace!("{:?}", result);
         assert!(matches!(
@@ -1985,11 +3516,73 @@ mod tests {
             false,
             &RequestRegistrationExtensions::default(),
             false,
+            false,
         );
         trace!("{:?}", result);
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_batch_verify_attestation() {
+        use crate::attestation::AttestationCaListBatchVerify;
+
+        let _ = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::TRACE)
+            .try_init();
+
+        // The packed attestation of the ipados 5ci registration.
+        let attestation_object = STANDARD.decode("o2NmbXRmcGFja2VkZ2F0dFN0bXSjY2FsZyZjc2lnWEcwRQIhAKAZODmj+uF5qXsDY2NFol3apRjld544KRUpHzwfk5cbAiBnp2gHmamr2xr46ilQuhzIR9BwMlwtxWd6IT2QEYeo7WN4NWOBWQLBMIICvTCCAaWgAwIBAgIEK/F8eDANBgkqhkiG9w0BAQsFADAuMSwwKgYDVQQDEyNZdWJpY28gVTJGIFJvb3QgQ0EgU2VyaWFsIDQ1NzIwMDYzMTAgFw0xNDA4MDEwMDAwMDBaGA8yMDUwMDkwNDAwMDAwMFowbjELMAkGA1UEBhMCU0UxEjAQBgNVBAoMCVl1YmljbyBBQjEiMCAGA1UECwwZQXV0aGVudGljYXRvciBBdHRlc3RhdGlvbjEnMCUGA1UEAwweWXViaWNvIFUyRiBFRSBTZXJpYWwgNzM3MjQ2MzI4MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEdMLHhCPIcS6bSPJZWGb8cECuTN8H13fVha8Ek5nt+pI8vrSflxb59Vp4bDQlH8jzXj3oW1ZwUDjHC6EnGWB5i6NsMGowIgYJKwYBBAGCxAoCBBUxLjMuNi4xLjQuMS40MTQ4Mi4xLjcwEwYLKwYBBAGC5RwCAQEEBAMCAiQwIQYLKwYBBAGC5RwBAQQEEgQQxe9V/62aS5+1gK3rr+Am0DAMBgNVHRMBAf8EAjAAMA0GCSqGSIb3DQEBCwUAA4IBAQCLbpN2nXhNbunZANJxAn/Cd+S4JuZsObnUiLnLLS0FPWa01TY8F7oJ8bE+aFa4kTe6NQQfi8+yiZrQ8N+JL4f7gNdQPSrH+r3iFd4SvroDe1jaJO4J9LeiFjmRdcVa+5cqNF4G1fPCofvw9W4lKnObuPakr0x/icdVq1MXhYdUtQk6Zr5mBnc4FhN9qi7DXqLHD5G7ZFUmGwfIcD2+0m1f1mwQS8yRD5+/aDCf3vutwddoi3crtivzyromwbKklR4qHunJ75LGZLZA8pJ/mXnUQ6TTsgRqPvPXgQPbSyGMf2z/DIPbQqCD/Bmc4dj9o6LozheBdDtcZCAjSPTAd/uiaGF1dGhEYXRhWMS3tF916xTswLEZrAO3fy8EzMmvvR8f5wWM7F5+4KJ0ikEAAAACxe9V/62aS5+1gK3rr+Am0ABAuZcVDBVS68E/MtAgeQpElJxldF/6cY9sSvbWqx/qRh8wiu42lyRBRmh5yFeD/r9k130dMbFHBHI9RTFgdJQIzaUBAgMmIAEhWCDCfn9t/BeDFfwG32Ms/owb5hFeBYUcaCmQRauVoRrI8yJYII97t5wYshX4dZ+iRas0vPwaOwYvZ1wTOnVn+QDbCF/E").unwrap();
+        let client_data_json = STANDARD.decode("eyJ0eXBlIjoid2ViYXV0aG4uY3JlYXRlIiwib3JpZ2luIjoiaHR0cHM6XC9cLzE3Mi4yMC4wLjE0MTo4NDQzIiwiY2hhbGxlbmdlIjoidHZSMW0tZF9vaFhyd1Z4UWpNZ0g4S25vdkhaN0JSV2habURONFRWTXBOVSJ9").unwrap();
+
+        let items = vec![
+            (attestation_object.clone(), client_data_json.clone()),
+            // Altering the client data must invalidate the attestation signature.
+            (attestation_object.clone(), b"{}".to_vec()),
+            (vec![0x00], client_data_json.clone()),
+        ];
+
+        let att_ca_list: AttestationCaList = YUBICO_U2F_ROOT_CA_SERIAL_457200631_PEM
+            .try_into()
+            .expect("Failed to build att ca list");
+
+        let results: Vec<_> = att_ca_list
+            .batch_verify(items.iter().map(|(a, c)| (a, c)))
+            .expect("Failed to prepare attestation ca list")
+            .collect();
+        trace!("{:?}", results);
+        assert_eq!(results.len(), 3);
+        assert!(matches!(
+            results[0],
+            Ok(AttestationMetadata::Packed { aaguid, .. })
+                if aaguid == uuid::uuid!("c5ef55ff-ad9a-4b9f-b580-adebafe026d0")
+        ));
+        assert!(matches!(
+            results[1],
+            Err(WebauthnError::AttestationStatementSigInvalid)
+        ));
+        assert!(results[2].is_err());
+
+        // An updated trust list without the Yubico CA no longer trusts the device.
+        let att_ca_list: AttestationCaList = APPLE_WEBAUTHN_ROOT_CA_PEM
+            .try_into()
+            .expect("Failed to build att ca list");
+        let results: Vec<_> = att_ca_list
+            .batch_verify(items.iter().take(1).map(|(a, c)| (a, c)))
+            .expect("Failed to prepare attestation ca list")
+            .collect();
+        assert!(matches!(
+            results[0],
+            Err(WebauthnError::AttestationChainNotTrusted(_))
+        ));
+
+        assert!(matches!(
+            AttestationCaList::default()
+                .batch_verify(items.iter().map(|(a, c)| (a, c)))
+                .err(),
+            Some(WebauthnError::AttestationCertificateTrustStoreEmpty)
+        ));
+    }
+
     #[test]
     fn test_deserialise_ipados_5ci() {
         // This is to test migration between the x/y byte array to base64 format.
@@ -2064,6 +3657,7 @@ mod tests {
             },
             type_: "public-key".to_string(),
             extensions: RegistrationExtensionsClientOutputs::default(),
+            authenticator_attachment: None,
         };
 
         let result = wan.register_credential_internal(
@@ -2076,6 +3670,7 @@ mod tests {
             false,
             &RequestRegistrationExtensions::default(),
             true,
+            false,
         );
         trace!("{:?}", result);
         assert!(result.is_ok());
@@ -2131,6 +3726,7 @@ mod tests {
             },
             extensions: AuthenticationExtensionsClientOutputs::default(),
             type_: "public-key".to_string(),
+            authenticator_attachment: None,
         };
 
         let r = wan.verify_credential_internal(
@@ -2433,6 +4029,7 @@ mod tests {
             },
             type_: "public-key".to_string(),
             extensions: RegistrationExtensionsClientOutputs::default(),
+            authenticator_attachment: None,
         };
 
         let result = wan.register_credential_internal(
@@ -2449,6 +4046,7 @@ mod tests {
             false,
             &RequestRegistrationExtensions::default(),
             true,
+            false,
         );
         trace!("{:?}", result);
         assert!(matches!(
@@ -2607,6 +4205,7 @@ mod tests {
             },
             type_: "public-key".to_string(),
             extensions: RegistrationExtensionsClientOutputs::default(),
+            authenticator_attachment: None,
         };
 
         // Attempt to request an AAGUID, but this format does not provide one.
@@ -2635,6 +4234,7 @@ mod tests {
             &RequestRegistrationExtensions::default(),
             // Don't allow passkeys
             false,
+            false,
         );
         debug!("{:?}", result);
         assert!(matches!(
@@ -2665,6 +4265,7 @@ mod tests {
             &RequestRegistrationExtensions::default(),
             // Don't allow passkeys
             false,
+            false,
         );
         debug!("{:?}", result);
         assert!(result.is_ok());
@@ -2692,6 +4293,7 @@ mod tests {
             &RequestRegistrationExtensions::default(),
             // Allow them.
             true,
+            false,
         );
         debug!("{:?}", result);
         assert!(result.is_ok());
@@ -2808,6 +4410,7 @@ mod tests {
             },
             type_: "public-key".to_string(),
             extensions: RegistrationExtensionsClientOutputs::default(),
+            authenticator_attachment: None,
         };
 
         let result = wan.register_credential_internal(
@@ -2832,6 +4435,7 @@ mod tests {
             true,
             &RequestRegistrationExtensions::default(),
             false,
+            false,
         );
         debug!("{:?}", result);
         assert!(matches!(
@@ -2893,6 +4497,7 @@ mod tests {
                     metadata: AttestationMetadata::None,
                 },
                 attestation_format: AttestationFormat::None,
+                registered_at: None,
             },
             Credential {
                 cred_id: Base64UrlSafeData(vec![
@@ -2932,6 +4537,7 @@ mod tests {
                     metadata: AttestationMetadata::None,
                 },
                 attestation_format: AttestationFormat::None,
+                registered_at: None,
             },
         ];
         // Ensure we get a bad result.
@@ -3094,6 +4700,7 @@ mod tests {
             },
             type_: "public-key".to_string(),
             extensions: RegistrationExtensionsClientOutputs::default(),
+            authenticator_attachment: None,
         };
 
         let cred = wan
@@ -3107,6 +4714,7 @@ mod tests {
                 false,
                 &RequestRegistrationExtensions::default(),
                 true,
+                false,
             )
             .expect("Failed to register credential");
 
@@ -3149,6 +4757,7 @@ mod tests {
             },
             extensions: AuthenticationExtensionsClientOutputs::default(),
             type_: "public-key".to_string(),
+            authenticator_attachment: None,
         };
 
         let r = wan.verify_credential_internal(
@@ -3201,10 +4810,18 @@ mod tests {
             false,
             &RequestRegistrationExtensions::default(),
             false,
+            false,
         );
         debug!("{:?}", result);
-        // Currently UNSUPPORTED as openssl doesn't have eddsa management utils that we need.
-        assert!(result.is_err());
+        let cred = result.unwrap();
+        assert_eq!(cred.cred.type_, COSEAlgorithm::EDDSA);
+        assert!(matches!(
+            cred.cred.key,
+            COSEKeyType::EC_OKP(COSEOKPKey {
+                curve: EDDSACurve::ED25519,
+                ..
+            })
+        ));
     }
 
     #[test]
@@ -3245,10 +4862,18 @@ mod tests {
             false,
             &RequestRegistrationExtensions::default(),
             false,
+            false,
         );
         debug!("{:?}", result);
-        // Currently UNSUPPORTED as openssl doesn't have eddsa management utils that we need.
-        assert!(result.is_err());
+        let cred = result.unwrap();
+        assert_eq!(cred.cred.type_, COSEAlgorithm::EDDSA);
+        assert!(matches!(
+            cred.cred.key,
+            COSEKeyType::EC_OKP(COSEOKPKey {
+                curve: EDDSACurve::ED25519,
+                ..
+            })
+        ));
     }
 
     // ⚠️  Currently IGNORED as it appears that pixel 3a send INVALID attestation requests.
@@ -3289,6 +4914,7 @@ mod tests {
             false,
             &RequestRegistrationExtensions::default(),
             false,
+            false,
         );
         debug!("{:?}", result);
         // Currently UNSUPPORTED as openssl doesn't have eddsa management utils that we need.
@@ -3334,6 +4960,7 @@ mod tests {
             false,
             &RequestRegistrationExtensions::default(),
             false,
+            false,
         );
         dbg!("{:?}", &result);
         assert!(result.is_ok());
@@ -3377,9 +5004,100 @@ mod tests {
             false,
             &RequestRegistrationExtensions::default(),
             true,
+            false,
         );
         debug!("{:?}", result);
         assert!(result.is_ok());
+
+        // When attestation is required, a none attestation has no x5c to verify.
+        let result = wan.register_credential_internal(
+            &rsp_d,
+            UserVerificationPolicy::Discouraged_DO_NOT_USE,
+            &chal,
+            &[],
+            &[COSEAlgorithm::ES256],
+            Some(&(APPLE_WEBAUTHN_ROOT_CA_PEM.try_into().unwrap())),
+            false,
+            &RequestRegistrationExtensions::default(),
+            true,
+            false,
+        );
+        debug!("{:?}", result);
+        assert!(matches!(
+            result,
+            Err(WebauthnError::AttestationStatementX5CMissing)
+        ));
+    }
+
+    #[test]
+    fn test_registration_credential_id_consistency() {
+        let _ = tracing_subscriber::fmt::try_init();
+        let wan = Webauthn::new_unsafe_experts_only(
+            "https://webauthn.firstyear.id.au",
+            "webauthn.firstyear.id.au",
+            vec![Url::parse("https://webauthn.firstyear.id.au").unwrap()],
+            None,
+            None,
+            None,
+        );
+
+        let chal: Base64UrlSafeData =
+            serde_json::from_str("\"55Wztjbgks9UkS5jYthawNFik0HSiYuCSB5pzNbT6k0\"").unwrap();
+        let chal = Challenge::from(chal);
+
+        let rsp_d: RegisterPublicKeyCredential = serde_json::from_str(r#"{
+        "id": "AfzEi3UOVveYjwUwIFO3QuN9V0fomECvAYrD_8S5FAsUJqtGbwpgB9bEfphVOURzFQoEszkuULIj5fMvnTkt6cs",
+        "rawId": "AfzEi3UOVveYjwUwIFO3QuN9V0fomECvAYrD_8S5FAsUJqtGbwpgB9bEfphVOURzFQoEszkuULIj5fMvnTkt6cs",
+        "response": {
+          "attestationObject": "o2NmbXRkbm9uZWdhdHRTdG10oGhhdXRoRGF0YVjFarm78N-aFvkduzO7sTL6-dF8eCxIJsbscOzuWNl-9SpFAAAAAAAAAAAAAAAAAAAAAAAAAAAAQQH8xIt1Dlb3mI8FMCBTt0LjfVdH6JhArwGKw__EuRQLFCarRm8KYAfWxH6YVTlEcxUKBLM5LlCyI-XzL505LenLpQECAyYgASFYII2OFisY2sjerzLYjLYvHsQh8V7cnpRcSL4A77wKqcRTIlggm7s0CUKEmkBBFp7Nng-9_pZ5Dm9y39uy6QJmDLgmgho",
+          "clientDataJSON": "eyJ0eXBlIjoid2ViYXV0aG4uY3JlYXRlIiwiY2hhbGxlbmdlIjoiNTVXenRqYmdrczlVa1M1all0aGF3TkZpazBIU2lZdUNTQjVwek5iVDZrMCIsIm9yaWdpbiI6Imh0dHBzOlwvXC93ZWJhdXRobi5maXJzdHllYXIuaWQuYXUiLCJhbmRyb2lkUGFja2FnZU5hbWUiOiJjb20uYW5kcm9pZC5jaHJvbWUifQ"
+        },
+        "type": "public-key"
+        }"#).unwrap();
+
+        let register = |rsp: &RegisterPublicKeyCredential| {
+            wan.register_credential_internal(
+                rsp,
+                UserVerificationPolicy::Discouraged_DO_NOT_USE,
+                &chal,
+                &[],
+                &[COSEAlgorithm::ES256],
+                None,
+                false,
+                &RequestRegistrationExtensions::default(),
+                true,
+            )
+        };
+
+        assert!(register(&rsp_d).is_ok());
+
+        // A rawId that differs from the credential id in the authenticator data is rejected.
+        let mut mismatched = rsp_d.clone();
+        if let Some(b) = mismatched.raw_id.0.last_mut() {
+            *b ^= 0xff;
+        }
+        let result = register(&mismatched);
+        debug!("{:?}", result);
+        assert!(matches!(result, Err(WebauthnError::CredentialIdMismatch)));
+
+        // A credential id length that overruns the authenticator data is rejected. The
+        // authData is a one byte length prefixed bstr, and the credential id length follows
+        // the rpIdHash, flags, counter and aaguid.
+        let mut overlong = rsp_d.clone();
+        let auth_data = overlong
+            .response
+            .attestation_object
+            .0
+            .windows(8)
+            .position(|w| w == b"authData")
+            .unwrap()
+            + 8
+            + 2;
+        overlong.response.attestation_object.0[auth_data + 53..auth_data + 55]
+            .copy_from_slice(&[0xff, 0xff]);
+        let result = register(&overlong);
+        debug!("{:?}", result);
+        assert!(matches!(result, Err(WebauthnError::ParseNOMFailure)));
     }
 
     #[test]
@@ -3424,6 +5142,7 @@ mod tests {
             false,
             &RequestRegistrationExtensions::default(),
             false,
+            false,
         );
         debug!("{:?}", result);
         assert!(result.is_err());
@@ -3472,6 +5191,7 @@ mod tests {
             false,
             &RequestRegistrationExtensions::default(),
             false,
+            false,
         );
         debug!("{:?}", result);
         assert!(matches!(result, Err(WebauthnError::ParseNOMFailure)));
@@ -3536,6 +5256,7 @@ mod tests {
             },
             type_: "public-key".to_string(),
             extensions: RegistrationExtensionsClientOutputs::default(),
+            authenticator_attachment: None,
         };
 
         debug!("{:?}", rsp_d);
@@ -3553,6 +5274,7 @@ mod tests {
             true,
             &RequestRegistrationExtensions::default(),
             true,
+            false,
         );
         debug!("{:?}", result);
         let cred = result.unwrap();
@@ -3627,6 +5349,7 @@ mod tests {
             true,
             &RequestRegistrationExtensions::default(),
             true,
+            false,
         );
         dbg!(&result);
         assert!(result.is_ok());
@@ -3685,6 +5408,7 @@ mod tests {
             true,
             &RequestRegistrationExtensions::default(),
             true,
+            false,
         );
         dbg!(&result);
         assert!(result.is_ok());
@@ -3734,17 +5458,35 @@ mod tests {
 
         debug!("{:?}", rsp_d);
 
-        let result = wan.register_credential_internal(
-            &rsp_d,
-            UserVerificationPolicy::Required,
-            &chal,
-            &[],
-            &[COSEAlgorithm::ES256],
-            Some(&(ANDROID_SOFTWARE_ROOT_CA.try_into().unwrap())),
-            true,
-            &RequestRegistrationExtensions::default(),
-            true,
-        );
+        let register = |rsp: &RegisterPublicKeyCredential| {
+            wan.register_credential_internal(
+                rsp,
+                UserVerificationPolicy::Required,
+                &chal,
+                &[],
+                &[COSEAlgorithm::ES256],
+                Some(&(ANDROID_SOFTWARE_ROOT_CA.try_into().unwrap())),
+                true,
+                &RequestRegistrationExtensions::default(),
+                true,
+                false,
+            )
+        };
+
+        // The rawId in this capture is not the credential id in the authenticator data.
+        let result = register(&rsp_d);
+        dbg!(&result);
+        assert!(matches!(result, Err(WebauthnError::CredentialIdMismatch)));
+
+        // rawId is not covered by the attestation signature, so the same capture with
+        // rawId set to the attested credential id must verify.
+        let mut rsp_d = rsp_d;
+        rsp_d.raw_id = serde_json::from_str(
+            "\"AVUvAmX241vMKYd7ZBdmkNWaYcNYhoSZCJjFRGmROb6I4ygQUVmH6k9IMwcbZGeAQ4v4WMNphORudwje5h7ty9A\"",
+        )
+        .unwrap();
+
+        let result = register(&rsp_d);
         dbg!(&result);
         assert!(result.is_ok());
 
@@ -3813,6 +5555,7 @@ mod tests {
             true,
             &RequestRegistrationExtensions::default(),
             true,
+            false,
         );
 
         assert!(matches!(
@@ -3837,4 +5580,135 @@ mod tests {
             &Url::parse("ios:bundle-id:com.foo.baz").unwrap(),
         ));
     }
+
+    #[test]
+    fn test_attestation_ca_chain_constraints() {
+        use crate::attestation::verify_attestation_ca_chain;
+        use openssl::x509::X509;
+
+        let _ = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::TRACE)
+            .try_init();
+
+        // A root that only permits subjects within "O=Webauthn-rs Test".
+        const NAME_CONSTRAINED_ROOT_CA_PEM: &[u8] = b"-----BEGIN CERTIFICATE-----
+MIIB4TCCAYagAwIBAgIBATAKBggqhkjOPQQDAjA+MRkwFwYDVQQKDBBXZWJhdXRo
+bi1ycyBUZXN0MSEwHwYDVQQDDBhOYW1lIENvbnN0cmFpbmVkIFJvb3QgQ0EwIBcN
+MjYxMDE0MDc1MzE0WhgPMjEyNjA5MjAwNzUzMTRaMD4xGTAXBgNVBAoMEFdlYmF1
+dGhuLXJzIFRlc3QxITAfBgNVBAMMGE5hbWUgQ29uc3RyYWluZWQgUm9vdCBDQTBZ
+MBMGByqGSM49AgEGCCqGSM49AwEHA0IABJgdw26BAE3tk7vaEbvYl5A7d27Z4fpg
+zz/HJL4OFGyy5i/tnLATYd1fEGlzriqo2q7cSno2Z5/moUHkHgaZq82jczBxMA8G
+A1UdEwEB/wQFMAMBAf8wDgYDVR0PAQH/BAQDAgEGMB0GA1UdDgQWBBRIIjYH157W
+qYbbRU3bpkGL3j7DjDAvBgNVHR4BAf8EJTAjoCEwH6QdMBsxGTAXBgNVBAoMEFdl
+YmF1dGhuLXJzIFRlc3QwCgYIKoZIzj0EAwIDSQAwRgIhAKVgnGNf0n1QN3NI+PKz
+A1q7T0Wxpo/cwQuSTYfJ9+Y+AiEA2P6M5Nf6nwb830YASIwnion4zy9smLl6zZbO
+Xa0sOb4=
+-----END CERTIFICATE-----
+";
+        // A leaf with the subject "O=Other Corp" issued by the name constrained root.
+        const NAME_VIOLATING_LEAF_PEM: &[u8] = b"-----BEGIN CERTIFICATE-----
+MIIBvDCCAWOgAwIBAgIBAjAKBggqhkjOPQQDAjA+MRkwFwYDVQQKDBBXZWJhdXRo
+bi1ycyBUZXN0MSEwHwYDVQQDDBhOYW1lIENvbnN0cmFpbmVkIFJvb3QgQ0EwIBcN
+MjYxMDE0MDc1MzE0WhgPMjEyNjA5MjAwNzUzMTRaMC4xEzARBgNVBAoMCk90aGVy
+IENvcnAxFzAVBgNVBAMMDlZpb2xhdGluZyBMZWFmMFkwEwYHKoZIzj0CAQYIKoZI
+zj0DAQcDQgAErU1oanb9kad3Sw01ktc6+hKiQhv/BMNrfetkUvHrj0eiCcJ5/emT
+QglWekGijVDyjL1S7BEr6mRaMYozRv8CkKNgMF4wDAYDVR0TAQH/BAIwADAOBgNV
+HQ8BAf8EBAMCB4AwHQYDVR0OBBYEFED3W0V+65vCprnDq/ZEmFeOyYF9MB8GA1Ud
+IwQYMBaAFEgiNgfXntaphttFTdumQYvePsOMMAoGCCqGSM49BAMCA0cAMEQCIGuo
+4hI9EN1qMAEnAONRaKovgow5gxL58n6Vv9R8kGs7AiAV8WM4l58jHDhtqVNRqVfB
+s5f4h7bZR6qBoiDbGSTJkw==
+-----END CERTIFICATE-----
+";
+        // A leaf with the subject "O=Webauthn-rs Test" issued by the name constrained root.
+        const NAME_PERMITTED_LEAF_PEM: &[u8] = b"-----BEGIN CERTIFICATE-----
+MIIBwzCCAWmgAwIBAgIBAzAKBggqhkjOPQQDAjA+MRkwFwYDVQQKDBBXZWJhdXRo
+bi1ycyBUZXN0MSEwHwYDVQQDDBhOYW1lIENvbnN0cmFpbmVkIFJvb3QgQ0EwIBcN
+MjYxMDE0MDc1MzE0WhgPMjEyNjA5MjAwNzUzMTRaMDQxGTAXBgNVBAoMEFdlYmF1
+dGhuLXJzIFRlc3QxFzAVBgNVBAMMDlBlcm1pdHRlZCBMZWFmMFkwEwYHKoZIzj0C
+AQYIKoZIzj0DAQcDQgAErU1oanb9kad3Sw01ktc6+hKiQhv/BMNrfetkUvHrj0ei
+CcJ5/emTQglWekGijVDyjL1S7BEr6mRaMYozRv8CkKNgMF4wDAYDVR0TAQH/BAIw
+ADAOBgNVHQ8BAf8EBAMCB4AwHQYDVR0OBBYEFED3W0V+65vCprnDq/ZEmFeOyYF9
+MB8GA1UdIwQYMBaAFEgiNgfXntaphttFTdumQYvePsOMMAoGCCqGSM49BAMCA0gA
+MEUCIQDTaqNrzhwwz4KvoyVpRjF3rfQRUh0qXu3Swyec6ta55wIgAgnU47MRvf9L
+dNwzQAOmmqFbu8ydaV9OONbFa1w1b1w=
+-----END CERTIFICATE-----
+";
+        // A root with a path length of 0, so it may only directly issue leaf certificates.
+        const PATH_LEN_CONSTRAINED_ROOT_CA_PEM: &[u8] = b"-----BEGIN CERTIFICATE-----
+MIIBwDCCAWagAwIBAgIBBDAKBggqhkjOPQQDAjBFMRkwFwYDVQQKDBBXZWJhdXRo
+bi1ycyBUZXN0MSgwJgYDVQQDDB9QYXRoIExlbmd0aCBDb25zdHJhaW5lZCBSb290
+IENBMCAXDTI2MTAxNDA3NTMyMVoYDzIxMjYwOTIwMDc1MzIxWjBFMRkwFwYDVQQK
+DBBXZWJhdXRobi1ycyBUZXN0MSgwJgYDVQQDDB9QYXRoIExlbmd0aCBDb25zdHJh
+aW5lZCBSb290IENBMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEmB3DboEATe2T
+u9oRu9iXkDt3btnh+mDPP8ckvg4UbLLmL+2csBNh3V8QaXOuKqjartxKejZnn+ah
+QeQeBpmrzaNFMEMwEgYDVR0TAQH/BAgwBgEB/wIBADAOBgNVHQ8BAf8EBAMCAQYw
+HQYDVR0OBBYEFEgiNgfXntaphttFTdumQYvePsOMMAoGCCqGSM49BAMCA0gAMEUC
+IQCMclzvX+WbEp+VQ0CZ1Jk0IMoKMRSuBdPnfVbC4naRmQIgeGERBRA92LfLDVTP
+nrR0BmJ9d50F2/BKX/Ss0IdInPo=
+-----END CERTIFICATE-----
+";
+        // An intermediate issued by the path length constrained root.
+        const PATH_LEN_INTERMEDIATE_PEM: &[u8] = b"-----BEGIN CERTIFICATE-----
+MIIBzjCCAXSgAwIBAgIBBTAKBggqhkjOPQQDAjBFMRkwFwYDVQQKDBBXZWJhdXRo
+bi1ycyBUZXN0MSgwJgYDVQQDDB9QYXRoIExlbmd0aCBDb25zdHJhaW5lZCBSb290
+IENBMCAXDTI2MTAxNDA3NTMyMVoYDzIxMjYwOTIwMDc1MzIxWjA1MRkwFwYDVQQK
+DBBXZWJhdXRobi1ycyBUZXN0MRgwFgYDVQQDDA9JbnRlcm1lZGlhdGUgQ0EwWTAT
+BgcqhkjOPQIBBggqhkjOPQMBBwNCAASRi8XnFxrIG+MCOYqVKFYjHCNz7TnWTL8A
+I20oTVTcruO76afGhEp2iHmLJwf4Dbbr5nFqgrxRL1xAn6CwwahFo2MwYTAPBgNV
+HRMBAf8EBTADAQH/MA4GA1UdDwEB/wQEAwIBBjAdBgNVHQ4EFgQUugxINpCUYG7z
+Se2rEuRiduGzInEwHwYDVR0jBBgwFoAUSCI2B9ee1qmG20VN26ZBi94+w4wwCgYI
+KoZIzj0EAwIDSAAwRQIhAM7tWFxYWeK/L0fT0O3kmlD1hyLrYbSKTuCwGXh83mTG
+AiA7T8NCKL+EoaMQek62AfwcpOR6/LGrv/Fc2RGplLmbqA==
+-----END CERTIFICATE-----
+";
+        // A leaf issued by the intermediate, exceeding the path length of the root.
+        const PATH_LEN_LEAF_PEM: &[u8] = b"-----BEGIN CERTIFICATE-----
+MIIBuzCCAWCgAwIBAgIBBjAKBggqhkjOPQQDAjA1MRkwFwYDVQQKDBBXZWJhdXRo
+bi1ycyBUZXN0MRgwFgYDVQQDDA9JbnRlcm1lZGlhdGUgQ0EwIBcNMjYxMDE0MDc1
+MzIxWhgPMjEyNjA5MjAwNzUzMjFaMDQxGTAXBgNVBAoMEFdlYmF1dGhuLXJzIFRl
+c3QxFzAVBgNVBAMMDlBlcm1pdHRlZCBMZWFmMFkwEwYHKoZIzj0CAQYIKoZIzj0D
+AQcDQgAErU1oanb9kad3Sw01ktc6+hKiQhv/BMNrfetkUvHrj0eiCcJ5/emTQglW
+ekGijVDyjL1S7BEr6mRaMYozRv8CkKNgMF4wDAYDVR0TAQH/BAIwADAOBgNVHQ8B
+Af8EBAMCB4AwHQYDVR0OBBYEFED3W0V+65vCprnDq/ZEmFeOyYF9MB8GA1UdIwQY
+MBaAFLoMSDaQlGBu80ntqxLkYnbhsyJxMAoGCCqGSM49BAMCA0kAMEYCIQCsliUv
+PlhQGnATPdRjqcvuFX0ntK38jKFiVgErBmolTgIhAIe2vHRMDFNTQk7PPtuSoX5s
+3g2mYe0KVve8OssL3Abn
+-----END CERTIFICATE-----
+";
+
+        let ca_list: AttestationCaList = NAME_CONSTRAINED_ROOT_CA_PEM
+            .try_into()
+            .expect("Failed to build att ca list");
+
+        let leaf = X509::from_pem(NAME_PERMITTED_LEAF_PEM).unwrap();
+        let result =
+            verify_attestation_ca_chain(&ParsedAttestationData::Basic(vec![leaf]), &ca_list, true);
+        assert!(matches!(result, Ok(Some(_))));
+
+        let leaf = X509::from_pem(NAME_VIOLATING_LEAF_PEM).unwrap();
+        let result =
+            verify_attestation_ca_chain(&ParsedAttestationData::Basic(vec![leaf]), &ca_list, true);
+        trace!("{:?}", result);
+        assert!(matches!(
+            result,
+            Err(WebauthnError::AttestationChainConstraintViolation(_))
+        ));
+
+        let ca_list: AttestationCaList = PATH_LEN_CONSTRAINED_ROOT_CA_PEM
+            .try_into()
+            .expect("Failed to build att ca list");
+
+        let leaf = X509::from_pem(PATH_LEN_LEAF_PEM).unwrap();
+        let intermediate = X509::from_pem(PATH_LEN_INTERMEDIATE_PEM).unwrap();
+        let result = verify_attestation_ca_chain(
+            &ParsedAttestationData::Basic(vec![leaf, intermediate]),
+            &ca_list,
+            true,
+        );
+        trace!("{:?}", result);
+        assert!(matches!(
+            result,
+            Err(WebauthnError::AttestationChainConstraintViolation(_))
+        ));
+    }
 }
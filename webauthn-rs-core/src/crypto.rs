@@ -16,6 +16,21 @@ use crate::proto::*;
 
 use crate::internals::{tpm_device_attribute_parser, TpmVendor};
 
+/// The minimum size in bits of an RSA modulus that will be accepted for a credential.
+pub const RSA_MIN_KEY_BITS: u32 = 2048;
+
+/// The set of algorithms that signatures can be verified with. Credentials using any other
+/// algorithm can never authenticate, so must not be requested during registration.
+pub fn supported_algorithms() -> &'static [COSEAlgorithm] {
+    &[
+        COSEAlgorithm::ES256,
+        COSEAlgorithm::ES384,
+        COSEAlgorithm::ES512,
+        COSEAlgorithm::RS256,
+        COSEAlgorithm::EDDSA,
+    ]
+}
+
 // Why OpenSSL over another rust crate?
 // - The openssl crate allows us to reconstruct a public key from the
 //   x/y group coords, where most others want a pkcs formatted structure. As
@@ -33,9 +48,23 @@ fn pkey_verify_signature(
     signature: &[u8],
     verification_data: &[u8],
 ) -> Result<bool, WebauthnError> {
+    if stype == COSEAlgorithm::EDDSA {
+        // EdDSA signs the message directly rather than a digest, so it must be verified in a
+        // single operation.
+        let mut verifier =
+            sign::Verifier::new_without_digest(pkey).map_err(WebauthnError::OpenSSLError)?;
+        return verifier
+            .verify_oneshot(signature, verification_data)
+            .map_err(WebauthnError::OpenSSLError);
+    }
+
     let mut verifier = match stype {
         COSEAlgorithm::ES256 => sign::Verifier::new(hash::MessageDigest::sha256(), pkey)
             .map_err(WebauthnError::OpenSSLError),
+        COSEAlgorithm::ES384 => sign::Verifier::new(hash::MessageDigest::sha384(), pkey)
+            .map_err(WebauthnError::OpenSSLError),
+        COSEAlgorithm::ES512 => sign::Verifier::new(hash::MessageDigest::sha512(), pkey)
+            .map_err(WebauthnError::OpenSSLError),
         COSEAlgorithm::RS256 => {
             let mut verifier = sign::Verifier::new(hash::MessageDigest::sha256(), pkey)
                 .map_err(WebauthnError::OpenSSLError)?;
@@ -308,6 +337,16 @@ pub(crate) fn assert_tpm_attest_req(x509: &x509::X509) -> Result<(), WebauthnErr
 ///
 /// [0]: https://www.w3.org/TR/webauthn-2/#sctn-packed-attestation-cert-requirements
 pub fn assert_packed_attest_req(pubk: &x509::X509) -> Result<(), WebauthnError> {
+    check_packed_attest_req(pubk, true)
+}
+
+/// Assert the packed attestation certificate requirements. If `strict_subject` is false, a
+/// Subject-OU that is not the literal "Authenticator Attestation" is only logged as a warning,
+/// as some vendors use a localised or slightly different value.
+pub(crate) fn check_packed_attest_req(
+    pubk: &x509::X509,
+    strict_subject: bool,
+) -> Result<(), WebauthnError> {
     // https://w3c.github.io/webauthn/#sctn-packed-attestation-cert-requirements
     let der_bytes = pubk.to_der()?;
     let x509_cert = x509_parser::parse_x509_certificate(&der_bytes)
@@ -343,22 +382,24 @@ pub fn assert_packed_attest_req(pubk: &x509::X509) -> Result<(), WebauthnError>
         return Err(WebauthnError::AttestationCertificateRequirementsNotMet);
     }
 
-    match subject_ou {
+    let ou_error = match subject_ou {
         Some(ou) => match ou.attr_value().as_str() {
-            Ok(ou_d) => {
-                if ou_d != "Authenticator Attestation" {
-                    trace!("ou != Authenticator Attestation");
-                    return Err(WebauthnError::AttestationCertificateRequirementsNotMet);
-                }
-            }
-            Err(_) => {
-                trace!("ou invalid");
-                return Err(WebauthnError::AttestationCertificateRequirementsNotMet);
-            }
+            Ok("Authenticator Attestation") => None,
+            Ok(_) => Some("ou != Authenticator Attestation"),
+            Err(_) => Some("ou invalid"),
         },
-        None => {
-            trace!("ou not found");
+        None => Some("ou not found"),
+    };
+
+    if let Some(ou_error) = ou_error {
+        if strict_subject {
+            trace!("{}", ou_error);
             return Err(WebauthnError::AttestationCertificateRequirementsNotMet);
+        } else {
+            warn!(
+                "{}, continuing as strict packed subject checks are disabled",
+                ou_error
+            );
         }
     }
 
@@ -440,6 +481,35 @@ pub(crate) fn only_hash_from_type(
     }
 }
 
+/// Replace the standard string labels of COSE key parameters with their integer labels, where
+/// the integer label is not already present. The key type specific parameters share integer
+/// labels, but their string labels are distinct, so this does not need to know the key type.
+/// <https://www.rfc-editor.org/rfc/rfc8152#section-7.1>
+pub(crate) fn relabel_cose_key(d: &serde_cbor_2::Value) -> serde_cbor_2::Value {
+    let serde_cbor_2::Value::Map(m) = d else {
+        return d.clone();
+    };
+
+    let mut relabelled = m.clone();
+    for (label, id) in [
+        ("kty", 1),
+        ("alg", 3),
+        ("crv", -1),
+        ("x", -2),
+        ("y", -3),
+        ("n", -1),
+        ("e", -2),
+    ] {
+        if let Some(value) = relabelled.remove(&serde_cbor_2::Value::Text(label.to_string())) {
+            relabelled
+                .entry(serde_cbor_2::Value::Integer(id))
+                .or_insert(value);
+        }
+    }
+
+    serde_cbor_2::Value::Map(relabelled)
+}
+
 impl TryFrom<&serde_cbor_2::Value> for COSEKey {
     type Error = WebauthnError;
     fn try_from(d: &serde_cbor_2::Value) -> Result<COSEKey, Self::Error> {
@@ -499,9 +569,21 @@ impl TryFrom<&serde_cbor_2::Value> for COSEKey {
             let y_value = m
                 .get(&serde_cbor_2::Value::Integer(-3))
                 .ok_or(WebauthnError::COSEKeyInvalidCBORValue)?;
+
+            // RFC8152 allows y to be a sign bit for point compression, but WebAuthn requires
+            // the uncompressed coordinates of the point.
+            if matches!(y_value, serde_cbor_2::Value::Bool(_)) {
+                return Err(WebauthnError::COSEKeyECDSAInvalidPointFormat);
+            }
             let y = cbor_try_bytes!(y_value)?;
 
             let coord_len = curve.coordinate_size();
+
+            // Reject a compressed X9.62 point (0x02 or 0x03 || x) provided in place of x.
+            if x.len() == coord_len + 1 && matches!(x.first(), Some(0x02) | Some(0x03)) {
+                return Err(WebauthnError::COSEKeyECDSAInvalidPointFormat);
+            }
+
             if x.len() != coord_len || y.len() != coord_len {
                 return Err(WebauthnError::COSEKeyECDSAXYInvalid);
             }
@@ -531,7 +613,7 @@ impl TryFrom<&serde_cbor_2::Value> for COSEKey {
             // -257 -> RS256 aka RSASSA-PKCS1-v1_5 with SHA-256
 
             // -1 -> n 256 bytes
-            // -2 -> e 3 bytes
+            // -2 -> e 3 bytes, but some authenticators encode it in 1 to 4 bytes
 
             let n_value = m
                 .get(&serde_cbor_2::Value::Integer(-1))
@@ -543,13 +625,20 @@ impl TryFrom<&serde_cbor_2::Value> for COSEKey {
                 .ok_or(WebauthnError::COSEKeyInvalidCBORValue)?;
             let e = cbor_try_bytes!(e_value)?;
 
-            if n.len() != 256 || e.len() != 3 {
+            if n.len() != 256 || e.is_empty() || e.len() > 4 {
                 return Err(WebauthnError::COSEKeyRSANEInvalid);
             }
 
-            // Set the n and e, we know they are proper sizes.
+            // The exponent is stored in 3 bytes, so it must fit once any leading zeros are
+            // removed.
+            let e = strip_leading_zeros(e.as_slice());
+            if e.is_empty() || e.len() > 3 {
+                return Err(WebauthnError::COSEKeyRSANEInvalid);
+            }
+
+            // Set the n and e, we know they are proper sizes. The exponent is left padded.
             let mut e_temp = [0; 3];
-            e_temp.copy_from_slice(e.as_slice());
+            e_temp[3 - e.len()..].copy_from_slice(e);
 
             // Right, now build the struct.
             let cose_key = COSEKey {
@@ -571,6 +660,14 @@ impl TryFrom<&serde_cbor_2::Value> for COSEKey {
                 .get(&serde_cbor_2::Value::Integer(-1))
                 .ok_or(WebauthnError::COSEKeyInvalidCBORValue)?;
             let curve_type = cbor_try_i128!(curve_type_value)?;
+            let curve = EDDSACurve::try_from(curve_type)?;
+
+            // Ed448 keys are larger than Ed25519 keys, so reject them explicitly before the
+            // length of x is checked to give a clear error.
+            if curve == EDDSACurve::ED448 {
+                warn!("ED448 keys are not currently supported");
+                return Err(WebauthnError::COSEKeyEd448Unsupported);
+            }
 
             let x_value = m
                 .get(&serde_cbor_2::Value::Integer(-2))
@@ -586,10 +683,7 @@ impl TryFrom<&serde_cbor_2::Value> for COSEKey {
 
             let cose_key = COSEKey {
                 type_,
-                key: COSEKeyType::EC_OKP(COSEOKPKey {
-                    curve: EDDSACurve::try_from(curve_type)?,
-                    x: x_temp,
-                }),
+                key: COSEKeyType::EC_OKP(COSEOKPKey { curve, x: x_temp }),
             };
 
             // The rfc additionally states:
@@ -670,6 +764,28 @@ impl TryFrom<(COSEAlgorithm, &x509::X509)> for COSEKey {
 }
 
 impl COSEKey {
+    /// Parse a COSE key, tolerating non-conformant authenticators that label the key
+    /// parameters with strings (such as "kty" and "alg") rather than integers. Integer labels
+    /// take precedence if both are present.
+    pub fn try_from_lenient(d: &serde_cbor_2::Value) -> Result<COSEKey, WebauthnError> {
+        COSEKey::try_from(&relabel_cose_key(d))
+    }
+
+    /// The size in bits of the modulus of an RSA key. Returns `None` for other key types.
+    pub fn rsa_key_bits(&self) -> Option<u32> {
+        match &self.key {
+            COSEKeyType::RSA(rsak) => {
+                let n = strip_leading_zeros(rsak.n.as_ref());
+                Some(
+                    n.first()
+                        .map(|msb| (n.len() as u32 - 1) * 8 + (8 - msb.leading_zeros()))
+                        .unwrap_or(0),
+                )
+            }
+            _ => None,
+        }
+    }
+
     pub(crate) fn get_alg_key_ecc_x962_raw(&self) -> Result<Vec<u8>, WebauthnError> {
         // Let publicKeyU2F be the concatenation 0x04 || x || y.
         // Note: This signifies uncompressed ECC key format.
@@ -689,7 +805,9 @@ impl COSEKey {
         }
     }
 
-    pub(crate) fn validate(&self) -> Result<(), WebauthnError> {
+    /// Verify that this key is structurally valid. EC keys must be a valid point on their curve,
+    /// RSA keys must meet the minimum modulus size, and EdDSA keys must have the correct length.
+    pub fn validate(&self) -> Result<(), WebauthnError> {
         match &self.key {
             COSEKeyType::EC_EC2(ec2k) => {
                 // Get the curve type
@@ -710,6 +828,13 @@ impl COSEKey {
             COSEKeyType::RSA(rsak) => {
                 let nbn =
                     bn::BigNum::from_slice(rsak.n.as_ref()).map_err(WebauthnError::OpenSSLError)?;
+
+                // Defend against moduli that are padded to the expected length with leading
+                // zeros.
+                if (nbn.num_bits() as u32) < RSA_MIN_KEY_BITS {
+                    return Err(WebauthnError::CredentialRsaKeyTooSmall);
+                }
+
                 let ebn = bn::BigNum::from_slice(&rsak.e).map_err(WebauthnError::OpenSSLError)?;
 
                 let _rsa_key = rsa::Rsa::from_public_components(nbn, ebn)
@@ -722,10 +847,7 @@ impl COSEKey {
                 */
                 Ok(())
             }
-            COSEKeyType::EC_OKP(_edk) => {
-                warn!("ED25519 or ED448 keys are not currently supported");
-                Err(WebauthnError::COSEKeyEDUnsupported)
-            }
+            COSEKeyType::EC_OKP(edk) => edk.get_openssl_pkey().map(|_| ()),
         }
     }
 
@@ -764,10 +886,7 @@ impl COSEKey {
                 let p = pkey::PKey::from_rsa(rsa_key).map_err(WebauthnError::OpenSSLError)?;
                 Ok(p)
             }
-            _ => {
-                debug!("get_openssl_pkey");
-                Err(WebauthnError::COSEKeyInvalidType)
-            }
+            COSEKeyType::EC_OKP(edk) => edk.get_openssl_pkey(),
         }
     }
 
@@ -780,6 +899,113 @@ impl COSEKey {
         let pkey = self.get_openssl_pkey()?;
         pkey_verify_signature(&pkey, self.type_, signature, verification_data)
     }
+
+    /// Compute the [RFC 7638](https://www.rfc-editor.org/rfc/rfc7638) JWK SHA-256 thumbprint of
+    /// this public key. This is a stable identifier of the key material, independent of the
+    /// credential id, which can be used to correlate this key with keys stored elsewhere.
+    pub fn jwk_thumbprint(&self) -> Result<[u8; 32], WebauthnError> {
+        // The required members of the JWK, in lexicographic order with no whitespace.
+        let jwk = match &self.key {
+            COSEKeyType::EC_EC2(ec2k) => {
+                let crv = match ec2k.curve {
+                    ECDSACurve::SECP256R1 => "P-256",
+                    ECDSACurve::SECP384R1 => "P-384",
+                    ECDSACurve::SECP521R1 => "P-521",
+                };
+                format!(
+                    r#"{{"crv":"{}","kty":"EC","x":"{}","y":"{}"}}"#,
+                    crv, ec2k.x, ec2k.y
+                )
+            }
+            COSEKeyType::RSA(rsak) => {
+                // Integers in a JWK are the minimal big endian octets, so leading zeros
+                // must be removed.
+                let e = Base64UrlSafeData(strip_leading_zeros(&rsak.e).to_vec());
+                let n = Base64UrlSafeData(strip_leading_zeros(rsak.n.as_ref()).to_vec());
+                format!(r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#, e, n)
+            }
+            COSEKeyType::EC_OKP(edk) => {
+                let crv = match edk.curve {
+                    EDDSACurve::ED25519 => "Ed25519",
+                    EDDSACurve::ED448 => {
+                        debug!("jwk_thumbprint ED448");
+                        return Err(WebauthnError::COSEKeyEDDSAInvalidCurve);
+                    }
+                };
+                let x = Base64UrlSafeData(edk.x.to_vec());
+                format!(r#"{{"crv":"{}","kty":"OKP","x":"{}"}}"#, crv, x)
+            }
+        };
+
+        Ok(compute_sha256(jwk.as_bytes()))
+    }
+
+    /// Encode this public key as a COSE_Key CBOR map with integer labels, as an authenticator
+    /// provides it. This can be parsed again with [`COSEKey::try_from`], for example when the
+    /// key is passed to a CTAP context or another library.
+    pub fn to_cbor(&self) -> Result<Vec<u8>, WebauthnError> {
+        use serde_cbor_2::Value;
+
+        let mut m = std::collections::BTreeMap::from([(
+            Value::Integer(3),
+            Value::Integer(self.type_ as i128),
+        )]);
+        match &self.key {
+            COSEKeyType::EC_EC2(ec2k) => {
+                m.insert(
+                    Value::Integer(1),
+                    Value::Integer(COSEKeyTypeId::EC_EC2 as i128),
+                );
+                m.insert(
+                    Value::Integer(-1),
+                    Value::Integer(ec2k.curve.clone() as i128),
+                );
+                m.insert(Value::Integer(-2), Value::Bytes(ec2k.x.to_vec()));
+                m.insert(Value::Integer(-3), Value::Bytes(ec2k.y.to_vec()));
+            }
+            COSEKeyType::RSA(rsak) => {
+                m.insert(
+                    Value::Integer(1),
+                    Value::Integer(COSEKeyTypeId::EC_RSA as i128),
+                );
+                m.insert(Value::Integer(-1), Value::Bytes(rsak.n.to_vec()));
+                m.insert(Value::Integer(-2), Value::Bytes(rsak.e.to_vec()));
+            }
+            COSEKeyType::EC_OKP(edk) => {
+                m.insert(
+                    Value::Integer(1),
+                    Value::Integer(COSEKeyTypeId::EC_OKP as i128),
+                );
+                m.insert(
+                    Value::Integer(-1),
+                    Value::Integer(edk.curve.clone() as i128),
+                );
+                m.insert(Value::Integer(-2), Value::Bytes(edk.x.to_vec()));
+            }
+        }
+
+        Ok(serde_cbor_2::to_vec(&Value::Map(m))?)
+    }
+}
+
+impl COSEOKPKey {
+    fn get_openssl_pkey(&self) -> Result<pkey::PKey<pkey::Public>, WebauthnError> {
+        match self.curve {
+            EDDSACurve::ED25519 => {
+                pkey::PKey::public_key_from_raw_bytes(&self.x, pkey::Id::ED25519)
+                    .map_err(WebauthnError::OpenSSLError)
+            }
+            EDDSACurve::ED448 => {
+                warn!("ED448 keys are not currently supported");
+                Err(WebauthnError::COSEKeyEd448Unsupported)
+            }
+        }
+    }
+}
+
+fn strip_leading_zeros(i: &[u8]) -> &[u8] {
+    let idx = i.iter().position(|b| *b != 0).unwrap_or(i.len());
+    &i[idx..]
 }
 
 /// Compute the sha256 of a slice of data.
@@ -804,6 +1030,306 @@ mod tests {
         );
     }
 
+    #[test]
+    fn supported_algorithms_match_verification() {
+        let group = ec::EcGroup::from_curve_name(nid::Nid::X9_62_PRIME256V1).unwrap();
+        let pkey = pkey::PKey::from_ec_key(ec::EcKey::generate(&group).unwrap()).unwrap();
+        let pkey = pkey::PKey::public_key_from_der(&pkey.public_key_to_der().unwrap()).unwrap();
+
+        for alg in COSEAlgorithm::all_possible_algs() {
+            // The key does not suit every algorithm, but only unimplemented algorithms are
+            // rejected before the key is used.
+            let implemented = !matches!(
+                pkey_verify_signature(&pkey, alg, &[0; 64], b"data"),
+                Err(WebauthnError::COSEKeyInvalidType)
+                    | Err(WebauthnError::CredentialInsecureCryptography)
+            );
+            assert_eq!(
+                implemented,
+                supported_algorithms().contains(&alg),
+                "{alg:?} is inconsistent"
+            );
+        }
+    }
+
+    #[test]
+    fn ecdsa_digest_selection() {
+        for (alg, curve, digest) in [
+            (
+                COSEAlgorithm::ES384,
+                nid::Nid::SECP384R1,
+                hash::MessageDigest::sha384(),
+            ),
+            (
+                COSEAlgorithm::ES512,
+                nid::Nid::SECP521R1,
+                hash::MessageDigest::sha512(),
+            ),
+        ] {
+            let group = ec::EcGroup::from_curve_name(curve).unwrap();
+            let key = pkey::PKey::from_ec_key(ec::EcKey::generate(&group).unwrap()).unwrap();
+            let public =
+                pkey::PKey::public_key_from_der(&key.public_key_to_der().unwrap()).unwrap();
+
+            let sign = |digest| {
+                let mut signer = sign::Signer::new(digest, &key).unwrap();
+                signer.update(b"verification data").unwrap();
+                signer.sign_to_vec().unwrap()
+            };
+
+            let signature = sign(digest);
+            assert!(
+                pkey_verify_signature(&public, alg, &signature, b"verification data").unwrap(),
+                "{alg:?} did not verify"
+            );
+            assert!(!pkey_verify_signature(&public, alg, &signature, b"other data").unwrap());
+
+            // A signature over the SHA-256 digest must not be accepted.
+            let signature = sign(hash::MessageDigest::sha256());
+            assert!(
+                !pkey_verify_signature(&public, alg, &signature, b"verification data").unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn cbor_ed448_unsupported() {
+        let val = Value::Map(std::collections::BTreeMap::from([
+            (Value::Integer(1), Value::Integer(1)),
+            (Value::Integer(3), Value::Integer(-8)),
+            (Value::Integer(-1), Value::Integer(7)),
+            (Value::Integer(-2), Value::Bytes(vec![0x42; 57])),
+        ]));
+        assert!(matches!(
+            COSEKey::try_from(&val),
+            Err(WebauthnError::COSEKeyEd448Unsupported)
+        ));
+    }
+
+    #[test]
+    fn cbor_ec2_compressed_point_rejected() {
+        let x = hex!("65eda5a12577c2bae829437fe338701a10aaa375e1bb5b5de108de439c08551d");
+        let y = hex!("1e52ed75701163f7f9e40ddf9f341b3dc9ba860af7e0ca7ca7e9eecd0084d19c");
+
+        let ec2_key = |x: Value, y: Value| {
+            Value::Map(std::collections::BTreeMap::from([
+                (Value::Integer(1), Value::Integer(2)),
+                (Value::Integer(3), Value::Integer(-7)),
+                (Value::Integer(-1), Value::Integer(1)),
+                (Value::Integer(-2), x),
+                (Value::Integer(-3), y),
+            ]))
+        };
+
+        // The sign bit form of point compression from RFC8152.
+        let val = ec2_key(Value::Bytes(x.to_vec()), Value::Bool(false));
+        assert!(matches!(
+            COSEKey::try_from(&val),
+            Err(WebauthnError::COSEKeyECDSAInvalidPointFormat)
+        ));
+
+        // A compressed X9.62 point in place of the x coordinate.
+        let mut compressed = vec![0x02];
+        compressed.extend_from_slice(&x);
+        let val = ec2_key(Value::Bytes(compressed), Value::Bytes(y.to_vec()));
+        assert!(matches!(
+            COSEKey::try_from(&val),
+            Err(WebauthnError::COSEKeyECDSAInvalidPointFormat)
+        ));
+
+        // Only the uncompressed coordinate pair is accepted.
+        let val = ec2_key(Value::Bytes(x.to_vec()), Value::Bytes(y.to_vec()));
+        let key = COSEKey::try_from(&val).unwrap();
+        assert_eq!(
+            key.get_alg_key_ecc_x962_raw().unwrap(),
+            [&[0x04][..], &x, &y].concat()
+        );
+    }
+
+    #[test]
+    fn cbor_string_labels_lenient() {
+        let val = Value::Map(std::collections::BTreeMap::from([
+            (Value::Text("kty".to_string()), Value::Integer(2)),
+            (Value::Text("alg".to_string()), Value::Integer(-7)),
+            (Value::Text("crv".to_string()), Value::Integer(1)),
+            (
+                Value::Text("x".to_string()),
+                Value::Bytes(
+                    hex!("65eda5a12577c2bae829437fe338701a10aaa375e1bb5b5de108de439c08551d")
+                        .to_vec(),
+                ),
+            ),
+            (
+                Value::Text("y".to_string()),
+                Value::Bytes(
+                    hex!("1e52ed75701163f7f9e40ddf9f341b3dc9ba860af7e0ca7ca7e9eecd0084d19c")
+                        .to_vec(),
+                ),
+            ),
+        ]));
+
+        assert!(matches!(
+            COSEKey::try_from(&val),
+            Err(WebauthnError::COSEKeyInvalidCBORValue)
+        ));
+
+        let key = COSEKey::try_from_lenient(&val).unwrap();
+        assert_eq!(key.type_, COSEAlgorithm::ES256);
+        match key.key {
+            COSEKeyType::EC_EC2(pkey) => {
+                assert_eq!(
+                    pkey.x.as_ref(),
+                    hex!("65eda5a12577c2bae829437fe338701a10aaa375e1bb5b5de108de439c08551d")
+                );
+                assert_eq!(pkey.curve, ECDSACurve::SECP256R1);
+            }
+            _ => panic!("Key should be parsed EC2 key"),
+        }
+    }
+
+    #[test]
+    fn cbor_rsa_key_too_small() {
+        // A modulus of the expected length, that is padded with a leading zero byte.
+        let mut n = vec![0xff; 256];
+        n[0] = 0;
+
+        let val = Value::Map(std::collections::BTreeMap::from([
+            (Value::Integer(1), Value::Integer(3)),
+            (Value::Integer(3), Value::Integer(-257)),
+            (Value::Integer(-1), Value::Bytes(n.clone())),
+            (Value::Integer(-2), Value::Bytes(vec![1, 0, 1])),
+        ]));
+        assert!(matches!(
+            COSEKey::try_from(&val),
+            Err(WebauthnError::CredentialRsaKeyTooSmall)
+        ));
+
+        let key = COSEKey {
+            type_: COSEAlgorithm::RS256,
+            key: COSEKeyType::RSA(COSERSAKey {
+                n: n.into(),
+                e: [1, 0, 1],
+            }),
+        };
+        assert_eq!(key.rsa_key_bits(), Some(2040));
+
+        let key = COSEKey {
+            type_: COSEAlgorithm::RS256,
+            key: COSEKeyType::RSA(COSERSAKey {
+                n: vec![0x80; 384].into(),
+                e: [1, 0, 1],
+            }),
+        };
+        assert_eq!(key.rsa_key_bits(), Some(3072));
+    }
+
+    #[test]
+    fn cose_key_to_cbor_round_trip() {
+        let round_trip = |key: COSEKey| {
+            let cbor = key.to_cbor().unwrap();
+            let value: Value = serde_cbor_2::from_slice(&cbor).unwrap();
+            assert_eq!(COSEKey::try_from(&value).unwrap(), key);
+        };
+
+        let group = ec::EcGroup::from_curve_name(nid::Nid::X9_62_PRIME256V1).unwrap();
+        let ec_key = ec::EcKey::generate(&group).unwrap();
+        let mut ctx = bn::BigNumContext::new().unwrap();
+        let mut x = bn::BigNum::new().unwrap();
+        let mut y = bn::BigNum::new().unwrap();
+        ec_key
+            .public_key()
+            .affine_coordinates_gfp(&group, &mut x, &mut y, &mut ctx)
+            .unwrap();
+        round_trip(COSEKey {
+            type_: COSEAlgorithm::ES256,
+            key: COSEKeyType::EC_EC2(COSEEC2Key {
+                curve: ECDSACurve::SECP256R1,
+                x: x.to_vec_padded(32).unwrap().into(),
+                y: y.to_vec_padded(32).unwrap().into(),
+            }),
+        });
+
+        let rsa_key = openssl::rsa::Rsa::generate(2048).unwrap();
+        round_trip(COSEKey {
+            type_: COSEAlgorithm::RS256,
+            key: COSEKeyType::RSA(COSERSAKey {
+                n: rsa_key.n().to_vec_padded(256).unwrap().into(),
+                e: [1, 0, 1],
+            }),
+        });
+
+        let ed_key = pkey::PKey::generate_ed25519().unwrap();
+        let mut ed_x = [0; 32];
+        ed_x.copy_from_slice(&ed_key.raw_public_key().unwrap());
+        round_trip(COSEKey {
+            type_: COSEAlgorithm::EDDSA,
+            key: COSEKeyType::EC_OKP(COSEOKPKey {
+                curve: EDDSACurve::ED25519,
+                x: ed_x,
+            }),
+        });
+
+        // A key as it was provided by an authenticator is encoded with the same labels.
+        let value = Value::Map(std::collections::BTreeMap::from([
+            (Value::Integer(1), Value::Integer(2)),
+            (Value::Integer(3), Value::Integer(-7)),
+            (Value::Integer(-1), Value::Integer(1)),
+            (
+                Value::Integer(-2),
+                Value::Bytes(x.to_vec_padded(32).unwrap()),
+            ),
+            (
+                Value::Integer(-3),
+                Value::Bytes(y.to_vec_padded(32).unwrap()),
+            ),
+        ]));
+        let key = COSEKey::try_from(&value).unwrap();
+        assert_eq!(
+            key.to_cbor().unwrap(),
+            serde_cbor_2::to_vec(&value).unwrap()
+        );
+    }
+
+    #[test]
+    fn cbor_rsa_exponent_length() {
+        let rsa_key = openssl::rsa::Rsa::generate(2048).unwrap();
+        let n = rsa_key.n().to_vec();
+        assert_eq!(n.len(), 256);
+
+        let val = |e: Vec<u8>| {
+            Value::Map(std::collections::BTreeMap::from([
+                (Value::Integer(1), Value::Integer(3)),
+                (Value::Integer(3), Value::Integer(-257)),
+                (Value::Integer(-1), Value::Bytes(n.clone())),
+                (Value::Integer(-2), Value::Bytes(e)),
+            ]))
+        };
+
+        // The standard 3 byte form, and the same exponent padded to 4 bytes.
+        for e in [vec![1, 0, 1], vec![0, 1, 0, 1]] {
+            let key = COSEKey::try_from(&val(e)).unwrap();
+            match key.key {
+                COSEKeyType::RSA(rsak) => assert_eq!(rsak.e, [1, 0, 1]),
+                _ => panic!("Key should be parsed RSA key"),
+            }
+        }
+
+        // A minimally encoded exponent is left padded.
+        let key = COSEKey::try_from(&val(vec![3])).unwrap();
+        match key.key {
+            COSEKeyType::RSA(rsak) => assert_eq!(rsak.e, [0, 0, 3]),
+            _ => panic!("Key should be parsed RSA key"),
+        }
+
+        // Exponents that are empty, zero, or too large to be stored are rejected.
+        for e in [vec![], vec![0, 0, 0], vec![1, 0, 0, 1], vec![0, 0, 1, 0, 1]] {
+            assert!(matches!(
+                COSEKey::try_from(&val(e)),
+                Err(WebauthnError::COSEKeyRSANEInvalid)
+            ));
+        }
+    }
+
     #[test]
     fn cbor_es256() {
         let hex_data = hex!(
@@ -913,4 +1439,71 @@ mod tests {
             _ => panic!("Key should be parsed EC2 key"),
         }
     }
+
+    #[test]
+    fn jwk_thumbprint_es256() {
+        let key = COSEKey {
+            type_: COSEAlgorithm::ES256,
+            key: COSEKeyType::EC_EC2(COSEEC2Key {
+                curve: ECDSACurve::SECP256R1,
+                x: hex!("65eda5a12577c2bae829437fe338701a10aaa375e1bb5b5de108de439c08551d")
+                    .to_vec()
+                    .into(),
+                y: hex!("1e52ed75701163f7f9e40ddf9f341b3dc9ba860af7e0ca7ca7e9eecd0084d19c")
+                    .to_vec()
+                    .into(),
+            }),
+        };
+
+        assert_eq!(
+            key.jwk_thumbprint().unwrap(),
+            hex!("1ec4856a5c30df23fe74efa558662015cc95e47db6a1270815ce15d98e863ada")
+        );
+    }
+
+    #[test]
+    fn jwk_thumbprint_rs256() {
+        // https://www.rfc-editor.org/rfc/rfc7638#section-3.1
+        let key = COSEKey {
+            type_: COSEAlgorithm::RS256,
+            key: COSEKeyType::RSA(COSERSAKey {
+                n: hex!(
+                    "d2fc7b6a0a1e6c67104aeb8f88b257669b4df679ddad099b5c4a6cd9a88015b5
+                     a133bf0b856c7871b6df000b554fceb3c2ed512bb68f145c6e8434752fab52a1
+                     cfc124408f79b58a4578c16428855789f7a249e384cb2d9fae2d67fd96fb926c
+                     198e077399fdc815c0af097dde5aadeff44de70e827f4878432439bfeeb96068
+                     d0474fc50d6d90bf3a98dfaf1040c89c02d692ab3b3c2896609d86fd73b774ce
+                     0740647ceeeaa310bd12f985a8eb9f59fdd426cea5b2120f4f2a34bcab764b7e
+                     6c54d6840238bcc40587a59e66ed1f33894577635c470af75cf92c20d1da43e1
+                     bfc419e222a6f0d0bb358c5e38f9cb050aeafe904814f1ac1aa49cca9ea0ca83"
+                )
+                .to_vec()
+                .into(),
+                e: [0x01, 0x00, 0x01],
+            }),
+        };
+
+        // NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs
+        assert_eq!(
+            Base64UrlSafeData(key.jwk_thumbprint().unwrap().to_vec()).to_string(),
+            "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs"
+        );
+    }
+
+    #[test]
+    fn jwk_thumbprint_eddsa() {
+        // https://www.rfc-editor.org/rfc/rfc8037#appendix-A.3
+        let key = COSEKey {
+            type_: COSEAlgorithm::EDDSA,
+            key: COSEKeyType::EC_OKP(COSEOKPKey {
+                curve: EDDSACurve::ED25519,
+                x: hex!("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a"),
+            }),
+        };
+
+        assert_eq!(
+            Base64UrlSafeData(key.jwk_thumbprint().unwrap().to_vec()).to_string(),
+            "kPrK_qmxVWaYVA9wwBF6Iuo3vVzz7TxHCTwXBygrS4k"
+        );
+    }
 }
@@ -1,5 +1,6 @@
 //! Possible errors that may occur during Webauthn Operation processing
 
+use crate::proto::CredentialID;
 use base64::DecodeError as b64DecodeError;
 use openssl::error::ErrorStack as OpenSSLErrorStack;
 use serde_cbor_2::error::Error as CBORError;
@@ -17,6 +18,20 @@ pub enum WebauthnError {
     #[error("The configuration was invalid")]
     Configuration,
 
+    #[error(
+        "The relying party id {rp_id:?} is not an effective domain of the allowed origin {origin}"
+    )]
+    ConfigurationRpIdNotEffectiveDomain { rp_id: String, origin: String },
+
+    #[error("The allowed origin {origin} contains a wildcard, which clients will never match")]
+    ConfigurationWildcardOrigin { origin: String },
+
+    #[error("No secure credential algorithm is configured")]
+    ConfigurationNoSecureAlgorithm,
+
+    #[error("The credential algorithm {alg} is insecure, and must not be configured")]
+    ConfigurationInsecureAlgorithm { alg: String },
+
     #[error("The JSON from the client did not indicate webauthn.<method> correctly")]
     InvalidClientDataType,
 
@@ -31,6 +46,13 @@ pub enum WebauthnError {
     #[error("The clients relying party origin does not match our servers information")]
     InvalidRPOrigin,
 
+    #[error("The authenticator data rpIdHash does not match the hash of the configured relying party id {expected_rp_id:?}. Credentials are always bound to the rp_id, irrespective of subdomain origin settings")]
+    RpIdHashMismatch { expected_rp_id: String },
+
+    #[deprecated(
+        since = "0.5.0",
+        note = "This is no longer returned, use RpIdHashMismatch which names the expected rp_id."
+    )]
     #[error("The clients relying party id hash does not match the hash of our relying party id")]
     InvalidRPIDHash,
 
@@ -112,6 +134,9 @@ pub enum WebauthnError {
     #[error("The attestation Certificate's OID 1.2.840.113635.100.8.2 value does not match the computed nonce")]
     AttestationCertificateNonceMismatch,
 
+    #[error("The android-key attestation certificate's key description is invalid, or its challenge does not match the clientDataHash")]
+    AttestationStatementAndroidKeyInvalid,
+
     #[error("The attestation created by the TPM is not correct")]
     AttestationTpmStInvalid,
 
@@ -142,20 +167,38 @@ pub enum WebauthnError {
     #[error("The leaf certificate we intented to verify is missing.")]
     AttestationLeafCertMissing,
 
+    #[error("Attestation was required, but the authenticator did not provide it")]
+    AttestationNotProvided,
+
     #[error("The attestation was parsed, but is not a format valid for CA chain validation")]
     AttestationNotVerifiable,
 
     #[error("The attestation CA that was trusted limits the aaguids allowed, this device is not a member of that set")]
     AttestationUntrustedAaguid,
 
+    #[error("The attested device model is known to have security issues")]
+    AttestationDeviceVulnerable,
+
     #[error("The attestation CA that was trusted limits the aaguids allowed, but this device does not have an aaguid")]
     AttestationFormatMissingAaguid,
 
+    #[error("The attested aaguid of this device does not match the expected aaguid")]
+    AttestationAaguidMismatch,
+
     #[error(
         "The attestation was parsed, but is not trusted by one of the selected CA certificates"
     )]
     AttestationChainNotTrusted(String),
 
+    #[error("The attestation chain violates a name or path length constraint of a trusted CA")]
+    AttestationChainConstraintViolation(String),
+
+    #[error("A certificate of the attestation chain has expired")]
+    AttestationCertificateExpired,
+
+    #[error("A certificate of the attestation chain is not yet valid")]
+    AttestationCertificateNotYetValid,
+
     #[error("The X5C trust root is not a valid algorithm for signing")]
     CertificatePublicKeyInvalid,
 
@@ -186,7 +229,14 @@ pub enum WebauthnError {
     #[error("The COSEKey type is not supported by this implementation")]
     COSEKeyInvalidType,
 
-    #[error("ED25519 and ED448 keys are not supported by this implementation")]
+    #[error("ED448 keys are not supported by this implementation")]
+    COSEKeyEd448Unsupported,
+
+    #[deprecated(
+        since = "0.5.0",
+        note = "This is no longer returned, use COSEKeyEd448Unsupported."
+    )]
+    #[error("ED448 keys are not supported by this implementation")]
     COSEKeyEDUnsupported,
 
     #[error("The COSEKey contains invalid ECDSA X/Y coordinate data")]
@@ -198,6 +248,9 @@ pub enum WebauthnError {
     #[error("The COSEKey uses a curve that is not supported by this implementation")]
     COSEKeyECDSAInvalidCurve,
 
+    #[error("The COSEKey contains a compressed ECDSA point, but uncompressed X/Y coordinates are required")]
+    COSEKeyECDSAInvalidPointFormat,
+
     #[error("The COSEKey contains invalid EDDSA X coordinate data")]
     COSEKeyEDDSAXInvalid,
 
@@ -207,9 +260,15 @@ pub enum WebauthnError {
     #[error("The COSEKey contains invalid cryptographic algorithm request")]
     COSEKeyInvalidAlgorithm,
 
+    #[error("The credential id in the attested credential data does not match the rawId of the response")]
+    CredentialIdMismatch,
+
     #[error("The credential may be a passkey and not truly bound to hardware.")]
     CredentialMayNotBeHardwareBound,
 
+    #[error("The credential RSA key is smaller than the minimum size allowed")]
+    CredentialRsaKeyTooSmall,
+
     #[error("The credential uses insecure cryptographic routines and is not trusted")]
     CredentialInsecureCryptography,
 
@@ -228,12 +287,30 @@ pub enum WebauthnError {
     #[error("The credential requested could not be found")]
     CredentialNotFound,
 
+    #[error("The credential id is empty or longer than 1023 bytes")]
+    CredentialIdInvalidLength,
+
+    /// The credential that responded to a discoverable authentication is not one of the
+    /// credentials that were provided. This contains the id of the responding credential, so
+    /// that it can be distinguished from a failed signature - for example the user may have
+    /// authenticated with a credential that the server has since removed.
+    #[error("The responding credential {0} is not one of the provided credentials")]
+    DiscoverableCredentialNotFound(CredentialID),
+
     #[error("A credential alg that was not allowed in the request was attempted.")]
     CredentialAlteredAlgFromRequest,
 
+    #[error(
+        "The credential algorithm {alg} is recognised, but is not supported by this implementation"
+    )]
+    CredentialAlgorithmNotSupported { alg: String },
+
     #[error("A credential that was excluded in the request attempted to register.")]
     CredentialExcludedFromRequest,
 
+    #[error("A resident key was required, but the client reported that the authenticator did not create one.")]
+    ResidentKeyNotCreated,
+
     #[error("The credential may have be compromised and should be inspected")]
     CredentialPossibleCompromise,
 
@@ -246,6 +323,15 @@ pub enum WebauthnError {
     #[error("The backup (passkey) elligibility of this device has changed, meaning it must be re-enrolled for security validation")]
     CredentialBackupElligibilityInconsistent,
 
+    #[error("The credential was backup eligible, but the authenticator reports it is no longer backup eligible")]
+    BackupEligibilityChanged,
+
+    #[error("The authenticator attachment reported by the client does not match the required attachment")]
+    AuthenticatorAttachmentMismatch,
+
+    #[error("The client response exceeds the maximum allowed size")]
+    ResponseTooLarge,
+
     #[error("The trust path could not be established")]
     TrustFailure,
 
@@ -261,6 +347,12 @@ pub enum WebauthnError {
     #[error("Invalid UserID supplied during authentication")]
     InvalidUserUniqueId,
 
+    #[error("Invalid user handle supplied, it must be between 1 and 64 bytes")]
+    InvalidUserHandle,
+
+    #[error("The registration hints are invalid, each hint may only be supplied once")]
+    InvalidRegistrationHints,
+
     #[error("Supplied Nid does not correspond to a supported ECDSA curve")]
     ECDSACurveInvalidNid,
 
@@ -291,8 +383,441 @@ pub enum WebauthnError {
     SshPublicKeyInconsistentUserVerification,
 }
 
+impl WebauthnError {
+    /// A generic message describing this error that is safe to show to end users. Unlike the
+    /// `Display` implementation, this never reveals which check failed (for example, an origin
+    /// mismatch or a counter regression), as that detail can aid an attacker. Log the error
+    /// itself for diagnostics.
+    #[allow(deprecated)]
+    pub fn user_facing_message(&self) -> &'static str {
+        match self {
+            WebauthnError::InvalidClientDataType
+            | WebauthnError::MismatchedChallenge
+            | WebauthnError::ChallengeNotFound
+            | WebauthnError::InvalidRPOrigin
+            | WebauthnError::RpIdHashMismatch { .. }
+            | WebauthnError::InvalidRPIDHash
+            | WebauthnError::UserNotPresent
+            | WebauthnError::UserNotVerified
+            | WebauthnError::InvalidExtensions
+            | WebauthnError::AuthenticatorDataMissingExtension
+            | WebauthnError::ParseBase64Failure(_)
+            | WebauthnError::ParseCBORFailure(_)
+            | WebauthnError::ParseJSONFailure(_)
+            | WebauthnError::ParseNOMFailure
+            | WebauthnError::ParseInsufficientBytesAvailable
+            | WebauthnError::AuthenticatorAttachmentMismatch
+            | WebauthnError::ResponseTooLarge
+            | WebauthnError::TrustFailure
+            | WebauthnError::CredentialCrossOrigin => "The credential could not be verified",
+            WebauthnError::MissingAttestationCredentialData
+            | WebauthnError::AttestationNotSupported
+            | WebauthnError::AttestationStatementMapInvalid
+            | WebauthnError::AttestationStatementResponseMissing
+            | WebauthnError::AttestationStatementResponseInvalid
+            | WebauthnError::AttestationStatementSigMissing
+            | WebauthnError::AttestationStatementSigInvalid
+            | WebauthnError::AttestationStatementVerMissing
+            | WebauthnError::AttestationStatementVerInvalid
+            | WebauthnError::AttestationStatementVerUnsupported
+            | WebauthnError::AttestationStatementX5CMissing
+            | WebauthnError::AttestationStatementX5CInvalid
+            | WebauthnError::AttestationStatementAlgMissing
+            | WebauthnError::AttestationStatementCertInfoMissing
+            | WebauthnError::AttestationStatementMissingExtension
+            | WebauthnError::AttestationStatementPubAreaMissing
+            | WebauthnError::AttestationStatementAlgMismatch
+            | WebauthnError::AttestationStatementAlgInvalid
+            | WebauthnError::AttestationTrustFailure
+            | WebauthnError::AttestationCertificateAAGUIDMismatch
+            | WebauthnError::AttestationCertificateNonceMismatch
+            | WebauthnError::AttestationStatementAndroidKeyInvalid
+            | WebauthnError::AttestationTpmStInvalid
+            | WebauthnError::AttestationTpmPubAreaMismatch
+            | WebauthnError::AttestationTpmExtraDataInvalid
+            | WebauthnError::AttestationTpmExtraDataMismatch
+            | WebauthnError::AttestationTpmPubAreaHashUnknown
+            | WebauthnError::AttestationTpmPubAreaHashInvalid
+            | WebauthnError::AttestationTpmAttestCertifyInvalid
+            | WebauthnError::AttestationCertificateRequirementsNotMet
+            | WebauthnError::AttestationLeafCertMissing
+            | WebauthnError::AttestationNotProvided
+            | WebauthnError::AttestationNotVerifiable
+            | WebauthnError::AttestationUntrustedAaguid
+            | WebauthnError::AttestationDeviceVulnerable
+            | WebauthnError::AttestationFormatMissingAaguid
+            | WebauthnError::AttestationAaguidMismatch
+            | WebauthnError::AttestationChainNotTrusted(_)
+            | WebauthnError::AttestationChainConstraintViolation(_)
+            | WebauthnError::AttestationCertificateExpired
+            | WebauthnError::AttestationCertificateNotYetValid
+            | WebauthnError::CertificatePublicKeyInvalid
+            | WebauthnError::COSEKeyInvalidCBORValue
+            | WebauthnError::COSEKeyInvalidType
+            | WebauthnError::COSEKeyEd448Unsupported
+            | WebauthnError::COSEKeyEDUnsupported
+            | WebauthnError::COSEKeyECDSAXYInvalid
+            | WebauthnError::COSEKeyRSANEInvalid
+            | WebauthnError::COSEKeyECDSAInvalidCurve
+            | WebauthnError::COSEKeyECDSAInvalidPointFormat
+            | WebauthnError::COSEKeyEDDSAXInvalid
+            | WebauthnError::COSEKeyEDDSAInvalidCurve
+            | WebauthnError::COSEKeyInvalidAlgorithm
+            | WebauthnError::CredentialIdMismatch
+            | WebauthnError::CredentialMayNotBeHardwareBound
+            | WebauthnError::CredentialRsaKeyTooSmall
+            | WebauthnError::CredentialInsecureCryptography
+            | WebauthnError::CredentialAlreadyExists
+            | WebauthnError::CredentialAlteredAlgFromRequest
+            | WebauthnError::CredentialAlgorithmNotSupported { .. }
+            | WebauthnError::CredentialExcludedFromRequest
+            | WebauthnError::ResidentKeyNotCreated
+            | WebauthnError::ECDSACurveInvalidNid
+            | WebauthnError::AttestationCredentialSubjectKeyMismatch
+            | WebauthnError::SshPublicKeyBackupState
+            | WebauthnError::SshPublicKeyEDUnsupported
+            | WebauthnError::SshPublicKeyInvalidCurve
+            | WebauthnError::SshPublicKeyInvalidPubkey
+            | WebauthnError::SshPublicKeyInconsistentUserVerification => "Registration failed",
+            WebauthnError::CredentialNotFound
+            | WebauthnError::DiscoverableCredentialNotFound(_)
+            | WebauthnError::CredentialPossibleCompromise
+            | WebauthnError::CredentialBackupElligibilityInconsistent
+            | WebauthnError::BackupEligibilityChanged
+            | WebauthnError::AuthenticationFailure
+            | WebauthnError::InconsistentUserVerificationPolicy => "Authentication failed",
+            WebauthnError::InvalidUsername
+            | WebauthnError::InvalidUserUniqueId
+            | WebauthnError::InvalidUserHandle => "The provided user details are invalid",
+            WebauthnError::Configuration
+            | WebauthnError::ConfigurationRpIdNotEffectiveDomain { .. }
+            | WebauthnError::ConfigurationWildcardOrigin { .. }
+            | WebauthnError::ConfigurationNoSecureAlgorithm
+            | WebauthnError::ConfigurationInsecureAlgorithm { .. }
+            | WebauthnError::ChallengePersistenceError
+            | WebauthnError::AttestationCertificateTrustStoreEmpty
+            | WebauthnError::OpenSSLError(_)
+            | WebauthnError::OpenSSLErrorNoCurveName
+            | WebauthnError::CredentialExistCheckError
+            | WebauthnError::CredentialPersistenceError
+            | WebauthnError::CredentialRetrievalError
+            | WebauthnError::CredentialIdInvalidLength
+            | WebauthnError::CredentialCounterUpdateFailure
+            | WebauthnError::CredentialCompromiseReportFailure
+            | WebauthnError::InvalidRegistrationHints
+            | WebauthnError::MissingAttestationCaList => "An internal error occurred",
+        }
+    }
+}
+
 impl PartialEq for WebauthnError {
     fn eq(&self, other: &Self) -> bool {
         std::mem::discriminant(self) == std::mem::discriminant(other)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    /// The number of variants of WebauthnError.
+    const VARIANTS: usize = 115;
+
+    /// The index of a variant. This match has no wildcard, so a new variant must be added here
+    /// (and to `every_error`) before these tests compile again.
+    #[allow(deprecated)]
+    fn variant_index(err: &WebauthnError) -> usize {
+        match err {
+            WebauthnError::Configuration => 0,
+            WebauthnError::ConfigurationRpIdNotEffectiveDomain { .. } => 1,
+            WebauthnError::ConfigurationWildcardOrigin { .. } => 2,
+            WebauthnError::ConfigurationNoSecureAlgorithm => 3,
+            WebauthnError::ConfigurationInsecureAlgorithm { .. } => 4,
+            WebauthnError::InvalidClientDataType => 5,
+            WebauthnError::MismatchedChallenge => 6,
+            WebauthnError::ChallengeNotFound => 7,
+            WebauthnError::InvalidRPOrigin => 8,
+            WebauthnError::RpIdHashMismatch { .. } => 9,
+            WebauthnError::InvalidRPIDHash => 10,
+            WebauthnError::UserNotPresent => 11,
+            WebauthnError::UserNotVerified => 12,
+            WebauthnError::InvalidExtensions => 13,
+            WebauthnError::AuthenticatorDataMissingExtension => 14,
+            WebauthnError::MissingAttestationCredentialData => 15,
+            WebauthnError::AttestationNotSupported => 16,
+            WebauthnError::ChallengePersistenceError => 17,
+            WebauthnError::AttestationStatementMapInvalid => 18,
+            WebauthnError::AttestationStatementResponseMissing => 19,
+            WebauthnError::AttestationStatementResponseInvalid => 20,
+            WebauthnError::AttestationStatementSigMissing => 21,
+            WebauthnError::AttestationStatementSigInvalid => 22,
+            WebauthnError::AttestationStatementVerMissing => 23,
+            WebauthnError::AttestationStatementVerInvalid => 24,
+            WebauthnError::AttestationStatementVerUnsupported => 25,
+            WebauthnError::AttestationStatementX5CMissing => 26,
+            WebauthnError::AttestationStatementX5CInvalid => 27,
+            WebauthnError::AttestationStatementAlgMissing => 28,
+            WebauthnError::AttestationStatementCertInfoMissing => 29,
+            WebauthnError::AttestationStatementMissingExtension => 30,
+            WebauthnError::AttestationStatementPubAreaMissing => 31,
+            WebauthnError::AttestationStatementAlgMismatch => 32,
+            WebauthnError::AttestationStatementAlgInvalid => 33,
+            WebauthnError::AttestationTrustFailure => 34,
+            WebauthnError::AttestationCertificateAAGUIDMismatch => 35,
+            WebauthnError::AttestationCertificateNonceMismatch => 36,
+            WebauthnError::AttestationStatementAndroidKeyInvalid => 37,
+            WebauthnError::AttestationTpmStInvalid => 38,
+            WebauthnError::AttestationTpmPubAreaMismatch => 39,
+            WebauthnError::AttestationTpmExtraDataInvalid => 40,
+            WebauthnError::AttestationTpmExtraDataMismatch => 41,
+            WebauthnError::AttestationTpmPubAreaHashUnknown => 42,
+            WebauthnError::AttestationTpmPubAreaHashInvalid => 43,
+            WebauthnError::AttestationTpmAttestCertifyInvalid => 44,
+            WebauthnError::AttestationCertificateRequirementsNotMet => 45,
+            WebauthnError::AttestationCertificateTrustStoreEmpty => 46,
+            WebauthnError::AttestationLeafCertMissing => 47,
+            WebauthnError::AttestationNotProvided => 48,
+            WebauthnError::AttestationNotVerifiable => 49,
+            WebauthnError::AttestationUntrustedAaguid => 50,
+            WebauthnError::AttestationDeviceVulnerable => 51,
+            WebauthnError::AttestationFormatMissingAaguid => 52,
+            WebauthnError::AttestationAaguidMismatch => 53,
+            WebauthnError::AttestationChainNotTrusted(_) => 54,
+            WebauthnError::AttestationChainConstraintViolation(_) => 55,
+            WebauthnError::AttestationCertificateExpired => 56,
+            WebauthnError::AttestationCertificateNotYetValid => 57,
+            WebauthnError::CertificatePublicKeyInvalid => 58,
+            WebauthnError::ParseBase64Failure(_) => 59,
+            WebauthnError::ParseCBORFailure(_) => 60,
+            WebauthnError::ParseJSONFailure(_) => 61,
+            WebauthnError::ParseNOMFailure => 62,
+            WebauthnError::ParseInsufficientBytesAvailable => 63,
+            WebauthnError::OpenSSLError(_) => 64,
+            WebauthnError::OpenSSLErrorNoCurveName => 65,
+            WebauthnError::COSEKeyInvalidCBORValue => 66,
+            WebauthnError::COSEKeyInvalidType => 67,
+            WebauthnError::COSEKeyEd448Unsupported => 68,
+            WebauthnError::COSEKeyEDUnsupported => 69,
+            WebauthnError::COSEKeyECDSAXYInvalid => 70,
+            WebauthnError::COSEKeyRSANEInvalid => 71,
+            WebauthnError::COSEKeyECDSAInvalidCurve => 72,
+            WebauthnError::COSEKeyECDSAInvalidPointFormat => 73,
+            WebauthnError::COSEKeyEDDSAXInvalid => 74,
+            WebauthnError::COSEKeyEDDSAInvalidCurve => 75,
+            WebauthnError::COSEKeyInvalidAlgorithm => 76,
+            WebauthnError::CredentialIdMismatch => 77,
+            WebauthnError::CredentialMayNotBeHardwareBound => 78,
+            WebauthnError::CredentialRsaKeyTooSmall => 79,
+            WebauthnError::CredentialInsecureCryptography => 80,
+            WebauthnError::CredentialExistCheckError => 81,
+            WebauthnError::CredentialAlreadyExists => 82,
+            WebauthnError::CredentialPersistenceError => 83,
+            WebauthnError::CredentialRetrievalError => 84,
+            WebauthnError::CredentialNotFound => 85,
+            WebauthnError::CredentialIdInvalidLength => 86,
+            WebauthnError::DiscoverableCredentialNotFound(_) => 87,
+            WebauthnError::CredentialAlteredAlgFromRequest => 88,
+            WebauthnError::CredentialAlgorithmNotSupported { .. } => 89,
+            WebauthnError::CredentialExcludedFromRequest => 90,
+            WebauthnError::ResidentKeyNotCreated => 91,
+            WebauthnError::CredentialPossibleCompromise => 92,
+            WebauthnError::CredentialCounterUpdateFailure => 93,
+            WebauthnError::CredentialCompromiseReportFailure => 94,
+            WebauthnError::CredentialBackupElligibilityInconsistent => 95,
+            WebauthnError::BackupEligibilityChanged => 96,
+            WebauthnError::AuthenticatorAttachmentMismatch => 97,
+            WebauthnError::ResponseTooLarge => 98,
+            WebauthnError::TrustFailure => 99,
+            WebauthnError::AuthenticationFailure => 100,
+            WebauthnError::InconsistentUserVerificationPolicy => 101,
+            WebauthnError::InvalidUsername => 102,
+            WebauthnError::InvalidUserUniqueId => 103,
+            WebauthnError::InvalidUserHandle => 104,
+            WebauthnError::InvalidRegistrationHints => 105,
+            WebauthnError::ECDSACurveInvalidNid => 106,
+            WebauthnError::AttestationCredentialSubjectKeyMismatch => 107,
+            WebauthnError::CredentialCrossOrigin => 108,
+            WebauthnError::MissingAttestationCaList => 109,
+            WebauthnError::SshPublicKeyBackupState => 110,
+            WebauthnError::SshPublicKeyEDUnsupported => 111,
+            WebauthnError::SshPublicKeyInvalidCurve => 112,
+            WebauthnError::SshPublicKeyInvalidPubkey => 113,
+            WebauthnError::SshPublicKeyInconsistentUserVerification => 114,
+        }
+    }
+
+    /// One instance of every variant of WebauthnError, with detail that must not leak.
+    #[allow(deprecated)]
+    fn every_error() -> Vec<WebauthnError> {
+        vec![
+            WebauthnError::Configuration,
+            WebauthnError::ConfigurationRpIdNotEffectiveDomain {
+                rp_id: "example.com".to_string(),
+                origin: "https://idm.different.com".to_string(),
+            },
+            WebauthnError::ConfigurationWildcardOrigin {
+                origin: "https://*.example.com".to_string(),
+            },
+            WebauthnError::ConfigurationNoSecureAlgorithm,
+            WebauthnError::ConfigurationInsecureAlgorithm {
+                alg: "INSECURE_RS1".to_string(),
+            },
+            WebauthnError::InvalidClientDataType,
+            WebauthnError::MismatchedChallenge,
+            WebauthnError::ChallengeNotFound,
+            WebauthnError::InvalidRPOrigin,
+            WebauthnError::RpIdHashMismatch {
+                expected_rp_id: "example.com".to_string(),
+            },
+            WebauthnError::InvalidRPIDHash,
+            WebauthnError::UserNotPresent,
+            WebauthnError::UserNotVerified,
+            WebauthnError::InvalidExtensions,
+            WebauthnError::AuthenticatorDataMissingExtension,
+            WebauthnError::MissingAttestationCredentialData,
+            WebauthnError::AttestationNotSupported,
+            WebauthnError::ChallengePersistenceError,
+            WebauthnError::AttestationStatementMapInvalid,
+            WebauthnError::AttestationStatementResponseMissing,
+            WebauthnError::AttestationStatementResponseInvalid,
+            WebauthnError::AttestationStatementSigMissing,
+            WebauthnError::AttestationStatementSigInvalid,
+            WebauthnError::AttestationStatementVerMissing,
+            WebauthnError::AttestationStatementVerInvalid,
+            WebauthnError::AttestationStatementVerUnsupported,
+            WebauthnError::AttestationStatementX5CMissing,
+            WebauthnError::AttestationStatementX5CInvalid,
+            WebauthnError::AttestationStatementAlgMissing,
+            WebauthnError::AttestationStatementCertInfoMissing,
+            WebauthnError::AttestationStatementMissingExtension,
+            WebauthnError::AttestationStatementPubAreaMissing,
+            WebauthnError::AttestationStatementAlgMismatch,
+            WebauthnError::AttestationStatementAlgInvalid,
+            WebauthnError::AttestationTrustFailure,
+            WebauthnError::AttestationCertificateAAGUIDMismatch,
+            WebauthnError::AttestationCertificateNonceMismatch,
+            WebauthnError::AttestationStatementAndroidKeyInvalid,
+            WebauthnError::AttestationTpmStInvalid,
+            WebauthnError::AttestationTpmPubAreaMismatch,
+            WebauthnError::AttestationTpmExtraDataInvalid,
+            WebauthnError::AttestationTpmExtraDataMismatch,
+            WebauthnError::AttestationTpmPubAreaHashUnknown,
+            WebauthnError::AttestationTpmPubAreaHashInvalid,
+            WebauthnError::AttestationTpmAttestCertifyInvalid,
+            WebauthnError::AttestationCertificateRequirementsNotMet,
+            WebauthnError::AttestationCertificateTrustStoreEmpty,
+            WebauthnError::AttestationLeafCertMissing,
+            WebauthnError::AttestationNotProvided,
+            WebauthnError::AttestationNotVerifiable,
+            WebauthnError::AttestationUntrustedAaguid,
+            WebauthnError::AttestationDeviceVulnerable,
+            WebauthnError::AttestationFormatMissingAaguid,
+            WebauthnError::AttestationAaguidMismatch,
+            WebauthnError::AttestationChainNotTrusted("certificate has expired".to_string()),
+            WebauthnError::AttestationChainConstraintViolation(
+                "permitted subtree violation".to_string(),
+            ),
+            WebauthnError::AttestationCertificateExpired,
+            WebauthnError::AttestationCertificateNotYetValid,
+            WebauthnError::CertificatePublicKeyInvalid,
+            WebauthnError::ParseBase64Failure(b64DecodeError::InvalidLength),
+            WebauthnError::ParseCBORFailure(serde_cbor_2::from_slice::<u8>(&[]).unwrap_err()),
+            WebauthnError::ParseJSONFailure(serde_json::from_str::<u8>("").unwrap_err()),
+            WebauthnError::ParseNOMFailure,
+            WebauthnError::ParseInsufficientBytesAvailable,
+            WebauthnError::OpenSSLError(OpenSSLErrorStack::get()),
+            WebauthnError::OpenSSLErrorNoCurveName,
+            WebauthnError::COSEKeyInvalidCBORValue,
+            WebauthnError::COSEKeyInvalidType,
+            WebauthnError::COSEKeyEd448Unsupported,
+            WebauthnError::COSEKeyEDUnsupported,
+            WebauthnError::COSEKeyECDSAXYInvalid,
+            WebauthnError::COSEKeyRSANEInvalid,
+            WebauthnError::COSEKeyECDSAInvalidCurve,
+            WebauthnError::COSEKeyECDSAInvalidPointFormat,
+            WebauthnError::COSEKeyEDDSAXInvalid,
+            WebauthnError::COSEKeyEDDSAInvalidCurve,
+            WebauthnError::COSEKeyInvalidAlgorithm,
+            WebauthnError::CredentialIdMismatch,
+            WebauthnError::CredentialMayNotBeHardwareBound,
+            WebauthnError::CredentialRsaKeyTooSmall,
+            WebauthnError::CredentialInsecureCryptography,
+            WebauthnError::CredentialExistCheckError,
+            WebauthnError::CredentialAlreadyExists,
+            WebauthnError::CredentialPersistenceError,
+            WebauthnError::CredentialRetrievalError,
+            WebauthnError::CredentialNotFound,
+            WebauthnError::CredentialIdInvalidLength,
+            WebauthnError::DiscoverableCredentialNotFound(vec![0; 16].into()),
+            WebauthnError::CredentialAlteredAlgFromRequest,
+            WebauthnError::CredentialAlgorithmNotSupported {
+                alg: "EDDSA".to_string(),
+            },
+            WebauthnError::CredentialExcludedFromRequest,
+            WebauthnError::ResidentKeyNotCreated,
+            WebauthnError::CredentialPossibleCompromise,
+            WebauthnError::CredentialCounterUpdateFailure,
+            WebauthnError::CredentialCompromiseReportFailure,
+            WebauthnError::CredentialBackupElligibilityInconsistent,
+            WebauthnError::BackupEligibilityChanged,
+            WebauthnError::AuthenticatorAttachmentMismatch,
+            WebauthnError::ResponseTooLarge,
+            WebauthnError::TrustFailure,
+            WebauthnError::AuthenticationFailure,
+            WebauthnError::InconsistentUserVerificationPolicy,
+            WebauthnError::InvalidUsername,
+            WebauthnError::InvalidUserUniqueId,
+            WebauthnError::InvalidUserHandle,
+            WebauthnError::InvalidRegistrationHints,
+            WebauthnError::ECDSACurveInvalidNid,
+            WebauthnError::AttestationCredentialSubjectKeyMismatch,
+            WebauthnError::CredentialCrossOrigin,
+            WebauthnError::MissingAttestationCaList,
+            WebauthnError::SshPublicKeyBackupState,
+            WebauthnError::SshPublicKeyEDUnsupported,
+            WebauthnError::SshPublicKeyInvalidCurve,
+            WebauthnError::SshPublicKeyInvalidPubkey,
+            WebauthnError::SshPublicKeyInconsistentUserVerification,
+        ]
+    }
+
+    #[test]
+    fn every_error_is_complete() {
+        let indexes: BTreeSet<usize> = every_error().iter().map(variant_index).collect();
+        assert_eq!(indexes, (0..VARIANTS).collect());
+    }
+
+    #[test]
+    fn user_facing_message_is_generic() {
+        for err in every_error() {
+            let msg = err.user_facing_message().to_lowercase();
+            for detail in [
+                "cert",
+                "origin",
+                "counter",
+                "compromise",
+                "example.com",
+                "rp",
+                "aaguid",
+                "x5c",
+                "subtree",
+                "expired",
+                "openssl",
+                "challenge",
+            ] {
+                assert!(
+                    !msg.contains(detail),
+                    "user facing message for {err:?} contains {detail}"
+                );
+            }
+        }
+
+        // The detailed message is still available for logs.
+        assert!(WebauthnError::RpIdHashMismatch {
+            expected_rp_id: "example.com".to_string(),
+        }
+        .to_string()
+        .contains("example.com"));
+    }
+}
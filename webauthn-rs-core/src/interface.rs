@@ -5,6 +5,7 @@ use crate::attestation::{verify_attestation_ca_chain, AttestationFormat};
 use crate::error::*;
 pub use crate::internals::AttestationObject;
 use std::fmt;
+use std::time::SystemTime;
 use webauthn_rs_proto::cose::*;
 use webauthn_rs_proto::extensions::*;
 use webauthn_rs_proto::options::*;
@@ -39,6 +40,55 @@ pub struct RegistrationState {
     pub(crate) authenticator_attachment: Option<AuthenticatorAttachment>,
     pub(crate) extensions: RequestRegistrationExtensions,
     pub(crate) experimental_allow_passkeys: bool,
+    #[serde(default)]
+    pub(crate) platform_authenticator: Option<PlatformAuthenticatorAvailability>,
+    #[serde(default)]
+    pub(crate) ignore_attestation: bool,
+}
+
+impl RegistrationState {
+    /// Record the platform authenticator availability that the client reported for this
+    /// ceremony. This is informational only, and does not affect verification.
+    pub fn set_platform_authenticator_availability(
+        &mut self,
+        availability: PlatformAuthenticatorAvailability,
+    ) {
+        self.platform_authenticator = Some(availability);
+    }
+
+    /// The platform authenticator availability that was recorded for this ceremony, if any.
+    pub fn platform_authenticator_availability(&self) -> Option<PlatformAuthenticatorAvailability> {
+        self.platform_authenticator
+    }
+
+    /// Ignore any attestation statement the client returns for this ceremony. The authenticator
+    /// data and credential public key are still verified, but the credential is registered as
+    /// if the `none` attestation format was used.
+    ///
+    /// This must only be set when attestation was not requested, as the client is free to return
+    /// an attestation the relying party did not ask for. It has no effect when an attestation CA
+    /// list is provided to [`register_credential`](crate::WebauthnCore::register_credential).
+    pub fn set_ignore_attestation(&mut self, ignore_attestation: bool) {
+        self.ignore_attestation = ignore_attestation;
+    }
+
+    /// The challenge that was issued for this ceremony. This can be logged to correlate the
+    /// ceremony with the client response, but does not allow the ceremony to be completed.
+    pub fn challenge(&self) -> &[u8] {
+        self.challenge.as_ref()
+    }
+
+    /// Replace the challenge of this ceremony with a pre-agreed value, so that a fixed fixture
+    /// response can be verified against it.
+    ///
+    /// ⚠️  This is ONLY for tests. A challenge that is not freshly generated for every ceremony
+    /// allows responses to be replayed, defeating the purpose of the challenge.
+    ///
+    /// *requires feature danger-set-challenge*
+    #[cfg(feature = "danger-set-challenge")]
+    pub fn danger_set_challenge(&mut self, challenge: Vec<u8>) {
+        self.challenge = challenge.into();
+    }
 }
 
 /// The in progress state of an authentication attempt. You must persist this associated to the UserID
@@ -50,9 +100,35 @@ pub struct AuthenticationState {
     pub(crate) challenge: Base64UrlSafeData,
     pub(crate) appid: Option<String>,
     pub(crate) allow_backup_eligible_upgrade: bool,
+    #[serde(default)]
+    pub(crate) platform_authenticator: Option<PlatformAuthenticatorAvailability>,
+    #[serde(default)]
+    pub(crate) allowed_credential_ids: Option<Vec<CredentialID>>,
+    #[serde(default)]
+    pub(crate) user_verification_fallback: bool,
 }
 
 impl AuthenticationState {
+    /// Record the platform authenticator availability that the client reported for this
+    /// ceremony. This is informational only, and does not affect verification.
+    pub fn set_platform_authenticator_availability(
+        &mut self,
+        availability: PlatformAuthenticatorAvailability,
+    ) {
+        self.platform_authenticator = Some(availability);
+    }
+
+    /// The platform authenticator availability that was recorded for this ceremony, if any.
+    pub fn platform_authenticator_availability(&self) -> Option<PlatformAuthenticatorAvailability> {
+        self.platform_authenticator
+    }
+
+    /// The challenge that was issued for this ceremony. This can be logged to correlate the
+    /// ceremony with the client response, but does not allow the ceremony to be completed.
+    pub fn challenge(&self) -> &[u8] {
+        self.challenge.as_ref()
+    }
+
     /// set which credentials the user is allowed to authenticate with. This
     /// is used as part of resident key authentication flows where we need
     /// to inject the set of viable credentials after the client has sent us
@@ -60,6 +136,16 @@ impl AuthenticationState {
     pub fn set_allowed_credentials(&mut self, credentials: Vec<Credential>) {
         self.credentials = credentials;
     }
+
+    /// Accept a presence-only assertion from a credential that would otherwise be held to user
+    /// verification, because it performed it at registration. This is intended for an
+    /// accessibility fallback for users who can not perform user verification.
+    ///
+    /// ⚠️  The caller MUST apply compensating controls, and check
+    /// [AuthenticationResult::user_verified] once the authentication is complete.
+    pub fn allow_user_verification_fallback(&mut self) {
+        self.user_verification_fallback = true;
+    }
 }
 
 /// An EDDSACurve identifier. You probably will never need to alter
@@ -276,9 +362,34 @@ pub struct Credential {
     pub attestation: ParsedAttestation,
     /// the format of the attestation
     pub attestation_format: AttestationFormat,
+    /// The time at which this credential was registered. This is recorded by `webauthn-rs`
+    /// from its configured clock, and is `None` for credentials that were registered before
+    /// this was recorded, or without `webauthn-rs`.
+    #[serde(default)]
+    pub registered_at: Option<SystemTime>,
 }
 
 impl Credential {
+    /// Whether this credential reported during registration that it can be used over the
+    /// hybrid (formerly caBLE) transport, such as by scanning a QR code with a phone. This
+    /// is a hint only, and is false if the transports were not reported.
+    pub fn is_hybrid_capable(&self) -> bool {
+        self.transports
+            .as_ref()
+            .map(|transports| transports.contains(&AuthenticatorTransport::Hybrid))
+            .unwrap_or(false)
+    }
+
+    /// Whether this credential is bound to an authenticator that can verify the user, such as
+    /// with a PIN or biometric. This is true if the user was verified during registration, or
+    /// if the authenticator reported that it applied the credProtect policy
+    /// `UserVerificationRequired`, in which case the credential can not be used without it.
+    pub fn is_user_verification_capable(&self) -> bool {
+        self.user_verified
+            || self.extensions.effective_credential_protection_policy()
+                == Some(CredentialProtectionPolicy::UserVerificationRequired)
+    }
+
     /// Re-verify this Credential's attestation chain. This re-applies the same process
     /// for certificate authority verification that occured at registration. This can
     /// be useful if you want to re-assert your credentials match an updated or changed
@@ -332,6 +443,7 @@ impl From<CredentialV3> for Credential {
                 metadata: AttestationMetadata::None,
             },
             attestation_format: AttestationFormat::None,
+            registered_at: None,
         }
     }
 }
@@ -433,6 +545,10 @@ pub enum AttestationMetadata {
         /// This is the unique id of the class/type of device. Often this id can imply the
         /// properties of the device.
         aaguid: Uuid,
+        /// The options this model of device reports, if they were provided by the attestation
+        /// CA list this credential was registered with.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        authenticator_options: Option<AuthenticatorOptions>,
     },
     /// This is found on TPM authenticators.
     Tpm {
@@ -443,6 +559,10 @@ pub enum AttestationMetadata {
         /// if this updates later, which may require you to re-register the device if
         /// you need to enforce a version update.
         firmware_version: u64,
+        /// The options this model of device reports, if they were provided by the attestation
+        /// CA list this credential was registered with.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        authenticator_options: Option<AuthenticatorOptions>,
     },
     /// various attestation flags set by the device (attested by OS)
     AndroidKey {
@@ -467,6 +587,52 @@ pub enum AttestationMetadata {
     },
 }
 
+impl AttestationMetadata {
+    /// The aaguid of the device, if the attestation provided one.
+    pub fn aaguid(&self) -> Option<Uuid> {
+        match self {
+            AttestationMetadata::Packed { aaguid, .. }
+            | AttestationMetadata::Tpm { aaguid, .. } => Some(*aaguid),
+            _ => None,
+        }
+    }
+
+    /// The options that this model of device reports, if they were provided by the attestation
+    /// CA list at registration, such as from the FIDO Metadata Service. These can be used to
+    /// make policy decisions, for example to only allow devices that support `alwaysUv`.
+    pub fn authenticator_options(&self) -> Option<&AuthenticatorOptions> {
+        match self {
+            AttestationMetadata::Packed {
+                authenticator_options,
+                ..
+            }
+            | AttestationMetadata::Tpm {
+                authenticator_options,
+                ..
+            } => authenticator_options.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Set the options of this device from the attestation CA list it was verified against.
+    pub(crate) fn set_authenticator_options(&mut self, ca_list: &AttestationCaList) {
+        match self {
+            AttestationMetadata::Packed {
+                aaguid,
+                authenticator_options,
+            }
+            | AttestationMetadata::Tpm {
+                aaguid,
+                authenticator_options,
+                ..
+            } => {
+                *authenticator_options = ca_list.get_authenticator_options(aaguid).cloned();
+            }
+            _ => {}
+        }
+    }
+}
+
 /// The processed Attestation that the Authenticator is providing in it's AttestedCredentialData
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(
@@ -496,6 +662,109 @@ pub enum ParsedAttestationData {
     Uncertain,
 }
 
+impl ParsedAttestationData {
+    /// The type of attestation that was provided, without the associated certificate chain.
+    pub fn attestation_type(&self) -> AttestationType {
+        match self {
+            ParsedAttestationData::Basic(_) => AttestationType::Basic,
+            ParsedAttestationData::Self_ => AttestationType::Self_,
+            ParsedAttestationData::AttCa(_) => AttestationType::AttCa,
+            ParsedAttestationData::AnonCa(_) => AttestationType::AnonCa,
+            ParsedAttestationData::ECDAA => AttestationType::ECDAA,
+            ParsedAttestationData::None => AttestationType::None,
+            ParsedAttestationData::Uncertain => AttestationType::Uncertain,
+        }
+    }
+}
+
+impl ParsedAttestation {
+    /// The type of attestation that was provided by the authenticator during registration.
+    pub fn attestation_type(&self) -> AttestationType {
+        self.data.attestation_type()
+    }
+
+    fn leaf_certificate(&self) -> Option<&x509::X509> {
+        match &self.data {
+            ParsedAttestationData::Basic(chain)
+            | ParsedAttestationData::AttCa(chain)
+            | ParsedAttestationData::AnonCa(chain) => chain.first(),
+            _ => None,
+        }
+    }
+
+    /// The serial number of the attestation leaf certificate as big-endian bytes. This is
+    /// useful to log for correlation with vendor advisories. Returns `None` if the attestation
+    /// did not contain a certificate chain.
+    pub fn attestation_cert_serial(&self) -> Option<Vec<u8>> {
+        self.leaf_certificate()
+            .and_then(|leaf| leaf.serial_number().to_bn().ok())
+            .map(|serial| serial.to_vec())
+    }
+
+    /// The issuer distinguished name of the attestation leaf certificate, formatted as
+    /// `KEY=value` components in certificate order separated by `, ` (for example
+    /// `C=SE, O=Yubico AB, CN=Yubico U2F Root CA`). Returns `None` if the attestation did not
+    /// contain a certificate chain.
+    pub fn attestation_cert_issuer(&self) -> Option<String> {
+        let leaf = self.leaf_certificate()?;
+        let components: Vec<String> = leaf
+            .issuer_name()
+            .entries()
+            .map(|entry| {
+                let key = entry
+                    .object()
+                    .nid()
+                    .short_name()
+                    .map(str::to_string)
+                    .unwrap_or_else(|_| entry.object().to_string());
+                let value = entry
+                    .data()
+                    .as_utf8()
+                    .map(|v| v.to_string())
+                    .unwrap_or_default();
+                format!("{key}={value}")
+            })
+            .collect();
+        Some(components.join(", "))
+    }
+}
+
+/// The type of attestation that an authenticator provided during registration. This is
+/// derived from the attestation statement during verification.
+///
+/// Note that [AttestationType::Self_] provides no guarantee that the credential is bound to
+/// hardware, as the attestation is signed by the credential private key itself. High assurance
+/// deployments should reject self attested credentials even though the signature is valid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Hash)]
+pub enum AttestationType {
+    /// The credential was attested by a signing X509 Certificate from a vendor or provider.
+    Basic,
+    /// The credential was attested with it's own private key (surrogate basic attestation).
+    Self_,
+    /// The credential was attested by an attestation CA.
+    AttCa,
+    /// The credential was attested by an anonymization CA.
+    AnonCa,
+    /// The credential was attested with ECDAA.
+    ECDAA,
+    /// No attestation was provided.
+    None,
+    /// The attestation provided was uncertain.
+    Uncertain,
+}
+
+impl AttestationType {
+    /// Returns true if this attestation type is rooted in a certificate chain, and as a result
+    /// may be validated against a trusted attestation CA list. Self and None attestation never
+    /// provide a hardware backing guarantee.
+    pub fn is_certificate_based(&self) -> bool {
+        matches!(
+            self,
+            AttestationType::Basic | AttestationType::AttCa | AttestationType::AnonCa
+        )
+    }
+}
+
 #[allow(clippy::from_over_into)]
 impl Into<SerialisableAttestationData> for ParsedAttestationData {
     fn into(self) -> SerialisableAttestationData {
@@ -631,6 +900,45 @@ pub struct AttestedCredentialData {
     pub credential_pk: serde_cbor_2::Value,
 }
 
+/// The structural properties of a registration response, as parsed without any of the
+/// ceremony's state. See
+/// [`inspect_registration`](crate::WebauthnCore::inspect_registration).
+///
+/// ⚠️  None of these values are verified, and they MUST NOT be used to make security decisions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegistrationInspection {
+    /// The aaguid of the device class, as reported in the authenticator data. This is all zeros
+    /// if the authenticator does not reveal its aaguid.
+    pub aaguid: Uuid,
+    /// The cryptographic algorithm of the credential public key.
+    pub algorithm: COSEAlgorithm,
+    /// The format of the attestation statement.
+    pub attestation_format: AttestationFormat,
+    /// If the user present flag was set in the authenticator data.
+    pub user_present: bool,
+    /// If the user verified flag was set in the authenticator data.
+    pub user_verified: bool,
+    /// If the backup eligible flag was set in the authenticator data.
+    pub backup_eligible: bool,
+    /// If the backup state flag was set in the authenticator data.
+    pub backup_state: bool,
+    /// The transports the client reported for the credential.
+    pub transports: Option<Vec<AuthenticatorTransport>>,
+}
+
+/// How the backup state of a credential changed between the stored credential and an
+/// authentication. This can be used as a signal in risk scoring.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BackupTransition {
+    /// The credential was not backed up, and now is.
+    BackedUp,
+    /// The credential was backed up, and now is not. This may indicate that it was restored
+    /// from a backup onto a device that does not continue to back it up.
+    Restored,
+    /// The backup state of the credential did not change.
+    NoChange,
+}
+
 /// Information about the authentication that occured.
 #[derive(Debug, Serialize, Clone, Deserialize)]
 pub struct AuthenticationResult {
@@ -648,10 +956,32 @@ pub struct AuthenticationResult {
     /// be false to true, never the reverse. This is common on passkeys
     /// during some upgrades.
     pub(crate) backup_eligible: bool,
+    /// If the authenticator became backup eligible during this authentication.
+    #[serde(default)]
+    pub(crate) backup_state_changed: bool,
     /// The state of the counter
     pub(crate) counter: Counter,
     /// The response from associated extensions.
     pub(crate) extensions: AuthenticationExtensions,
+    /// If a largeBlob write was requested, whether the authenticator reported it as persisted.
+    #[serde(default)]
+    pub(crate) large_blob_written: Option<bool>,
+    /// The raw authenticator data of the assertion, as covered by the verified signature.
+    #[serde(default)]
+    pub(crate) authenticator_data: Vec<u8>,
+    /// If user verification was preferred, but the authenticator did not perform it.
+    #[serde(default)]
+    pub(crate) uv_downgraded: bool,
+    /// If an hmac-secret output was returned, whether it was derived from the user verified
+    /// secret of the credential.
+    #[serde(default)]
+    pub(crate) hmac_secret_user_verified: Option<bool>,
+    /// The display name the client reported for this credential, if any.
+    #[serde(default)]
+    pub(crate) authenticator_display_name: Option<String>,
+    /// How the backup state changed compared to the stored credential.
+    #[serde(default)]
+    pub(crate) backup_state_transition: Option<BackupTransition>,
 }
 
 impl AuthenticationResult {
@@ -685,13 +1015,86 @@ impl AuthenticationResult {
         self.backup_eligible
     }
 
+    /// How the backup state of the credential changed compared to the stored credential. This is
+    /// `None` if the credential is not backup eligible, as it can never be backed up.
+    pub fn backup_state_transition(&self) -> Option<BackupTransition> {
+        self.backup_state_transition
+    }
+
+    /// If the backup eligibility of the credential transitioned from false to true during
+    /// this authentication. This means that a credential that was bound to a single device
+    /// is now able to be backed up or synchronised between devices, which may change your
+    /// risk assessment of this credential. You may wish to trigger a re-verification of the
+    /// user, or notify them that their credential is now syncing.
+    ///
+    /// The stored credential must be updated with this result to reflect the new state.
+    pub fn backup_state_changed(&self) -> bool {
+        self.backup_state_changed
+    }
+
     /// The state of the counter
     pub fn counter(&self) -> Counter {
         self.counter
     }
 
+    /// If the authenticator of this credential maintains a signature counter. Most passkeys
+    /// report a counter of 0 that never increments, and a counter of 0 is always treated as
+    /// counterless. For these credentials cloned authenticators can NOT be detected by their
+    /// counter, so you may wish to rely on other signals, such as the backup state.
+    pub fn has_counter(&self) -> bool {
+        self.counter > 0
+    }
+
     /// The response from associated extensions.
     pub fn extensions(&self) -> &AuthenticationExtensions {
         &self.extensions
     }
+
+    /// The names of the extensions for which an output was present and parsed during this
+    /// authentication, such as `hmacGetSecret`.
+    pub fn processed_extensions(&self) -> Vec<&'static str> {
+        self.extensions.processed_extensions()
+    }
+
+    /// If a largeBlob write was requested during this authentication, whether the
+    /// authenticator confirmed that the blob was stored. Authenticators may fail to
+    /// store a blob (for example, when their storage is full), so a value of
+    /// `Some(false)` means the write did *not* persist. `None` indicates that no
+    /// write confirmation was returned.
+    pub fn large_blob_written(&self) -> Option<bool> {
+        self.large_blob_written
+    }
+
+    /// The raw authenticator data bytes of the assertion. These bytes were covered by the
+    /// signature that was verified, together with the hash of the clientDataJSON, so they can
+    /// be archived as a record of exactly what the authenticator signed.
+    pub fn authenticator_data(&self) -> &[u8] {
+        &self.authenticator_data
+    }
+
+    /// If user verification was preferred for this authentication, but the authenticator only
+    /// tested for user presence. This authentication was single factor, and the rate at which
+    /// this occurs can be monitored to understand how often users fall back to it.
+    pub fn uv_downgraded(&self) -> bool {
+        self.uv_downgraded
+    }
+
+    /// If the client returned an hmacGetSecret output, whether the output was derived from the
+    /// credential's user verified secret. CTAP2 authenticators hold two separate secrets for
+    /// hmac-secret, and select one by whether user verification was performed for this
+    /// assertion, so the same salt produces different outputs in each mode. If you derive keys
+    /// from these outputs, you must use this to select the matching key derivation context.
+    ///
+    /// This is `None` if no hmacGetSecret output was returned.
+    pub fn hmac_secret_user_verified(&self) -> Option<bool> {
+        self.hmac_secret_user_verified
+    }
+
+    /// The display name the client reported for this credential in its credProps, if any.
+    /// Platforms may allow users to rename their credentials, so if this differs from the
+    /// name you have stored, you may wish to update it. This is NOT SIGNED and CAN NOT be
+    /// trusted, so it must only be used for display.
+    pub fn authenticator_display_name(&self) -> Option<&str> {
+        self.authenticator_display_name.as_deref()
+    }
 }
@@ -126,7 +126,7 @@ impl Credential {
         req_extn: &RequestRegistrationExtensions,
         client_extn: &RegistrationExtensionsClientOutputs,
         attestation_format: AttestationFormat,
-        _transports: &Option<Vec<AuthenticatorTransport>>,
+        transports: &Option<Vec<AuthenticatorTransport>>,
     ) -> Self {
         let cred_protect = match (
             auth_data.extensions.cred_protect.as_ref(),
@@ -169,8 +169,14 @@ impl Credential {
             (None, false) => ExtnState::NotRequested,
         };
 
+        let cred_protect_requested = req_extn
+            .cred_protect
+            .as_ref()
+            .map(|c| c.credential_protection_policy);
+
         let extensions = RegisteredExtensions {
             cred_protect,
+            cred_protect_requested,
             hmac_create_secret,
             appid,
             cred_props,
@@ -182,8 +188,10 @@ impl Credential {
         let backup_eligible = auth_data.backup_eligible;
         let backup_state = auth_data.backup_state;
 
-        // due to bugs in chrome, we have to disable transports because lol.
-        let transports = None;
+        // These are a hint only, and are stored exactly as the client reported them so that
+        // they can be provided in allowCredentials of future authentications. Due to bugs in
+        // chrome, the caller only provides them when storing transports was opted in to.
+        let transports = transports.clone();
 
         Credential {
             cred_id: acd.credential_id.clone(),
@@ -197,15 +205,22 @@ impl Credential {
             extensions,
             attestation,
             attestation_format,
+            registered_at: None,
         }
     }
 }
 
 pub(crate) fn process_authentication_extensions(
     auth_extn: &AuthenticationSignedExtensions,
+    client_extn: &AuthenticationExtensionsClientOutputs,
 ) -> AuthenticationExtensions {
     trace!(?auth_extn);
-    AuthenticationExtensions {}
+    trace!(?client_extn);
+    AuthenticationExtensions {
+        appid: client_extn.appid.is_some(),
+        hmac_get_secret: client_extn.hmac_get_secret.is_some(),
+        large_blob: client_extn.large_blob.is_some(),
+    }
 }
 
 /*
@@ -297,6 +312,10 @@ fn acd_parser(i: &[u8]) -> nom::IResult<&[u8], AttestedCredentialData> {
             cred_id_len,
             i.len()
         );
+        return Err(nom::Err::Failure(nom::error::Error::from_error_kind(
+            i,
+            nom::error::ErrorKind::LengthValue,
+        )));
     }
 
     let (i, cred_id) = take(cred_id_len as usize)(i)?;
@@ -931,7 +950,18 @@ pub(crate) struct TpmsRsaParms {
     _keybits: u16,
     // u32
     /// The Rsa Exponent
-    _exponent: u32,
+    exponent: u32,
+}
+
+impl TpmsRsaParms {
+    /// The Rsa Exponent. The TPM encodes the default exponent of 2^16 + 1 as zero.
+    pub(crate) fn exponent(&self) -> u32 {
+        if self.exponent == 0 {
+            65537
+        } else {
+            self.exponent
+        }
+    }
 }
 
 fn tpmsrsaparms_parser(i: &[u8]) -> nom::IResult<&[u8], TpmsRsaParms> {
@@ -945,7 +975,7 @@ fn tpmsrsaparms_parser(i: &[u8]) -> nom::IResult<&[u8], TpmsRsaParms> {
             _symmetric: symmetric,
             _scheme: scheme,
             _keybits: keybits,
-            _exponent: exponent,
+            exponent,
         },
     ))
     /*
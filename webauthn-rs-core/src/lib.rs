@@ -50,6 +50,9 @@ pub mod proto {
 }
 
 pub use attestation::verify_attestation_ca_chain;
+pub use attestation::AttestationBatchVerify;
+pub use attestation::AttestationCaListBatchVerify;
 pub use attestation::AttestationFormat;
+pub use attestation::AttestationFormatVerifier;
 
 pub use crate::core::*;
@@ -3,6 +3,7 @@
 use base64urlsafedata::Base64UrlSafeData;
 use serde::{Deserialize, Serialize};
 
+use crate::cose::COSEAlgorithm;
 use crate::extensions::{RegistrationExtensionsClientOutputs, RequestRegistrationExtensions};
 use crate::options::*;
 
@@ -35,6 +36,11 @@ pub struct PublicKeyCredentialCreationOptions {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub authenticator_selection: Option<AuthenticatorSelectionCriteria>,
 
+    /// Hints to the client about which authenticator types should be presented
+    /// to the user, in order of preference.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hints: Option<Vec<PublicKeyCredentialHint>>,
+
     /// Non-standard extensions that may be used by the browser/authenticator.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub extensions: Option<RequestRegistrationExtensions>,
@@ -51,6 +57,37 @@ pub struct CreationChallengeResponse {
     pub public_key: PublicKeyCredentialCreationOptions,
 }
 
+impl CreationChallengeResponse {
+    /// Replace the relying party name in this challenge, for example to brand a registration
+    /// for one tenant of a multi-tenant deployment. The name is only displayed to the user and
+    /// is not verified when the registration is completed, so it can safely vary between
+    /// ceremonies. The relying party id is security critical, and is never changed.
+    pub fn with_rp_name(mut self, rp_name: &str) -> Self {
+        self.public_key.rp.name = rp_name.to_string();
+        self
+    }
+
+    /// The user verification policy this challenge requested from the authenticator, if any.
+    pub fn user_verification(&self) -> Option<UserVerificationPolicy> {
+        self.public_key
+            .authenticator_selection
+            .as_ref()
+            .map(|auth_sel| auth_sel.user_verification)
+    }
+
+    /// The credential algorithms this challenge offered to the authenticator, in order of
+    /// preference. This can help to debug why an authenticator refuses to register, by comparing
+    /// it to the algorithms the authenticator is known to support. Algorithm identifiers that
+    /// this library does not know are skipped.
+    pub fn offered_algorithms(&self) -> Vec<COSEAlgorithm> {
+        self.public_key
+            .pub_key_cred_params
+            .iter()
+            .filter_map(|param| COSEAlgorithm::try_from(param.alg as i128).ok())
+            .collect()
+    }
+}
+
 #[cfg(feature = "wasm")]
 impl From<CreationChallengeResponse> for web_sys::CredentialCreationOptions {
     fn from(ccr: CreationChallengeResponse) -> Self {
@@ -155,6 +192,25 @@ pub struct RegisterPublicKeyCredential {
     /// Unsigned Client processed extensions.
     #[serde(default)]
     pub extensions: RegistrationExtensionsClientOutputs,
+    /// The attachment modality of the authenticator, as reported by the client. This value is
+    /// NOT signed by the authenticator, and is only advisory.
+    #[serde(
+        default,
+        rename = "authenticatorAttachment",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub authenticator_attachment: Option<AuthenticatorAttachment>,
+}
+
+impl RegisterPublicKeyCredential {
+    /// Retrieve the challenge that the client echoed in its clientDataJSON. This can be used
+    /// to find the registration state of this response, if you store them by challenge.
+    ///
+    /// This performs NO verification of the response, only of the structure of the
+    /// clientDataJSON. The challenge must still be checked when the registration is finished.
+    pub fn client_data_challenge(&self) -> Result<Vec<u8>, serde_json::Error> {
+        client_data_challenge(self.response.client_data_json.as_ref())
+    }
 }
 
 #[cfg(feature = "wasm")]
@@ -193,6 +249,16 @@ impl From<web_sys::PublicKeyCredential> for RegisterPublicKeyCredential {
 
         let data_response_client_data_json_b64 = Base64UrlSafeData(data_response_client_data_json);
 
+        let authenticator_attachment =
+            js_sys::Reflect::get(&data, &"authenticatorAttachment".into())
+                .ok()
+                .and_then(|v| v.as_string())
+                .and_then(|v| match v.as_str() {
+                    "platform" => Some(AuthenticatorAttachment::Platform),
+                    "cross-platform" => Some(AuthenticatorAttachment::CrossPlatform),
+                    _ => None,
+                });
+
         RegisterPublicKeyCredential {
             id: format!("{data_raw_id_b64}"),
             raw_id: data_raw_id_b64,
@@ -203,6 +269,7 @@ impl From<web_sys::PublicKeyCredential> for RegisterPublicKeyCredential {
             },
             type_: "public-key".to_string(),
             extensions: data_extensions.into(),
+            authenticator_attachment,
         }
     }
 }
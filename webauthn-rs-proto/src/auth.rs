@@ -2,6 +2,7 @@
 
 use base64urlsafedata::Base64UrlSafeData;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 use crate::extensions::{AuthenticationExtensionsClientOutputs, RequestAuthenticationExtensions};
 use crate::options::*;
@@ -60,6 +61,25 @@ pub struct RequestChallengeResponse {
     pub mediation: Option<Mediation>,
 }
 
+impl RequestChallengeResponse {
+    /// Return a copy of this challenge with the timeout changed, such as to retry a ceremony
+    /// that timed out on the client with a longer timeout. The challenge is not changed.
+    /// Timeouts longer than `u32::MAX` milliseconds are saturated.
+    ///
+    /// ⚠️  Reusing a challenge is only safe if the server side authentication state for it has
+    /// not yet been consumed by a finish call. If in doubt, start a new authentication instead.
+    pub fn with_timeout(&self, timeout: Duration) -> RequestChallengeResponse {
+        let mut rcr = self.clone();
+        rcr.public_key.timeout = Some(u32::try_from(timeout.as_millis()).unwrap_or(u32::MAX));
+        rcr
+    }
+
+    /// The user verification policy this challenge requested from the authenticator.
+    pub fn user_verification(&self) -> UserVerificationPolicy {
+        self.public_key.user_verification
+    }
+}
+
 #[cfg(feature = "wasm")]
 impl From<RequestChallengeResponse> for web_sys::CredentialRequestOptions {
     fn from(rcr: RequestChallengeResponse) -> Self {
@@ -147,6 +167,14 @@ pub struct PublicKeyCredential {
     /// The authenticator type.
     #[serde(rename = "type")]
     pub type_: String,
+    /// The attachment modality of the authenticator, as reported by the client. This value is
+    /// NOT signed by the authenticator, and is only advisory.
+    #[serde(
+        default,
+        rename = "authenticatorAttachment",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub authenticator_attachment: Option<AuthenticatorAttachment>,
 }
 
 impl PublicKeyCredential {
@@ -156,10 +184,21 @@ impl PublicKeyCredential {
         self.response.user_handle.as_ref().map(|b| b.as_ref())
     }
 
-    /// Retrieve the credential id that was provided in this authentication
+    /// Retrieve the credential id that was provided in this authentication. This is the decoded
+    /// `rawId`, so it can be compared to stored credential ids regardless of how the client
+    /// encoded it. The `id` string should not be used for lookups.
     pub fn get_credential_id(&self) -> &[u8] {
         self.raw_id.0.as_slice()
     }
+
+    /// Retrieve the challenge that the client echoed in its clientDataJSON. This can be used
+    /// to find the authentication state of this response, if you store them by challenge.
+    ///
+    /// This performs NO verification of the response, only of the structure of the
+    /// clientDataJSON. The challenge must still be checked when the authentication is finished.
+    pub fn client_data_challenge(&self) -> Result<Vec<u8>, serde_json::Error> {
+        client_data_challenge(self.response.client_data_json.as_ref())
+    }
 }
 
 #[cfg(feature = "wasm")]
@@ -207,6 +246,16 @@ impl From<web_sys::PublicKeyCredential> for PublicKeyCredential {
 
         let data_response_user_handle_b64 = data_response_user_handle.map(Base64UrlSafeData);
 
+        let authenticator_attachment =
+            js_sys::Reflect::get(&data, &"authenticatorAttachment".into())
+                .ok()
+                .and_then(|v| v.as_string())
+                .and_then(|v| match v.as_str() {
+                    "platform" => Some(AuthenticatorAttachment::Platform),
+                    "cross-platform" => Some(AuthenticatorAttachment::CrossPlatform),
+                    _ => None,
+                });
+
         PublicKeyCredential {
             id: format!("{data_raw_id_b64}"),
             raw_id: data_raw_id_b64,
@@ -218,6 +267,7 @@ impl From<web_sys::PublicKeyCredential> for PublicKeyCredential {
             },
             extensions: data_extensions.into(),
             type_: "public-key".to_string(),
+            authenticator_attachment,
         }
     }
 }
@@ -46,6 +46,15 @@ impl COSEAlgorithm {
         vec![COSEAlgorithm::ES256, COSEAlgorithm::RS256]
     }
 
+    /// Returns true if this algorithm is insecure, or is not a signature algorithm, and so must
+    /// never be requested for a credential.
+    pub fn is_insecure(self) -> bool {
+        matches!(
+            self,
+            COSEAlgorithm::INSECURE_RS1 | COSEAlgorithm::PinUvProtocol
+        )
+    }
+
     /// Return the set of all possible algorithms that may exist as a COSEAlgorithm
     pub fn all_possible_algs() -> Vec<Self> {
         vec![
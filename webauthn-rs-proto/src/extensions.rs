@@ -3,8 +3,9 @@
 use base64urlsafedata::Base64UrlSafeData;
 use serde::{Deserialize, Serialize};
 
-/// Valid credential protection policies
-#[derive(Debug, Serialize, Clone, Copy, Deserialize, PartialEq, Eq)]
+/// Valid credential protection policies. These are ordered from the weakest to the strongest
+/// protection.
+#[derive(Debug, Serialize, Clone, Copy, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(rename_all = "camelCase")]
 #[repr(u8)]
 pub enum CredentialProtectionPolicy {
@@ -233,6 +234,19 @@ pub struct HmacGetSecretOutput {
     pub output2: Option<Base64UrlSafeData>,
 }
 
+/// The response to a largeBlob request.
+/// <https://w3c.github.io/webauthn/#dictdef-authenticationextensionslargebloboutputs>
+#[derive(Debug, Serialize, Clone, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LargeBlobOutput {
+    /// The blob that was read from the authenticator, if a read was requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob: Option<Base64UrlSafeData>,
+    /// If a write was requested, whether the authenticator persisted the blob.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub written: Option<bool>,
+}
+
 /// <https://w3c.github.io/webauthn/#dictdef-authenticationextensionsclientoutputs>
 /// The default option here for Options are None, so it can be derived
 #[derive(Debug, Deserialize, Serialize, Clone, Default)]
@@ -243,6 +257,14 @@ pub struct AuthenticationExtensionsClientOutputs {
     /// The response to a hmac get secret request.
     #[serde(default)]
     pub hmac_get_secret: Option<HmacGetSecretOutput>,
+    /// The response to a largeBlob read or write request.
+    #[serde(default, rename = "largeBlob", skip_serializing_if = "Option::is_none")]
+    pub large_blob: Option<LargeBlobOutput>,
+    /// The credential properties some clients report during authentication, such as a display
+    /// name the platform has since given the credential. This is NOT SIGNED and CAN NOT be
+    /// trusted!
+    #[serde(default, rename = "credProps", skip_serializing_if = "Option::is_none")]
+    pub cred_props: Option<CredProps>,
 }
 
 #[cfg(feature = "wasm")]
@@ -274,9 +296,49 @@ impl From<web_sys::AuthenticationExtensionsClientOutputs>
                 output1.map(|output1| HmacGetSecretOutput { output1, output2 })
             });
 
+        let large_blob = js_sys::Reflect::get(&ext, &"largeBlob".into())
+            .ok()
+            .filter(|jv| jv.is_object())
+            .map(|jv| {
+                let blob = js_sys::Reflect::get(&jv, &"blob".into())
+                    .ok()
+                    .filter(|v| !v.is_undefined())
+                    .map(|v| Uint8Array::new(&v).to_vec())
+                    .map(Base64UrlSafeData);
+
+                let written = js_sys::Reflect::get(&jv, &"written".into())
+                    .ok()
+                    .and_then(|v| v.as_bool());
+
+                LargeBlobOutput { blob, written }
+            });
+
+        // "credProps":{"authenticatorDisplayName":"..."}, where rk is usually absent.
+        let cred_props = js_sys::Reflect::get(&ext, &"credProps".into())
+            .ok()
+            .filter(|jv| jv.is_object())
+            .map(|jv| {
+                let rk = js_sys::Reflect::get(&jv, &"rk".into())
+                    .ok()
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+
+                let authenticator_display_name =
+                    js_sys::Reflect::get(&jv, &"authenticatorDisplayName".into())
+                        .ok()
+                        .and_then(|v| v.as_string());
+
+                CredProps {
+                    rk,
+                    authenticator_display_name,
+                }
+            });
+
         AuthenticationExtensionsClientOutputs {
             appid,
             hmac_get_secret,
+            large_blob,
+            cred_props,
         }
     }
 }
@@ -284,7 +346,28 @@ impl From<web_sys::AuthenticationExtensionsClientOutputs>
 /// <https://www.w3.org/TR/webauthn-3/#sctn-authenticator-credential-properties-extension>
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct CredProps {
+    /// Authentication results may only carry the display name, so this defaults to false.
+    #[serde(default)]
     rk: bool,
+    /// A human-palatable name the authenticator or client assigned to this credential.
+    #[serde(
+        default,
+        rename = "authenticatorDisplayName",
+        skip_serializing_if = "Option::is_none"
+    )]
+    authenticator_display_name: Option<String>,
+}
+
+impl CredProps {
+    /// Indicates if the client believes it created a resident key.
+    pub fn rk(&self) -> bool {
+        self.rk
+    }
+
+    /// The display name the authenticator or client assigned to this credential, if any.
+    pub fn authenticator_display_name(&self) -> Option<&str> {
+        self.authenticator_display_name.as_deref()
+    }
 }
 
 /// <https://w3c.github.io/webauthn/#dictdef-authenticationextensionsclientoutputs>
@@ -297,7 +380,7 @@ pub struct RegistrationExtensionsClientOutputs {
 
     /// Indicates if the client believes it created a resident key. This
     /// property is managed by the webbrowser, and is NOT SIGNED and CAN NOT be trusted!
-    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(default, alias = "credProps", skip_serializing_if = "Option::is_none")]
     pub cred_props: Option<CredProps>,
 
     /// Indicates if the client successfully applied a HMAC Secret
@@ -322,14 +405,22 @@ impl From<web_sys::AuthenticationExtensionsClientOutputs> for RegistrationExtens
             .ok()
             .and_then(|jv| jv.as_bool());
 
-        // Destructure "credProps":{"rk":false} from within a map.
+        // Destructure "credProps":{"rk":false,"authenticatorDisplayName":"..."} from within a map.
         let cred_props = js_sys::Reflect::get(&ext, &"credProps".into())
             .ok()
             .and_then(|cred_props_struct| {
+                let authenticator_display_name =
+                    js_sys::Reflect::get(&cred_props_struct, &"authenticatorDisplayName".into())
+                        .ok()
+                        .and_then(|jv| jv.as_string());
+
                 js_sys::Reflect::get(&cred_props_struct, &"rk".into())
                     .ok()
                     .and_then(|jv| jv.as_bool())
-                    .map(|rk| CredProps { rk })
+                    .map(|rk| CredProps {
+                        rk,
+                        authenticator_display_name,
+                    })
             });
 
         let hmac_secret = js_sys::Reflect::get(&ext, &"hmac-secret".into())
@@ -376,14 +467,31 @@ where
     Unsigned(T),
 }
 
+impl<T> ExtnState<T>
+where
+    T: Clone + std::fmt::Debug,
+{
+    /// If an output for this extension was returned, whether or not it was requested.
+    pub fn is_present(&self) -> bool {
+        matches!(
+            self,
+            ExtnState::Set(_) | ExtnState::Unsolicited(_) | ExtnState::Unsigned(_)
+        )
+    }
+}
+
 /// The set of extensions that were registered by this credential.
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct RegisteredExtensions {
     // ⚠️  It's critical we place serde default here so that we
     // can deserialise in the future as we add new types!
-    /// The state of the cred_protect extension
+    /// The state of the cred_protect extension. This is the policy that the authenticator
+    /// reported it applied, which may differ from the requested policy.
     #[serde(default)]
     pub cred_protect: ExtnState<CredentialProtectionPolicy>,
+    /// The credential protection policy that was requested during registration, if any.
+    #[serde(default)]
+    pub cred_protect_requested: Option<CredentialProtectionPolicy>,
     /// The state of the hmac-secret extension, if it was created
     #[serde(default)]
     pub hmac_create_secret: ExtnState<bool>,
@@ -400,13 +508,77 @@ impl RegisteredExtensions {
     pub fn none() -> Self {
         RegisteredExtensions {
             cred_protect: ExtnState::NotRequested,
+            cred_protect_requested: None,
             hmac_create_secret: ExtnState::NotRequested,
             appid: ExtnState::NotRequested,
             cred_props: ExtnState::NotRequested,
         }
     }
+
+    /// The credential protection policy that was requested during registration, if any.
+    pub fn requested_credential_protection_policy(&self) -> Option<CredentialProtectionPolicy> {
+        self.cred_protect_requested
+    }
+
+    /// The credential protection policy that the authenticator reported it applied to this
+    /// credential. If the authenticator did not report a policy this is `None`, and the
+    /// credential should be assumed to have no protection beyond "FIDO_2_0" semantics.
+    pub fn effective_credential_protection_policy(&self) -> Option<CredentialProtectionPolicy> {
+        match self.cred_protect {
+            ExtnState::Set(policy) | ExtnState::Unsolicited(policy) => Some(policy),
+            ExtnState::NotRequested | ExtnState::Ignored | ExtnState::Unsigned(_) => None,
+        }
+    }
+
+    /// Whether the authenticator reported that it enabled the hmac-secret extension for this
+    /// credential. If the authenticator did not return the extension this is `None`.
+    pub fn hmac_secret_enabled(&self) -> Option<bool> {
+        match self.hmac_create_secret {
+            ExtnState::Set(enabled) | ExtnState::Unsolicited(enabled) => Some(enabled),
+            ExtnState::NotRequested | ExtnState::Ignored | ExtnState::Unsigned(_) => None,
+        }
+    }
+
+    /// The names of the extensions for which an output was present and parsed during the
+    /// registration of this credential.
+    pub fn processed_extensions(&self) -> Vec<&'static str> {
+        [
+            (self.cred_protect.is_present(), "credProtect"),
+            (self.hmac_create_secret.is_present(), "hmac-secret"),
+            (self.appid.is_present(), "appid"),
+            (self.cred_props.is_present(), "credProps"),
+        ]
+        .into_iter()
+        .filter_map(|(processed, name)| processed.then_some(name))
+        .collect()
+    }
 }
 
 /// The set of extensions that were provided by the client during authentication
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct AuthenticationExtensions {}
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AuthenticationExtensions {
+    /// If the client returned an output for the appid extension.
+    #[serde(default)]
+    pub appid: bool,
+    /// If the client returned an output for the hmacGetSecret extension.
+    #[serde(default)]
+    pub hmac_get_secret: bool,
+    /// If the client returned an output for the largeBlob extension.
+    #[serde(default)]
+    pub large_blob: bool,
+}
+
+impl AuthenticationExtensions {
+    /// The names of the extensions for which an output was present and parsed during this
+    /// authentication.
+    pub fn processed_extensions(&self) -> Vec<&'static str> {
+        [
+            (self.appid, "appid"),
+            (self.hmac_get_secret, "hmacGetSecret"),
+            (self.large_blob, "largeBlob"),
+        ]
+        .into_iter()
+        .filter_map(|(processed, name)| processed.then_some(name))
+        .collect()
+    }
+}
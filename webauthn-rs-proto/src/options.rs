@@ -107,7 +107,7 @@ pub struct PubKeyCredParams {
 }
 
 /// <https://www.w3.org/TR/webauthn/#enumdef-attestationconveyancepreference>
-#[derive(Debug, Serialize, Clone, Deserialize)]
+#[derive(Debug, Serialize, Clone, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum AttestationConveyancePreference {
     /// Do not request attestation.
@@ -210,6 +210,114 @@ pub enum AuthenticatorAttachment {
     CrossPlatform,
 }
 
+/// A hint to the client as to which type of authenticator the relying party believes the
+/// user will use. This allows the client to order or direct the user interface to the most
+/// relevant authenticator type. These hints are ordered by preference, and are NOT enforced.
+///
+/// <https://w3c.github.io/webauthn/#enumdef-publickeycredentialhints>
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PublicKeyCredentialHint {
+    /// The user is expected to use a physical security key.
+    /// <https://w3c.github.io/webauthn/#dom-publickeycredentialhints-security-key>
+    SecurityKey,
+    /// The user is expected to use a platform authenticator built into the client device.
+    /// <https://w3c.github.io/webauthn/#dom-publickeycredentialhints-client-device>
+    ClientDevice,
+    /// The user is expected to use a general purpose authenticator such as a smartphone,
+    /// connected via the hybrid transport.
+    /// <https://w3c.github.io/webauthn/#dom-publickeycredentialhints-hybrid>
+    Hybrid,
+}
+
+/// The capabilities of the client, as reported by `PublicKeyCredential.getClientCapabilities()`.
+/// The front-end should pass these values back to the server as they were returned by the
+/// browser. A value of `None` indicates the client did not report the capability.
+///
+/// These capabilities are used to tailor the hints that are sent to the client when a ceremony is
+/// started. This is an optimisation for the user experience ONLY - it is NOT a security control, as
+/// these values are supplied by the client and can not be trusted.
+///
+/// <https://w3c.github.io/webauthn/#sctn-getClientCapabilities>
+#[derive(Debug, Default, Serialize, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientCapabilities {
+    /// The client supports conditional mediation during registration.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conditional_create: Option<bool>,
+    /// The client supports conditional mediation (autofill UI) during authentication.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conditional_get: Option<bool>,
+    /// The client supports the hybrid (caBLE) transport.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hybrid_transport: Option<bool>,
+    /// The client has a passkey capable platform authenticator.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passkey_platform_authenticator: Option<bool>,
+    /// The client has a user verifying platform authenticator.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_verifying_platform_authenticator: Option<bool>,
+}
+
+impl ClientCapabilities {
+    /// Returns false only if the client explicitly reported it has no usable platform
+    /// authenticator.
+    pub fn supports_platform_authenticator(&self) -> bool {
+        !matches!(
+            (
+                self.passkey_platform_authenticator,
+                self.user_verifying_platform_authenticator
+            ),
+            (Some(false), Some(false)) | (Some(false), None) | (None, Some(false))
+        )
+    }
+
+    /// Returns false only if the client explicitly reported it does not support conditional
+    /// mediation for authentication.
+    pub fn supports_conditional_get(&self) -> bool {
+        self.conditional_get != Some(false)
+    }
+
+    /// Returns false only if the client explicitly reported it does not support the hybrid
+    /// transport.
+    pub fn supports_hybrid_transport(&self) -> bool {
+        self.hybrid_transport != Some(false)
+    }
+
+    /// Filter the set of transport hints to those that the client is able to use.
+    pub fn filter_transports(&self, transports: &mut Option<Vec<AuthenticatorTransport>>) {
+        if !self.supports_hybrid_transport() {
+            if let Some(transports) = transports.as_mut() {
+                transports.retain(|t| t != &AuthenticatorTransport::Hybrid);
+            }
+        }
+    }
+
+    /// Filter the set of authenticator hints to those that the client is able to use.
+    pub fn filter_hints(&self, hints: &mut Vec<PublicKeyCredentialHint>) {
+        hints.retain(|hint| match hint {
+            PublicKeyCredentialHint::ClientDevice => self.supports_platform_authenticator(),
+            PublicKeyCredentialHint::Hybrid => self.supports_hybrid_transport(),
+            PublicKeyCredentialHint::SecurityKey => true,
+        });
+    }
+}
+
+/// The result of `PublicKeyCredential.isUserVerifyingPlatformAuthenticatorAvailable()`, as
+/// reported by the front-end. This can be stored in a ceremony state so that it is available
+/// when the ceremony is finished, allowing later flows to be tailored to the client.
+///
+/// This is informational ONLY - it is NOT a security control, as it is supplied by the client
+/// and can not be trusted.
+///
+/// <https://w3c.github.io/webauthn/#sctn-isUserVerifyingPlatformAuthenticatorAvailable>
+#[derive(Debug, Default, Serialize, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PlatformAuthenticatorAvailability {
+    /// The value returned by `isUserVerifyingPlatformAuthenticatorAvailable()`.
+    pub user_verifying_platform_authenticator_available: bool,
+}
+
 /// The Relying Party's requirements for client-side discoverable credentials.
 ///
 /// <https://www.w3.org/TR/webauthn-2/#enumdef-residentkeyrequirement>
@@ -270,6 +378,9 @@ pub struct AllowCredentials {
 
 /// The data collected and hashed in the operation.
 /// <https://www.w3.org/TR/webauthn-2/#dictdef-collectedclientdata>
+///
+/// This is always parsed and compared by field, never byte for byte, so the key order and
+/// whitespace that a client chooses to serialise it with do not matter.
 #[derive(Debug, Serialize, Clone, Deserialize)]
 pub struct CollectedClientData {
     /// The credential type
@@ -312,11 +423,21 @@ pub struct TokenBinding {
     pub id: Option<String>,
 }
 
+/// Parse the challenge from a clientDataJSON, without verifying any other part of it.
+pub(crate) fn client_data_challenge(client_data_json: &[u8]) -> Result<Vec<u8>, serde_json::Error> {
+    serde_json::from_slice::<CollectedClientData>(client_data_json)
+        .map(|client_data| client_data.challenge.into())
+}
+
 #[cfg(test)]
 mod test {
     use std::str::FromStr;
 
-    use crate::AuthenticatorTransport;
+    use crate::attest::PublicKeyCredentialCreationOptions;
+    use crate::auth::RequestChallengeResponse;
+    use crate::{
+        AuthenticatorTransport, ClientCapabilities, PublicKeyCredentialHint, RelyingParty, User,
+    };
 
     #[test]
     fn test_authenticator_transports() {
@@ -339,4 +460,162 @@ mod test {
 
         assert!(AuthenticatorTransport::from_str("fake fake").is_err());
     }
+
+    #[test]
+    fn test_client_capabilities() {
+        let caps: ClientCapabilities = serde_json::from_str(
+            r#"{"conditionalGet":true,"hybridTransport":false,"passkeyPlatformAuthenticator":false,"userVerifyingPlatformAuthenticator":false}"#,
+        )
+        .expect("failed to parse client capabilities");
+
+        assert!(caps.supports_conditional_get());
+        assert!(!caps.supports_hybrid_transport());
+        assert!(!caps.supports_platform_authenticator());
+
+        let mut transports = Some(vec![
+            AuthenticatorTransport::Usb,
+            AuthenticatorTransport::Hybrid,
+        ]);
+        caps.filter_transports(&mut transports);
+        assert_eq!(transports, Some(vec![AuthenticatorTransport::Usb]));
+
+        let mut hints = vec![
+            PublicKeyCredentialHint::ClientDevice,
+            PublicKeyCredentialHint::Hybrid,
+            PublicKeyCredentialHint::SecurityKey,
+        ];
+        caps.filter_hints(&mut hints);
+        assert_eq!(hints, vec![PublicKeyCredentialHint::SecurityKey]);
+
+        // Unreported capabilities must not remove any hints.
+        let caps = ClientCapabilities::default();
+        assert!(caps.supports_conditional_get());
+        assert!(caps.supports_hybrid_transport());
+        assert!(caps.supports_platform_authenticator());
+    }
+
+    #[test]
+    fn test_request_challenge_response_with_timeout() {
+        let rcr: RequestChallengeResponse = serde_json::from_value(serde_json::json!({
+            "publicKey": {
+                "challenge": "AQIDBA",
+                "timeout": 60000,
+                "rpId": "example.com",
+                "allowCredentials": [{"type": "public-key", "id": "BQYHCA"}],
+                "userVerification": "preferred",
+            },
+        }))
+        .expect("failed to parse request challenge response");
+
+        let retry = rcr.with_timeout(std::time::Duration::from_secs(300));
+        assert_eq!(retry.public_key.timeout, Some(300000));
+
+        // Only the timeout is changed.
+        let mut expected = serde_json::to_value(&rcr).expect("failed to serialise challenge");
+        expected["publicKey"]["timeout"] = serde_json::json!(300000);
+        assert_eq!(
+            serde_json::to_value(&retry).expect("failed to serialise challenge"),
+            expected
+        );
+        assert_eq!(rcr.public_key.timeout, Some(60000));
+    }
+
+    #[test]
+    fn test_public_key_credential_hints() {
+        let hints = vec![
+            PublicKeyCredentialHint::SecurityKey,
+            PublicKeyCredentialHint::ClientDevice,
+            PublicKeyCredentialHint::Hybrid,
+        ];
+        assert_eq!(
+            serde_json::to_string(&hints).expect("failed to serialise hints"),
+            r#"["security-key","client-device","hybrid"]"#
+        );
+
+        // Hints outside of the known set are rejected.
+        assert!(serde_json::from_str::<Vec<PublicKeyCredentialHint>>(r#"["usb"]"#).is_err());
+
+        let options = PublicKeyCredentialCreationOptions {
+            rp: RelyingParty {
+                name: "example.com".to_string(),
+                id: "example.com".to_string(),
+            },
+            user: User {
+                id: vec![1, 2, 3, 4].into(),
+                name: "claire".to_string(),
+                display_name: "Claire".to_string(),
+            },
+            challenge: vec![5, 6, 7, 8].into(),
+            pub_key_cred_params: vec![],
+            timeout: None,
+            attestation: None,
+            exclude_credentials: None,
+            authenticator_selection: None,
+            hints: Some(vec![PublicKeyCredentialHint::SecurityKey]),
+            extensions: None,
+        };
+        let json = serde_json::to_value(&options).expect("failed to serialise options");
+        assert_eq!(json["hints"], serde_json::json!(["security-key"]));
+
+        // Options without hints omit the field entirely, and deserialise to none.
+        let options = PublicKeyCredentialCreationOptions {
+            hints: None,
+            ..options
+        };
+        let json = serde_json::to_value(&options).expect("failed to serialise options");
+        assert!(json.get("hints").is_none());
+        let options: PublicKeyCredentialCreationOptions =
+            serde_json::from_value(json).expect("failed to deserialise options");
+        assert_eq!(options.hints, None);
+    }
+
+    #[test]
+    fn test_client_data_challenge() {
+        let rsp: crate::auth::PublicKeyCredential = serde_json::from_str(
+            r#"{
+                "id":"at-FfKGsOI21EhtCu7Vx-7t7FKkpUOyKXIkEBBD_vC-eym_AdW6Y9V8WyKxHmii11EBQEe7uFQ0bkYwb0GWmUQ",
+                "rawId":"at-FfKGsOI21EhtCu7Vx-7t7FKkpUOyKXIkEBBD_vC-eym_AdW6Y9V8WyKxHmii11EBQEe7uFQ0bkYwb0GWmUQ",
+                "response":{
+                    "authenticatorData":"SZYN5YgOjGh0NBcPZHZgW4_krrmihjLHmVzzuoMdl2MBAAAAFA",
+                    "clientDataJSON":"eyJjaGFsbGVuZ2UiOiJXZ1h6X2tUdjNXVVUxa3c4aG0tT0dvR1M0WkNIWF8zYkVxSEgyUHZWcDhNIiwiY2xpZW50RXh0ZW5zaW9ucyI6e30sImhhc2hBbGdvcml0aG0iOiJTSEEtMjU2Iiwib3JpZ2luIjoiaHR0cDovL2xvY2FsaG9zdDo4MDgwIiwidHlwZSI6IndlYmF1dGhuLmdldCJ9",
+                    "signature":"MEYCIQDmLVOqv85cdRup4Fr8Pf9zC4AWO-XKBJqa8xPwYFCCMAIhAOiExLoyes0xipmUmq0BVlqJaCKLn_MFKG9GIDsCGq_-",
+                    "userHandle":null
+                },
+                "type":"public-key"
+            }"#,
+        )
+        .expect("failed to parse authentication response");
+        let challenge = rsp
+            .client_data_challenge()
+            .expect("failed to parse challenge");
+        assert_eq!(
+            Base64UrlSafeData::from(challenge).to_string(),
+            "WgXz_kTv3WUU1kw8hm-OGoGS4ZCHX_3bEqHH2PvVp8M"
+        );
+
+        // Only the challenge is parsed, so the unverified attestationObject is not inspected.
+        let rsp: crate::attest::RegisterPublicKeyCredential = serde_json::from_str(
+            r#"{
+                "id":"ATk_7QKbi_ntSdp16LXeU6RDf9YnRLIDTCqEjJFzc6rKBhbqoSYccxNa",
+                "rawId":"ATk_7QKbi_ntSdp16LXeU6RDf9YnRLIDTCqEjJFzc6rKBhbqoSYccxNa",
+                "response":{
+                    "attestationObject":"AA",
+                    "clientDataJSON":"eyJjaGFsbGVuZ2UiOiJsUDZtV05BdEctX1Z2MTVpTTdsYl9YUmtkV012VlEtbFR5S3dadU9nMVZvIiwiZXh0cmFfa2V5c19tYXlfYmVfYWRkZWRfaGVyZSI6ImRvIG5vdCBjb21wYXJlIGNsaWVudERhdGFKU09OIGFnYWluc3QgYSB0ZW1wbGF0ZS4gU2VlIGh0dHBzOi8vZ29vLmdsL3lhYlBleCIsIm9yaWdpbiI6Imh0dHBzOi8vbG9jYWxob3N0Ojg0NDMiLCJ0eXBlIjoid2ViYXV0aG4uY3JlYXRlIn0"
+                },
+                "type":"public-key"
+            }"#,
+        )
+        .expect("failed to parse registration response");
+        let challenge = rsp
+            .client_data_challenge()
+            .expect("failed to parse challenge");
+        assert_eq!(
+            Base64UrlSafeData::from(challenge).to_string(),
+            "lP6mWNAtG-_Vv15iM7lb_XRkdWMvVQ-lTyKwZuOg1Vo"
+        );
+
+        let mut rsp = rsp;
+        rsp.response.client_data_json = br#"{"type":"webauthn.create"}"#.to_vec().into();
+        assert!(rsp.client_data_challenge().is_err());
+    }
 }
@@ -1,13 +1,181 @@
 //! Types that are expected to be serialised in applications using [crate::Webauthn]
 
 use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
 
+use base64urlsafedata::Base64UrlSafeData;
+use uuid::Uuid;
+use webauthn_rs_core::crypto::compute_sha256;
+use webauthn_rs_core::error::{WebauthnError, WebauthnResult};
 use webauthn_rs_core::interface::{
     AttestationCaList, AuthenticationResult, AuthenticationState, RegistrationState,
 };
 use webauthn_rs_core::proto::{
-    COSEAlgorithm, COSEKey, Credential, CredentialID, ParsedAttestation,
+    AttestationType, AuthenticatorAttachment, AuthenticatorTransport, COSEAlgorithm, COSEKey,
+    CreationChallengeResponse, Credential, CredentialID, CredentialProtectionPolicy, ExtnState,
+    ParsedAttestation, PlatformAuthenticatorAvailability, PublicKeyCredential,
+    UserVerificationPolicy,
 };
+use webauthn_rs_core::AttestationFormat;
+
+/// The maximum length of a user handle in bytes.
+///
+/// <https://www.w3.org/TR/webauthn-2/#user-handle>
+const USER_HANDLE_MAX_LEN: usize = 64;
+
+/// The maximum length of a credential id in bytes.
+///
+/// <https://www.w3.org/TR/webauthn-3/#credential-id>
+const CREDENTIAL_ID_MAX_LEN: usize = 1023;
+
+macro_rules! impl_platform_authenticator_availability {
+    ($ty:ty, $state:ident) => {
+        impl $ty {
+            /// Record the result of `isUserVerifyingPlatformAuthenticatorAvailable()` that the
+            /// front-end reported, so that it is available when this ceremony is finished. This
+            /// is informational only, and does not affect verification.
+            pub fn set_platform_authenticator_availability(
+                &mut self,
+                availability: PlatformAuthenticatorAvailability,
+            ) {
+                self.$state
+                    .set_platform_authenticator_availability(availability)
+            }
+
+            /// The platform authenticator availability recorded for this ceremony, if any.
+            pub fn platform_authenticator_availability(
+                &self,
+            ) -> Option<PlatformAuthenticatorAvailability> {
+                self.$state.platform_authenticator_availability()
+            }
+        }
+    };
+}
+
+impl_platform_authenticator_availability!(PasskeyRegistration, rs);
+impl_platform_authenticator_availability!(PasskeyAuthentication, ast);
+impl_platform_authenticator_availability!(SecurityKeyRegistration, rs);
+impl_platform_authenticator_availability!(SecurityKeyAuthentication, ast);
+#[cfg(feature = "preview-features")]
+impl_platform_authenticator_availability!(AttestedPasskeyRegistration, rs);
+#[cfg(feature = "preview-features")]
+impl_platform_authenticator_availability!(AttestedPasskeyAuthentication, ast);
+#[cfg(feature = "preview-features")]
+impl_platform_authenticator_availability!(DiscoverableAuthentication, ast);
+#[cfg(feature = "resident-key-support")]
+impl_platform_authenticator_availability!(AttestedResidentKeyRegistration, rs);
+#[cfg(feature = "resident-key-support")]
+impl_platform_authenticator_availability!(AttestedResidentKeyAuthentication, ast);
+
+macro_rules! impl_ceremony_challenge {
+    ($ty:ty, $state:ident) => {
+        impl $ty {
+            /// The challenge that was issued for this ceremony. This can be logged to correlate
+            /// the ceremony with the client response, such as in an audit trail. It does not
+            /// allow the ceremony to be completed without this state.
+            pub fn challenge(&self) -> &[u8] {
+                self.$state.challenge()
+            }
+        }
+    };
+}
+
+impl_ceremony_challenge!(PasskeyRegistration, rs);
+impl_ceremony_challenge!(PasskeyAuthentication, ast);
+impl_ceremony_challenge!(SecurityKeyRegistration, rs);
+impl_ceremony_challenge!(SecurityKeyAuthentication, ast);
+#[cfg(feature = "preview-features")]
+impl_ceremony_challenge!(AttestedPasskeyRegistration, rs);
+#[cfg(feature = "preview-features")]
+impl_ceremony_challenge!(AttestedPasskeyAuthentication, ast);
+#[cfg(feature = "preview-features")]
+impl_ceremony_challenge!(DiscoverableAuthentication, ast);
+#[cfg(feature = "resident-key-support")]
+impl_ceremony_challenge!(AttestedResidentKeyRegistration, rs);
+#[cfg(feature = "resident-key-support")]
+impl_ceremony_challenge!(AttestedResidentKeyAuthentication, ast);
+
+macro_rules! impl_expected_aaguid {
+    ($ty:ty) => {
+        impl $ty {
+            /// Require that the attested aaguid of the registering device matches `aaguid`,
+            /// otherwise finishing this registration fails with
+            /// [WebauthnError::AttestationAaguidMismatch]. This allows a later step of a
+            /// multi-step enrollment to be bound to the same device model as an earlier step. A
+            /// device that does not provide an attested aaguid is rejected.
+            pub fn set_expected_aaguid(&mut self, aaguid: Uuid) {
+                self.expected_aaguid = Some(aaguid);
+            }
+
+            /// The aaguid that the registering device is required to attest, if any.
+            pub fn expected_aaguid(&self) -> Option<Uuid> {
+                self.expected_aaguid
+            }
+        }
+    };
+}
+
+impl_expected_aaguid!(SecurityKeyRegistration);
+#[cfg(feature = "preview-features")]
+impl_expected_aaguid!(AttestedPasskeyRegistration);
+#[cfg(feature = "resident-key-support")]
+impl_expected_aaguid!(AttestedResidentKeyRegistration);
+
+/// A user handle that is provided to the authenticator during registration. A user handle
+/// is between 1 and 64 bytes, and must NOT contain personally identifying information. This
+/// value can NEVER be changed for a credential, so should be derived from a stable primary key
+/// of the user's account.
+///
+/// This type ensures that user handles are not mixed up with other byte arrays, and that the
+/// length of the handle is always valid.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "Base64UrlSafeData", into = "Base64UrlSafeData")]
+pub struct UserHandle(Base64UrlSafeData);
+
+impl UserHandle {
+    /// Retrieve the bytes of this user handle.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+}
+
+impl AsRef<[u8]> for UserHandle {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl From<Uuid> for UserHandle {
+    fn from(u: Uuid) -> Self {
+        UserHandle(u.as_bytes().to_vec().into())
+    }
+}
+
+impl TryFrom<&[u8]> for UserHandle {
+    type Error = WebauthnError;
+
+    fn try_from(b: &[u8]) -> WebauthnResult<Self> {
+        if b.is_empty() || b.len() > USER_HANDLE_MAX_LEN {
+            Err(WebauthnError::InvalidUserHandle)
+        } else {
+            Ok(UserHandle(b.to_vec().into()))
+        }
+    }
+}
+
+impl TryFrom<Base64UrlSafeData> for UserHandle {
+    type Error = WebauthnError;
+
+    fn try_from(b: Base64UrlSafeData) -> WebauthnResult<Self> {
+        UserHandle::try_from(b.as_ref())
+    }
+}
+
+impl From<UserHandle> for Base64UrlSafeData {
+    fn from(h: UserHandle) -> Self {
+        h.0
+    }
+}
 
 /// An in progress registration session for a [Passkey].
 ///
@@ -27,6 +195,17 @@ pub struct PasskeyRegistration {
     pub(crate) rs: RegistrationState,
 }
 
+impl PasskeyRegistration {
+    /// Confirm that this registration state was generated alongside the given
+    /// [CreationChallengeResponse], by comparing the challenge they contain. This is useful where
+    /// the options are generated in one service and sent to the client by another, to detect the
+    /// state of one ceremony being paired with the options of another before the registration
+    /// fails verification.
+    pub fn matches_challenge(&self, ccr: &CreationChallengeResponse) -> bool {
+        self.challenge() == ccr.public_key.challenge.as_ref()
+    }
+}
+
 /// An in progress authentication session for a [Passkey].
 ///
 /// WARNING ⚠️  YOU MUST STORE THIS VALUE SERVER SIDE.
@@ -75,6 +254,149 @@ impl Passkey {
         &self.cred.cred
     }
 
+    /// Verify that this Passkey is structurally valid, such that the public key is a valid
+    /// point on its curve (or a valid RSA key) and the credential id is of a permitted length.
+    /// This is useful to detect corrupted credentials after they are loaded from storage, or
+    /// migrated from another system, before they are used in an authentication ceremony.
+    pub fn validate(&self) -> WebauthnResult<()> {
+        let cred_id_len = self.cred.cred_id.as_ref().len();
+        if cred_id_len == 0 || cred_id_len > CREDENTIAL_ID_MAX_LEN {
+            error!(?cred_id_len, "credential id length is invalid");
+            return Err(WebauthnError::CredentialIdInvalidLength);
+        }
+
+        self.cred.cred.validate()
+    }
+
+    /// Retrieve a short fingerprint of this Passkey for display, so that users can tell their
+    /// credentials apart, in the way SSH key fingerprints are used. This is the first 8 bytes of
+    /// the SHA256 of the credential id, as colon separated hex such as `1a:2b:3c:4d:5e:6f:70:81`.
+    ///
+    /// This is only intended for display, and MUST NOT be used to identify the credential.
+    pub fn fingerprint(&self) -> String {
+        compute_sha256(self.cred.cred_id.as_ref())
+            .iter()
+            .take(8)
+            .map(|b| format!("{b:02x}"))
+            .collect::<Vec<_>>()
+            .join(":")
+    }
+
+    /// Retrieve the non-identifying properties of this Passkey, which are safe to emit
+    /// to metrics and telemetry. See [RegistrationTelemetry].
+    pub fn telemetry(&self) -> RegistrationTelemetry {
+        RegistrationTelemetry::from(&self.cred)
+    }
+
+    /// Retrieve the credential protection policy that was requested when this credential was
+    /// registered, if any.
+    pub fn requested_credential_protection_policy(&self) -> Option<CredentialProtectionPolicy> {
+        self.cred
+            .extensions
+            .requested_credential_protection_policy()
+    }
+
+    /// Retrieve the credential protection policy that the authenticator reported it applied to
+    /// this credential. Since the policy may not be enforced during registration this may be
+    /// weaker than the requested policy, or `None` if the authenticator did not report one.
+    pub fn effective_credential_protection_policy(&self) -> Option<CredentialProtectionPolicy> {
+        self.cred
+            .extensions
+            .effective_credential_protection_policy()
+    }
+
+    /// Retrieve whether the authenticator reported that it enabled the hmac-secret extension
+    /// for this credential, allowing secrets to be derived from it later. This is `None` if
+    /// the authenticator did not return the extension.
+    pub fn hmac_secret_enabled(&self) -> Option<bool> {
+        self.cred.extensions.hmac_secret_enabled()
+    }
+
+    /// Retrieve the names of the extensions for which an output was present and parsed when
+    /// this credential was registered, such as `credProtect` or `hmac-secret`. This can be used
+    /// to confirm which requested extensions the authenticator applied.
+    pub fn processed_extensions(&self) -> Vec<&'static str> {
+        self.cred.extensions.processed_extensions()
+    }
+
+    /// Retrieve whether this credential reported that it can be used over the hybrid
+    /// (formerly caBLE) transport, for example by scanning a QR code with a phone. As
+    /// transports are a hint only, this should only be used to tailor the user interface.
+    pub fn is_hybrid_capable(&self) -> bool {
+        self.cred.is_hybrid_capable()
+    }
+
+    /// Retrieve the transports of this credential. These are the transports the client
+    /// reported during registration, or if it did not report any, those that were set for the
+    /// attested aaguid with [`aaguid_transports`](crate::WebauthnBuilder::aaguid_transports).
+    pub fn transports(&self) -> Option<&[AuthenticatorTransport]> {
+        self.cred.transports.as_deref()
+    }
+
+    /// Retrieve whether this credential is bound to an authenticator that can verify the user,
+    /// as observed at registration from the user verification flag and credProtect policy.
+    pub fn is_user_verification_capable(&self) -> bool {
+        self.cred.is_user_verification_capable()
+    }
+
+    /// Retrieve the time at which this credential was registered. This is `None` for
+    /// credentials that were registered before this was recorded.
+    pub fn registered_at(&self) -> Option<SystemTime> {
+        self.cred.registered_at
+    }
+
+    /// Retrieve the display name the authenticator or client assigned to this Passkey during
+    /// registration, if one was reported through the `credProps` extension.
+    ///
+    /// ⚠️  This value is provided by the client and is NOT SIGNED. It is only suitable as a
+    /// default label to show the user, for example in a list of their registered Passkeys.
+    pub fn authenticator_display_name(&self) -> Option<String> {
+        match &self.cred.extensions.cred_props {
+            ExtnState::Set(cred_props)
+            | ExtnState::Unsolicited(cred_props)
+            | ExtnState::Unsigned(cred_props) => {
+                cred_props.authenticator_display_name().map(str::to_string)
+            }
+            ExtnState::NotRequested | ExtnState::Ignored => None,
+        }
+    }
+
+    /// Verify an assertion made by this Passkey over a challenge that you supply directly,
+    /// rather than one issued by [crate::Webauthn::start_passkey_authentication]. This allows
+    /// the Passkey to produce application level signatures, for example where the challenge
+    /// is the digest of a document the user is signing.
+    ///
+    /// User verification is required, as it is for all Passkey authentications.
+    ///
+    /// The [crate::Webauthn] instance must be provided as the Passkey does not store the
+    /// relying party it was registered with. It supplies the rp_id and allowed origins that
+    /// the assertion is checked against, and the required authenticator attachment, exactly
+    /// as for [crate::Webauthn::finish_passkey_authentication]. Without these checks, an
+    /// assertion made for any other site would be accepted.
+    ///
+    /// # Replay
+    ///
+    /// There is no server side state for this operation, so an assertion that verifies once
+    /// will verify every time it is presented. The challenge must commit to the data being
+    /// signed (and ideally a nonce or timestamp), and if a signature must only be accepted
+    /// once, you must record and reject assertions you have already seen. The counter in the
+    /// returned [AuthenticationResult] may help, but many passkeys do not implement one.
+    pub fn verify_assertion_over(
+        &self,
+        webauthn: &crate::Webauthn,
+        challenge: &[u8],
+        reg: &PublicKeyCredential,
+    ) -> WebauthnResult<AuthenticationResult> {
+        webauthn.check_attachment(reg.authenticator_attachment)?;
+        webauthn.core.authenticate_credential_with_challenge(
+            reg,
+            &self.cred,
+            UserVerificationPolicy::Required,
+            challenge,
+            true,
+        )
+    }
+
     /// Post authentication, update this credentials properties.
     ///
     /// To determine if this is required, you can inspect the result of
@@ -83,6 +405,11 @@ impl Passkey {
     /// internal device activation counter (due to their synchronisation), and the
     /// backup-state flags are rarely if ever changed.
     ///
+    /// If `authentication_result.backup_state_changed()` is true, this Passkey was device
+    /// bound and has now become backup eligible, meaning it may be synchronised between devices.
+    /// Applying the result updates the stored backup state, but you may also wish to re-verify
+    /// the user or notify them that their Passkey is now syncing.
+    ///
     /// If the credential_id does not match, None is returned.
     /// If the cred id matches and the credential is updated, Some(true) is returned.
     /// If the cred id matches, but the credential is not changed, Some(false) is returned.
@@ -114,6 +441,94 @@ impl Passkey {
     }
 }
 
+/// A set of non-identifying properties of a registered credential, which are safe to emit to
+/// metrics and telemetry pipelines.
+///
+/// This deliberately excludes the credential id, the public key and the user handle, as these
+/// could be used to correlate a credential to a user.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegistrationTelemetry {
+    /// The cryptographic algorithm of the credential.
+    pub algorithm: COSEAlgorithm,
+    /// The attachment of the authenticator, as inferred from the transports that it reported
+    /// during registration. This is `None` if the transports were not reported or are ambiguous.
+    pub authenticator_attachment: Option<AuthenticatorAttachment>,
+    /// The aaguid of the device class, if the attestation provided one.
+    pub aaguid: Option<Uuid>,
+    /// The format of the attestation that was provided during registration.
+    pub attestation_format: AttestationFormat,
+    /// If this credential indicated that it *may* be backed up or shared between devices.
+    pub backup_eligible: bool,
+}
+
+/// An aggregate of the capabilities of a set of [Passkey]s registered to a single user, for
+/// example to decide which authentication options to offer them. See
+/// [`summarize_credentials`](crate::Webauthn::summarize_credentials).
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CredentialSummary {
+    /// The number of credentials that were summarized.
+    pub count: usize,
+    /// The distinct algorithms of the credentials, in the order they were first seen.
+    pub algorithms_present: Vec<COSEAlgorithm>,
+    /// If any credential reported that it is currently backed up or synced between devices.
+    pub has_synced: bool,
+    /// If any credential was reported by the client to be discoverable (a resident key).
+    pub has_discoverable: bool,
+    /// If any credential is bound to an authenticator that can verify the user. See
+    /// [`Passkey::is_user_verification_capable`].
+    pub has_uv_capable: bool,
+}
+
+impl<'a> FromIterator<&'a Passkey> for CredentialSummary {
+    fn from_iter<I: IntoIterator<Item = &'a Passkey>>(iter: I) -> Self {
+        let mut summary = CredentialSummary::default();
+        for passkey in iter {
+            let cred = &passkey.cred;
+            summary.count += 1;
+            if !summary.algorithms_present.contains(&cred.cred.type_) {
+                summary.algorithms_present.push(cred.cred.type_);
+            }
+            summary.has_synced |= cred.backup_state;
+            summary.has_discoverable |= match &cred.extensions.cred_props {
+                ExtnState::Set(cred_props)
+                | ExtnState::Unsolicited(cred_props)
+                | ExtnState::Unsigned(cred_props) => cred_props.rk(),
+                ExtnState::NotRequested | ExtnState::Ignored => false,
+            };
+            summary.has_uv_capable |= cred.is_user_verification_capable();
+        }
+        summary
+    }
+}
+
+impl From<&Credential> for RegistrationTelemetry {
+    fn from(cred: &Credential) -> Self {
+        let authenticator_attachment = match cred.transports.as_deref() {
+            Some(transports) if !transports.is_empty() => {
+                if transports
+                    .iter()
+                    .all(|t| t == &AuthenticatorTransport::Internal)
+                {
+                    Some(AuthenticatorAttachment::Platform)
+                } else if !transports.contains(&AuthenticatorTransport::Internal) {
+                    Some(AuthenticatorAttachment::CrossPlatform)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+
+        RegistrationTelemetry {
+            algorithm: cred.cred.type_,
+            authenticator_attachment,
+            aaguid: cred.attestation.metadata.aaguid(),
+            attestation_format: cred.attestation_format.clone(),
+            backup_eligible: cred.backup_eligible,
+        }
+    }
+}
+
 #[cfg(feature = "danger-credential-internals")]
 impl From<Passkey> for Credential {
     fn from(pk: Passkey) -> Self {
@@ -155,6 +570,10 @@ impl PartialEq for Passkey {
 pub struct AttestedPasskeyRegistration {
     pub(crate) rs: RegistrationState,
     pub(crate) ca_list: AttestationCaList,
+    #[cfg_attr(feature = "danger-allow-state-serialisation", serde(default))]
+    pub(crate) recovery: bool,
+    #[cfg_attr(feature = "danger-allow-state-serialisation", serde(default))]
+    pub(crate) expected_aaguid: Option<Uuid>,
 }
 
 /// An in progress authentication session for a [AttestedPasskey].
@@ -185,6 +604,8 @@ pub struct AttestedPasskeyAuthentication {
 #[cfg(feature = "preview-features")]
 pub struct AttestedPasskey {
     pub(crate) cred: Credential,
+    #[serde(default)]
+    pub(crate) recovery: bool,
 }
 
 #[cfg(feature = "preview-features")]
@@ -206,6 +627,125 @@ impl AttestedPasskey {
         &self.cred.attestation
     }
 
+    /// Retrieve the type of attestation that was provided during this [`Credential`]'s
+    /// registration. Self attestation provides no guarantee that the credential is
+    /// bound to hardware, so you may wish to reject it in high assurance deployments.
+    pub fn attestation_type(&self) -> AttestationType {
+        self.cred.attestation.attestation_type()
+    }
+
+    /// Retrieve the non-identifying properties of this credential, which are safe to emit
+    /// to metrics and telemetry. See [RegistrationTelemetry].
+    pub fn telemetry(&self) -> RegistrationTelemetry {
+        RegistrationTelemetry::from(&self.cred)
+    }
+
+    /// Retrieve the credential protection policy that was requested when this credential was
+    /// registered, if any.
+    pub fn requested_credential_protection_policy(&self) -> Option<CredentialProtectionPolicy> {
+        self.cred
+            .extensions
+            .requested_credential_protection_policy()
+    }
+
+    /// Retrieve the credential protection policy that the authenticator reported it applied to
+    /// this credential. Since the policy may not be enforced during registration this may be
+    /// weaker than the requested policy, or `None` if the authenticator did not report one.
+    pub fn effective_credential_protection_policy(&self) -> Option<CredentialProtectionPolicy> {
+        self.cred
+            .extensions
+            .effective_credential_protection_policy()
+    }
+
+    /// Retrieve whether the authenticator reported that it enabled the hmac-secret extension
+    /// for this credential, allowing secrets to be derived from it later. This is `None` if
+    /// the authenticator did not return the extension.
+    pub fn hmac_secret_enabled(&self) -> Option<bool> {
+        self.cred.extensions.hmac_secret_enabled()
+    }
+
+    /// Retrieve the names of the extensions for which an output was present and parsed when
+    /// this credential was registered, such as `credProtect` or `hmac-secret`. This can be used
+    /// to confirm which requested extensions the authenticator applied.
+    pub fn processed_extensions(&self) -> Vec<&'static str> {
+        self.cred.extensions.processed_extensions()
+    }
+
+    /// Retrieve whether this credential reported that it can be used over the hybrid
+    /// (formerly caBLE) transport, for example by scanning a QR code with a phone. As
+    /// transports are a hint only, this should only be used to tailor the user interface.
+    pub fn is_hybrid_capable(&self) -> bool {
+        self.cred.is_hybrid_capable()
+    }
+
+    /// Retrieve the transports of this credential. These are the transports the client
+    /// reported during registration, or if it did not report any, those that were set for the
+    /// attested aaguid with [`aaguid_transports`](crate::WebauthnBuilder::aaguid_transports).
+    pub fn transports(&self) -> Option<&[AuthenticatorTransport]> {
+        self.cred.transports.as_deref()
+    }
+
+    /// Retrieve whether this credential is bound to an authenticator that can verify the user,
+    /// as observed at registration from the user verification flag and credProtect policy.
+    pub fn is_user_verification_capable(&self) -> bool {
+        self.cred.is_user_verification_capable()
+    }
+
+    /// Retrieve the time at which this credential was registered. This is `None` for
+    /// credentials that were registered before this was recorded.
+    pub fn registered_at(&self) -> Option<SystemTime> {
+        self.cred.registered_at
+    }
+
+    /// Retrieve whether this credential was registered as an account recovery credential with
+    /// [`start_recovery_key_registration`](crate::Webauthn::start_recovery_key_registration).
+    /// Recovery credentials are kept separately from the user's everyday devices, so you may
+    /// wish to only accept them in your account recovery workflow rather than a normal login.
+    pub fn is_recovery_credential(&self) -> bool {
+        self.recovery
+    }
+
+    /// Determine if this credential and `other` were created by the same physical authenticator,
+    /// as far as their attestation can show. This returns `None` when it can not be determined.
+    ///
+    /// The aaguid only identifies the model of an authenticator, and attestation certificates are
+    /// usually batch certificates shared by many (at least 100,000 for FIDO certified) devices of
+    /// the same model. As a result:
+    ///
+    /// * Credentials from different models, or with different batch certificates, are from
+    ///   different devices, and `Some(false)` is returned.
+    /// * Credentials sharing a batch certificate may or may not be from the same device, so
+    ///   `None` is returned.
+    /// * CA, anonymisation CA and self attestation issue a new certificate (or none) for each
+    ///   credential, so they can never show the device, and `None` is returned.
+    ///
+    /// This can NOT prove that two credentials come from the same device, so it must not be
+    /// relied upon to enforce "one credential per device", only to detect some registrations
+    /// that are definitely from different devices.
+    pub fn same_device_as(&self, other: &AttestedPasskey) -> Option<bool> {
+        use webauthn_rs_core::proto::ParsedAttestationData;
+
+        let aaguid = self.cred.attestation.metadata.aaguid()?;
+        if aaguid != other.cred.attestation.metadata.aaguid()? {
+            return Some(false);
+        }
+
+        match (&self.cred.attestation.data, &other.cred.attestation.data) {
+            (ParsedAttestationData::Basic(chain), ParsedAttestationData::Basic(other_chain)) => {
+                let leaf = chain.first()?.to_der().ok()?;
+                let other_leaf = other_chain.first()?.to_der().ok()?;
+                // A device holds a single batch certificate, but that certificate is shared with
+                // the rest of its batch.
+                if leaf != other_leaf {
+                    Some(false)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
     /// Post authentication, update this credentials properties.
     ///
     /// To determine if this is required, you can inspect the result of
@@ -254,7 +794,10 @@ impl From<AttestedPasskey> for Credential {
 impl From<Credential> for AttestedPasskey {
     /// Convert a generic webauthn credential into a Passkey
     fn from(cred: Credential) -> Self {
-        AttestedPasskey { cred }
+        AttestedPasskey {
+            cred,
+            recovery: false,
+        }
     }
 }
 
@@ -275,6 +818,8 @@ impl From<Credential> for AttestedPasskey {
 pub struct SecurityKeyRegistration {
     pub(crate) rs: RegistrationState,
     pub(crate) ca_list: Option<AttestationCaList>,
+    #[cfg_attr(feature = "danger-allow-state-serialisation", serde(default))]
+    pub(crate) expected_aaguid: Option<Uuid>,
 }
 
 /// An in progress authentication session for a [SecurityKey].
@@ -323,6 +868,76 @@ impl SecurityKey {
         &self.cred.attestation
     }
 
+    /// Retrieve the type of attestation that was provided during this [`Credential`]'s
+    /// registration. Self attestation provides no guarantee that the credential is
+    /// bound to hardware, so you may wish to reject it in high assurance deployments.
+    pub fn attestation_type(&self) -> AttestationType {
+        self.cred.attestation.attestation_type()
+    }
+
+    /// Retrieve the non-identifying properties of this credential, which are safe to emit
+    /// to metrics and telemetry. See [RegistrationTelemetry].
+    pub fn telemetry(&self) -> RegistrationTelemetry {
+        RegistrationTelemetry::from(&self.cred)
+    }
+
+    /// Retrieve the credential protection policy that was requested when this credential was
+    /// registered, if any.
+    pub fn requested_credential_protection_policy(&self) -> Option<CredentialProtectionPolicy> {
+        self.cred
+            .extensions
+            .requested_credential_protection_policy()
+    }
+
+    /// Retrieve the credential protection policy that the authenticator reported it applied to
+    /// this credential. Since the policy may not be enforced during registration this may be
+    /// weaker than the requested policy, or `None` if the authenticator did not report one.
+    pub fn effective_credential_protection_policy(&self) -> Option<CredentialProtectionPolicy> {
+        self.cred
+            .extensions
+            .effective_credential_protection_policy()
+    }
+
+    /// Retrieve whether the authenticator reported that it enabled the hmac-secret extension
+    /// for this credential, allowing secrets to be derived from it later. This is `None` if
+    /// the authenticator did not return the extension.
+    pub fn hmac_secret_enabled(&self) -> Option<bool> {
+        self.cred.extensions.hmac_secret_enabled()
+    }
+
+    /// Retrieve the names of the extensions for which an output was present and parsed when
+    /// this credential was registered, such as `credProtect` or `hmac-secret`. This can be used
+    /// to confirm which requested extensions the authenticator applied.
+    pub fn processed_extensions(&self) -> Vec<&'static str> {
+        self.cred.extensions.processed_extensions()
+    }
+
+    /// Retrieve whether this credential reported that it can be used over the hybrid
+    /// (formerly caBLE) transport, for example by scanning a QR code with a phone. As
+    /// transports are a hint only, this should only be used to tailor the user interface.
+    pub fn is_hybrid_capable(&self) -> bool {
+        self.cred.is_hybrid_capable()
+    }
+
+    /// Retrieve the transports of this credential. These are the transports the client
+    /// reported during registration, or if it did not report any, those that were set for the
+    /// attested aaguid with [`aaguid_transports`](crate::WebauthnBuilder::aaguid_transports).
+    pub fn transports(&self) -> Option<&[AuthenticatorTransport]> {
+        self.cred.transports.as_deref()
+    }
+
+    /// Retrieve whether this credential is bound to an authenticator that can verify the user,
+    /// as observed at registration from the user verification flag and credProtect policy.
+    pub fn is_user_verification_capable(&self) -> bool {
+        self.cred.is_user_verification_capable()
+    }
+
+    /// Retrieve the time at which this credential was registered. This is `None` for
+    /// credentials that were registered before this was recorded.
+    pub fn registered_at(&self) -> Option<SystemTime> {
+        self.cred.registered_at
+    }
+
     /// Post authentication, update this credentials properties.
     ///
     /// To determine if this is required, you can inspect the result of
@@ -392,6 +1007,8 @@ impl From<Credential> for SecurityKey {
 pub struct AttestedResidentKeyRegistration {
     pub(crate) rs: RegistrationState,
     pub(crate) ca_list: AttestationCaList,
+    #[cfg_attr(feature = "danger-allow-state-serialisation", serde(default))]
+    pub(crate) expected_aaguid: Option<Uuid>,
 }
 
 /// An in progress authentication session for a [AttestedResidentKey].
@@ -450,6 +1067,76 @@ impl AttestedResidentKey {
         &self.cred.attestation
     }
 
+    /// Retrieve the type of attestation that was provided during this [`Credential`]'s
+    /// registration. Self attestation provides no guarantee that the credential is
+    /// bound to hardware, so you may wish to reject it in high assurance deployments.
+    pub fn attestation_type(&self) -> AttestationType {
+        self.cred.attestation.attestation_type()
+    }
+
+    /// Retrieve the non-identifying properties of this credential, which are safe to emit
+    /// to metrics and telemetry. See [RegistrationTelemetry].
+    pub fn telemetry(&self) -> RegistrationTelemetry {
+        RegistrationTelemetry::from(&self.cred)
+    }
+
+    /// Retrieve the credential protection policy that was requested when this credential was
+    /// registered, if any.
+    pub fn requested_credential_protection_policy(&self) -> Option<CredentialProtectionPolicy> {
+        self.cred
+            .extensions
+            .requested_credential_protection_policy()
+    }
+
+    /// Retrieve the credential protection policy that the authenticator reported it applied to
+    /// this credential. Since the policy may not be enforced during registration this may be
+    /// weaker than the requested policy, or `None` if the authenticator did not report one.
+    pub fn effective_credential_protection_policy(&self) -> Option<CredentialProtectionPolicy> {
+        self.cred
+            .extensions
+            .effective_credential_protection_policy()
+    }
+
+    /// Retrieve whether the authenticator reported that it enabled the hmac-secret extension
+    /// for this credential, allowing secrets to be derived from it later. This is `None` if
+    /// the authenticator did not return the extension.
+    pub fn hmac_secret_enabled(&self) -> Option<bool> {
+        self.cred.extensions.hmac_secret_enabled()
+    }
+
+    /// Retrieve the names of the extensions for which an output was present and parsed when
+    /// this credential was registered, such as `credProtect` or `hmac-secret`. This can be used
+    /// to confirm which requested extensions the authenticator applied.
+    pub fn processed_extensions(&self) -> Vec<&'static str> {
+        self.cred.extensions.processed_extensions()
+    }
+
+    /// Retrieve whether this credential reported that it can be used over the hybrid
+    /// (formerly caBLE) transport, for example by scanning a QR code with a phone. As
+    /// transports are a hint only, this should only be used to tailor the user interface.
+    pub fn is_hybrid_capable(&self) -> bool {
+        self.cred.is_hybrid_capable()
+    }
+
+    /// Retrieve the transports of this credential. These are the transports the client
+    /// reported during registration, or if it did not report any, those that were set for the
+    /// attested aaguid with [`aaguid_transports`](crate::WebauthnBuilder::aaguid_transports).
+    pub fn transports(&self) -> Option<&[AuthenticatorTransport]> {
+        self.cred.transports.as_deref()
+    }
+
+    /// Retrieve whether this credential is bound to an authenticator that can verify the user,
+    /// as observed at registration from the user verification flag and credProtect policy.
+    pub fn is_user_verification_capable(&self) -> bool {
+        self.cred.is_user_verification_capable()
+    }
+
+    /// Retrieve the time at which this credential was registered. This is `None` for
+    /// credentials that were registered before this was recorded.
+    pub fn registered_at(&self) -> Option<SystemTime> {
+        self.cred.registered_at
+    }
+
     /// Post authentication, update this credentials properties.
     ///
     /// To determine if this is required, you can inspect the result of
@@ -41,6 +41,16 @@
 //! After this point you then need to use `finish_passkey_registration`, followed by
 //! `start_passkey_authentication` and `finish_passkey_authentication`
 //!
+//! If your front-end reports the result of `PublicKeyCredential.getClientCapabilities()`, you can
+//! pass this back as [ClientCapabilities](prelude::ClientCapabilities) and start the ceremony with
+//! [`with_client_capabilities`](Webauthn::with_client_capabilities). This tailors the hints in the
+//! ceremony to what the client can support. This is an optimisation only, and is not a security control.
+//!
+//! Similarly, the result of `isUserVerifyingPlatformAuthenticatorAvailable()` can be recorded in a
+//! ceremony state with `set_platform_authenticator_availability` as a
+//! [PlatformAuthenticatorAvailability](prelude::PlatformAuthenticatorAvailability), so that it is
+//! available when the ceremony is finished. This is informational only.
+//!
 //! No other authentication factors are needed! A passkey combines inbuilt user verification (pin, biometrics, etc)
 //! with a hardware cryptographic authenticator.
 //!
@@ -147,35 +157,55 @@ extern crate tracing;
 
 mod interface;
 
+#[cfg(feature = "testing")]
+pub mod testing;
+
+#[cfg(test)]
+mod test_authenticator;
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::SystemTime;
 use url::Url;
 use uuid::Uuid;
+use webauthn_rs_core::crypto::RSA_MIN_KEY_BITS;
 use webauthn_rs_core::error::{WebauthnError, WebauthnResult};
 use webauthn_rs_core::proto::*;
-use webauthn_rs_core::WebauthnCore;
+use webauthn_rs_core::{AttestationFormat, AttestationFormatVerifier, WebauthnCore};
 
 use crate::interface::*;
 
 /// A prelude of types that are used by `Webauthn`
 pub mod prelude {
     pub use crate::interface::*;
-    pub use crate::{Webauthn, WebauthnBuilder};
+    pub use crate::{Webauthn, WebauthnBuilder, WebauthnConfig};
     pub use base64urlsafedata::Base64UrlSafeData;
     pub use url::Url;
     pub use uuid::Uuid;
     pub use webauthn_rs_core::error::{WebauthnError, WebauthnResult};
+    pub use webauthn_rs_core::proto::AttestedCredentialData;
     #[cfg(feature = "danger-credential-internals")]
     pub use webauthn_rs_core::proto::Credential;
-    pub use webauthn_rs_core::proto::{AttestationCa, AttestationCaList, AuthenticatorAttachment};
+    pub use webauthn_rs_core::proto::{AllowCredentials, AuthenticatorTransport};
+    pub use webauthn_rs_core::proto::{
+        AttestationCa, AttestationCaList, AuthenticatorAttachment, AuthenticatorOptions,
+    };
+    pub use webauthn_rs_core::proto::{
+        AttestationConveyancePreference, ClientCapabilities, CredentialProtectionPolicy,
+        PlatformAuthenticatorAvailability, PublicKeyCredentialHint, UserVerificationPolicy,
+    };
     pub use webauthn_rs_core::proto::{
-        AttestationMetadata, AuthenticationResult, AuthenticationState, CreationChallengeResponse,
-        CredentialID, ParsedAttestation, ParsedAttestationData, PublicKeyCredential,
-        RegisterPublicKeyCredential, RequestChallengeResponse,
+        AttestationMetadata, AttestationType, AuthenticationResult, AuthenticationState,
+        BackupTransition, CreationChallengeResponse, CredentialID, ParsedAttestation,
+        ParsedAttestationData, PublicKeyCredential, RegisterPublicKeyCredential,
+        RegistrationInspection, RequestChallengeResponse,
     };
     pub use webauthn_rs_core::proto::{
         COSEAlgorithm, COSEEC2Key, COSEKey, COSEKeyType, COSEKeyTypeId, COSEOKPKey, COSERSAKey,
         ECDSACurve, EDDSACurve,
     };
-    pub use webauthn_rs_core::AttestationFormat;
+    pub use webauthn_rs_core::{AttestationFormat, AttestationFormatVerifier};
 }
 
 /// A constructor for a new [Webauthn] instance. This accepts and configures a number of site-wide
@@ -187,10 +217,35 @@ pub struct WebauthnBuilder<'a> {
     allowed_origins: Vec<Url>,
     allow_subdomains: bool,
     allow_any_port: bool,
+    require_https_origins: bool,
+    allow_insecure_localhost: bool,
     algorithms: Vec<COSEAlgorithm>,
     user_presence_only_security_keys: bool,
+    security_key_discourage_resident_key: bool,
+    passkey_attestation: Option<AttestationConveyancePreference>,
+    required_attachment: Option<AuthenticatorAttachment>,
+    min_rsa_key_bits: u32,
+    lenient_cose_parsing: bool,
+    max_attestation_object_size: usize,
+    max_client_data_size: usize,
+    legacy_appid: Option<&'a str>,
+    clock: Clock,
+    strict_packed_subject: bool,
+    store_credential_transports: bool,
+    warn_uv_downgrade: bool,
+    attestation_format_verifiers: Vec<Arc<dyn AttestationFormatVerifier>>,
+    request_uvm: bool,
+    aaguid_names: BTreeMap<Uuid, String>,
+    aaguid_transports: BTreeMap<Uuid, Vec<AuthenticatorTransport>>,
+    reject_vulnerable_firmware: bool,
 }
 
+/// The default maximum size in bytes of an attestationObject in a registration response.
+const DEFAULT_MAX_ATTESTATION_OBJECT_SIZE: usize = 64 * 1024;
+
+/// The default maximum size in bytes of clientDataJSON in a registration response.
+const DEFAULT_MAX_CLIENT_DATA_SIZE: usize = 64 * 1024;
+
 impl<'a> WebauthnBuilder<'a> {
     /// Initiate a new builder. This takes the relying party id and relying party origin.
     ///
@@ -226,24 +281,34 @@ impl<'a> WebauthnBuilder<'a> {
     /// ```
     pub fn new(rp_id: &'a str, rp_origin: &'a Url) -> WebauthnResult<Self> {
         // Check the rp_name and rp_id.
-        let valid = rp_origin
-            .domain()
-            .map(|effective_domain| {
-                // We need to prepend the '.' here to ensure that myexample.com != example.com,
-                // rather than just ends with.
-                effective_domain.ends_with(&format!(".{rp_id}")) || effective_domain == rp_id
-            })
-            .unwrap_or(false);
-
-        if valid {
+        if is_effective_domain(rp_id, rp_origin) {
             Ok(WebauthnBuilder {
                 rp_name: None,
                 rp_id,
                 allowed_origins: vec![rp_origin.to_owned()],
                 allow_subdomains: false,
                 allow_any_port: false,
+                require_https_origins: true,
+                allow_insecure_localhost: false,
                 algorithms: COSEAlgorithm::secure_algs(),
                 user_presence_only_security_keys: false,
+                security_key_discourage_resident_key: true,
+                passkey_attestation: None,
+                required_attachment: None,
+                min_rsa_key_bits: RSA_MIN_KEY_BITS,
+                lenient_cose_parsing: false,
+                max_attestation_object_size: DEFAULT_MAX_ATTESTATION_OBJECT_SIZE,
+                max_client_data_size: DEFAULT_MAX_CLIENT_DATA_SIZE,
+                legacy_appid: None,
+                clock: Clock(Arc::new(SystemTime::now)),
+                strict_packed_subject: true,
+                store_credential_transports: false,
+                warn_uv_downgrade: false,
+                attestation_format_verifiers: Vec::new(),
+                request_uvm: true,
+                aaguid_names: BTreeMap::new(),
+                aaguid_transports: BTreeMap::new(),
+                reject_vulnerable_firmware: false,
             })
         } else {
             error!("rp_id is not an effective_domain of rp_origin");
@@ -257,6 +322,12 @@ impl<'a> WebauthnBuilder<'a> {
     /// when you have a centralised IDM system, but location specific systems with DNS based
     /// redirection or routing.
     ///
+    /// This only affects the validation of the origin. Credentials are always bound to the
+    /// rp_id, and the rpIdHash in the authenticator data must match the hash of the rp_id
+    /// provided to [WebauthnBuilder::new]. If credentials were registered with a different
+    /// (for example, subdomain specific) rp_id they will be rejected with
+    /// [WebauthnError::RpIdHashMismatch].
+    ///
     /// If in doubt, do NOT change this value. Defaults to "false".
     pub fn allow_subdomains(mut self, allow: bool) -> Self {
         self.allow_subdomains = allow;
@@ -269,6 +340,33 @@ impl<'a> WebauthnBuilder<'a> {
         self
     }
 
+    /// Reject allowed origins that use the plain http scheme, independent of
+    /// [WebauthnBuilder::allow_any_port]. If any allowed origin does, [WebauthnBuilder::build]
+    /// returns [WebauthnError::Configuration]. This prevents a plain http origin from being
+    /// appended to a production configuration by accident.
+    ///
+    /// Only http is rejected. Non-web origins, such as those of browser extensions and the
+    /// native app origins described in [WebauthnBuilder::append_allowed_origin], are not
+    /// affected.
+    ///
+    /// Defaults to "true".
+    pub fn require_https_origins(mut self, require: bool) -> Self {
+        self.require_https_origins = require;
+        self
+    }
+
+    /// Accept plain http origins on `localhost` or a loopback address, even when
+    /// [WebauthnBuilder::require_https_origins] is set. Browsers treat these as secure
+    /// contexts, so this allows local development without a certificate.
+    ///
+    /// Do NOT enable this in production.
+    ///
+    /// Defaults to "false".
+    pub fn allow_insecure_localhost(mut self, allow: bool) -> Self {
+        self.allow_insecure_localhost = allow;
+        self
+    }
+
     /// Set extra origins to be considered valid in Webauthn operations. A common example of this is
     /// enabling use with iOS or Android native "webauthn-like" APIs, which return different
     /// app-specific origins than a web browser would.
@@ -280,7 +378,8 @@ impl<'a> WebauthnBuilder<'a> {
     /// Set the relying party name. This may be shown to the user. This value can be changed in
     /// the future without affecting credentials that have already registered.
     ///
-    /// If not set, defaults to rp_id.
+    /// If not set, defaults to rp_id. To vary the name per ceremony, see
+    /// [`CreationChallengeResponse::with_rp_name`].
     pub fn rp_name(mut self, rp_name: &'a str) -> Self {
         self.rp_name = Some(rp_name);
         self
@@ -296,6 +395,248 @@ impl<'a> WebauthnBuilder<'a> {
         self
     }
 
+    /// Request attestation with this conveyance preference during passkey registration, and
+    /// reject passkeys whose authenticator withholds it by returning `none` attestation with
+    /// [WebauthnError::AttestationNotProvided]. This can be used to weed out software
+    /// authenticators while still allowing any passkey that does provide attestation.
+    ///
+    /// The attestation is recorded with the passkey, but it is *not* verified against a
+    /// trusted CA list. If you need that, use
+    /// [`start_attested_passkey_registration`](Webauthn::start_attested_passkey_registration).
+    ///
+    /// Setting [AttestationConveyancePreference::None] removes this requirement, which is the
+    /// default.
+    pub fn passkey_require_attestation(
+        mut self,
+        conveyance: AttestationConveyancePreference,
+    ) -> Self {
+        self.passkey_attestation = match conveyance {
+            AttestationConveyancePreference::None => None,
+            conveyance => Some(conveyance),
+        };
+        self
+    }
+
+    /// Setting this flag to true offers `EDDSA` (Ed25519) as an acceptable credential algorithm
+    /// during registration, in addition to the secure default algorithms. This allows
+    /// authenticators that support Ed25519, such as some SoloKeys, to create Ed25519
+    /// credentials rather than falling back to another algorithm.
+    ///
+    /// Ed448 is not supported, and credentials using it will be rejected. Defaults to "false".
+    pub fn enable_eddsa(mut self, enable: bool) -> Self {
+        self.algorithms.retain(|alg| *alg != COSEAlgorithm::EDDSA);
+        if enable {
+            self.algorithms.push(COSEAlgorithm::EDDSA);
+        }
+        self
+    }
+
+    /// Setting this flag to true causes security key registrations to explicitly request
+    /// `residentKey: "discouraged"`. Many security keys have very limited storage for resident
+    /// keys, and some ctap2.0 devices may be bricked if it is exhausted, so this actively asks
+    /// the authenticator to not consume that storage.
+    ///
+    /// Setting this to false omits `residentKey` from the request, leaving the choice to the
+    /// client's default behaviour. `requireResidentKey` is always false for security keys.
+    ///
+    /// If in doubt, do NOT change this value. Defaults to "true".
+    pub fn discourage_security_key_resident_key(mut self, discourage: bool) -> Self {
+        self.security_key_discourage_resident_key = discourage;
+        self
+    }
+
+    /// Require that credentials use the given authenticator attachment. When set, this is
+    /// requested as the authenticator attachment during passkey registration, and every
+    /// `finish_*` operation rejects responses whose `authenticatorAttachment` does not match
+    /// with [WebauthnError::AuthenticatorAttachmentMismatch]. Responses that do not report an
+    /// attachment are also rejected. The required attachment takes precedence over any
+    /// attachment UI hint given to a `start_*` operation, and registration hints that conflict
+    /// with it are not emitted.
+    ///
+    /// # Limitations
+    ///
+    /// The `authenticatorAttachment` of a response is reported by the client and is NOT signed
+    /// by the authenticator. A malicious or modified client can report any value, so this
+    /// only prevents honest clients from using an authenticator of the wrong type. If you need
+    /// a cryptographic guarantee of the device type, you must use attestation instead.
+    pub fn require_attachment(mut self, attachment: AuthenticatorAttachment) -> Self {
+        self.required_attachment = Some(attachment);
+        self
+    }
+
+    /// Set the minimum size in bits of the modulus of RSA credentials that may be registered.
+    /// Credentials with a smaller key are rejected with
+    /// [WebauthnError::CredentialRsaKeyTooSmall]. This allows deployments to require stronger
+    /// keys, such as 3072 bits.
+    ///
+    /// Values below 2048 bits are ignored, as smaller keys are never accepted. Defaults to 2048.
+    pub fn min_rsa_key_bits(mut self, bits: u32) -> Self {
+        self.min_rsa_key_bits = bits.max(RSA_MIN_KEY_BITS);
+        self
+    }
+
+    /// Setting this flag to true allows registration of credentials whose public key uses the
+    /// string labels of COSE key parameters (such as "kty" and "alg") instead of the integer
+    /// labels required by the specification. A few non-conformant authenticators emit keys
+    /// in this form.
+    ///
+    /// If in doubt, do NOT change this value. Defaults to "false".
+    pub fn lenient_cose_parsing(mut self, lenient: bool) -> Self {
+        self.lenient_cose_parsing = lenient;
+        self
+    }
+
+    /// Setting this flag to true stores the transports that the client reported during
+    /// registration on the credential, which are then provided in allowCredentials of future
+    /// authentications, and used by `is_hybrid_capable`. Some clients have reported incorrect
+    /// transports, which can prevent the authenticator from being found, so these are
+    /// discarded by default.
+    ///
+    /// Defaults to "false".
+    pub fn store_credential_transports(mut self, store: bool) -> Self {
+        self.store_credential_transports = store;
+        self
+    }
+
+    /// Setting this flag to true logs a warning whenever user verification was preferred for
+    /// an authentication, but the authenticator only tested for user presence. This is the
+    /// normal case for many security keys, so it is only logged at debug level by default.
+    /// Either way, it is reported by [AuthenticationResult::uv_downgraded].
+    ///
+    /// Defaults to "false".
+    pub fn warn_uv_downgrade(mut self, warn: bool) -> Self {
+        self.warn_uv_downgrade = warn;
+        self
+    }
+
+    /// Set the maximum size in bytes of the attestationObject of a registration response.
+    /// Larger responses are rejected with [WebauthnError::ResponseTooLarge] before they are
+    /// parsed. Attestation objects with long certificate chains may need a larger limit.
+    ///
+    /// Defaults to 64KiB.
+    pub fn max_attestation_object_size(mut self, size: usize) -> Self {
+        self.max_attestation_object_size = size;
+        self
+    }
+
+    /// Set the maximum size in bytes of the clientDataJSON of a registration response.
+    /// Larger responses are rejected with [WebauthnError::ResponseTooLarge] before they are
+    /// parsed.
+    ///
+    /// Defaults to 64KiB.
+    pub fn max_client_data_size(mut self, size: usize) -> Self {
+        self.max_client_data_size = size;
+        self
+    }
+
+    /// Set the U2F appId that legacy security keys were registered with before you migrated to
+    /// Webauthn. This requests the `appid` extension during security key authentication, so
+    /// that these credentials can continue to authenticate.
+    ///
+    /// When the client reports that it used the extension, the rpIdHash of the assertion is
+    /// verified against the hash of this appId rather than the rp_id.
+    pub fn legacy_appid(mut self, appid: &'a str) -> Self {
+        self.legacy_appid = Some(appid);
+        self
+    }
+
+    /// Set the clock that is used to record the time at which credentials are registered. This
+    /// is intended for testing, so that a fixed or advancing time can be used.
+    ///
+    /// Defaults to [SystemTime::now].
+    pub fn clock(mut self, clock: Arc<dyn Fn() -> SystemTime + Send + Sync>) -> Self {
+        self.clock = Clock(clock);
+        self
+    }
+
+    /// Setting this flag to false relaxes the requirement that the attestation certificate of
+    /// a packed attestation has a Subject-OU of "Authenticator Attestation". Some vendors use a
+    /// localised or otherwise different value. When relaxed, a mismatched Subject-OU is logged
+    /// as a warning and the remaining certificate requirements are still enforced.
+    ///
+    /// If in doubt, do NOT change this value. Defaults to "true".
+    pub fn strict_packed_subject(mut self, strict: bool) -> Self {
+        self.strict_packed_subject = strict;
+        self
+    }
+
+    /// Register a verifier for an attestation statement format that is not supported by this
+    /// library, such as a proprietary format used to import bring-your-own-key credentials.
+    /// The verifier is only used when the `fmt` of a registration is not a built in format.
+    ///
+    /// See [AttestationFormatVerifier] for the responsibilities of a verifier.
+    pub fn attestation_format_verifier(
+        mut self,
+        verifier: Arc<dyn AttestationFormatVerifier>,
+    ) -> Self {
+        self.attestation_format_verifiers.push(verifier);
+        self
+    }
+
+    /// Setting this flag to false stops the `uvm` (user verification method) extension from
+    /// being requested in registration and authentication challenges. This reduces the size of
+    /// responses for deployments that do not need to know how the user was verified.
+    ///
+    /// Defaults to "true".
+    pub fn request_uvm(mut self, request: bool) -> Self {
+        self.request_uvm = request;
+        self
+    }
+
+    /// Set a human readable name for an aaguid, such as the model of the authenticator. This is
+    /// used to describe the authenticator in log messages when its attestation is rejected.
+    ///
+    /// The name is purely cosmetic, and does NOT affect which authenticators are trusted.
+    pub fn aaguid_name(mut self, aaguid: Uuid, name: &str) -> Self {
+        self.aaguid_names.insert(aaguid, name.to_string());
+        self
+    }
+
+    /// Set the transports that an aaguid supports, such as from the `attachmentHint` of its
+    /// FIDO Metadata Service statement. When a client does not report the transports of a
+    /// credential during registration, these are stored for it instead if its attested aaguid
+    /// matches, which allows better `allowCredentials` to be sent during authentication.
+    ///
+    /// Transports that the client reports are preferred over these when
+    /// [WebauthnBuilder::store_credential_transports] is enabled. Otherwise the client's
+    /// transports are discarded, and these are stored for a matching aaguid.
+    pub fn aaguid_transports(
+        mut self,
+        aaguid: Uuid,
+        transports: &[AuthenticatorTransport],
+    ) -> Self {
+        self.aaguid_transports.insert(aaguid, transports.to_vec());
+        self
+    }
+
+    /// Setting this flag to true rejects the registration of attested credentials whose device
+    /// model is known to have security issues, with
+    /// [WebauthnError::AttestationDeviceVulnerable]. Vulnerable device models are marked in the
+    /// attestation CA list, such as when it is built from the status reports of the FIDO Metadata
+    /// Service. See [`AttestationCaList::insert_vulnerable_aaguid`].
+    ///
+    /// This has no effect on registrations that are not verified against an attestation CA list.
+    ///
+    /// Defaults to "false".
+    pub fn reject_vulnerable_firmware(mut self, reject: bool) -> Self {
+        self.reject_vulnerable_firmware = reject;
+        self
+    }
+
+    /// Set the algorithms that credentials may use during registration, in order of preference.
+    /// This replaces the default set of secure algorithms.
+    ///
+    /// The list is validated when the instance is built. An empty list, a list containing
+    /// duplicates, or a list containing an insecure algorithm such as
+    /// [COSEAlgorithm::INSECURE_RS1] or one that is not in [Webauthn::supported_algorithms]
+    /// will cause [WebauthnBuilder::build] to return [WebauthnError::Configuration].
+    ///
+    /// If in doubt, do NOT change this value.
+    pub fn with_algorithms(mut self, algorithms: &[COSEAlgorithm]) -> Self {
+        self.algorithms = algorithms.to_vec();
+        self
+    }
+
     /// Complete the construction of the [Webauthn] instance. If an invalid configuration setting
     /// is found, an Error will be returned.
     ///
@@ -313,21 +654,277 @@ impl<'a> WebauthnBuilder<'a> {
     ///     .expect("Invalid configuration");
     /// ```
     pub fn build(self) -> WebauthnResult<Webauthn> {
+        if self.require_https_origins {
+            if let Some(origin) = self.allowed_origins.iter().find(|origin| {
+                origin.scheme() == "http" && !(self.allow_insecure_localhost && is_loopback(origin))
+            }) {
+                error!(%origin, "allowed origin is plain http");
+                return Err(WebauthnError::Configuration);
+            }
+        }
+
+        if self.algorithms.is_empty() {
+            error!("at least one credential algorithm is required");
+            return Err(WebauthnError::Configuration);
+        }
+
+        for (i, alg) in self.algorithms.iter().enumerate() {
+            if alg.is_insecure() {
+                error!(?alg, "credential algorithm is insecure");
+                return Err(WebauthnError::Configuration);
+            }
+
+            if !Webauthn::supported_algorithms().contains(alg) {
+                error!(?alg, "credential algorithm can not be verified");
+                return Err(WebauthnError::Configuration);
+            }
+
+            if self.algorithms[..i].contains(alg) {
+                error!(?alg, "credential algorithm is duplicated");
+                return Err(WebauthnError::Configuration);
+            }
+        }
+
+        let mut core = WebauthnCore::new_unsafe_experts_only(
+            self.rp_name.unwrap_or(self.rp_id),
+            self.rp_id,
+            self.allowed_origins,
+            None,
+            Some(self.allow_subdomains),
+            Some(self.allow_any_port),
+        );
+        core.set_lenient_cose_parsing(self.lenient_cose_parsing);
+        core.set_strict_packed_subject(self.strict_packed_subject);
+        core.set_store_transports(self.store_credential_transports);
+        core.set_warn_uv_downgrade(self.warn_uv_downgrade);
+        for verifier in self.attestation_format_verifiers {
+            core.register_attestation_format_verifier(verifier);
+        }
+
         Ok(Webauthn {
-            core: WebauthnCore::new_unsafe_experts_only(
-                self.rp_name.unwrap_or(self.rp_id),
-                self.rp_id,
-                self.allowed_origins,
-                None,
-                Some(self.allow_subdomains),
-                Some(self.allow_any_port),
-            ),
+            core,
             algorithms: self.algorithms,
             user_presence_only_security_keys: self.user_presence_only_security_keys,
+            security_key_discourage_resident_key: self.security_key_discourage_resident_key,
+            passkey_attestation: self.passkey_attestation,
+            required_attachment: self.required_attachment,
+            min_rsa_key_bits: self.min_rsa_key_bits,
+            max_attestation_object_size: self.max_attestation_object_size,
+            max_client_data_size: self.max_client_data_size,
+            legacy_appid: self.legacy_appid.map(str::to_string),
+            clock: self.clock,
+            request_uvm: self.request_uvm,
+            aaguid_names: self.aaguid_names,
+            aaguid_transports: self.aaguid_transports,
+            reject_vulnerable_firmware: self.reject_vulnerable_firmware,
+            client_capabilities: None,
         })
     }
 }
 
+/// An owned and serialisable configuration of a [Webauthn] instance. This is useful where
+/// configurations are loaded dynamically, such as per tenant in a multi-tenant server, since it
+/// does not borrow its values like [WebauthnBuilder] does.
+///
+/// The configuration is validated with the same rules as [WebauthnBuilder] when it is
+/// instantiated with [Webauthn::from_config].
+///
+/// # Safety
+///
+/// rp_id is what Credentials (Authenticators) bind themself to - rp_id can NOT be changed
+/// without breaking all of your users' associated credentials in the future!
+///
+/// Every field other than rp_id and allowed_origins may be omitted when deserialising, in
+/// which case it takes the same default as in [WebauthnBuilder]. The clock and any
+/// attestation format verifiers can not be serialised, and are not part of this configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct WebauthnConfig {
+    /// The relying party id. This *must* be an effective domain of every allowed origin.
+    pub rp_id: String,
+    /// The relying party name, which may be shown to the user. Defaults to rp_id if not set.
+    #[serde(default)]
+    pub rp_name: Option<String>,
+    /// The origins that are considered valid in Webauthn operations. The first origin is the
+    /// relying party origin, and rp_id *must* be an effective domain of it. Any further origins
+    /// are treated as per [WebauthnBuilder::append_allowed_origin].
+    pub allowed_origins: Vec<Url>,
+    /// Allow subdomains of the allowed origins. See [WebauthnBuilder::allow_subdomains].
+    #[serde(default)]
+    pub allow_subdomains: bool,
+    /// Skip port checks on origin matches. See [WebauthnBuilder::allow_any_port].
+    #[serde(default)]
+    pub allow_any_port: bool,
+    /// Reject plain http origins. See [WebauthnBuilder::require_https_origins].
+    #[serde(default = "default_require_https_origins")]
+    pub require_https_origins: bool,
+    /// Accept plain http origins on loopback addresses. See
+    /// [WebauthnBuilder::allow_insecure_localhost].
+    #[serde(default)]
+    pub allow_insecure_localhost: bool,
+    /// The algorithms that credentials may use during registration, in order of preference.
+    /// The secure default algorithms are used if this is not set. See
+    /// [WebauthnBuilder::with_algorithms].
+    #[serde(default)]
+    pub algorithms: Option<Vec<COSEAlgorithm>>,
+    /// Offer EDDSA in addition to the configured algorithms. See
+    /// [WebauthnBuilder::enable_eddsa].
+    #[serde(default)]
+    pub enable_eddsa: bool,
+    /// Discourage security keys from creating resident keys. See
+    /// [WebauthnBuilder::discourage_security_key_resident_key].
+    #[serde(default = "default_discourage_security_key_resident_key")]
+    pub discourage_security_key_resident_key: bool,
+    /// Require attestation during passkey registration. See
+    /// [WebauthnBuilder::passkey_require_attestation].
+    #[serde(default)]
+    pub passkey_attestation: Option<AttestationConveyancePreference>,
+    /// Require credentials to use this attachment. See [WebauthnBuilder::require_attachment].
+    #[serde(default)]
+    pub required_attachment: Option<AuthenticatorAttachment>,
+    /// The minimum size in bits of RSA credentials. See [WebauthnBuilder::min_rsa_key_bits].
+    #[serde(default = "default_min_rsa_key_bits")]
+    pub min_rsa_key_bits: u32,
+    /// Accept COSE keys with unexpected parameters. See [WebauthnBuilder::lenient_cose_parsing].
+    #[serde(default)]
+    pub lenient_cose_parsing: bool,
+    /// Store the transports reported by the client. See
+    /// [WebauthnBuilder::store_credential_transports].
+    #[serde(default)]
+    pub store_credential_transports: bool,
+    /// Warn when user verification was preferred but not performed. See
+    /// [WebauthnBuilder::warn_uv_downgrade].
+    #[serde(default)]
+    pub warn_uv_downgrade: bool,
+    /// The maximum size in bytes of an attestationObject. See
+    /// [WebauthnBuilder::max_attestation_object_size].
+    #[serde(default = "default_max_attestation_object_size")]
+    pub max_attestation_object_size: usize,
+    /// The maximum size in bytes of clientDataJSON. See [WebauthnBuilder::max_client_data_size].
+    #[serde(default = "default_max_client_data_size")]
+    pub max_client_data_size: usize,
+    /// The U2F appId of legacy security keys. See [WebauthnBuilder::legacy_appid].
+    #[serde(default)]
+    pub legacy_appid: Option<String>,
+    /// Check the subject of packed attestation certificates. See
+    /// [WebauthnBuilder::strict_packed_subject].
+    #[serde(default = "default_strict_packed_subject")]
+    pub strict_packed_subject: bool,
+    /// Request the uvm extension. See [WebauthnBuilder::request_uvm].
+    #[serde(default = "default_request_uvm")]
+    pub request_uvm: bool,
+    /// Names of aaguids for log messages. See [WebauthnBuilder::aaguid_name].
+    #[serde(default)]
+    pub aaguid_names: BTreeMap<Uuid, String>,
+    /// The transports that aaguids support. See [WebauthnBuilder::aaguid_transports].
+    #[serde(default)]
+    pub aaguid_transports: BTreeMap<Uuid, Vec<AuthenticatorTransport>>,
+    /// Reject attested credentials of vulnerable device models. See
+    /// [WebauthnBuilder::reject_vulnerable_firmware].
+    #[serde(default)]
+    pub reject_vulnerable_firmware: bool,
+}
+
+impl WebauthnConfig {
+    /// Create a configuration for the relying party id and origin, with every other setting
+    /// at its default. The fields may then be changed before the configuration is stored or
+    /// instantiated.
+    ///
+    /// ```
+    /// use webauthn_rs::prelude::*;
+    ///
+    /// let rp_origin = Url::parse("https://idm.example.com").expect("Invalid URL");
+    /// let mut config = WebauthnConfig::new("example.com", &rp_origin);
+    /// config.allow_any_port = true;
+    /// ```
+    pub fn new(rp_id: &str, rp_origin: &Url) -> Self {
+        WebauthnConfig {
+            rp_id: rp_id.to_string(),
+            rp_name: None,
+            allowed_origins: vec![rp_origin.to_owned()],
+            allow_subdomains: false,
+            allow_any_port: false,
+            require_https_origins: default_require_https_origins(),
+            allow_insecure_localhost: false,
+            algorithms: None,
+            enable_eddsa: false,
+            discourage_security_key_resident_key: default_discourage_security_key_resident_key(),
+            passkey_attestation: None,
+            required_attachment: None,
+            min_rsa_key_bits: default_min_rsa_key_bits(),
+            lenient_cose_parsing: false,
+            store_credential_transports: false,
+            warn_uv_downgrade: false,
+            max_attestation_object_size: default_max_attestation_object_size(),
+            max_client_data_size: default_max_client_data_size(),
+            legacy_appid: None,
+            strict_packed_subject: default_strict_packed_subject(),
+            request_uvm: default_request_uvm(),
+            aaguid_names: BTreeMap::new(),
+            aaguid_transports: BTreeMap::new(),
+            reject_vulnerable_firmware: false,
+        }
+    }
+}
+
+/// The clock of a [Webauthn] instance, set with [WebauthnBuilder::clock].
+#[derive(Clone)]
+struct Clock(Arc<dyn Fn() -> SystemTime + Send + Sync>);
+
+impl std::fmt::Debug for Clock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Clock")
+    }
+}
+
+fn default_require_https_origins() -> bool {
+    true
+}
+
+fn default_discourage_security_key_resident_key() -> bool {
+    true
+}
+
+fn default_min_rsa_key_bits() -> u32 {
+    RSA_MIN_KEY_BITS
+}
+
+fn default_max_attestation_object_size() -> usize {
+    DEFAULT_MAX_ATTESTATION_OBJECT_SIZE
+}
+
+fn default_max_client_data_size() -> usize {
+    DEFAULT_MAX_CLIENT_DATA_SIZE
+}
+
+fn default_strict_packed_subject() -> bool {
+    true
+}
+
+fn default_request_uvm() -> bool {
+    true
+}
+
+fn is_loopback(origin: &Url) -> bool {
+    match origin.host() {
+        Some(url::Host::Domain(domain)) => domain == "localhost",
+        Some(url::Host::Ipv4(addr)) => addr.is_loopback(),
+        Some(url::Host::Ipv6(addr)) => addr.is_loopback(),
+        None => false,
+    }
+}
+
+fn is_effective_domain(rp_id: &str, origin: &Url) -> bool {
+    origin
+        .domain()
+        .map(|effective_domain| {
+            // We need to prepend the '.' here to ensure that myexample.com != example.com,
+            // rather than just ends with.
+            effective_domain.ends_with(&format!(".{rp_id}")) || effective_domain == rp_id
+        })
+        .unwrap_or(false)
+}
+
 /// An instance of a Webauthn site. This is the main point of interaction for registering and
 /// authenticating credentials for users. Depending on your needs, you'll want to allow users
 /// to register and authenticate with different kinds of authenticators.
@@ -365,72 +962,655 @@ impl<'a> WebauthnBuilder<'a> {
 ///
 /// > If you really want a security key, you should use [`start_securitykey_registration`](Webauthn::start_securitykey_registration)
 ///
+/// # Sharing
+///
+/// A [Webauthn] instance is immutable once built, and is `Send + Sync`. Rather than cloning
+/// it for each request, you should build it once at startup and share it between threads or
+/// async tasks with an [`Arc`](std::sync::Arc).
+///
+/// ```
+/// # use webauthn_rs::prelude::*;
+/// use std::sync::Arc;
+///
+/// let rp_origin = Url::parse("https://idm.example.com").expect("Invalid URL");
+/// let webauthn = Arc::new(
+///     WebauthnBuilder::new("example.com", &rp_origin)
+///         .expect("Invalid configuration")
+///         .build()
+///         .expect("Invalid configuration"),
+/// );
+///
+/// let handle = {
+///     let webauthn = Arc::clone(&webauthn);
+///     std::thread::spawn(move || {
+///         webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)
+///     })
+/// };
+/// let (ccr, reg_state) = handle
+///     .join()
+///     .expect("Registration thread panicked")
+///     .expect("Failed to start registration.");
+/// ```
+///
+/// The registration and authentication state types, and the credential types, are also
+/// `Send + Sync`, so they may be held across `.await` points or stored in a shared session
+/// cache.
 #[derive(Debug, Clone)]
 pub struct Webauthn {
     core: WebauthnCore,
     algorithms: Vec<COSEAlgorithm>,
     user_presence_only_security_keys: bool,
+    security_key_discourage_resident_key: bool,
+    passkey_attestation: Option<AttestationConveyancePreference>,
+    required_attachment: Option<AuthenticatorAttachment>,
+    min_rsa_key_bits: u32,
+    max_attestation_object_size: usize,
+    max_client_data_size: usize,
+    legacy_appid: Option<String>,
+    clock: Clock,
+    request_uvm: bool,
+    aaguid_names: BTreeMap<Uuid, String>,
+    aaguid_transports: BTreeMap<Uuid, Vec<AuthenticatorTransport>>,
+    reject_vulnerable_firmware: bool,
+    client_capabilities: Option<ClientCapabilities>,
 }
 
 impl Webauthn {
+    /// Construct a new [Webauthn] instance from an owned configuration. If an invalid
+    /// configuration setting is found, an Error will be returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use webauthn_rs::prelude::*;
+    ///
+    /// let rp_origin = Url::parse("https://idm.example.com").expect("Invalid URL");
+    /// let config = WebauthnConfig::new("example.com", &rp_origin);
+    /// let webauthn = Webauthn::from_config(config)
+    ///     .expect("Invalid configuration");
+    /// ```
+    pub fn from_config(config: WebauthnConfig) -> WebauthnResult<Webauthn> {
+        let WebauthnConfig {
+            rp_id,
+            rp_name,
+            allowed_origins,
+            allow_subdomains,
+            allow_any_port,
+            require_https_origins,
+            allow_insecure_localhost,
+            algorithms,
+            enable_eddsa,
+            discourage_security_key_resident_key,
+            passkey_attestation,
+            required_attachment,
+            min_rsa_key_bits,
+            lenient_cose_parsing,
+            store_credential_transports,
+            warn_uv_downgrade,
+            max_attestation_object_size,
+            max_client_data_size,
+            legacy_appid,
+            strict_packed_subject,
+            request_uvm,
+            aaguid_names,
+            aaguid_transports,
+            reject_vulnerable_firmware,
+        } = config;
+
+        let (rp_origin, extra_origins) = allowed_origins.split_first().ok_or_else(|| {
+            error!("at least one allowed origin is required");
+            WebauthnError::Configuration
+        })?;
+
+        let mut builder = WebauthnBuilder::new(&rp_id, rp_origin)?
+            .allow_subdomains(allow_subdomains)
+            .allow_any_port(allow_any_port)
+            .require_https_origins(require_https_origins)
+            .allow_insecure_localhost(allow_insecure_localhost)
+            .discourage_security_key_resident_key(discourage_security_key_resident_key)
+            .min_rsa_key_bits(min_rsa_key_bits)
+            .lenient_cose_parsing(lenient_cose_parsing)
+            .store_credential_transports(store_credential_transports)
+            .warn_uv_downgrade(warn_uv_downgrade)
+            .max_attestation_object_size(max_attestation_object_size)
+            .max_client_data_size(max_client_data_size)
+            .strict_packed_subject(strict_packed_subject)
+            .request_uvm(request_uvm)
+            .reject_vulnerable_firmware(reject_vulnerable_firmware);
+
+        for origin in extra_origins {
+            builder = builder.append_allowed_origin(origin);
+        }
+
+        if let Some(rp_name) = rp_name.as_deref() {
+            builder = builder.rp_name(rp_name);
+        }
+
+        if let Some(algorithms) = algorithms.as_deref() {
+            builder = builder.with_algorithms(algorithms);
+        }
+
+        // Only add EDDSA, so that it is not removed from explicitly configured algorithms.
+        if enable_eddsa {
+            builder = builder.enable_eddsa(true);
+        }
+
+        if let Some(conveyance) = passkey_attestation {
+            builder = builder.passkey_require_attestation(conveyance);
+        }
+
+        if let Some(attachment) = required_attachment {
+            builder = builder.require_attachment(attachment);
+        }
+
+        if let Some(appid) = legacy_appid.as_deref() {
+            builder = builder.legacy_appid(appid);
+        }
+
+        for (aaguid, name) in aaguid_names {
+            builder = builder.aaguid_name(aaguid, &name);
+        }
+
+        for (aaguid, transports) in aaguid_transports {
+            builder = builder.aaguid_transports(aaguid, &transports);
+        }
+
+        builder.build()
+    }
+
     /// Get the currently configured origins
     pub fn get_allowed_origins(&self) -> &[Url] {
         self.core.get_allowed_origins()
     }
 
-    /// Initiate the registration of a new passkey for a user. A passkey is any cryptographic
-    /// authenticator acting as a single factor of authentication, far stronger than a password
-    /// or email-reset link.
-    ///
-    /// Some examples of passkeys include Yubikeys, TouchID, FaceID, Windows Hello and others.
-    ///
-    /// The keys *may* exist and 'roam' between multiple devices. For example, Apple allows Passkeys
-    /// to sync between devices owned by the same Apple account. This can affect your risk model
-    /// related to these credentials, but generally in all cases passkeys are better than passwords!
-    ///
-    /// You *should* NOT pair this authentication with another factor. A passkey may opportunistically
-    /// allow and enforce user-verification (MFA), but this is NOT guaranteed with all authenticator
-    /// types.
-    ///
-    /// `user_unique_id` *may* be stored in the authenticator. This may allow the credential to
-    ///  identify the user during certain client side work flows.
-    ///
-    /// `user_name` and `user_display_name` *may* be stored in the authenticator. `user_name` is a
-    /// friendly account name such as "claire@example.com". `user_display_name` is the persons chosen
-    /// way to be identified such as "Claire". Both can change at *any* time on the client side, and
-    /// MUST NOT be used as primary keys. They *may not* be present in authentication, these are only
-    /// present to allow client facing work flows to display human friendly identifiers.
+    /// The SHA-256 hash of the relying party id, as it is expected to appear in the
+    /// rpIdHash of authenticator data. This is computed once when the instance is built.
+    pub fn rp_id_hash(&self) -> &[u8; 32] {
+        self.core.rp_id_hash()
+    }
+
+    /// The algorithms that this crate can verify signatures with. Only these may be configured
+    /// with [WebauthnBuilder::with_algorithms].
     ///
-    /// `exclude_credentials` ensures that a set of credentials may not participate in this registration.
-    /// You *should* provide the list of credentials that are already registered to this user's account
-    /// to prevent duplicate credential registrations. These credentials *can* be from different
-    /// authenticator classes since we only require the `CredentialID`
+    /// ```
+    /// use webauthn_rs::prelude::*;
     ///
-    /// # Returns
+    /// assert!(Webauthn::supported_algorithms().contains(&COSEAlgorithm::ES256));
+    /// assert!(!Webauthn::supported_algorithms().contains(&COSEAlgorithm::INSECURE_RS1));
+    /// ```
+    pub fn supported_algorithms() -> &'static [COSEAlgorithm] {
+        webauthn_rs_core::crypto::supported_algorithms()
+    }
+
+    /// Describe an aaguid for log messages, including its name if one was set with
+    /// [WebauthnBuilder::aaguid_name].
     ///
-    /// This function returns a `CreationChallengeResponse` which you must serialise to json and
-    /// send to the user agent (e.g. a browser) for it to conduct the registration. You must persist
-    /// on the server the `PasskeyRegistration` which contains the state of this registration
-    /// attempt and is paired to the `CreationChallengeResponse`.
+    /// ```
+    /// use webauthn_rs::prelude::*;
     ///
-    /// Finally you need to call [`finish_passkey_registration`](Webauthn::finish_passkey_registration)
-    /// to complete the registration.
+    /// let aaguid = Uuid::nil();
+    /// let rp_origin = Url::parse("https://idm.example.com").expect("Invalid URL");
+    /// let webauthn = WebauthnBuilder::new("example.com", &rp_origin)
+    ///     .expect("Invalid configuration")
+    ///     .aaguid_name(aaguid, "Example Authenticator")
+    ///     .build()
+    ///     .expect("Invalid configuration");
     ///
-    /// WARNING ⚠️  YOU MUST STORE THE [PasskeyRegistration] VALUE SERVER SIDE.
+    /// assert_eq!(
+    ///     webauthn.describe_aaguid(aaguid),
+    ///     "Example Authenticator (aaguid 00000000-0000-0000-0000-000000000000)"
+    /// );
+    /// ```
+    pub fn describe_aaguid(&self, aaguid: Uuid) -> String {
+        match self.aaguid_names.get(&aaguid) {
+            Some(name) => format!("{name} (aaguid {aaguid})"),
+            None => format!("aaguid {aaguid}"),
+        }
+    }
+
+    /// Check that this configuration is sane before it is used, so that mistakes are found at
+    /// startup rather than at the first failed ceremony. This verifies that:
     ///
-    /// Failure to do so *may* open you to replay attacks which can significantly weaken the
-    /// security of this system.
+    /// * the rp_id is an effective domain of every allowed http or https origin. Other origins,
+    ///   such as the native app origins described in [WebauthnBuilder::append_allowed_origin],
+    ///   are not web origins and have no domain to check.
+    /// * no allowed origin contains a wildcard, as clients never match these
+    /// * at least one secure credential algorithm is configured
+    /// * no insecure credential algorithm is configured
     ///
     /// ```
-    /// # use webauthn_rs::prelude::*;
+    /// use webauthn_rs::prelude::*;
     ///
-    /// # let rp_id = "example.com";
-    /// # let rp_origin = Url::parse("https://idm.example.com")
-    /// #     .expect("Invalid URL");
-    /// # let mut builder = WebauthnBuilder::new(rp_id, &rp_origin)
-    /// #     .expect("Invalid configuration");
-    /// # let webauthn = builder.build()
-    /// #     .expect("Invalid configuration");
+    /// let rp_origin = Url::parse("https://idm.example.com").expect("Invalid URL");
+    /// let webauthn = WebauthnBuilder::new("example.com", &rp_origin)
+    ///     .expect("Invalid configuration")
+    ///     .append_allowed_origin(&Url::parse("https://idm.different.com").expect("Invalid URL"))
+    ///     .build()
+    ///     .expect("Invalid configuration");
+    /// assert!(webauthn.validate_configuration().is_err());
+    /// ```
+    pub fn validate_configuration(&self) -> WebauthnResult<()> {
+        let rp_id = self.core.rp_id();
+
+        for origin in self.core.get_allowed_origins() {
+            if origin.host_str().is_some_and(|host| host.contains('*')) {
+                error!(%origin, "allowed origin contains a wildcard");
+                return Err(WebauthnError::ConfigurationWildcardOrigin {
+                    origin: origin.to_string(),
+                });
+            }
+
+            if matches!(origin.scheme(), "http" | "https") && !is_effective_domain(rp_id, origin) {
+                error!(%origin, %rp_id, "rp_id is not an effective_domain of allowed origin");
+                return Err(WebauthnError::ConfigurationRpIdNotEffectiveDomain {
+                    rp_id: rp_id.to_string(),
+                    origin: origin.to_string(),
+                });
+            }
+        }
+
+        if let Some(alg) = self.algorithms.iter().find(|alg| alg.is_insecure()) {
+            error!(?alg, "credential algorithm is insecure");
+            return Err(WebauthnError::ConfigurationInsecureAlgorithm {
+                alg: format!("{alg:?}"),
+            });
+        }
+
+        if !self
+            .algorithms
+            .iter()
+            .any(|alg| Webauthn::supported_algorithms().contains(alg))
+        {
+            error!("no secure credential algorithm is configured");
+            return Err(WebauthnError::ConfigurationNoSecureAlgorithm);
+        }
+
+        Ok(())
+    }
+
+    /// Summarize the capabilities of the Passkeys registered to a single user, such as whether
+    /// any of them are synced or can verify the user. This is derived only from what was
+    /// observed at registration, and is intended to drive adaptive authentication interfaces.
+    pub fn summarize_credentials(&self, creds: &[Passkey]) -> CredentialSummary {
+        creds.iter().collect()
+    }
+
+    fn check_expected_aaguid(
+        &self,
+        cred: &Credential,
+        expected_aaguid: Option<Uuid>,
+    ) -> WebauthnResult<()> {
+        let aaguid = cred.attestation.metadata.aaguid();
+        match expected_aaguid {
+            Some(expected) if aaguid != Some(expected) => {
+                error!(
+                    expected = %self.describe_aaguid(expected),
+                    aaguid = %aaguid.map(|aaguid| self.describe_aaguid(aaguid)).unwrap_or_default(),
+                    "credential aaguid does not match the expected aaguid"
+                );
+                Err(WebauthnError::AttestationAaguidMismatch)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn register_credential(
+        &self,
+        reg: &RegisterPublicKeyCredential,
+        state: &RegistrationState,
+        attestation_cas: Option<&AttestationCaList>,
+    ) -> WebauthnResult<Credential> {
+        let result = self.core.register_credential(reg, state, attestation_cas);
+        if let Err(
+            e @ (WebauthnError::AttestationUntrustedAaguid
+            | WebauthnError::AttestationChainNotTrusted(_)
+            | WebauthnError::AttestationCertificateExpired
+            | WebauthnError::AttestationCertificateNotYetValid),
+        ) = &result
+        {
+            // The response has already been parsed, so this only fails if it is malformed.
+            if let Ok(inspection) = self.core.inspect_registration(reg) {
+                error!(
+                    "rejected {}: {}",
+                    self.describe_aaguid(inspection.aaguid),
+                    e
+                );
+            }
+        }
+        let mut cred = result?;
+
+        if self.reject_vulnerable_firmware {
+            if let (Some(ca_list), Some(aaguid)) =
+                (attestation_cas, cred.attestation.metadata.aaguid())
+            {
+                if ca_list.is_vulnerable_aaguid(&aaguid) {
+                    error!(
+                        "rejected {}: the device model is known to be vulnerable",
+                        self.describe_aaguid(aaguid)
+                    );
+                    return Err(WebauthnError::AttestationDeviceVulnerable);
+                }
+            }
+        }
+
+        cred.registered_at = Some((self.clock.0)());
+
+        // Prefer the transports the client reported, falling back to those known for the
+        // attested aaguid.
+        if cred.transports.as_deref().unwrap_or_default().is_empty() {
+            if let Some(transports) = cred
+                .attestation
+                .metadata
+                .aaguid()
+                .and_then(|aaguid| self.aaguid_transports.get(&aaguid))
+            {
+                debug!(
+                    ?transports,
+                    "using the known transports of the attested aaguid"
+                );
+                cred.transports = Some(transports.clone());
+            }
+        }
+        Ok(cred)
+    }
+
+    fn check_response_size(&self, reg: &RegisterPublicKeyCredential) -> WebauthnResult<()> {
+        let attestation_object_size = reg.response.attestation_object.as_ref().len();
+        let client_data_size = reg.response.client_data_json.as_ref().len();
+
+        if attestation_object_size > self.max_attestation_object_size {
+            error!(
+                attestation_object_size,
+                max_attestation_object_size = self.max_attestation_object_size,
+                "attestation object is too large"
+            );
+            return Err(WebauthnError::ResponseTooLarge);
+        }
+
+        if client_data_size > self.max_client_data_size {
+            error!(
+                client_data_size,
+                max_client_data_size = self.max_client_data_size,
+                "client data is too large"
+            );
+            return Err(WebauthnError::ResponseTooLarge);
+        }
+
+        Ok(())
+    }
+
+    fn securitykey_authentication_options(
+        &self,
+    ) -> (
+        UserVerificationPolicy,
+        Option<RequestAuthenticationExtensions>,
+    ) {
+        let policy = if self.user_presence_only_security_keys {
+            UserVerificationPolicy::Discouraged_DO_NOT_USE
+        } else {
+            UserVerificationPolicy::Preferred
+        };
+
+        let extensions = self
+            .legacy_appid
+            .as_ref()
+            .map(|appid| RequestAuthenticationExtensions {
+                appid: Some(appid.clone()),
+                uvm: None,
+                hmac_get_secret: None,
+            });
+
+        (policy, extensions)
+    }
+
+    fn check_credential_key(&self, cred: &Credential) -> WebauthnResult<()> {
+        match cred.cred.rsa_key_bits() {
+            Some(bits) if bits < self.min_rsa_key_bits => {
+                error!(
+                    bits,
+                    min_rsa_key_bits = self.min_rsa_key_bits,
+                    "credential rsa key is too small"
+                );
+                Err(WebauthnError::CredentialRsaKeyTooSmall)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn check_attachment(
+        &self,
+        attachment: Option<AuthenticatorAttachment>,
+    ) -> WebauthnResult<()> {
+        match self.required_attachment {
+            Some(required) if attachment != Some(required) => {
+                error!(
+                    ?required,
+                    ?attachment,
+                    "authenticator attachment does not match the required attachment"
+                );
+                Err(WebauthnError::AuthenticatorAttachmentMismatch)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Resolve the authenticator attachment and hints to request in a registration. An attachment
+    /// required by [WebauthnBuilder::require_attachment] always takes precedence over the UI hint,
+    /// and any hints for authenticators that could not satisfy it are dropped, since the
+    /// registration would be rejected by the `finish_*` operation anyway. Hints for authenticators
+    /// that the client reported it can not use are also dropped.
+    fn registration_attachment(
+        &self,
+        ui_hint_authenticator_attachment: Option<AuthenticatorAttachment>,
+        hints: Option<Vec<PublicKeyCredentialHint>>,
+    ) -> (
+        Option<AuthenticatorAttachment>,
+        Option<Vec<PublicKeyCredentialHint>>,
+    ) {
+        let hints = match self.client_capabilities.as_ref() {
+            Some(client_capabilities) => hints.and_then(|mut hints| {
+                client_capabilities.filter_hints(&mut hints);
+                (!hints.is_empty()).then_some(hints)
+            }),
+            None => hints,
+        };
+
+        let Some(required) = self.required_attachment else {
+            let no_platform_authenticator = self
+                .client_capabilities
+                .is_some_and(|caps| !caps.supports_platform_authenticator());
+            let ui_hint_authenticator_attachment =
+                ui_hint_authenticator_attachment.filter(|hint| {
+                    !(no_platform_authenticator && *hint == AuthenticatorAttachment::Platform)
+                });
+            return (ui_hint_authenticator_attachment, hints);
+        };
+
+        if ui_hint_authenticator_attachment.is_some_and(|hint| hint != required) {
+            warn!(
+                ?required,
+                ?ui_hint_authenticator_attachment,
+                "ignoring authenticator attachment hint that conflicts with the required attachment"
+            );
+        }
+
+        let hints = hints.and_then(|mut hints| {
+            hints.retain(|hint| {
+                let compatible = match hint {
+                    PublicKeyCredentialHint::ClientDevice => {
+                        required == AuthenticatorAttachment::Platform
+                    }
+                    PublicKeyCredentialHint::SecurityKey | PublicKeyCredentialHint::Hybrid => {
+                        required == AuthenticatorAttachment::CrossPlatform
+                    }
+                };
+                if !compatible {
+                    warn!(
+                        ?required,
+                        ?hint,
+                        "ignoring registration hint that conflicts with the required attachment"
+                    );
+                }
+                compatible
+            });
+            (!hints.is_empty()).then_some(hints)
+        });
+
+        (Some(required), hints)
+    }
+
+    /// Returns a copy of this [Webauthn] instance that will not offer the listed algorithms
+    /// during registration. This allows a single ceremony to subtract algorithms from the
+    /// configured set (for example, to avoid RS256 credentials) without respecifying the
+    /// whole list. Credentials using a denied algorithm will be rejected when the registration
+    /// is finished.
+    ///
+    /// If no algorithms remain after the filter is applied, [WebauthnError::Configuration]
+    /// is returned.
+    ///
+    /// ```
+    /// # use webauthn_rs::prelude::*;
+    /// # let rp_origin = Url::parse("https://idm.example.com").expect("Invalid URL");
+    /// # let webauthn = WebauthnBuilder::new("example.com", &rp_origin)
+    /// #     .expect("Invalid configuration")
+    /// #     .build()
+    /// #     .expect("Invalid configuration");
+    /// let (ccr, skr) = webauthn
+    ///     .with_denied_algorithms(&[COSEAlgorithm::RS256])
+    ///     .expect("No algorithms remain")
+    ///     .start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)
+    ///     .expect("Failed to start registration.");
+    /// ```
+    pub fn with_denied_algorithms(
+        &self,
+        deny_algorithms: &[COSEAlgorithm],
+    ) -> WebauthnResult<Webauthn> {
+        let algorithms: Vec<_> = self
+            .algorithms
+            .iter()
+            .filter(|alg| !deny_algorithms.contains(alg))
+            .copied()
+            .collect();
+
+        if algorithms.is_empty() {
+            error!("all configured credential algorithms were denied");
+            return Err(WebauthnError::Configuration);
+        }
+
+        Ok(Webauthn {
+            algorithms,
+            ..self.clone()
+        })
+    }
+
+    /// Returns a copy of this [Webauthn] instance that tailors the ceremonies it starts to the
+    /// [ClientCapabilities] reported by the client. For example, if the client has no platform
+    /// authenticator then hints for one are not emitted, and if it does not support the hybrid
+    /// transport then it is not offered for the allowed credentials of an authentication.
+    /// Capabilities that the client did not report are assumed to be supported.
+    ///
+    /// An attachment required by [WebauthnBuilder::require_attachment] is never removed, even
+    /// if the client reports that it can not satisfy it.
+    ///
+    /// This is an optimisation of the user experience, NOT a security control, since the
+    /// capabilities are reported by the client and can not be trusted.
+    ///
+    /// ```
+    /// # use webauthn_rs::prelude::*;
+    /// # let rp_origin = Url::parse("https://idm.example.com").expect("Invalid URL");
+    /// # let webauthn = WebauthnBuilder::new("example.com", &rp_origin)
+    /// #     .expect("Invalid configuration")
+    /// #     .build()
+    /// #     .expect("Invalid configuration");
+    /// // As returned by the front-end from `PublicKeyCredential.getClientCapabilities()`
+    /// let client_capabilities: ClientCapabilities =
+    ///     serde_json::from_str(r#"{"hybridTransport":false}"#)
+    ///         .expect("Invalid client capabilities");
+    ///
+    /// let (ccr, skr) = webauthn
+    ///     .with_client_capabilities(client_capabilities)
+    ///     .start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)
+    ///     .expect("Failed to start registration.");
+    /// ```
+    pub fn with_client_capabilities(&self, client_capabilities: ClientCapabilities) -> Webauthn {
+        Webauthn {
+            client_capabilities: Some(client_capabilities),
+            ..self.clone()
+        }
+    }
+
+    /// Clone the credentials for an authentication challenge, removing any transports that the
+    /// client reported it can not use.
+    fn credentials_for_client<'a>(
+        &self,
+        creds: impl Iterator<Item = &'a Credential>,
+    ) -> Vec<Credential> {
+        creds
+            .map(|cred| {
+                let mut cred = cred.clone();
+                if let Some(client_capabilities) = self.client_capabilities.as_ref() {
+                    client_capabilities.filter_transports(&mut cred.transports);
+                }
+                cred
+            })
+            .collect()
+    }
+
+    /// Initiate the registration of a new passkey for a user. A passkey is any cryptographic
+    /// authenticator acting as a single factor of authentication, far stronger than a password
+    /// or email-reset link.
+    ///
+    /// Some examples of passkeys include Yubikeys, TouchID, FaceID, Windows Hello and others.
+    ///
+    /// The keys *may* exist and 'roam' between multiple devices. For example, Apple allows Passkeys
+    /// to sync between devices owned by the same Apple account. This can affect your risk model
+    /// related to these credentials, but generally in all cases passkeys are better than passwords!
+    ///
+    /// You *should* NOT pair this authentication with another factor. A passkey may opportunistically
+    /// allow and enforce user-verification (MFA), but this is NOT guaranteed with all authenticator
+    /// types.
+    ///
+    /// `user_unique_id` *may* be stored in the authenticator. This may allow the credential to
+    ///  identify the user during certain client side work flows.
+    ///
+    /// `user_name` and `user_display_name` *may* be stored in the authenticator. `user_name` is a
+    /// friendly account name such as "claire@example.com". `user_display_name` is the persons chosen
+    /// way to be identified such as "Claire". Both can change at *any* time on the client side, and
+    /// MUST NOT be used as primary keys. They *may not* be present in authentication, these are only
+    /// present to allow client facing work flows to display human friendly identifiers.
+    ///
+    /// `exclude_credentials` ensures that a set of credentials may not participate in this registration.
+    /// You *should* provide the list of credentials that are already registered to this user's account
+    /// to prevent duplicate credential registrations. These credentials *can* be from different
+    /// authenticator classes since we only require the `CredentialID`. These are also stored in the
+    /// [PasskeyRegistration], so that if a client ignores them, finishing the registration is
+    /// rejected with [WebauthnError::CredentialExcludedFromRequest].
+    ///
+    /// # Returns
+    ///
+    /// This function returns a `CreationChallengeResponse` which you must serialise to json and
+    /// send to the user agent (e.g. a browser) for it to conduct the registration. You must persist
+    /// on the server the `PasskeyRegistration` which contains the state of this registration
+    /// attempt and is paired to the `CreationChallengeResponse`.
+    ///
+    /// Finally you need to call [`finish_passkey_registration`](Webauthn::finish_passkey_registration)
+    /// to complete the registration.
+    ///
+    /// WARNING ⚠️  YOU MUST STORE THE [PasskeyRegistration] VALUE SERVER SIDE.
+    ///
+    /// Failure to do so *may* open you to replay attacks which can significantly weaken the
+    /// security of this system.
+    ///
+    /// ```
+    /// # use webauthn_rs::prelude::*;
+    ///
+    /// # let rp_id = "example.com";
+    /// # let rp_origin = Url::parse("https://idm.example.com")
+    /// #     .expect("Invalid URL");
+    /// # let mut builder = WebauthnBuilder::new(rp_id, &rp_origin)
+    /// #     .expect("Invalid configuration");
+    /// # let webauthn = builder.build()
+    /// #     .expect("Invalid configuration");
     ///
     /// // you must store this user's unique id with the account. Alternatelly you can
     /// // use an existed UUID source.
@@ -453,10 +1633,88 @@ impl Webauthn {
         user_display_name: &str,
         exclude_credentials: Option<Vec<CredentialID>>,
     ) -> WebauthnResult<(CreationChallengeResponse, PasskeyRegistration)> {
-        let attestation = AttestationConveyancePreference::None;
+        self.start_passkey_registration_with_handle(
+            &UserHandle::from(user_unique_id),
+            user_name,
+            user_display_name,
+            exclude_credentials,
+        )
+    }
+
+    /// Create a [UserHandle] from an application specific primary key, such as a database
+    /// row id. The user handle must be between 1 and 64 bytes, and must NOT contain personally
+    /// identifying information.
+    ///
+    /// ```
+    /// # use webauthn_rs::prelude::*;
+    /// let user_handle = Webauthn::user_handle_from_bytes(&1234_u64.to_be_bytes())
+    ///     .expect("Invalid user handle");
+    /// ```
+    pub fn user_handle_from_bytes(bytes: &[u8]) -> WebauthnResult<UserHandle> {
+        UserHandle::try_from(bytes)
+    }
+
+    /// Create a [UserHandle] from a user's unique id.
+    pub fn user_handle_from_uuid(user_unique_id: Uuid) -> UserHandle {
+        UserHandle::from(user_unique_id)
+    }
+
+    /// Identical to [`start_passkey_registration`](Webauthn::start_passkey_registration), but
+    /// using the provided [UserHandle] as the user's unique id. This allows applications which
+    /// do not use UUIDs as primary keys to derive a stable user handle for the account.
+    pub fn start_passkey_registration_with_handle(
+        &self,
+        user_handle: &UserHandle,
+        user_name: &str,
+        user_display_name: &str,
+        exclude_credentials: Option<Vec<CredentialID>>,
+    ) -> WebauthnResult<(CreationChallengeResponse, PasskeyRegistration)> {
+        self.start_passkey_registration_internal(
+            user_handle,
+            user_name,
+            user_display_name,
+            exclude_credentials,
+            None,
+        )
+    }
+
+    /// Identical to [`start_passkey_registration`](Webauthn::start_passkey_registration), but
+    /// additionally emitting `hints`, an ordered list of the authenticator types you expect the
+    /// user to use. Browsers *may* use these to prioritise their UI, but they are not enforced.
+    /// Each hint may only be provided once, and an empty list emits no hints.
+    pub fn start_passkey_registration_with_hints(
+        &self,
+        user_unique_id: Uuid,
+        user_name: &str,
+        user_display_name: &str,
+        exclude_credentials: Option<Vec<CredentialID>>,
+        hints: Vec<PublicKeyCredentialHint>,
+    ) -> WebauthnResult<(CreationChallengeResponse, PasskeyRegistration)> {
+        let hints = validate_registration_hints(hints)?;
+        self.start_passkey_registration_internal(
+            &UserHandle::from(user_unique_id),
+            user_name,
+            user_display_name,
+            exclude_credentials,
+            hints,
+        )
+    }
+
+    fn start_passkey_registration_internal(
+        &self,
+        user_handle: &UserHandle,
+        user_name: &str,
+        user_display_name: &str,
+        exclude_credentials: Option<Vec<CredentialID>>,
+        hints: Option<Vec<PublicKeyCredentialHint>>,
+    ) -> WebauthnResult<(CreationChallengeResponse, PasskeyRegistration)> {
+        let attestation = self
+            .passkey_attestation
+            .clone()
+            .unwrap_or(AttestationConveyancePreference::None);
         let credential_algorithms = self.algorithms.clone();
         let require_resident_key = false;
-        let authenticator_attachment = None;
+        let (authenticator_attachment, hints) = self.registration_attachment(None, hints);
         let policy = Some(UserVerificationPolicy::Required);
         let reject_passkeys = false;
 
@@ -470,7 +1728,7 @@ impl Webauthn {
                 // and can't satisfy it, they fail the operation instead.
                 enforce_credential_protection_policy: Some(false),
             }),
-            uvm: Some(true),
+            uvm: self.request_uvm.then_some(true),
             cred_props: Some(true),
             min_pin_length: None,
             hmac_create_secret: None,
@@ -478,7 +1736,7 @@ impl Webauthn {
 
         self.core
             .generate_challenge_register_options(
-                user_unique_id.as_bytes(),
+                user_handle.as_bytes(),
                 user_name,
                 user_display_name,
                 attestation,
@@ -490,7 +1748,40 @@ impl Webauthn {
                 authenticator_attachment,
                 reject_passkeys,
             )
-            .map(|(ccr, rs)| (ccr, PasskeyRegistration { rs }))
+            .map(|(mut ccr, mut rs)| {
+                ccr.public_key.hints = hints;
+                // Attestation was not requested, so any that the client returns is ignored.
+                rs.set_ignore_attestation(self.passkey_attestation.is_none());
+                (ccr, PasskeyRegistration { rs })
+            })
+    }
+
+    /// Identical to [`start_passkey_registration`](Webauthn::start_passkey_registration), but
+    /// using the supplied `challenge` instead of a freshly generated one. This allows a
+    /// captured or pre-computed registration response to be verified in tests.
+    ///
+    /// ⚠️  This MUST NOT be used outside of tests. Reusing a challenge allows an attacker to
+    /// replay a previous registration response.
+    ///
+    /// *requires feature testing*
+    #[cfg(feature = "testing")]
+    pub fn start_passkey_registration_with_challenge(
+        &self,
+        challenge: Vec<u8>,
+        user_unique_id: Uuid,
+        user_name: &str,
+        user_display_name: &str,
+        exclude_credentials: Option<Vec<CredentialID>>,
+    ) -> WebauthnResult<(CreationChallengeResponse, PasskeyRegistration)> {
+        let (mut ccr, mut reg_state) = self.start_passkey_registration(
+            user_unique_id,
+            user_name,
+            user_display_name,
+            exclude_credentials,
+        )?;
+        ccr.public_key.challenge = challenge.clone().into();
+        reg_state.rs.danger_set_challenge(challenge);
+        Ok((ccr, reg_state))
     }
 
     /// Complete the registration of the credential. The user agent (e.g. a browser) will return the data of `RegisterPublicKeyCredential`,
@@ -505,6 +1796,9 @@ impl Webauthn {
     /// The returned `Passkey` must be associated to the users account, and is used for future
     /// authentications via [`start_passkey_authentication`](Webauthn::start_passkey_authentication).
     ///
+    /// If attestation was not requested, any attestation statement the client returns is ignored
+    /// rather than verified, and the passkey is registered as if no attestation was provided.
+    ///
     /// You MUST assert that the registered `CredentialID` has not previously been registered.
     /// to any other account.
     pub fn finish_passkey_registration(
@@ -512,9 +1806,33 @@ impl Webauthn {
         reg: &RegisterPublicKeyCredential,
         state: &PasskeyRegistration,
     ) -> WebauthnResult<Passkey> {
-        self.core
-            .register_credential(reg, &state.rs, None)
-            .map(|cred| Passkey { cred })
+        self.check_response_size(reg)?;
+        self.check_attachment(reg.authenticator_attachment)?;
+        let cred = self.register_credential(reg, &state.rs, None)?;
+        self.check_credential_key(&cred)?;
+
+        if self.passkey_attestation.is_some() && cred.attestation_format == AttestationFormat::None
+        {
+            error!("attestation was required, but the authenticator did not provide it");
+            return Err(WebauthnError::AttestationNotProvided);
+        }
+
+        Ok(Passkey { cred })
+    }
+
+    /// Parse a registration response and report its structural properties, such as the aaguid,
+    /// algorithm and attestation format, without the registration state of the ceremony. This
+    /// is intended to help debug the payloads that a client sends during development.
+    ///
+    /// ⚠️  THIS IS NOT A SECURITY CHECK. The challenge, origin and attestation are NOT verified,
+    /// and the response may be forged or replayed. You MUST use one of the `finish_*_registration`
+    /// functions to register a credential.
+    pub fn inspect_registration(
+        &self,
+        reg: &RegisterPublicKeyCredential,
+    ) -> WebauthnResult<RegistrationInspection> {
+        self.check_response_size(reg)?;
+        self.core.inspect_registration(reg)
     }
 
     /// Given a set of `Passkey`'s, begin an authentication of the user. This returns
@@ -532,10 +1850,44 @@ impl Webauthn {
     pub fn start_passkey_authentication(
         &self,
         creds: &[Passkey],
+    ) -> WebauthnResult<(RequestChallengeResponse, PasskeyAuthentication)> {
+        self.start_passkey_authentication_with_policy(creds, UserVerificationPolicy::Required)
+    }
+
+    /// Begin an authentication of the user as with
+    /// [`start_passkey_authentication`](Webauthn::start_passkey_authentication), but only
+    /// *preferring* user verification rather than requiring it. This is intended to be an
+    /// accessibility fallback for users who can not perform user verification, such as when
+    /// their authenticator has no PIN or biometrics fail for them.
+    ///
+    /// ⚠️  A presence-only authentication is a SINGLE factor. You MUST only offer this path after
+    /// verifying the user through another channel, and you MUST apply compensating controls (for
+    /// example, extra out-of-band verification or a reduced session privilege). After finishing
+    /// the authentication, check [AuthenticationResult::user_verified] to know which path the
+    /// user took.
+    ///
+    /// Passkeys normally remain held to the user verification they performed at registration,
+    /// and this path lifts that requirement. They are also registered with credential protection
+    /// that asks authenticators to require user verification, so some authenticators may still
+    /// refuse to use the credential without it.
+    pub fn start_passkey_authentication_accessible(
+        &self,
+        creds: &[Passkey],
+    ) -> WebauthnResult<(RequestChallengeResponse, PasskeyAuthentication)> {
+        self.start_passkey_authentication_with_policy(creds, UserVerificationPolicy::Preferred)
+            .map(|(rcr, mut state)| {
+                state.ast.allow_user_verification_fallback();
+                (rcr, state)
+            })
+    }
+
+    fn start_passkey_authentication_with_policy(
+        &self,
+        creds: &[Passkey],
+        policy: UserVerificationPolicy,
     ) -> WebauthnResult<(RequestChallengeResponse, PasskeyAuthentication)> {
         let extensions = None;
-        let creds = creds.iter().map(|sk| sk.cred.clone()).collect();
-        let policy = UserVerificationPolicy::Required;
+        let creds = self.credentials_for_client(creds.iter().map(|pk| &pk.cred));
         let allow_backup_eligible_upgrade = true;
 
         self.core
@@ -548,12 +1900,40 @@ impl Webauthn {
             .map(|(rcr, ast)| (rcr, PasskeyAuthentication { ast }))
     }
 
+    /// Begin an authentication of the user as with
+    /// [`start_passkey_authentication`](Webauthn::start_passkey_authentication), but with the
+    /// `preferred` credential listed first in `allowCredentials`, such as the passkey the user
+    /// most recently authenticated with. This nudges the browser UI towards the likely correct
+    /// credential, while any of the other passkeys may still be used.
+    ///
+    /// If `preferred` is not one of `creds`, [WebauthnError::CredentialNotFound] is returned.
+    pub fn start_passkey_authentication_preferring(
+        &self,
+        creds: &[Passkey],
+        preferred: &CredentialID,
+    ) -> WebauthnResult<(RequestChallengeResponse, PasskeyAuthentication)> {
+        let Some(idx) = creds.iter().position(|pk| pk.cred_id() == preferred) else {
+            error!("the preferred credential is not one of the provided passkeys");
+            return Err(WebauthnError::CredentialNotFound);
+        };
+
+        let mut creds = creds.to_vec();
+        let preferred = creds.remove(idx);
+        creds.insert(0, preferred);
+
+        self.start_passkey_authentication(&creds)
+    }
+
     /// Given the `PublicKeyCredential` returned by the user agent (e.g. a browser), and the stored [PasskeyAuthentication]
     /// complete the authentication of the user.
     ///
     /// # Errors
     /// If any part of the registration is incorrect or invalid, an error will be returned. See [WebauthnError].
     ///
+    /// If the authenticator only tested for user presence, and did not verify the user (for
+    /// example with a PIN or biometric), [WebauthnError::UserNotVerified] is returned. This is
+    /// recoverable: the user can be asked to complete verification and retry the ceremony.
+    ///
     /// # Returns
     /// On success, [AuthenticationResult] is returned which contains some details of the Authentication
     /// process.
@@ -573,6 +1953,7 @@ impl Webauthn {
         reg: &PublicKeyCredential,
         state: &PasskeyAuthentication,
     ) -> WebauthnResult<AuthenticationResult> {
+        self.check_attachment(reg.authenticator_attachment)?;
         self.core.authenticate_credential(reg, &state.ast)
     }
 
@@ -698,31 +2079,85 @@ impl Webauthn {
         attestation_ca_list: Option<AttestationCaList>,
         ui_hint_authenticator_attachment: Option<AuthenticatorAttachment>,
     ) -> WebauthnResult<(CreationChallengeResponse, SecurityKeyRegistration)> {
-        let attestation = if let Some(ca_list) = attestation_ca_list.as_ref() {
-            if ca_list.is_empty() {
-                return Err(WebauthnError::MissingAttestationCaList);
-            } else {
-                AttestationConveyancePreference::Direct
-            }
-        } else {
-            AttestationConveyancePreference::None
-        };
-
-        let cred_protect = if self.user_presence_only_security_keys {
-            None
-        } else {
-            Some(CredProtect {
-                // We want the device to strictly enforce it's UV state.
-                credential_protection_policy: CredentialProtectionPolicy::UserVerificationRequired,
-                // If set to true, causes many authenticators to shit the bed. Since this type doesn't
-                // have the same strict rules about attestation, then we just use this opportunistically.
-                enforce_credential_protection_policy: Some(false),
-            })
-        };
+        self.start_securitykey_registration_with_hints(
+            user_unique_id,
+            user_name,
+            user_display_name,
+            exclude_credentials,
+            attestation_ca_list,
+            ui_hint_authenticator_attachment,
+            Vec::new(),
+        )
+    }
+
+    /// Identical to [`start_securitykey_registration`](Webauthn::start_securitykey_registration),
+    /// but additionally emitting `hints`, an ordered list of the authenticator types you expect
+    /// the user to use. Browsers *may* use these to prioritise their UI, but like
+    /// `ui_hint_authenticator_attachment` they are not enforced. Each hint may only be provided
+    /// once, and an empty list emits no hints.
+    ///
+    /// ```
+    /// # use webauthn_rs::prelude::*;
+    /// # let rp_id = "example.com";
+    /// # let rp_origin = Url::parse("https://idm.example.com")
+    /// #     .expect("Invalid URL");
+    /// # let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)
+    /// #     .expect("Invalid configuration")
+    /// #     .build()
+    /// #     .expect("Invalid configuration");
+    /// // Direct the browser towards a physical security key first.
+    /// let (ccr, skr) = webauthn
+    ///     .start_securitykey_registration_with_hints(
+    ///         Uuid::new_v4(),
+    ///         "claire",
+    ///         "Claire",
+    ///         None,
+    ///         None,
+    ///         None,
+    ///         vec![PublicKeyCredentialHint::SecurityKey],
+    ///     )
+    ///     .expect("Failed to start registration.");
+    /// ```
+    #[allow(clippy::too_many_arguments)]
+    pub fn start_securitykey_registration_with_hints(
+        &self,
+        user_unique_id: Uuid,
+        user_name: &str,
+        user_display_name: &str,
+        exclude_credentials: Option<Vec<CredentialID>>,
+        attestation_ca_list: Option<AttestationCaList>,
+        ui_hint_authenticator_attachment: Option<AuthenticatorAttachment>,
+        hints: Vec<PublicKeyCredentialHint>,
+    ) -> WebauthnResult<(CreationChallengeResponse, SecurityKeyRegistration)> {
+        let hints = validate_registration_hints(hints)?;
+        let (authenticator_attachment, hints) =
+            self.registration_attachment(ui_hint_authenticator_attachment, hints);
+
+        let attestation = if let Some(ca_list) = attestation_ca_list.as_ref() {
+            if ca_list.is_empty() {
+                return Err(WebauthnError::MissingAttestationCaList);
+            } else {
+                AttestationConveyancePreference::Direct
+            }
+        } else {
+            AttestationConveyancePreference::None
+        };
+
+        let cred_protect = if self.user_presence_only_security_keys {
+            None
+        } else {
+            Some(CredProtect {
+                // We want the device to strictly enforce it's UV state.
+                credential_protection_policy: CredentialProtectionPolicy::UserVerificationRequired,
+                // If set to true, causes many authenticators to shit the bed. Since this type doesn't
+                // have the same strict rules about attestation, then we just use this opportunistically.
+                enforce_credential_protection_policy: Some(false),
+            })
+        };
 
         let extensions = Some(RequestRegistrationExtensions {
             cred_protect,
-            uvm: Some(true),
+            uvm: self.request_uvm.then_some(true),
             cred_props: Some(true),
             min_pin_length: None,
             hmac_create_secret: None,
@@ -748,15 +2183,24 @@ impl Webauthn {
                 extensions,
                 credential_algorithms,
                 require_resident_key,
-                ui_hint_authenticator_attachment,
+                authenticator_attachment,
                 reject_passkeys,
             )
-            .map(|(ccr, rs)| {
+            .map(|(mut ccr, rs)| {
+                ccr.public_key.hints = hints;
+                if let Some(selection) = ccr.public_key.authenticator_selection.as_mut() {
+                    selection.resident_key = if self.security_key_discourage_resident_key {
+                        Some(ResidentKeyRequirement::Discouraged)
+                    } else {
+                        None
+                    };
+                }
                 (
                     ccr,
                     SecurityKeyRegistration {
                         rs,
                         ca_list: attestation_ca_list,
+                        expected_aaguid: None,
                     },
                 )
             })
@@ -782,14 +2226,21 @@ impl Webauthn {
     /// SecurityKey `attestation()` and it's associated metadata. You can use this to check for
     /// specific device aaguids for example.
     ///
+    /// If an aaguid was set on the registration state with `set_expected_aaguid`, the attested
+    /// aaguid of the device must match it or [WebauthnError::AttestationAaguidMismatch] is
+    /// returned.
+    ///
     pub fn finish_securitykey_registration(
         &self,
         reg: &RegisterPublicKeyCredential,
         state: &SecurityKeyRegistration,
     ) -> WebauthnResult<SecurityKey> {
-        self.core
-            .register_credential(reg, &state.rs, state.ca_list.as_ref())
-            .map(|cred| SecurityKey { cred })
+        self.check_response_size(reg)?;
+        self.check_attachment(reg.authenticator_attachment)?;
+        let cred = self.register_credential(reg, &state.rs, state.ca_list.as_ref())?;
+        self.check_credential_key(&cred)?;
+        self.check_expected_aaguid(&cred, state.expected_aaguid)?;
+        Ok(SecurityKey { cred })
     }
 
     /// Given a set of [SecurityKey], begin an authentication of the user. This returns
@@ -797,6 +2248,9 @@ impl Webauthn {
     /// The server must persist the [SecurityKeyAuthentication] state as it is paired to the
     /// `RequestChallengeResponse` and required to complete the authentication.
     ///
+    /// If a [legacy_appid](WebauthnBuilder::legacy_appid) is configured, the `appid` extension
+    /// is requested so that security keys registered with U2F can authenticate.
+    ///
     /// Finally you need to call [`finish_securitykey_authentication`](Webauthn::finish_securitykey_authentication)
     /// to complete the authentication.
     ///
@@ -808,16 +2262,10 @@ impl Webauthn {
         &self,
         creds: &[SecurityKey],
     ) -> WebauthnResult<(RequestChallengeResponse, SecurityKeyAuthentication)> {
-        let extensions = None;
-        let creds = creds.iter().map(|sk| sk.cred.clone()).collect();
+        let (policy, extensions) = self.securitykey_authentication_options();
+        let creds = self.credentials_for_client(creds.iter().map(|sk| &sk.cred));
         let allow_backup_eligible_upgrade = false;
 
-        let policy = if self.user_presence_only_security_keys {
-            UserVerificationPolicy::Discouraged_DO_NOT_USE
-        } else {
-            UserVerificationPolicy::Preferred
-        };
-
         self.core
             .generate_challenge_authenticate_policy(
                 creds,
@@ -828,6 +2276,43 @@ impl Webauthn {
             .map(|(rcr, ast)| (rcr, SecurityKeyAuthentication { ast }))
     }
 
+    /// Begin an authentication of the user from the stored credential ids and transports of
+    /// their [SecurityKey]s, rather than the full credentials. This allows deployments such as
+    /// serverless functions to defer loading the credentials until the authentication is
+    /// finished.
+    ///
+    /// The authentication must be completed with
+    /// [`finish_securitykey_authentication_with_credentials`](Webauthn::finish_securitykey_authentication_with_credentials),
+    /// which is provided the full credentials.
+    ///
+    /// WARNING ⚠️  YOU MUST STORE THE [SecurityKeyAuthentication] VALUE SERVER SIDE.
+    ///
+    /// Failure to do so *may* open you to replay attacks which can significantly weaken the
+    /// security of this system.
+    pub fn start_securitykey_authentication_from_ids(
+        &self,
+        creds: &[AllowCredentials],
+    ) -> WebauthnResult<(RequestChallengeResponse, SecurityKeyAuthentication)> {
+        let (policy, extensions) = self.securitykey_authentication_options();
+        let allow_backup_eligible_upgrade = false;
+
+        let mut creds = creds.to_vec();
+        if let Some(client_capabilities) = self.client_capabilities.as_ref() {
+            creds
+                .iter_mut()
+                .for_each(|cred| client_capabilities.filter_transports(&mut cred.transports));
+        }
+
+        self.core
+            .generate_challenge_authenticate_allow_list(
+                creds,
+                policy,
+                extensions,
+                allow_backup_eligible_upgrade,
+            )
+            .map(|(rcr, ast)| (rcr, SecurityKeyAuthentication { ast }))
+    }
+
     /// Given the `PublicKeyCredential` returned by the user agent (e.g. a browser), and the stored [SecurityKeyAuthentication]
     /// complete the authentication of the user.
     ///
@@ -845,8 +2330,36 @@ impl Webauthn {
         reg: &PublicKeyCredential,
         state: &SecurityKeyAuthentication,
     ) -> WebauthnResult<AuthenticationResult> {
+        self.check_attachment(reg.authenticator_attachment)?;
         self.core.authenticate_credential(reg, &state.ast)
     }
+
+    /// Complete an authentication that was started with
+    /// [`start_securitykey_authentication_from_ids`](Webauthn::start_securitykey_authentication_from_ids),
+    /// given the full [SecurityKey]s of the user. Only the credential identified by
+    /// `reg.get_credential_id()` needs to be provided. Credentials that were not part of the
+    /// allow list when the authentication was started are rejected.
+    ///
+    /// # Errors
+    /// If any part of the registration is incorrect or invalid, an error will be returned. See [WebauthnError].
+    ///
+    /// # Returns
+    /// On success, [AuthenticationResult] is returned which contains some details of the Authentication
+    /// process.
+    ///
+    /// You should use `SecurityKey::update_credential` on the returned [AuthenticationResult] and
+    /// ensure it is persisted.
+    pub fn finish_securitykey_authentication_with_credentials(
+        &self,
+        reg: &PublicKeyCredential,
+        state: &SecurityKeyAuthentication,
+        creds: &[SecurityKey],
+    ) -> WebauthnResult<AuthenticationResult> {
+        self.check_attachment(reg.authenticator_attachment)?;
+        let mut ast = state.ast.clone();
+        ast.set_allowed_credentials(creds.iter().map(|sk| sk.cred.clone()).collect());
+        self.core.authenticate_credential(reg, &ast)
+    }
 }
 
 #[cfg(feature = "preview-features")]
@@ -988,6 +2501,37 @@ impl Webauthn {
         ui_hint_authenticator_attachment: Option<AuthenticatorAttachment>,
         // extensions
     ) -> WebauthnResult<(CreationChallengeResponse, AttestedPasskeyRegistration)> {
+        self.start_attested_passkey_registration_with_hints(
+            user_unique_id,
+            user_name,
+            user_display_name,
+            exclude_credentials,
+            attestation_ca_list,
+            ui_hint_authenticator_attachment,
+            Vec::new(),
+        )
+    }
+
+    /// Identical to [`start_attested_passkey_registration`](Webauthn::start_attested_passkey_registration),
+    /// but additionally emitting `hints`, an ordered list of the authenticator types you expect
+    /// the user to use. Browsers *may* use these to prioritise their UI, but like
+    /// `ui_hint_authenticator_attachment` they are not enforced. Each hint may only be provided
+    /// once, and an empty list emits no hints.
+    #[allow(clippy::too_many_arguments)]
+    pub fn start_attested_passkey_registration_with_hints(
+        &self,
+        user_unique_id: Uuid,
+        user_name: &str,
+        user_display_name: &str,
+        exclude_credentials: Option<Vec<CredentialID>>,
+        attestation_ca_list: AttestationCaList,
+        ui_hint_authenticator_attachment: Option<AuthenticatorAttachment>,
+        hints: Vec<PublicKeyCredentialHint>,
+    ) -> WebauthnResult<(CreationChallengeResponse, AttestedPasskeyRegistration)> {
+        let hints = validate_registration_hints(hints)?;
+        let (authenticator_attachment, hints) =
+            self.registration_attachment(ui_hint_authenticator_attachment, hints);
+
         let attestation = AttestationConveyancePreference::Direct;
         if attestation_ca_list.is_empty() {
             return Err(WebauthnError::MissingAttestationCaList);
@@ -1007,7 +2551,7 @@ impl Webauthn {
                 // only viable on FIDO2/CTAP2 creds that actually support this.
                 enforce_credential_protection_policy: Some(true),
             }),
-            uvm: Some(true),
+            uvm: self.request_uvm.then_some(true),
             cred_props: Some(true),
             min_pin_length: Some(true),
             hmac_create_secret: None,
@@ -1024,15 +2568,18 @@ impl Webauthn {
                 extensions,
                 credential_algorithms,
                 require_resident_key,
-                ui_hint_authenticator_attachment,
+                authenticator_attachment,
                 reject_passkeys,
             )
-            .map(|(ccr, rs)| {
+            .map(|(mut ccr, rs)| {
+                ccr.public_key.hints = hints;
                 (
                     ccr,
                     AttestedPasskeyRegistration {
                         rs,
                         ca_list: attestation_ca_list,
+                        recovery: false,
+                        expected_aaguid: None,
                     },
                 )
             })
@@ -1054,14 +2601,82 @@ impl Webauthn {
     /// AttestedPasskey `attestation()` and it's associated metadata. You can use this to check for
     /// specific device aaguids for example.
     ///
+    /// If an aaguid was set on the registration state with `set_expected_aaguid`, the attested
+    /// aaguid of the device must match it or [WebauthnError::AttestationAaguidMismatch] is
+    /// returned.
+    ///
     pub fn finish_attested_passkey_registration(
         &self,
         reg: &RegisterPublicKeyCredential,
         state: &AttestedPasskeyRegistration,
     ) -> WebauthnResult<AttestedPasskey> {
-        self.core
-            .register_credential(reg, &state.rs, Some(&state.ca_list))
-            .map(|cred| AttestedPasskey { cred })
+        self.check_response_size(reg)?;
+        self.check_attachment(reg.authenticator_attachment)?;
+        let cred = self.register_credential(reg, &state.rs, Some(&state.ca_list))?;
+        self.check_credential_key(&cred)?;
+        self.check_expected_aaguid(&cred, state.expected_aaguid)?;
+        Ok(AttestedPasskey {
+            cred,
+            recovery: state.recovery,
+        })
+    }
+
+    /// Initiate the registration of an account recovery credential for a user. This is
+    /// identical to [`start_attested_passkey_registration`](Webauthn::start_attested_passkey_registration),
+    /// and attestation is always required, but the resulting [AttestedPasskey] is tagged as a
+    /// recovery credential. See [`AttestedPasskey::is_recovery_credential`].
+    ///
+    /// Recovery credentials are intended to be stored separately from the user's everyday
+    /// devices, for example a security key kept in a safe. The tag allows you to treat them
+    /// specially, such as not offering them during a normal login and only accepting them in
+    /// your account recovery workflow.
+    ///
+    /// The registration is completed with
+    /// [`finish_attested_passkey_registration`](Webauthn::finish_attested_passkey_registration).
+    pub fn start_recovery_key_registration(
+        &self,
+        user_unique_id: Uuid,
+        user_name: &str,
+        user_display_name: &str,
+        exclude_credentials: Option<Vec<CredentialID>>,
+        attestation_ca_list: AttestationCaList,
+        ui_hint_authenticator_attachment: Option<AuthenticatorAttachment>,
+    ) -> WebauthnResult<(CreationChallengeResponse, AttestedPasskeyRegistration)> {
+        self.start_attested_passkey_registration(
+            user_unique_id,
+            user_name,
+            user_display_name,
+            exclude_credentials,
+            attestation_ca_list,
+            ui_hint_authenticator_attachment,
+        )
+        .map(|(ccr, mut state)| {
+            state.recovery = true;
+            (ccr, state)
+        })
+    }
+
+    /// Collect the credential ids of all of a user's credentials, for use as the
+    /// `exclude_credentials` of a new registration. The same authenticator may have been
+    /// registered through different flows, so all of them should be excluded. Each id is only
+    /// returned once.
+    pub fn all_credential_ids(
+        passkeys: &[Passkey],
+        security_keys: &[SecurityKey],
+        attested: &[AttestedPasskey],
+    ) -> Vec<CredentialID> {
+        let mut cred_ids: Vec<CredentialID> = Vec::new();
+        for cred_id in passkeys
+            .iter()
+            .map(Passkey::cred_id)
+            .chain(security_keys.iter().map(SecurityKey::cred_id))
+            .chain(attested.iter().map(AttestedPasskey::cred_id))
+        {
+            if !cred_ids.contains(cred_id) {
+                cred_ids.push(cred_id.clone());
+            }
+        }
+        cred_ids
     }
 
     /// Given a set of `AttestedPasskey`'s, begin an authentication of the user. This returns
@@ -1080,11 +2695,11 @@ impl Webauthn {
         &self,
         creds: &[AttestedPasskey],
     ) -> WebauthnResult<(RequestChallengeResponse, AttestedPasskeyAuthentication)> {
-        let creds = creds.iter().map(|sk| sk.cred.clone()).collect();
+        let creds = self.credentials_for_client(creds.iter().map(|sk| &sk.cred));
 
         let extensions = Some(RequestAuthenticationExtensions {
             appid: None,
-            uvm: Some(true),
+            uvm: self.request_uvm.then_some(true),
             hmac_get_secret: None,
         });
 
@@ -1129,6 +2744,7 @@ impl Webauthn {
         reg: &PublicKeyCredential,
         state: &AttestedPasskeyAuthentication,
     ) -> WebauthnResult<AuthenticationResult> {
+        self.check_attachment(reg.authenticator_attachment)?;
         self.core.authenticate_credential(reg, &state.ast)
     }
 
@@ -1139,7 +2755,7 @@ impl Webauthn {
         let policy = UserVerificationPolicy::Required;
         let extensions = Some(RequestAuthenticationExtensions {
             appid: None,
-            uvm: Some(true),
+            uvm: self.request_uvm.then_some(true),
             hmac_get_secret: None,
         });
 
@@ -1161,12 +2777,24 @@ impl Webauthn {
     }
 
     /// WIP DO NOT USE
+    ///
+    /// If the responding credential is not one of `creds`, this returns
+    /// [WebauthnError::DiscoverableCredentialNotFound] containing the id of the responding
+    /// credential, so that you may decide whether to prompt the user to register again.
     pub fn finish_discoverable_authentication(
         &self,
         reg: &PublicKeyCredential,
         mut state: DiscoverableAuthentication,
         creds: &[DiscoverableKey],
     ) -> WebauthnResult<AuthenticationResult> {
+        self.check_attachment(reg.authenticator_attachment)?;
+        // Report an unknown credential before any cryptographic verification, so that it can be
+        // told apart from a credential that failed to authenticate.
+        if !creds.iter().any(|dk| dk.cred.cred_id == reg.raw_id) {
+            return Err(WebauthnError::DiscoverableCredentialNotFound(
+                reg.raw_id.clone(),
+            ));
+        }
         let creds = creds.iter().map(|dk| dk.cred.clone()).collect();
         state.ast.set_allowed_credentials(creds);
         self.core.authenticate_credential(reg, &state.ast)
@@ -1185,6 +2813,35 @@ impl Webauthn {
         attestation_ca_list: AttestationCaList,
         ui_hint_authenticator_attachment: Option<AuthenticatorAttachment>,
     ) -> WebauthnResult<(CreationChallengeResponse, AttestedResidentKeyRegistration)> {
+        self.start_attested_resident_key_registration_with_hints(
+            user_unique_id,
+            user_name,
+            user_display_name,
+            exclude_credentials,
+            attestation_ca_list,
+            ui_hint_authenticator_attachment,
+            Vec::new(),
+        )
+    }
+
+    /// Identical to [`start_attested_resident_key_registration`](Webauthn::start_attested_resident_key_registration),
+    /// but additionally emitting `hints`, an ordered list of the authenticator types you expect
+    /// the user to use. These are not enforced, and an empty list emits no hints.
+    #[allow(clippy::too_many_arguments)]
+    pub fn start_attested_resident_key_registration_with_hints(
+        &self,
+        user_unique_id: Uuid,
+        user_name: &str,
+        user_display_name: &str,
+        exclude_credentials: Option<Vec<CredentialID>>,
+        attestation_ca_list: AttestationCaList,
+        ui_hint_authenticator_attachment: Option<AuthenticatorAttachment>,
+        hints: Vec<PublicKeyCredentialHint>,
+    ) -> WebauthnResult<(CreationChallengeResponse, AttestedResidentKeyRegistration)> {
+        let hints = validate_registration_hints(hints)?;
+        let (authenticator_attachment, hints) =
+            self.registration_attachment(ui_hint_authenticator_attachment, hints);
+
         if attestation_ca_list.is_empty() {
             return Err(WebauthnError::MissingAttestationCaList);
         }
@@ -1205,7 +2862,7 @@ impl Webauthn {
                 enforce_credential_protection_policy: Some(true),
             }),
             // https://www.w3.org/TR/webauthn-2/#sctn-uvm-extension
-            uvm: Some(true),
+            uvm: self.request_uvm.then_some(true),
             cred_props: Some(true),
             // https://fidoalliance.org/specs/fido-v2.1-rd-20210309/fido-client-to-authenticator-protocol-v2.1-rd-20210309.html#sctn-minpinlength-extension
             min_pin_length: Some(true),
@@ -1223,15 +2880,17 @@ impl Webauthn {
                 extensions,
                 credential_algorithms,
                 require_resident_key,
-                ui_hint_authenticator_attachment,
+                authenticator_attachment,
                 reject_passkeys,
             )
-            .map(|(ccr, rs)| {
+            .map(|(mut ccr, rs)| {
+                ccr.public_key.hints = hints;
                 (
                     ccr,
                     AttestedResidentKeyRegistration {
                         rs,
                         ca_list: attestation_ca_list,
+                        expected_aaguid: None,
                     },
                 )
             })
@@ -1243,16 +2902,26 @@ impl Webauthn {
         reg: &RegisterPublicKeyCredential,
         state: &AttestedResidentKeyRegistration,
     ) -> WebauthnResult<AttestedResidentKey> {
-        let cred = self
-            .core
-            .register_credential(reg, &state.rs, Some(&state.ca_list))?;
+        self.check_response_size(reg)?;
+        self.check_attachment(reg.authenticator_attachment)?;
+        let cred = self.register_credential(reg, &state.rs, Some(&state.ca_list))?;
+        self.check_credential_key(&cred)?;
+        self.check_expected_aaguid(&cred, state.expected_aaguid)?;
 
         trace!("finish attested_resident_key -> {:?}", cred);
 
         // cred protect ignored :(
         // Is the pin long enough?
-        // Is it rk?
-        // I guess we'll never know ...
+
+        // Is it rk? The credProps result is not signed by the authenticator, but if the client
+        // reports that no discoverable credential was created, the credential is not usable in
+        // this flow. If the client does not report it, we still can't know.
+        if let ExtnState::Unsigned(cred_props) = &cred.extensions.cred_props {
+            if !cred_props.rk() {
+                error!("credProps reports that a resident key was not created");
+                return Err(WebauthnError::ResidentKeyNotCreated);
+            }
+        }
 
         // Is it an approved cred / aaguid?
 
@@ -1264,10 +2933,10 @@ impl Webauthn {
         &self,
         creds: &[AttestedResidentKey],
     ) -> WebauthnResult<(RequestChallengeResponse, AttestedResidentKeyAuthentication)> {
-        let creds = creds.iter().map(|sk| sk.cred.clone()).collect();
+        let creds = self.credentials_for_client(creds.iter().map(|sk| &sk.cred));
         let extensions = Some(RequestAuthenticationExtensions {
             appid: None,
-            uvm: Some(true),
+            uvm: self.request_uvm.then_some(true),
             hmac_get_secret: None,
         });
 
@@ -1290,10 +2959,308 @@ impl Webauthn {
         reg: &PublicKeyCredential,
         state: &AttestedResidentKeyAuthentication,
     ) -> WebauthnResult<AuthenticationResult> {
+        self.check_attachment(reg.authenticator_attachment)?;
         self.core.authenticate_credential(reg, &state.ast)
     }
 }
 
+/// Check that the authenticator hints requested for a registration are sensible. An empty set
+/// of hints is the same as providing no hints at all.
+fn validate_registration_hints(
+    hints: Vec<PublicKeyCredentialHint>,
+) -> WebauthnResult<Option<Vec<PublicKeyCredentialHint>>> {
+    // Hints are an ordered preference list, so each hint may only appear once.
+    if hints
+        .iter()
+        .enumerate()
+        .any(|(i, hint)| hints[..i].contains(hint))
+    {
+        error!("Registration hints may not contain duplicates");
+        Err(WebauthnError::InvalidRegistrationHints)
+    } else if hints.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(hints))
+    }
+}
+
+#[test]
+/// Test that registration hints are emitted in the challenge, and that duplicates are rejected.
+fn test_registration_hints() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?.build()?;
+
+    let (ccr, _) = webauthn.start_securitykey_registration_with_hints(
+        Uuid::new_v4(),
+        "claire",
+        "Claire",
+        None,
+        None,
+        None,
+        vec![
+            PublicKeyCredentialHint::SecurityKey,
+            PublicKeyCredentialHint::Hybrid,
+        ],
+    )?;
+    let json = serde_json::to_value(&ccr)?;
+    assert_eq!(
+        json["publicKey"]["hints"],
+        serde_json::json!(["security-key", "hybrid"])
+    );
+
+    let (ccr, _) = webauthn.start_passkey_registration_with_hints(
+        Uuid::new_v4(),
+        "claire",
+        "Claire",
+        None,
+        vec![PublicKeyCredentialHint::ClientDevice],
+    )?;
+    let json = serde_json::to_value(&ccr)?;
+    assert_eq!(
+        json["publicKey"]["hints"],
+        serde_json::json!(["client-device"])
+    );
+
+    // No hints are emitted by default, or when the set of hints is empty.
+    let (ccr, _) = webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+    let json = serde_json::to_value(&ccr)?;
+    assert!(json["publicKey"].get("hints").is_none());
+
+    let (ccr, _) = webauthn.start_securitykey_registration_with_hints(
+        Uuid::new_v4(),
+        "claire",
+        "Claire",
+        None,
+        None,
+        None,
+        vec![],
+    )?;
+    assert_eq!(ccr.public_key.hints, None);
+
+    let err = webauthn
+        .start_securitykey_registration_with_hints(
+            Uuid::new_v4(),
+            "claire",
+            "Claire",
+            None,
+            None,
+            None,
+            vec![
+                PublicKeyCredentialHint::ClientDevice,
+                PublicKeyCredentialHint::ClientDevice,
+            ],
+        )
+        .err();
+    assert_eq!(err, Some(WebauthnError::InvalidRegistrationHints));
+    Ok(())
+}
+
+#[test]
+/// Test that the telemetry of a credential only contains non-identifying properties.
+fn test_passkey_telemetry() {
+    use crate::prelude::*;
+    use webauthn_rs_core::proto::{
+        AuthenticatorTransport, RegisteredExtensions, UserVerificationPolicy,
+    };
+
+    let cred = Credential {
+        cred_id: vec![0xde, 0xad, 0xbe, 0xef].into(),
+        cred: COSEKey {
+            type_: COSEAlgorithm::ES256,
+            key: COSEKeyType::EC_EC2(COSEEC2Key {
+                curve: ECDSACurve::SECP256R1,
+                x: vec![1; 32].into(),
+                y: vec![2; 32].into(),
+            }),
+        },
+        counter: 1,
+        transports: Some(vec![
+            AuthenticatorTransport::Usb,
+            AuthenticatorTransport::Nfc,
+        ]),
+        user_verified: true,
+        backup_eligible: false,
+        backup_state: false,
+        registration_policy: UserVerificationPolicy::Required,
+        extensions: RegisteredExtensions::none(),
+        attestation: ParsedAttestation {
+            data: ParsedAttestationData::None,
+            metadata: AttestationMetadata::Packed {
+                aaguid: uuid::uuid!("c5ef55ff-ad9a-4b9f-b580-adebafe026d0"),
+                authenticator_options: None,
+            },
+        },
+        attestation_format: AttestationFormat::Packed,
+        registered_at: None,
+    };
+    let passkey = Passkey { cred };
+
+    // Destructure the telemetry exhaustively, so that adding a field to it requires this
+    // test to be revisited. None of these fields may identify the user or the credential.
+    let RegistrationTelemetry {
+        algorithm,
+        authenticator_attachment,
+        aaguid,
+        attestation_format,
+        backup_eligible,
+    } = passkey.telemetry();
+
+    assert_eq!(algorithm, COSEAlgorithm::ES256);
+    assert_eq!(
+        authenticator_attachment,
+        Some(AuthenticatorAttachment::CrossPlatform)
+    );
+    assert_eq!(
+        aaguid,
+        Some(uuid::uuid!("c5ef55ff-ad9a-4b9f-b580-adebafe026d0"))
+    );
+    assert_eq!(attestation_format, AttestationFormat::Packed);
+    assert!(!backup_eligible);
+
+    // The credential id must not be present anywhere in the telemetry.
+    let debug = format!("{:?}", passkey.telemetry());
+    assert!(!debug.contains("cred_id"));
+    assert!(!debug.contains("user"));
+}
+
+#[test]
+/// Test that a serialised configuration round trips, and is validated when instantiated.
+fn test_webauthn_from_config() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    let aaguid = Uuid::nil();
+    let mut config = WebauthnConfig::new("example.com", &Url::parse("https://idm.example.com")?);
+    config.rp_name = Some("Example".to_string());
+    config
+        .allowed_origins
+        .push(Url::parse("https://auth.example.com:8443")?);
+    config.allow_any_port = true;
+    config.algorithms = Some(vec![COSEAlgorithm::RS256, COSEAlgorithm::ES256]);
+    config.enable_eddsa = true;
+    config.discourage_security_key_resident_key = false;
+    config.passkey_attestation = Some(AttestationConveyancePreference::Direct);
+    config.required_attachment = Some(AuthenticatorAttachment::CrossPlatform);
+    config.min_rsa_key_bits = 3072;
+    config.lenient_cose_parsing = true;
+    config.store_credential_transports = true;
+    config.warn_uv_downgrade = true;
+    config.max_attestation_object_size = 1024;
+    config.max_client_data_size = 512;
+    config.legacy_appid = Some("https://example.com/appid.json".to_string());
+    config.strict_packed_subject = false;
+    config.request_uvm = false;
+    config
+        .aaguid_names
+        .insert(aaguid, "Example Authenticator".to_string());
+    config
+        .aaguid_transports
+        .insert(aaguid, vec![AuthenticatorTransport::Usb]);
+    config.reject_vulnerable_firmware = true;
+
+    let json = serde_json::to_string(&config)?;
+    let restored: WebauthnConfig = serde_json::from_str(&json)?;
+    assert_eq!(restored, config);
+
+    let webauthn = Webauthn::from_config(restored)?;
+    assert_eq!(
+        webauthn.get_allowed_origins(),
+        config.allowed_origins.as_slice()
+    );
+    assert_eq!(webauthn.core.rp_name(), "Example");
+    assert_eq!(
+        webauthn.algorithms,
+        vec![
+            COSEAlgorithm::RS256,
+            COSEAlgorithm::ES256,
+            COSEAlgorithm::EDDSA
+        ]
+    );
+    assert!(!webauthn.security_key_discourage_resident_key);
+    assert_eq!(
+        webauthn.passkey_attestation,
+        Some(AttestationConveyancePreference::Direct)
+    );
+    assert_eq!(
+        webauthn.required_attachment,
+        Some(AuthenticatorAttachment::CrossPlatform)
+    );
+    assert_eq!(webauthn.min_rsa_key_bits, 3072);
+    assert_eq!(webauthn.max_attestation_object_size, 1024);
+    assert_eq!(webauthn.max_client_data_size, 512);
+    assert_eq!(
+        webauthn.legacy_appid.as_deref(),
+        Some("https://example.com/appid.json")
+    );
+    assert!(!webauthn.request_uvm);
+    assert_eq!(webauthn.aaguid_names, config.aaguid_names);
+    assert_eq!(webauthn.aaguid_transports, config.aaguid_transports);
+    assert!(webauthn.reject_vulnerable_firmware);
+
+    // Optional fields may be omitted, and take the defaults of the builder.
+    let restored: WebauthnConfig = serde_json::from_str(
+        r#"{"rp_id":"example.com","allowed_origins":["https://idm.example.com"]}"#,
+    )?;
+    assert_eq!(
+        restored,
+        WebauthnConfig::new("example.com", &Url::parse("https://idm.example.com")?)
+    );
+    let webauthn = Webauthn::from_config(restored)?;
+    let built =
+        WebauthnBuilder::new("example.com", &Url::parse("https://idm.example.com")?)?.build()?;
+    assert_eq!(webauthn.core.rp_name(), "example.com");
+    assert_eq!(webauthn.algorithms, built.algorithms);
+    assert_eq!(webauthn.min_rsa_key_bits, built.min_rsa_key_bits);
+    assert_eq!(
+        webauthn.max_attestation_object_size,
+        built.max_attestation_object_size
+    );
+    assert_eq!(webauthn.request_uvm, built.request_uvm);
+
+    // No origins, or a relying party origin that is not valid for the rp_id is rejected.
+    let invalid = WebauthnConfig {
+        allowed_origins: vec![],
+        ..config.clone()
+    };
+    assert!(Webauthn::from_config(invalid).is_err());
+
+    let invalid = WebauthnConfig {
+        allowed_origins: vec![Url::parse("https://idm.different.com")?],
+        ..config.clone()
+    };
+    assert!(Webauthn::from_config(invalid).is_err());
+
+    // An http origin is only accepted when https origins are not required.
+    let http = WebauthnConfig {
+        allowed_origins: vec![Url::parse("http://idm.example.com")?],
+        ..config
+    };
+    assert!(Webauthn::from_config(http.clone()).is_err());
+    assert!(Webauthn::from_config(WebauthnConfig {
+        require_https_origins: false,
+        ..http
+    })
+    .is_ok());
+    Ok(())
+}
+
+#[test]
+/// Test that user handles are only created with valid lengths.
+fn test_user_handle_length() {
+    use crate::prelude::*;
+    assert!(Webauthn::user_handle_from_bytes(&[]).is_err());
+    assert!(Webauthn::user_handle_from_bytes(&[0; 65]).is_err());
+
+    let handle = Webauthn::user_handle_from_bytes(&[1]).unwrap();
+    assert_eq!(handle.as_bytes(), &[1]);
+    let handle = Webauthn::user_handle_from_bytes(&[0xff; 64]).unwrap();
+    assert_eq!(handle.as_bytes().len(), 64);
+
+    let user_unique_id = Uuid::new_v4();
+    let handle = Webauthn::user_handle_from_uuid(user_unique_id);
+    assert_eq!(handle.as_bytes(), user_unique_id.as_bytes());
+}
+
 #[test]
 /// Test that building a webauthn object from a chrome extension origin is successful.
 fn test_webauthnbuilder_chrome_url() -> Result<(), Box<dyn std::error::Error>> {
@@ -1307,3 +3274,3482 @@ fn test_webauthnbuilder_chrome_url() -> Result<(), Box<dyn std::error::Error>> {
     eprintln!("rp_name: {}", built.core.rp_name());
     Ok(())
 }
+
+#[test]
+/// Test that the credProps authenticatorDisplayName is parsed from the client extension
+/// results of a registration, and survives the Passkey being serialised.
+fn test_passkey_authenticator_display_name() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use webauthn_rs_core::proto::{
+        ExtnState, RegisteredExtensions, RegistrationExtensionsClientOutputs,
+        UserVerificationPolicy,
+    };
+
+    let client_extn: RegistrationExtensionsClientOutputs = serde_json::from_str(
+        r#"{"credProps":{"rk":true,"authenticatorDisplayName":"Work Laptop"}}"#,
+    )?;
+    let cred_props = client_extn.cred_props.ok_or("credProps was not parsed")?;
+    assert!(cred_props.rk());
+    assert_eq!(cred_props.authenticator_display_name(), Some("Work Laptop"));
+
+    let mut cred = Credential {
+        cred_id: vec![0xde, 0xad, 0xbe, 0xef].into(),
+        cred: COSEKey {
+            type_: COSEAlgorithm::ES256,
+            key: COSEKeyType::EC_EC2(COSEEC2Key {
+                curve: ECDSACurve::SECP256R1,
+                x: vec![1; 32].into(),
+                y: vec![2; 32].into(),
+            }),
+        },
+        counter: 0,
+        transports: None,
+        user_verified: true,
+        backup_eligible: true,
+        backup_state: true,
+        registration_policy: UserVerificationPolicy::Required,
+        extensions: RegisteredExtensions::none(),
+        attestation: ParsedAttestation {
+            data: ParsedAttestationData::None,
+            metadata: AttestationMetadata::None,
+        },
+        attestation_format: AttestationFormat::None,
+        registered_at: None,
+    };
+
+    // Without credProps there is no name to report.
+    let passkey = Passkey { cred: cred.clone() };
+    assert_eq!(passkey.authenticator_display_name(), None);
+
+    cred.extensions.cred_props = ExtnState::Unsigned(cred_props);
+    let passkey = Passkey { cred };
+    assert_eq!(
+        passkey.authenticator_display_name().as_deref(),
+        Some("Work Laptop")
+    );
+
+    let json = serde_json::to_string(&passkey)?;
+    let restored: Passkey = serde_json::from_str(&json)?;
+    assert_eq!(
+        restored.authenticator_display_name().as_deref(),
+        Some("Work Laptop")
+    );
+
+    // Passkeys serialised before the name was supported still deserialise.
+    let legacy = json.replace(r#","authenticatorDisplayName":"Work Laptop""#, "");
+    assert_ne!(legacy, json);
+    let restored: Passkey = serde_json::from_str(&legacy)?;
+    assert_eq!(restored.authenticator_display_name(), None);
+
+    Ok(())
+}
+
+#[test]
+/// Test that security key registrations explicitly discourage resident keys by default, and
+/// that this can be disabled.
+fn test_securitykey_registration_discourages_resident_key() -> Result<(), Box<dyn std::error::Error>>
+{
+    use crate::prelude::*;
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?.build()?;
+    let (ccr, _) = webauthn.start_securitykey_registration(
+        Uuid::new_v4(),
+        "claire",
+        "Claire",
+        None,
+        None,
+        None,
+    )?;
+    let json = serde_json::to_value(&ccr)?;
+    let selection = &json["publicKey"]["authenticatorSelection"];
+    assert_eq!(selection["residentKey"], "discouraged");
+    assert_eq!(selection["requireResidentKey"], false);
+
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?
+        .discourage_security_key_resident_key(false)
+        .build()?;
+    let (ccr, _) = webauthn.start_securitykey_registration(
+        Uuid::new_v4(),
+        "claire",
+        "Claire",
+        None,
+        None,
+        None,
+    )?;
+    let json = serde_json::to_value(&ccr)?;
+    let selection = &json["publicKey"]["authenticatorSelection"];
+    assert!(selection.get("residentKey").is_none());
+    assert_eq!(selection["requireResidentKey"], false);
+
+    Ok(())
+}
+
+#[test]
+/// Test that a Passkey can verify an assertion over a caller supplied challenge, and that the
+/// assertion is bound to that challenge.
+fn test_passkey_verify_assertion_over() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use webauthn_rs_core::proto::{RegisteredExtensions, UserVerificationPolicy};
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?.build()?;
+
+    let cred = Credential {
+        cred_id: (100..132).collect::<Vec<u8>>().into(),
+        cred: COSEKey {
+            type_: COSEAlgorithm::ES256,
+            key: COSEKeyType::EC_EC2(COSEEC2Key {
+                curve: ECDSACurve::SECP256R1,
+                x: vec![
+                    115, 14, 82, 231, 208, 245, 113, 111, 237, 10, 33, 162, 203, 10, 58, 193, 235,
+                    103, 124, 247, 99, 28, 23, 152, 201, 207, 85, 130, 134, 88, 13, 54,
+                ]
+                .into(),
+                y: vec![
+                    72, 249, 140, 238, 231, 197, 131, 87, 119, 64, 189, 6, 170, 165, 165, 124, 181,
+                    68, 197, 47, 116, 252, 152, 166, 110, 49, 20, 3, 205, 200, 110, 107,
+                ]
+                .into(),
+            }),
+        },
+        counter: 1,
+        transports: None,
+        user_verified: true,
+        backup_eligible: false,
+        backup_state: false,
+        registration_policy: UserVerificationPolicy::Required,
+        extensions: RegisteredExtensions::none(),
+        attestation: ParsedAttestation {
+            data: ParsedAttestationData::None,
+            metadata: AttestationMetadata::None,
+        },
+        attestation_format: AttestationFormat::None,
+        registered_at: None,
+    };
+    let passkey = Passkey { cred };
+
+    // The application digest that the user signed, which was provided as the challenge.
+    let digest: Vec<u8> = (0..32).collect();
+
+    let rsp: PublicKeyCredential = serde_json::from_str(
+        r#"{
+            "id":"ZGVmZ2hpamtsbW5vcHFyc3R1dnd4eXp7fH1-f4CBgoM",
+            "rawId":"ZGVmZ2hpamtsbW5vcHFyc3R1dnd4eXp7fH1-f4CBgoM",
+            "response":{
+                "authenticatorData":"o3mm9u6vuaVeN4wRgDTidR5oL6ufLTCrE9ISVYbOGUcFAAAAAg",
+                "clientDataJSON":"eyJ0eXBlIjoid2ViYXV0aG4uZ2V0IiwiY2hhbGxlbmdlIjoiQUFFQ0F3UUZCZ2NJQ1FvTERBME9EeEFSRWhNVUZSWVhHQmthR3h3ZEhoOCIsIm9yaWdpbiI6Imh0dHBzOi8vaWRtLmV4YW1wbGUuY29tIiwiY3Jvc3NPcmlnaW4iOmZhbHNlfQ",
+                "signature":"MEUCIQDbki2BZjqwdQbm8JLrlC3-s1zTxooDyJ9dNue-NpUGgAIgQywV33PSx75LNpI_2HJDJIRDbBWetTiO344pd4CMOHE",
+                "userHandle":null
+            },
+            "type":"public-key"
+        }"#,
+    )?;
+
+    let result = passkey.verify_assertion_over(&webauthn, &digest, &rsp)?;
+    assert_eq!(result.cred_id(), passkey.cred_id());
+    assert!(result.user_verified());
+    assert_eq!(result.counter(), 2);
+
+    // The same assertion does not verify over any other data.
+    let other: Vec<u8> = (1..33).collect();
+    assert_eq!(
+        passkey.verify_assertion_over(&webauthn, &other, &rsp).err(),
+        Some(WebauthnError::MismatchedChallenge)
+    );
+
+    Ok(())
+}
+
+#[test]
+/// Test that EDDSA is only offered when enabled, and that an Ed25519 credential can register
+/// and authenticate end to end with a simulated authenticator.
+fn test_eddsa_passkey_registration_and_authentication() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::SoftAuthenticator;
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+
+    let offers_eddsa = |ccr: &CreationChallengeResponse| {
+        ccr.public_key
+            .pub_key_cred_params
+            .iter()
+            .any(|p| p.alg == COSEAlgorithm::EDDSA as i64)
+    };
+
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?.build()?;
+    let (ccr, _) = webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+    assert!(!offers_eddsa(&ccr));
+
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?
+        .enable_eddsa(true)
+        .build()?;
+    let (ccr, reg_state) =
+        webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+    assert!(offers_eddsa(&ccr));
+
+    // The simulated authenticator.
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::EDDSA)?;
+
+    let passkey =
+        webauthn.finish_passkey_registration(&authenticator.register(&ccr)?, &reg_state)?;
+    assert_eq!(passkey.cred_algorithm(), &COSEAlgorithm::EDDSA);
+
+    let (rcr, auth_state) = webauthn.start_passkey_authentication(&[passkey.clone()])?;
+
+    // flags (UP | UV)
+    let assertion = authenticator.authenticate(&rcr, 0x05, 1)?;
+
+    let result = webauthn.finish_passkey_authentication(&assertion, &auth_state)?;
+    assert_eq!(result.cred_id(), passkey.cred_id());
+    assert_eq!(result.counter(), 1);
+
+    // A signature that does not verify is rejected.
+    let mut bad_assertion = assertion;
+    bad_assertion.response.signature.0[0] ^= 0xff;
+    assert!(webauthn
+        .finish_passkey_authentication(&bad_assertion, &auth_state)
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+/// Test that passkey registration can require attestation, and rejects authenticators that
+/// withhold it.
+fn test_passkey_require_attestation() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::SoftAuthenticator;
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+
+    // The simulated authenticator.
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::ES256)?;
+
+    // Complete a registration where the authenticator either provides packed self attestation,
+    // or withholds attestation.
+    let register = |webauthn: &Webauthn,
+                    provide_attestation: bool|
+     -> Result<WebauthnResult<Passkey>, Box<dyn std::error::Error>> {
+        let (ccr, reg_state) =
+            webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+
+        let reg = if provide_attestation {
+            authenticator.register_packed(&ccr, authenticator.key(), &[])?
+        } else {
+            authenticator.register(&ccr)?
+        };
+
+        Ok(webauthn.finish_passkey_registration(&reg, &reg_state))
+    };
+
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?
+        .passkey_require_attestation(AttestationConveyancePreference::Direct)
+        .build()?;
+
+    let (ccr, _) = webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+    assert_eq!(
+        serde_json::to_value(&ccr)?["publicKey"]["attestation"],
+        "direct"
+    );
+
+    let passkey = register(&webauthn, true)??;
+    assert_eq!(
+        passkey.telemetry().attestation_format,
+        AttestationFormat::Packed
+    );
+
+    assert_eq!(
+        register(&webauthn, false)?.err(),
+        Some(WebauthnError::AttestationNotProvided)
+    );
+
+    // By default attestation is neither requested nor required.
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?.build()?;
+
+    let (ccr, _) = webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+    assert_eq!(
+        serde_json::to_value(&ccr)?["publicKey"]["attestation"],
+        "none"
+    );
+
+    let passkey = register(&webauthn, false)??;
+    assert_eq!(
+        passkey.telemetry().attestation_format,
+        AttestationFormat::None
+    );
+
+    Ok(())
+}
+
+#[test]
+/// Test that the credential protection policy the authenticator applied is reported separately
+/// from the requested policy, so that a downgrade can be detected.
+fn test_securitykey_credential_protection_downgrade() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::{attestation_object, client_data, SoftAuthenticator};
+    use serde_cbor_2::Value;
+    use std::collections::BTreeMap;
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?.build()?;
+
+    let (ccr, reg_state) = webauthn.start_securitykey_registration(
+        Uuid::new_v4(),
+        "claire",
+        "Claire",
+        None,
+        None,
+        None,
+    )?;
+
+    // The simulated authenticator.
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::ES256)?;
+
+    // The device applies a weaker policy than the requested UserVerificationRequired.
+    let extensions = Value::Map(BTreeMap::from([(
+        Value::Text("credProtect".to_string()),
+        Value::Integer(CredentialProtectionPolicy::UserVerificationOptional as i128),
+    )]));
+
+    let auth_data =
+        authenticator.auth_data_with(rp_id, &authenticator.cose_key()?, Some(&extensions))?;
+    let attestation_object = attestation_object("none", BTreeMap::new(), auth_data)?;
+    let client_data_json = client_data("webauthn.create", &ccr.public_key.challenge);
+    let reg = authenticator.registration(&attestation_object, &client_data_json)?;
+
+    let security_key = webauthn.finish_securitykey_registration(&reg, &reg_state)?;
+
+    assert_eq!(
+        security_key.requested_credential_protection_policy(),
+        Some(CredentialProtectionPolicy::UserVerificationRequired)
+    );
+    assert_eq!(
+        security_key.effective_credential_protection_policy(),
+        Some(CredentialProtectionPolicy::UserVerificationOptional)
+    );
+    assert!(
+        security_key.effective_credential_protection_policy()
+            < security_key.requested_credential_protection_policy()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_with_denied_algorithms() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let webauthn = WebauthnBuilder::new("example.com", &rp_origin)?.build()?;
+
+    let offered = |ccr: &CreationChallengeResponse| -> Vec<i64> {
+        ccr.public_key
+            .pub_key_cred_params
+            .iter()
+            .map(|p| p.alg)
+            .collect()
+    };
+
+    let restricted = webauthn.with_denied_algorithms(&[COSEAlgorithm::RS256])?;
+    let (ccr, _) =
+        restricted.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+    assert_eq!(offered(&ccr), vec![COSEAlgorithm::ES256 as i64]);
+
+    let (ccr, _) = restricted.start_securitykey_registration(
+        Uuid::new_v4(),
+        "claire",
+        "Claire",
+        None,
+        None,
+        None,
+    )?;
+    assert!(!offered(&ccr).contains(&(COSEAlgorithm::RS256 as i64)));
+
+    // The original instance is unchanged.
+    let (ccr, _) = webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+    assert!(offered(&ccr).contains(&(COSEAlgorithm::RS256 as i64)));
+
+    assert_eq!(
+        webauthn
+            .with_denied_algorithms(&[COSEAlgorithm::ES256, COSEAlgorithm::RS256])
+            .unwrap_err(),
+        WebauthnError::Configuration
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_types_are_send_sync() {
+    use crate::prelude::*;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<Webauthn>();
+    assert_send_sync::<WebauthnBuilder<'static>>();
+    assert_send_sync::<WebauthnConfig>();
+    assert_send_sync::<UserHandle>();
+    assert_send_sync::<Passkey>();
+    assert_send_sync::<PasskeyRegistration>();
+    assert_send_sync::<PasskeyAuthentication>();
+    assert_send_sync::<SecurityKey>();
+    assert_send_sync::<SecurityKeyRegistration>();
+    assert_send_sync::<SecurityKeyAuthentication>();
+    assert_send_sync::<AuthenticationResult>();
+    assert_send_sync::<AttestationCaList>();
+
+    #[cfg(feature = "preview-features")]
+    {
+        assert_send_sync::<AttestedPasskey>();
+        assert_send_sync::<AttestedPasskeyRegistration>();
+        assert_send_sync::<AttestedPasskeyAuthentication>();
+        assert_send_sync::<DiscoverableAuthentication>();
+        assert_send_sync::<DiscoverableKey>();
+    }
+
+    #[cfg(feature = "resident-key-support")]
+    {
+        assert_send_sync::<AttestedResidentKey>();
+        assert_send_sync::<AttestedResidentKeyRegistration>();
+        assert_send_sync::<AttestedResidentKeyAuthentication>();
+    }
+}
+
+#[test]
+fn test_require_attachment_rejects_mismatched_response() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let webauthn = WebauthnBuilder::new("example.com", &rp_origin)?
+        .require_attachment(AuthenticatorAttachment::Platform)
+        .build()?;
+
+    let (ccr, reg_state) =
+        webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+    assert_eq!(
+        ccr.public_key
+            .authenticator_selection
+            .as_ref()
+            .and_then(|sel| sel.authenticator_attachment),
+        Some(AuthenticatorAttachment::Platform)
+    );
+
+    let registration =
+        |attachment: &str| -> Result<RegisterPublicKeyCredential, serde_json::Error> {
+            serde_json::from_value(serde_json::json!({
+                "id": "AAEC",
+                "rawId": "AAEC",
+                "response": {
+                    "attestationObject": "oA",
+                    "clientDataJSON": "e30",
+                },
+                "type": "public-key",
+                "authenticatorAttachment": attachment,
+            }))
+        };
+
+    assert_eq!(
+        webauthn
+            .finish_passkey_registration(&registration("cross-platform")?, &reg_state)
+            .unwrap_err(),
+        WebauthnError::AuthenticatorAttachmentMismatch
+    );
+    // A response from a platform authenticator passes the attachment check, and then fails
+    // verification since it is not a real response.
+    assert_ne!(
+        webauthn
+            .finish_passkey_registration(&registration("platform")?, &reg_state)
+            .unwrap_err(),
+        WebauthnError::AuthenticatorAttachmentMismatch
+    );
+
+    let (_, auth_state) = webauthn.start_passkey_authentication(&[])?;
+    let assertion: PublicKeyCredential = serde_json::from_value(serde_json::json!({
+        "id": "AAEC",
+        "rawId": "AAEC",
+        "response": {
+            "authenticatorData": "AA",
+            "clientDataJSON": "e30",
+            "signature": "AA",
+            "userHandle": null,
+        },
+        "type": "public-key",
+        "authenticatorAttachment": "cross-platform",
+    }))?;
+    assert_eq!(
+        webauthn
+            .finish_passkey_authentication(&assertion, &auth_state)
+            .unwrap_err(),
+        WebauthnError::AuthenticatorAttachmentMismatch
+    );
+
+    Ok(())
+}
+
+#[test]
+/// Test that ceremonies are tailored to the reported client capabilities when they are started.
+fn test_client_capabilities() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let webauthn = WebauthnBuilder::new("example.com", &rp_origin)?.build()?;
+    let client_capabilities: ClientCapabilities = serde_json::from_str(
+        r#"{"hybridTransport":false,"passkeyPlatformAuthenticator":false,"userVerifyingPlatformAuthenticator":false}"#,
+    )?;
+    let tailored = webauthn.with_client_capabilities(client_capabilities);
+
+    let start_registration = |webauthn: &Webauthn| {
+        webauthn.start_securitykey_registration_with_hints(
+            Uuid::new_v4(),
+            "claire",
+            "Claire",
+            None,
+            None,
+            Some(AuthenticatorAttachment::Platform),
+            vec![
+                PublicKeyCredentialHint::ClientDevice,
+                PublicKeyCredentialHint::Hybrid,
+                PublicKeyCredentialHint::SecurityKey,
+            ],
+        )
+    };
+
+    let (ccr, _) = start_registration(&tailored)?;
+    assert_eq!(
+        ccr.public_key
+            .authenticator_selection
+            .as_ref()
+            .and_then(|sel| sel.authenticator_attachment),
+        None
+    );
+    assert_eq!(
+        ccr.public_key.hints,
+        Some(vec![PublicKeyCredentialHint::SecurityKey])
+    );
+
+    // The original instance is not changed.
+    let (ccr, _) = start_registration(&webauthn)?;
+    assert_eq!(
+        ccr.public_key
+            .authenticator_selection
+            .as_ref()
+            .and_then(|sel| sel.authenticator_attachment),
+        Some(AuthenticatorAttachment::Platform)
+    );
+    assert_eq!(ccr.public_key.hints.map(|hints| hints.len()), Some(3));
+
+    let creds = [AllowCredentials {
+        type_: "public-key".to_string(),
+        id: Base64UrlSafeData(vec![0; 16]),
+        transports: Some(vec![
+            AuthenticatorTransport::Usb,
+            AuthenticatorTransport::Hybrid,
+        ]),
+    }];
+    let (rcr, _) = tailored.start_securitykey_authentication_from_ids(&creds)?;
+    assert_eq!(
+        rcr.public_key.allow_credentials[0].transports,
+        Some(vec![AuthenticatorTransport::Usb])
+    );
+
+    // A required attachment is never removed.
+    let webauthn = WebauthnBuilder::new("example.com", &rp_origin)?
+        .require_attachment(AuthenticatorAttachment::Platform)
+        .build()?
+        .with_client_capabilities(client_capabilities);
+    let (ccr, _) = webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+    assert_eq!(
+        ccr.public_key
+            .authenticator_selection
+            .as_ref()
+            .and_then(|sel| sel.authenticator_attachment),
+        Some(AuthenticatorAttachment::Platform)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_require_attachment_drops_conflicting_hints() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let webauthn = WebauthnBuilder::new("example.com", &rp_origin)?
+        .require_attachment(AuthenticatorAttachment::Platform)
+        .build()?;
+
+    // A conflicting UI hint can not override the required attachment, and only the hints for
+    // platform authenticators are kept.
+    let (ccr, _) = webauthn.start_securitykey_registration_with_hints(
+        Uuid::new_v4(),
+        "claire",
+        "Claire",
+        None,
+        None,
+        Some(AuthenticatorAttachment::CrossPlatform),
+        vec![
+            PublicKeyCredentialHint::SecurityKey,
+            PublicKeyCredentialHint::ClientDevice,
+            PublicKeyCredentialHint::Hybrid,
+        ],
+    )?;
+    assert_eq!(
+        ccr.public_key
+            .authenticator_selection
+            .as_ref()
+            .and_then(|sel| sel.authenticator_attachment),
+        Some(AuthenticatorAttachment::Platform)
+    );
+    assert_eq!(
+        ccr.public_key.hints,
+        Some(vec![PublicKeyCredentialHint::ClientDevice])
+    );
+
+    // If every hint conflicts, no hints are emitted.
+    let (ccr, _) = webauthn.start_passkey_registration_with_hints(
+        Uuid::new_v4(),
+        "claire",
+        "Claire",
+        None,
+        vec![PublicKeyCredentialHint::SecurityKey],
+    )?;
+    assert_eq!(
+        ccr.public_key
+            .authenticator_selection
+            .as_ref()
+            .and_then(|sel| sel.authenticator_attachment),
+        Some(AuthenticatorAttachment::Platform)
+    );
+    assert_eq!(ccr.public_key.hints, None);
+
+    Ok(())
+}
+
+#[test]
+fn test_rp_id_hash() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let webauthn = WebauthnBuilder::new("example.com", &rp_origin)?.build()?;
+    assert_eq!(
+        webauthn.rp_id_hash(),
+        &openssl::sha::sha256("example.com".as_bytes())
+    );
+    Ok(())
+}
+
+#[test]
+fn test_min_rsa_key_bits() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::SoftAuthenticator;
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+
+    // The simulated authenticator, using a 2048 bit RSA key.
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::RS256)?;
+
+    let register = |webauthn: &Webauthn| -> WebauthnResult<Passkey> {
+        let (ccr, reg_state) =
+            webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+        webauthn.finish_passkey_registration(&authenticator.register(&ccr)?, &reg_state)
+    };
+
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?.build()?;
+    let passkey = register(&webauthn)?;
+    assert_eq!(passkey.cred_algorithm(), &COSEAlgorithm::RS256);
+
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?
+        .min_rsa_key_bits(3072)
+        .build()?;
+    assert_eq!(
+        register(&webauthn).unwrap_err(),
+        WebauthnError::CredentialRsaKeyTooSmall
+    );
+
+    // The minimum can not be lowered below 2048 bits.
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?
+        .min_rsa_key_bits(1024)
+        .build()?;
+    assert!(register(&webauthn).is_ok());
+
+    Ok(())
+}
+
+#[test]
+fn test_platform_authenticator_availability() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let webauthn = WebauthnBuilder::new("example.com", &rp_origin)?.build()?;
+
+    let availability: PlatformAuthenticatorAvailability =
+        serde_json::from_str(r#"{"userVerifyingPlatformAuthenticatorAvailable":true}"#)?;
+    assert!(availability.user_verifying_platform_authenticator_available);
+
+    let (_, mut reg_state) =
+        webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+    assert_eq!(reg_state.platform_authenticator_availability(), None);
+    reg_state.set_platform_authenticator_availability(availability);
+    assert_eq!(
+        reg_state.platform_authenticator_availability(),
+        Some(availability)
+    );
+
+    let (_, mut auth_state) = webauthn.start_passkey_authentication(&[])?;
+    assert_eq!(auth_state.platform_authenticator_availability(), None);
+    auth_state.set_platform_authenticator_availability(PlatformAuthenticatorAvailability {
+        user_verifying_platform_authenticator_available: false,
+    });
+    assert_eq!(
+        auth_state
+            .platform_authenticator_availability()
+            .map(|a| a.user_verifying_platform_authenticator_available),
+        Some(false)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_passkey_registration_matches_challenge() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let webauthn = WebauthnBuilder::new("example.com", &rp_origin)?.build()?;
+
+    let (ccr_a, reg_state_a) =
+        webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+    let (ccr_b, reg_state_b) =
+        webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+
+    assert!(reg_state_a.matches_challenge(&ccr_a));
+    assert!(reg_state_b.matches_challenge(&ccr_b));
+    assert!(!reg_state_a.matches_challenge(&ccr_b));
+    assert!(!reg_state_b.matches_challenge(&ccr_a));
+
+    Ok(())
+}
+
+#[test]
+fn test_builder_algorithm_validation() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?
+        .with_algorithms(&[COSEAlgorithm::RS256, COSEAlgorithm::ES256])
+        .build()?;
+    let (ccr, _) = webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+    assert_eq!(
+        ccr.public_key
+            .pub_key_cred_params
+            .iter()
+            .map(|p| p.alg)
+            .collect::<Vec<_>>(),
+        vec![COSEAlgorithm::RS256 as i64, COSEAlgorithm::ES256 as i64]
+    );
+
+    for algorithms in [
+        vec![],
+        vec![
+            COSEAlgorithm::ES256,
+            COSEAlgorithm::RS256,
+            COSEAlgorithm::ES256,
+        ],
+        vec![COSEAlgorithm::ES256, COSEAlgorithm::INSECURE_RS1],
+        vec![COSEAlgorithm::PinUvProtocol],
+        vec![COSEAlgorithm::ES256, COSEAlgorithm::PS256],
+    ] {
+        assert_eq!(
+            WebauthnBuilder::new(rp_id, &rp_origin)?
+                .with_algorithms(&algorithms)
+                .build()
+                .unwrap_err(),
+            WebauthnError::Configuration
+        );
+    }
+
+    // Enabling eddsa on top of a list that already contains it does not duplicate it.
+    assert!(WebauthnBuilder::new(rp_id, &rp_origin)?
+        .with_algorithms(&[COSEAlgorithm::EDDSA, COSEAlgorithm::ES256])
+        .enable_eddsa(true)
+        .build()
+        .is_ok());
+
+    Ok(())
+}
+
+#[test]
+/// Test that the hmac-secret registration extension output is reported on the credential.
+fn test_hmac_secret_enabled() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::{attestation_object, client_data, SoftAuthenticator};
+    use serde_cbor_2::Value;
+    use std::collections::BTreeMap;
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?
+        .enable_eddsa(true)
+        .build()?;
+
+    // The simulated authenticator.
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::EDDSA)?;
+
+    let register = |hmac_secret: Option<bool>| -> Result<Passkey, Box<dyn std::error::Error>> {
+        let (ccr, reg_state) =
+            webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+
+        let extensions = hmac_secret.map(|hmac_secret| {
+            Value::Map(BTreeMap::from([(
+                Value::Text("hmac-secret".to_string()),
+                Value::Bool(hmac_secret),
+            )]))
+        });
+        let auth_data =
+            authenticator.auth_data_with(rp_id, &authenticator.cose_key()?, extensions.as_ref())?;
+        let attestation_object = attestation_object("none", BTreeMap::new(), auth_data)?;
+        let client_data_json = client_data("webauthn.create", &ccr.public_key.challenge);
+        let reg = authenticator.registration(&attestation_object, &client_data_json)?;
+
+        Ok(webauthn.finish_passkey_registration(&reg, &reg_state)?)
+    };
+
+    assert_eq!(register(Some(true))?.hmac_secret_enabled(), Some(true));
+    assert_eq!(register(Some(false))?.hmac_secret_enabled(), Some(false));
+    assert_eq!(register(None)?.hmac_secret_enabled(), None);
+
+    Ok(())
+}
+
+#[test]
+/// Test that a security key registration can be bound to an expected device aaguid.
+fn test_securitykey_expected_aaguid() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::{
+        attestation_certificate, es256_key, SoftAuthenticator, PACKED_SUBJECT_OU,
+    };
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?.build()?;
+
+    let aaguid = Uuid::new_v4();
+
+    // The simulated authenticator, with a self signed packed attestation certificate.
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::ES256)?.with_aaguid(aaguid);
+    let att_key = es256_key()?;
+    let att_cert = attestation_certificate(&att_key, PACKED_SUBJECT_OU, None, None)?.to_der()?;
+
+    let register = |expected_aaguid: Option<Uuid>| -> WebauthnResult<SecurityKey> {
+        let (ccr, mut reg_state) = webauthn.start_securitykey_registration(
+            Uuid::new_v4(),
+            "claire",
+            "Claire",
+            None,
+            None,
+            None,
+        )?;
+        if let Some(expected_aaguid) = expected_aaguid {
+            reg_state.set_expected_aaguid(expected_aaguid);
+        }
+        assert_eq!(reg_state.expected_aaguid(), expected_aaguid);
+
+        let reg = authenticator.register_packed(&ccr, &att_key, &[&att_cert])?;
+        webauthn.finish_securitykey_registration(&reg, &reg_state)
+    };
+
+    let security_key = register(None)?;
+    assert_eq!(security_key.attestation().metadata.aaguid(), Some(aaguid));
+
+    assert!(register(Some(aaguid)).is_ok());
+    assert_eq!(
+        register(Some(Uuid::new_v4())).unwrap_err(),
+        WebauthnError::AttestationAaguidMismatch
+    );
+
+    Ok(())
+}
+
+#[test]
+/// Test that an attestation returned when none was requested is ignored by passkey registration,
+/// while the authenticator data it carries is still verified.
+fn test_passkey_ignores_unrequested_attestation() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::{
+        attestation_certificate, attestation_object, client_data, es256_key, packed_statement,
+        SoftAuthenticator, PACKED_SUBJECT_OU,
+    };
+    use serde_cbor_2::Value;
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?.build()?;
+
+    // The simulated authenticator, with a self signed packed attestation certificate.
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::ES256)?;
+    let att_key = es256_key()?;
+    let att_cert = attestation_certificate(&att_key, PACKED_SUBJECT_OU, None, None)?.to_der()?;
+
+    // Build a response for rp_id with a packed attestation statement, whose signature is not
+    // valid.
+    let response = |challenge: &Base64UrlSafeData,
+                    rp_id: &str|
+     -> Result<RegisterPublicKeyCredential, Box<dyn std::error::Error>> {
+        let auth_data = authenticator.auth_data(rp_id)?;
+        let client_data_json = client_data("webauthn.create", challenge);
+        let mut att_stmt = packed_statement(&att_key, &auth_data, &client_data_json, &[&att_cert])?;
+        att_stmt.insert(Value::Text("sig".to_string()), Value::Bytes(vec![0; 64]));
+        let attestation_object = attestation_object("packed", att_stmt, auth_data)?;
+        Ok(authenticator.registration(&attestation_object, &client_data_json)?)
+    };
+
+    // The passkey flow did not request attestation, so the statement is ignored.
+    let (ccr, reg_state) =
+        webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+    let reg = response(&ccr.public_key.challenge, rp_id)?;
+    let passkey = webauthn.finish_passkey_registration(&reg, &reg_state)?;
+    assert_eq!(passkey.cred_id().as_ref(), authenticator.cred_id());
+    assert_eq!(
+        passkey.telemetry().attestation_format,
+        AttestationFormat::None
+    );
+
+    // The authenticator data is still verified.
+    let (ccr, reg_state) =
+        webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+    let reg = response(&ccr.public_key.challenge, "example.net")?;
+    assert!(matches!(
+        webauthn.finish_passkey_registration(&reg, &reg_state),
+        Err(WebauthnError::RpIdHashMismatch { .. })
+    ));
+
+    // Security keys still verify any attestation they are given.
+    let (ccr, reg_state) = webauthn.start_securitykey_registration(
+        Uuid::new_v4(),
+        "claire",
+        "Claire",
+        None,
+        None,
+        None,
+    )?;
+    let reg = response(&ccr.public_key.challenge, rp_id)?;
+    assert!(webauthn
+        .finish_securitykey_registration(&reg, &reg_state)
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+/// Test that a packed attestation certificate whose id-fido-gen-ce-aaguid extension does not
+/// match the aaguid of the authenticator data is rejected.
+fn test_securitykey_packed_certificate_aaguid_mismatch() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::{
+        attestation_certificate, es256_key, SoftAuthenticator, PACKED_SUBJECT_OU,
+    };
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?.build()?;
+
+    let aaguid = Uuid::new_v4();
+
+    // The simulated authenticator.
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::ES256)?.with_aaguid(aaguid);
+
+    // Register with a self signed packed attestation certificate, which embeds cert_aaguid in
+    // its id-fido-gen-ce-aaguid extension.
+    let register =
+        |cert_aaguid: Uuid| -> Result<WebauthnResult<SecurityKey>, Box<dyn std::error::Error>> {
+            let att_key = es256_key()?;
+            let att_cert =
+                attestation_certificate(&att_key, PACKED_SUBJECT_OU, Some(cert_aaguid), None)?
+                    .to_der()?;
+
+            let (ccr, reg_state) = webauthn.start_securitykey_registration(
+                Uuid::new_v4(),
+                "claire",
+                "Claire",
+                None,
+                None,
+                None,
+            )?;
+
+            let reg = authenticator.register_packed(&ccr, &att_key, &[&att_cert])?;
+            Ok(webauthn.finish_securitykey_registration(&reg, &reg_state))
+        };
+
+    let security_key = register(aaguid)??;
+    assert_eq!(security_key.attestation().metadata.aaguid(), Some(aaguid));
+
+    assert_eq!(
+        register(Uuid::new_v4())?.unwrap_err(),
+        WebauthnError::AttestationCertificateAAGUIDMismatch
+    );
+
+    Ok(())
+}
+
+#[test]
+/// Test that when a client does not report the transports of a credential, the transports set
+/// for its attested aaguid are used instead, and that client reported transports are preferred.
+fn test_securitykey_aaguid_transports_fallback() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::{
+        attestation_certificate, es256_key, SoftAuthenticator, PACKED_SUBJECT_OU,
+    };
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let aaguid = Uuid::new_v4();
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?
+        .aaguid_transports(
+            aaguid,
+            &[AuthenticatorTransport::Usb, AuthenticatorTransport::Nfc],
+        )
+        .store_credential_transports(true)
+        .build()?;
+
+    // The simulated authenticator.
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::ES256)?;
+
+    // Register with a self signed packed attestation certificate for the given aaguid.
+    let register = |aaguid: Uuid,
+                    transports: Option<Vec<AuthenticatorTransport>>|
+     -> Result<SecurityKey, Box<dyn std::error::Error>> {
+        let att_key = es256_key()?;
+        let att_cert =
+            attestation_certificate(&att_key, PACKED_SUBJECT_OU, Some(aaguid), None)?.to_der()?;
+
+        let (ccr, reg_state) = webauthn.start_securitykey_registration(
+            Uuid::new_v4(),
+            "claire",
+            "Claire",
+            None,
+            None,
+            None,
+        )?;
+
+        let mut reg = authenticator.clone().with_aaguid(aaguid).register_packed(
+            &ccr,
+            &att_key,
+            &[&att_cert],
+        )?;
+        reg.response.transports = transports;
+
+        Ok(webauthn.finish_securitykey_registration(&reg, &reg_state)?)
+    };
+
+    // The client did not report transports, so those known for the aaguid are used.
+    let security_key = register(aaguid, None)?;
+    assert_eq!(
+        security_key.transports(),
+        Some([AuthenticatorTransport::Usb, AuthenticatorTransport::Nfc].as_slice())
+    );
+
+    // They are provided to the client during authentication.
+    let (rcr, _) = webauthn.start_securitykey_authentication(&[security_key])?;
+    assert_eq!(
+        rcr.public_key.allow_credentials[0].transports,
+        Some(vec![
+            AuthenticatorTransport::Usb,
+            AuthenticatorTransport::Nfc
+        ])
+    );
+
+    // Transports reported by the client are preferred.
+    let security_key = register(aaguid, Some(vec![AuthenticatorTransport::Usb]))?;
+    assert_eq!(
+        security_key.transports(),
+        Some([AuthenticatorTransport::Usb].as_slice())
+    );
+
+    // An aaguid without known transports is left as the client reported.
+    let security_key = register(Uuid::new_v4(), None)?;
+    assert_eq!(security_key.transports(), None);
+
+    Ok(())
+}
+
+#[test]
+/// Test that a registration attested as a device model that the attestation CA list marks as
+/// vulnerable is rejected when configured, as when the list is built from the FIDO MDS.
+fn test_securitykey_reject_vulnerable_firmware() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::{
+        attestation_ca, attestation_certificate, es256_key, SoftAuthenticator, PACKED_SUBJECT_OU,
+    };
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+
+    // The simulated authenticator, with a packed attestation certificate issued by a CA.
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::ES256)?;
+    let (ca_key, ca_cert) = attestation_ca()?;
+    let att_key = es256_key()?;
+    let att_cert =
+        attestation_certificate(&att_key, PACKED_SUBJECT_OU, None, Some((&ca_cert, &ca_key)))?
+            .to_der()?;
+
+    // The vulnerable model is trusted by its CA, but flagged by its status reports.
+    let vulnerable = Uuid::new_v4();
+    let fixed = Uuid::new_v4();
+    let mut ca_list =
+        AttestationCaList::try_from(AttestationCa::new_from_der(&ca_cert.to_der()?)?)?;
+    ca_list.insert_vulnerable_aaguid(vulnerable);
+
+    let register = |webauthn: &Webauthn, aaguid: Uuid| -> WebauthnResult<SecurityKey> {
+        let (ccr, reg_state) = webauthn.start_securitykey_registration(
+            Uuid::new_v4(),
+            "claire",
+            "Claire",
+            None,
+            Some(ca_list.clone()),
+            None,
+        )?;
+
+        let reg = authenticator.clone().with_aaguid(aaguid).register_packed(
+            &ccr,
+            &att_key,
+            &[&att_cert],
+        )?;
+        webauthn.finish_securitykey_registration(&reg, &reg_state)
+    };
+
+    // By default, the status of the device model is not enforced.
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?.build()?;
+    assert!(register(&webauthn, vulnerable).is_ok());
+
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?
+        .reject_vulnerable_firmware(true)
+        .build()?;
+    assert_eq!(
+        register(&webauthn, vulnerable).unwrap_err(),
+        WebauthnError::AttestationDeviceVulnerable
+    );
+    assert!(register(&webauthn, fixed).is_ok());
+
+    Ok(())
+}
+
+#[test]
+/// Test that the authenticatorGetInfo options that the attestation CA list has for a device model,
+/// as when the list is built from the FIDO MDS, are added to the attestation metadata.
+fn test_securitykey_authenticator_options() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::{
+        attestation_ca, attestation_certificate, es256_key, SoftAuthenticator, PACKED_SUBJECT_OU,
+    };
+    use std::collections::BTreeMap;
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+
+    // The simulated authenticator, with a packed attestation certificate issued by a CA.
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::ES256)?;
+    let (ca_key, ca_cert) = attestation_ca()?;
+    let att_key = es256_key()?;
+    let att_cert =
+        attestation_certificate(&att_key, PACKED_SUBJECT_OU, None, Some((&ca_cert, &ca_key)))?
+            .to_der()?;
+
+    // The options of one model are known, as the MDS carries its authenticatorGetInfo.
+    let known = Uuid::new_v4();
+    let unknown = Uuid::new_v4();
+    let options = AuthenticatorOptions::from(&BTreeMap::from([
+        ("rk".to_string(), true),
+        ("clientPin".to_string(), false),
+        ("alwaysUv".to_string(), true),
+        ("credMgmt".to_string(), true),
+    ]));
+    assert!(options.resident_key);
+    assert!(options.user_presence);
+    assert_eq!(options.client_pin, Some(false));
+    assert_eq!(options.user_verification, None);
+    assert!(!options.platform_device);
+
+    let mut ca_list =
+        AttestationCaList::try_from(AttestationCa::new_from_der(&ca_cert.to_der()?)?)?;
+    ca_list.insert_authenticator_options(known, options.clone());
+
+    let register = |webauthn: &Webauthn, aaguid: Uuid| -> WebauthnResult<SecurityKey> {
+        let (ccr, reg_state) = webauthn.start_securitykey_registration(
+            Uuid::new_v4(),
+            "claire",
+            "Claire",
+            None,
+            Some(ca_list.clone()),
+            None,
+        )?;
+
+        let reg = authenticator.clone().with_aaguid(aaguid).register_packed(
+            &ccr,
+            &att_key,
+            &[&att_cert],
+        )?;
+        webauthn.finish_securitykey_registration(&reg, &reg_state)
+    };
+
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?.build()?;
+    let security_key = register(&webauthn, known)?;
+    let registered = security_key
+        .attestation()
+        .metadata
+        .authenticator_options()
+        .ok_or("authenticator options were not populated")?;
+    assert_eq!(registered, &options);
+    assert_eq!(registered.always_uv, Some(true));
+
+    // The options survive serialisation of the credential.
+    let security_key: SecurityKey = serde_json::from_str(&serde_json::to_string(&security_key)?)?;
+    assert_eq!(
+        security_key.attestation().metadata.authenticator_options(),
+        Some(&options)
+    );
+
+    let security_key = register(&webauthn, unknown)?;
+    assert_eq!(
+        security_key.attestation().metadata.authenticator_options(),
+        None
+    );
+
+    Ok(())
+}
+
+#[test]
+/// Test that a packed attestation is verified with the algorithm of its statement and the key of
+/// its certificate, independently of the credential public key, by attesting an RSA credential
+/// with an ECDSA certificate.
+fn test_securitykey_ecdsa_attestation_rsa_credential() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::{
+        attestation_certificate, attestation_object, client_data, es256_key, packed_statement,
+        SoftAuthenticator, PACKED_SUBJECT_OU,
+    };
+    use serde_cbor_2::Value;
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?.build()?;
+
+    // The simulated authenticator, with an RSA credential.
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::RS256)?;
+
+    // And a self signed ECDSA packed attestation certificate.
+    let att_key = es256_key()?;
+    let att_cert = attestation_certificate(&att_key, PACKED_SUBJECT_OU, None, None)?.to_der()?;
+
+    let register = |alg: i128| -> Result<WebauthnResult<SecurityKey>, Box<dyn std::error::Error>> {
+        let (ccr, reg_state) = webauthn.start_securitykey_registration(
+            Uuid::new_v4(),
+            "claire",
+            "Claire",
+            None,
+            None,
+            None,
+        )?;
+
+        let auth_data = authenticator.auth_data(rp_id)?;
+        let client_data_json = client_data("webauthn.create", &ccr.public_key.challenge);
+
+        // The statement is always signed by the ECDSA attestation key.
+        let mut att_stmt = packed_statement(&att_key, &auth_data, &client_data_json, &[&att_cert])?;
+        att_stmt.insert(Value::Text("alg".to_string()), Value::Integer(alg));
+        let attestation_object = attestation_object("packed", att_stmt, auth_data)?;
+        let reg = authenticator.registration(&attestation_object, &client_data_json)?;
+
+        Ok(webauthn.finish_securitykey_registration(&reg, &reg_state))
+    };
+
+    // The statement names the algorithm of the attestation key, ES256.
+    let security_key = register(-7)??;
+    assert_eq!(security_key.cred_algorithm(), &COSEAlgorithm::RS256);
+    assert_eq!(security_key.attestation_type(), AttestationType::Basic);
+
+    // Naming the algorithm of the credential instead does not verify with the attestation key.
+    assert!(register(-257)?.is_err());
+
+    Ok(())
+}
+
+#[test]
+/// Test that a credential public key with string labels is only accepted in lenient mode.
+fn test_lenient_cose_parsing() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::{attestation_object, client_data, SoftAuthenticator};
+    use serde_cbor_2::Value;
+    use std::collections::BTreeMap;
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+
+    // The simulated authenticator, which labels its public key parameters with strings.
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::EDDSA)?;
+
+    let cose_key = serde_cbor_2::to_vec(&Value::Map(BTreeMap::from([
+        (Value::Text("kty".to_string()), Value::Integer(1)),
+        (Value::Text("alg".to_string()), Value::Integer(-8)),
+        (Value::Text("crv".to_string()), Value::Integer(6)),
+        (
+            Value::Text("x".to_string()),
+            Value::Bytes(authenticator.key().raw_public_key()?),
+        ),
+    ])))?;
+
+    let auth_data = authenticator.auth_data_with(rp_id, &cose_key, None)?;
+    let attestation_object = attestation_object("none", BTreeMap::new(), auth_data)?;
+
+    let register = |lenient: bool| -> WebauthnResult<Passkey> {
+        let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?
+            .enable_eddsa(true)
+            .lenient_cose_parsing(lenient)
+            .build()?;
+        let (ccr, reg_state) =
+            webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+
+        let client_data_json = client_data("webauthn.create", &ccr.public_key.challenge);
+        let reg = authenticator.registration(&attestation_object, &client_data_json)?;
+
+        webauthn.finish_passkey_registration(&reg, &reg_state)
+    };
+
+    assert_eq!(
+        register(false).unwrap_err(),
+        WebauthnError::COSEKeyInvalidCBORValue
+    );
+
+    let passkey = register(true)?;
+    assert_eq!(passkey.cred_algorithm(), &COSEAlgorithm::EDDSA);
+
+    Ok(())
+}
+
+#[test]
+fn test_ceremony_state_challenge() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let webauthn = WebauthnBuilder::new("example.com", &rp_origin)?.build()?;
+
+    let (ccr, reg_state) =
+        webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+    assert_eq!(reg_state.challenge(), ccr.public_key.challenge.as_ref());
+
+    let (ccr, reg_state) = webauthn.start_securitykey_registration(
+        Uuid::new_v4(),
+        "claire",
+        "Claire",
+        None,
+        None,
+        None,
+    )?;
+    assert_eq!(reg_state.challenge(), ccr.public_key.challenge.as_ref());
+
+    let (rcr, auth_state) = webauthn.start_passkey_authentication(&[])?;
+    assert_eq!(auth_state.challenge(), rcr.public_key.challenge.as_ref());
+
+    Ok(())
+}
+
+#[test]
+/// Test that clientDataJSON is compared semantically, so that clients which reorder keys or
+/// add whitespace are accepted for both registration and authentication.
+fn test_client_data_json_reordered_keys() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::{assertion_auth_data, attestation_object, SoftAuthenticator};
+    use std::collections::BTreeMap;
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?
+        .enable_eddsa(true)
+        .build()?;
+
+    let (ccr, reg_state) =
+        webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+
+    // The simulated authenticator.
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::EDDSA)?;
+
+    let attestation_object =
+        attestation_object("none", BTreeMap::new(), authenticator.auth_data(rp_id)?)?;
+
+    let client_data_json = format!(
+        "{{\n  \"crossOrigin\" : false,\n  \"origin\" : \"https://idm.example.com\",\n  \"challenge\" : \"{}\",\n  \"type\" : \"webauthn.create\"\n}}",
+        ccr.public_key.challenge
+    );
+
+    let reg = authenticator.registration(&attestation_object, &client_data_json)?;
+    let passkey = webauthn.finish_passkey_registration(&reg, &reg_state)?;
+
+    let (rcr, auth_state) = webauthn.start_passkey_authentication(&[passkey.clone()])?;
+
+    let client_data_json = format!(
+        r#"{{ "origin": "https://idm.example.com", "type": "webauthn.get", "crossOrigin": false, "challenge": "{}" }}"#,
+        rcr.public_key.challenge
+    );
+
+    // flags (UP | UV)
+    let assertion =
+        authenticator.assertion(&assertion_auth_data(rp_id, 0x05, 1), &client_data_json)?;
+
+    let result = webauthn.finish_passkey_authentication(&assertion, &auth_state)?;
+    assert_eq!(result.cred_id(), passkey.cred_id());
+
+    Ok(())
+}
+
+#[test]
+/// Test that oversized registration responses are rejected before they are parsed.
+fn test_registration_response_size_limits() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::b64;
+
+    let rp_origin = Url::parse("https://idm.example.com")?;
+
+    let response = |attestation_object: &[u8], client_data: &[u8]| {
+        serde_json::from_value::<RegisterPublicKeyCredential>(serde_json::json!({
+            "id": b64(&[0; 16]),
+            "rawId": b64(&[0; 16]),
+            "response": {
+                "attestationObject": b64(attestation_object),
+                "clientDataJSON": b64(client_data),
+            },
+            "type": "public-key",
+        }))
+    };
+
+    let webauthn = WebauthnBuilder::new("example.com", &rp_origin)?.build()?;
+    let (_, reg_state) =
+        webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+
+    // The garbage attestation object would fail to parse, so this is rejected early.
+    let reg = response(&vec![0xff; 64 * 1024 + 1], b"{}")?;
+    assert_eq!(
+        webauthn
+            .finish_passkey_registration(&reg, &reg_state)
+            .unwrap_err(),
+        WebauthnError::ResponseTooLarge
+    );
+
+    // Within the default limit, the response is parsed (and fails).
+    let reg = response(&vec![0xff; 1024], b"{}")?;
+    assert_ne!(
+        webauthn
+            .finish_passkey_registration(&reg, &reg_state)
+            .unwrap_err(),
+        WebauthnError::ResponseTooLarge
+    );
+
+    let webauthn = WebauthnBuilder::new("example.com", &rp_origin)?
+        .max_attestation_object_size(512)
+        .max_client_data_size(16)
+        .build()?;
+    let (_, reg_state) = webauthn.start_securitykey_registration(
+        Uuid::new_v4(),
+        "claire",
+        "Claire",
+        None,
+        None,
+        None,
+    )?;
+
+    let reg = response(&vec![0xff; 1024], b"{}")?;
+    assert_eq!(
+        webauthn
+            .finish_securitykey_registration(&reg, &reg_state)
+            .unwrap_err(),
+        WebauthnError::ResponseTooLarge
+    );
+
+    let reg = response(&[0xff; 16], &[b' '; 17])?;
+    assert_eq!(
+        webauthn
+            .finish_securitykey_registration(&reg, &reg_state)
+            .unwrap_err(),
+        WebauthnError::ResponseTooLarge
+    );
+
+    Ok(())
+}
+
+#[test]
+/// Test that the transports reported during registration are stored, and that a credential
+/// reporting the hybrid transport is hybrid capable.
+fn test_passkey_hybrid_transport() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::SoftAuthenticator;
+    use webauthn_rs_core::proto::AuthenticatorTransport;
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?
+        .enable_eddsa(true)
+        .store_credential_transports(true)
+        .build()?;
+
+    // The simulated authenticator.
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::EDDSA)?;
+
+    let register = |webauthn: &Webauthn,
+                    transports: Option<Vec<AuthenticatorTransport>>|
+     -> WebauthnResult<Passkey> {
+        let (ccr, reg_state) =
+            webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+
+        let mut reg = authenticator.register(&ccr)?;
+        reg.response.transports = transports;
+
+        webauthn.finish_passkey_registration(&reg, &reg_state)
+    };
+
+    let hybrid_internal = Some(vec![
+        AuthenticatorTransport::Hybrid,
+        AuthenticatorTransport::Internal,
+    ]);
+
+    let passkey = register(&webauthn, hybrid_internal.clone())?;
+    assert!(passkey.is_hybrid_capable());
+
+    // The stored transports are provided to the client during authentication.
+    let (rcr, _) = webauthn.start_passkey_authentication(&[passkey])?;
+    let allowed = rcr
+        .public_key
+        .allow_credentials
+        .first()
+        .and_then(|c| c.transports.clone());
+    assert_eq!(allowed, hybrid_internal);
+
+    let passkey = register(&webauthn, Some(vec![AuthenticatorTransport::Usb]))?;
+    assert!(!passkey.is_hybrid_capable());
+
+    let passkey = register(&webauthn, None)?;
+    assert!(!passkey.is_hybrid_capable());
+
+    // Unless opted in to, the reported transports are not stored.
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?
+        .enable_eddsa(true)
+        .build()?;
+    let passkey = register(&webauthn, hybrid_internal)?;
+    assert!(!passkey.is_hybrid_capable());
+    let (rcr, _) = webauthn.start_passkey_authentication(&[passkey])?;
+    assert_eq!(rcr.public_key.allow_credentials[0].transports, None);
+
+    Ok(())
+}
+
+#[test]
+/// Test that a security key registered with a legacy U2F appId can authenticate when the
+/// client reports it used the appid extension.
+fn test_securitykey_legacy_appid() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::{assertion_auth_data, client_data, SoftAuthenticator};
+    use webauthn_rs_core::proto::{RegisteredExtensions, UserVerificationPolicy};
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let appid = "https://idm.example.com/u2f/app-id.json";
+
+    // The simulated U2F security key, registered under the appId.
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::ES256)?;
+
+    let security_key = SecurityKey {
+        cred: Credential {
+            cred_id: authenticator.cred_id().to_vec().into(),
+            cred: authenticator.credential_key()?,
+            counter: 0,
+            transports: None,
+            user_verified: false,
+            backup_eligible: false,
+            backup_state: false,
+            registration_policy: UserVerificationPolicy::Discouraged_DO_NOT_USE,
+            extensions: RegisteredExtensions::none(),
+            attestation: ParsedAttestation {
+                data: ParsedAttestationData::None,
+                metadata: AttestationMetadata::None,
+            },
+            attestation_format: AttestationFormat::None,
+            registered_at: None,
+        },
+    };
+
+    let authenticate = |webauthn: &Webauthn, appid_used: bool| -> WebauthnResult<_> {
+        let (rcr, auth_state) =
+            webauthn.start_securitykey_authentication(&[security_key.clone()])?;
+
+        // The rpIdHash is of the appId, with flags (UP)
+        let mut assertion = authenticator.assertion(
+            &assertion_auth_data(appid, 0x01, 1),
+            &client_data("webauthn.get", &rcr.public_key.challenge),
+        )?;
+        assertion.extensions = serde_json::from_value(serde_json::json!({
+            "appid": appid_used,
+        }))?;
+
+        Ok((
+            rcr,
+            webauthn.finish_securitykey_authentication(&assertion, &auth_state),
+        ))
+    };
+
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?
+        .legacy_appid(appid)
+        .build()?;
+
+    let (rcr, result) = authenticate(&webauthn, true)?;
+    assert_eq!(
+        rcr.public_key
+            .extensions
+            .as_ref()
+            .and_then(|e| e.appid.as_deref()),
+        Some(appid)
+    );
+    assert_eq!(result?.counter(), 1);
+
+    // The client did not use the appid, so the rpIdHash must match the rp_id.
+    let (_, result) = authenticate(&webauthn, false)?;
+    assert!(matches!(
+        result,
+        Err(WebauthnError::RpIdHashMismatch { .. })
+    ));
+
+    // Without a configured appId, the extension is not requested and the assertion fails.
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?.build()?;
+    let (rcr, result) = authenticate(&webauthn, true)?;
+    assert!(rcr.public_key.extensions.is_none());
+    assert!(matches!(
+        result,
+        Err(WebauthnError::RpIdHashMismatch { .. })
+    ));
+
+    Ok(())
+}
+
+#[test]
+/// Test that a security key authentication can be started from stored credential ids, and
+/// finished with the full credential.
+fn test_securitykey_authentication_from_ids() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::SoftAuthenticator;
+    use webauthn_rs_core::proto::{RegisteredExtensions, UserVerificationPolicy};
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+
+    // The simulated security key, and the same key under another credential id.
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::ES256)?;
+    let other_authenticator = authenticator.clone().with_cred_id((16..32).collect());
+
+    let security_key = |authenticator: &SoftAuthenticator| -> WebauthnResult<SecurityKey> {
+        Ok(SecurityKey {
+            cred: Credential {
+                cred_id: authenticator.cred_id().to_vec().into(),
+                cred: authenticator.credential_key()?,
+                counter: 0,
+                transports: Some(vec![AuthenticatorTransport::Usb]),
+                user_verified: false,
+                backup_eligible: false,
+                backup_state: false,
+                registration_policy: UserVerificationPolicy::Discouraged_DO_NOT_USE,
+                extensions: RegisteredExtensions::none(),
+                attestation: ParsedAttestation {
+                    data: ParsedAttestationData::None,
+                    metadata: AttestationMetadata::None,
+                },
+                attestation_format: AttestationFormat::None,
+                registered_at: None,
+            },
+        })
+    };
+
+    let allowed_key = security_key(&authenticator)?;
+    let other_key = security_key(&other_authenticator)?;
+
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?.build()?;
+
+    // Only the credential id and transports are needed to start the authentication.
+    let (rcr, auth_state) =
+        webauthn.start_securitykey_authentication_from_ids(&[AllowCredentials {
+            type_: "public-key".to_string(),
+            id: allowed_key.cred_id().clone(),
+            transports: Some(vec![AuthenticatorTransport::Usb]),
+        }])?;
+    assert_eq!(rcr.public_key.allow_credentials.len(), 1);
+    assert_eq!(
+        &rcr.public_key.allow_credentials[0].id,
+        allowed_key.cred_id()
+    );
+
+    // The state does not contain the credential, so it must be provided to finish.
+    // flags (UP)
+    let reg = authenticator.authenticate(&rcr, 0x01, 1)?;
+
+    assert_eq!(
+        webauthn
+            .finish_securitykey_authentication(&reg, &auth_state)
+            .unwrap_err(),
+        WebauthnError::CredentialNotFound
+    );
+
+    let result = webauthn.finish_securitykey_authentication_with_credentials(
+        &reg,
+        &auth_state,
+        &[allowed_key.clone()],
+    )?;
+    assert_eq!(result.cred_id(), allowed_key.cred_id());
+    assert_eq!(result.counter(), 1);
+
+    // A credential that was not in the allow list is rejected, even if it is provided.
+    let reg = other_authenticator.authenticate(&rcr, 0x01, 1)?;
+    assert_eq!(
+        webauthn
+            .finish_securitykey_authentication_with_credentials(
+                &reg,
+                &auth_state,
+                &[allowed_key, other_key]
+            )
+            .unwrap_err(),
+        WebauthnError::CredentialNotFound
+    );
+
+    // An empty allow list can not be used to start an authentication.
+    assert!(webauthn
+        .start_securitykey_authentication_from_ids(&[])
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+/// Test that the registration time of a credential is taken from the configured clock, and
+/// that it is serialised with the credential.
+fn test_passkey_registered_at() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::SoftAuthenticator;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+
+    // A clock that the test advances between registrations.
+    let now = Arc::new(Mutex::new(UNIX_EPOCH + Duration::from_secs(1_700_000_000)));
+    let clock = now.clone();
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?
+        .enable_eddsa(true)
+        .clock(Arc::new(move || -> SystemTime {
+            *clock.lock().expect("Clock poisoned")
+        }))
+        .build()?;
+
+    let register = || -> WebauthnResult<Passkey> {
+        let (ccr, reg_state) =
+            webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+        // The simulated authenticator.
+        let reg = SoftAuthenticator::new(COSEAlgorithm::EDDSA)?.register(&ccr)?;
+        webauthn.finish_passkey_registration(&reg, &reg_state)
+    };
+
+    let passkey = register()?;
+    assert_eq!(
+        passkey.registered_at(),
+        Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+    );
+
+    *now.lock().expect("Clock poisoned") += Duration::from_secs(60);
+    assert_eq!(
+        register()?.registered_at(),
+        Some(UNIX_EPOCH + Duration::from_secs(1_700_000_060))
+    );
+
+    // The registration time survives being stored.
+    let stored = serde_json::to_string(&passkey)?;
+    let passkey: Passkey = serde_json::from_str(&stored)?;
+    assert_eq!(
+        passkey.registered_at(),
+        Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+    );
+
+    // Credentials stored before the registration time was recorded have none.
+    let mut stored: serde_json::Value = serde_json::from_str(&stored)?;
+    if let Some(cred) = stored.get_mut("cred").and_then(|c| c.as_object_mut()) {
+        cred.remove("registered_at");
+    }
+    let passkey: Passkey = serde_json::from_value(stored)?;
+    assert_eq!(passkey.registered_at(), None);
+
+    Ok(())
+}
+
+#[test]
+/// Test that a packed attestation certificate with a different Subject-OU is only accepted when
+/// the strict subject check is relaxed.
+fn test_strict_packed_subject() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::{attestation_certificate, es256_key, SoftAuthenticator};
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+
+    // The simulated authenticator, with a self signed packed attestation certificate
+    // that uses a localised Subject-OU.
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::ES256)?;
+    let att_key = es256_key()?;
+    let att_cert =
+        attestation_certificate(&att_key, "Authentifikator-Attestierung", None, None)?.to_der()?;
+
+    let register = |strict: bool| -> WebauthnResult<SecurityKey> {
+        let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?
+            .strict_packed_subject(strict)
+            .build()?;
+        let (ccr, reg_state) = webauthn.start_securitykey_registration(
+            Uuid::new_v4(),
+            "claire",
+            "Claire",
+            None,
+            None,
+            None,
+        )?;
+
+        let reg = authenticator.register_packed(&ccr, &att_key, &[&att_cert])?;
+        webauthn.finish_securitykey_registration(&reg, &reg_state)
+    };
+
+    assert_eq!(
+        register(true).unwrap_err(),
+        WebauthnError::AttestationCertificateRequirementsNotMet
+    );
+    assert!(register(false).is_ok());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "preview-features")]
+/// Test that a discoverable authentication by a credential that is not in the provided set
+/// reports the id of the responding credential.
+fn test_discoverable_authentication_unknown_credential() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::SoftAuthenticator;
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?
+        .enable_eddsa(true)
+        .build()?;
+    let user_unique_id = Uuid::new_v4();
+    let (ccr, reg_state) =
+        webauthn.start_passkey_registration(user_unique_id, "claire", "Claire", None)?;
+
+    // The simulated authenticator.
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::EDDSA)?;
+
+    let passkey =
+        webauthn.finish_passkey_registration(&authenticator.register(&ccr)?, &reg_state)?;
+
+    let assertion = |rcr: &RequestChallengeResponse,
+                     authenticator: &SoftAuthenticator|
+     -> WebauthnResult<PublicKeyCredential> {
+        // flags (UP | UV)
+        let mut assertion = authenticator.authenticate(rcr, 0x05, 1)?;
+        assertion.response.user_handle = Some(user_unique_id.as_bytes().to_vec().into());
+        Ok(assertion)
+    };
+
+    // A credential the server no longer knows about reports its credential id.
+    let (rcr, auth_state) = webauthn.start_discoverable_authentication()?;
+    let unknown_cred_id: Vec<u8> = (16..32).collect();
+    let reg = assertion(
+        &rcr,
+        &authenticator.clone().with_cred_id(unknown_cred_id.clone()),
+    )?;
+    let (unique_id, _) = webauthn.identify_discoverable_authentication(&reg)?;
+    assert_eq!(unique_id, user_unique_id);
+
+    let err = webauthn
+        .finish_discoverable_authentication(&reg, auth_state, &[(&passkey).into()])
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        WebauthnError::DiscoverableCredentialNotFound(ref responding_cred_id)
+            if responding_cred_id.0 == unknown_cred_id
+    ));
+
+    // The known credential still authenticates.
+    let (rcr, auth_state) = webauthn.start_discoverable_authentication()?;
+    let reg = assertion(&rcr, &authenticator)?;
+    let result =
+        webauthn.finish_discoverable_authentication(&reg, auth_state, &[(&passkey).into()])?;
+    assert_eq!(result.cred_id(), passkey.cred_id());
+
+    Ok(())
+}
+
+#[test]
+/// Test that a registered verifier is used for an attestation format that is not built in.
+fn test_attestation_format_verifier() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::{attestation_object, client_data, SoftAuthenticator};
+    use serde_cbor_2::Value;
+    use std::collections::BTreeMap;
+
+    // A made up format, where the credential key signs over the authenticator data and the
+    // client data hash, and the statement conveys a supplemental public key.
+    #[derive(Debug)]
+    struct ExampleByokVerifier;
+
+    impl AttestationFormatVerifier for ExampleByokVerifier {
+        fn format(&self) -> &str {
+            "example-byok"
+        }
+
+        fn verify(
+            &self,
+            acd: &AttestedCredentialData,
+            att_stmt: &Value,
+            auth_data: &[u8],
+            client_data_hash: &[u8],
+        ) -> WebauthnResult<(ParsedAttestationData, AttestationMetadata)> {
+            let Value::Map(att_stmt) = att_stmt else {
+                return Err(WebauthnError::AttestationStatementMapInvalid);
+            };
+            if !matches!(
+                att_stmt.get(&Value::Text("supplementalPubKey".to_string())),
+                Some(Value::Bytes(_))
+            ) {
+                return Err(WebauthnError::AttestationStatementMissingExtension);
+            }
+            let Some(Value::Bytes(sig)) = att_stmt.get(&Value::Text("sig".to_string())) else {
+                return Err(WebauthnError::AttestationStatementSigMissing);
+            };
+
+            let verification_data = [auth_data, client_data_hash].concat();
+            let credential_key = COSEKey::try_from(&acd.credential_pk)?;
+            if credential_key.verify_signature(sig, &verification_data)? {
+                Ok((ParsedAttestationData::Self_, AttestationMetadata::None))
+            } else {
+                Err(WebauthnError::AttestationStatementSigInvalid)
+            }
+        }
+    }
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+
+    // The simulated authenticator.
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::ES256)?;
+
+    let register = |webauthn: &Webauthn| -> WebauthnResult<SecurityKey> {
+        let (ccr, reg_state) = webauthn.start_securitykey_registration(
+            Uuid::new_v4(),
+            "claire",
+            "Claire",
+            None,
+            None,
+            None,
+        )?;
+
+        let auth_data = authenticator.auth_data(rp_id)?;
+        let client_data_json = client_data("webauthn.create", &ccr.public_key.challenge);
+        let att_stmt = BTreeMap::from([
+            (
+                Value::Text("sig".to_string()),
+                Value::Bytes(authenticator.sign(&auth_data, &client_data_json)?),
+            ),
+            (
+                Value::Text("supplementalPubKey".to_string()),
+                Value::Bytes(authenticator.cose_key()?),
+            ),
+        ]);
+        let attestation_object = attestation_object("example-byok", att_stmt, auth_data)?;
+        let reg = authenticator.registration(&attestation_object, &client_data_json)?;
+
+        webauthn.finish_securitykey_registration(&reg, &reg_state)
+    };
+
+    // Without a verifier the format is not supported.
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?.build()?;
+    assert_eq!(
+        register(&webauthn).unwrap_err(),
+        WebauthnError::AttestationNotSupported
+    );
+
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?
+        .attestation_format_verifier(Arc::new(ExampleByokVerifier))
+        .build()?;
+    let security_key = register(&webauthn)?;
+    assert_eq!(
+        security_key.telemetry().attestation_format,
+        AttestationFormat::Custom("example-byok".to_string())
+    );
+    assert_eq!(security_key.attestation_type(), AttestationType::Self_);
+
+    Ok(())
+}
+
+#[test]
+/// Test that a passkey with a corrupted public key or credential id fails validation.
+fn test_passkey_validate() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::SoftAuthenticator;
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?.build()?;
+    let (ccr, reg_state) =
+        webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+
+    // The simulated authenticator.
+    let reg = SoftAuthenticator::new(COSEAlgorithm::ES256)?.register(&ccr)?;
+
+    let passkey = webauthn.finish_passkey_registration(&reg, &reg_state)?;
+    assert!(passkey.validate().is_ok());
+
+    // A public key that is not on the curve, as may be left by corrupted storage.
+    let mut stored = serde_json::to_value(&passkey)?;
+    stored["cred"]["cred"]["key"]["EC_EC2"]["y"] =
+        stored["cred"]["cred"]["key"]["EC_EC2"]["x"].clone();
+    let corrupted: Passkey = serde_json::from_value(stored)?;
+    assert!(corrupted.validate().is_err());
+
+    // An empty credential id.
+    let mut stored = serde_json::to_value(&passkey)?;
+    stored["cred"]["cred_id"] = serde_json::json!("");
+    let corrupted: Passkey = serde_json::from_value(stored)?;
+    assert_eq!(
+        corrupted.validate().unwrap_err(),
+        WebauthnError::CredentialIdInvalidLength
+    );
+
+    Ok(())
+}
+
+#[test]
+/// Test that a client which ignores the exclude list is rejected when finishing the registration.
+fn test_passkey_registration_excluded_credential() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::SoftAuthenticator;
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?
+        .enable_eddsa(true)
+        .build()?;
+
+    // The simulated authenticator, which has already been registered to this account.
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::EDDSA)?;
+
+    let register = |exclude_credentials: Option<Vec<CredentialID>>| -> WebauthnResult<Passkey> {
+        let (ccr, reg_state) = webauthn.start_passkey_registration(
+            Uuid::new_v4(),
+            "claire",
+            "Claire",
+            exclude_credentials,
+        )?;
+
+        // The client ignores excludeCredentials, and registers the credential again.
+        let reg = authenticator.register(&ccr)?;
+
+        webauthn.finish_passkey_registration(&reg, &reg_state)
+    };
+
+    assert!(register(None).is_ok());
+    assert!(register(Some(vec![(16..32).collect::<Vec<u8>>().into()])).is_ok());
+    assert_eq!(
+        register(Some(vec![authenticator.cred_id().to_vec().into()])).unwrap_err(),
+        WebauthnError::CredentialExcludedFromRequest
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "resident-key-support")]
+/// Test that an attested resident key registration is rejected when the client reports that the
+/// authenticator did not create a resident key.
+fn test_attested_resident_key_cred_props_rk() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::{
+        attestation_ca, attestation_certificate, es256_key, SoftAuthenticator, PACKED_SUBJECT_OU,
+    };
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?.build()?;
+
+    // The simulated authenticator, with a packed attestation certificate issued by a CA.
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::ES256)?;
+    let (ca_key, ca_cert) = attestation_ca()?;
+    let att_key = es256_key()?;
+    let att_cert =
+        attestation_certificate(&att_key, PACKED_SUBJECT_OU, None, Some((&ca_cert, &ca_key)))?
+            .to_der()?;
+
+    let ca_list = AttestationCaList::try_from(AttestationCa::new_from_der(&ca_cert.to_der()?)?)?;
+
+    let register = |extensions: serde_json::Value| -> WebauthnResult<AttestedResidentKey> {
+        let (ccr, reg_state) = webauthn.start_attested_resident_key_registration(
+            Uuid::new_v4(),
+            "claire",
+            "Claire",
+            None,
+            ca_list.clone(),
+            None,
+        )?;
+
+        let mut reg = authenticator.register_packed(&ccr, &att_key, &[&att_cert])?;
+        reg.extensions = serde_json::from_value(extensions)?;
+
+        webauthn.finish_attested_resident_key_registration(&reg, &reg_state)
+    };
+
+    assert!(register(serde_json::json!({ "credProps": { "rk": true } })).is_ok());
+    // Without credProps, it is not known if a resident key was created.
+    assert!(register(serde_json::json!({})).is_ok());
+    assert_eq!(
+        register(serde_json::json!({ "credProps": { "rk": false } })).unwrap_err(),
+        WebauthnError::ResidentKeyNotCreated
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "preview-features")]
+/// Test that a recovery key registration tags the attested passkey as a recovery credential,
+/// that the tag survives serialisation, and that the credential can authenticate.
+fn test_recovery_key_registration() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::{
+        attestation_ca, attestation_certificate, es256_key, SoftAuthenticator, PACKED_SUBJECT_OU,
+    };
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?.build()?;
+
+    // The simulated authenticator, with a packed attestation certificate issued by a CA.
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::ES256)?;
+    let (ca_key, ca_cert) = attestation_ca()?;
+    let att_key = es256_key()?;
+    let att_cert =
+        attestation_certificate(&att_key, PACKED_SUBJECT_OU, None, Some((&ca_cert, &ca_key)))?
+            .to_der()?;
+
+    let ca_list = AttestationCaList::try_from(AttestationCa::new_from_der(&ca_cert.to_der()?)?)?;
+
+    let register = |ccr: &CreationChallengeResponse| {
+        authenticator.register_packed(ccr, &att_key, &[&att_cert])
+    };
+
+    // An ordinary attested passkey is not a recovery credential.
+    let (ccr, reg_state) = webauthn.start_attested_passkey_registration(
+        Uuid::new_v4(),
+        "claire",
+        "Claire",
+        None,
+        ca_list.clone(),
+        None,
+    )?;
+    let attested_passkey =
+        webauthn.finish_attested_passkey_registration(&register(&ccr)?, &reg_state)?;
+    assert!(!attested_passkey.is_recovery_credential());
+
+    // Recovery keys require attestation.
+    assert_eq!(
+        webauthn
+            .start_recovery_key_registration(
+                Uuid::new_v4(),
+                "claire",
+                "Claire",
+                None,
+                AttestationCaList::default(),
+                None,
+            )
+            .unwrap_err(),
+        WebauthnError::MissingAttestationCaList
+    );
+
+    let (ccr, reg_state) = webauthn.start_recovery_key_registration(
+        Uuid::new_v4(),
+        "claire",
+        "Claire",
+        None,
+        ca_list.clone(),
+        Some(AuthenticatorAttachment::CrossPlatform),
+    )?;
+    assert!(matches!(
+        ccr.public_key.attestation,
+        Some(AttestationConveyancePreference::Direct)
+    ));
+    let recovery_key =
+        webauthn.finish_attested_passkey_registration(&register(&ccr)?, &reg_state)?;
+    assert!(recovery_key.is_recovery_credential());
+    assert_eq!(recovery_key.attestation_type(), AttestationType::Basic);
+
+    // The tag is persisted, and credentials stored before it existed are not recovery keys.
+    let mut value = serde_json::to_value(&recovery_key)?;
+    let recovery_key: AttestedPasskey = serde_json::from_value(value.clone())?;
+    assert!(recovery_key.is_recovery_credential());
+    if let Some(obj) = value.as_object_mut() {
+        obj.remove("recovery");
+    }
+    assert!(!serde_json::from_value::<AttestedPasskey>(value)?.is_recovery_credential());
+
+    // The recovery key authenticates like any other attested passkey.
+    let (rcr, auth_state) = webauthn.start_attested_passkey_authentication(&[recovery_key])?;
+
+    // flags (UP | UV)
+    let assertion = authenticator.authenticate(&rcr, 0x05, 1)?;
+
+    let result = webauthn.finish_attested_passkey_authentication(&assertion, &auth_state)?;
+    assert_eq!(result.cred_id().as_ref(), authenticator.cred_id());
+    assert_eq!(result.counter(), 1);
+
+    Ok(())
+}
+
+#[test]
+/// Test that the fingerprint of a passkey is stable across serialisation.
+fn test_passkey_fingerprint() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::SoftAuthenticator;
+    use openssl::sha;
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?
+        .enable_eddsa(true)
+        .build()?;
+    let (ccr, reg_state) =
+        webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+
+    // The simulated authenticator.
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::EDDSA)?;
+
+    let passkey =
+        webauthn.finish_passkey_registration(&authenticator.register(&ccr)?, &reg_state)?;
+
+    let fingerprint = passkey.fingerprint();
+    let expected = sha::sha256(authenticator.cred_id())[..8]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<Vec<_>>()
+        .join(":");
+    assert_eq!(fingerprint, expected);
+
+    let stored = serde_json::to_string(&passkey)?;
+    let passkey: Passkey = serde_json::from_str(&stored)?;
+    assert_eq!(passkey.fingerprint(), fingerprint);
+
+    Ok(())
+}
+
+#[test]
+/// Test that a registration response can be inspected without the state of its ceremony.
+fn test_inspect_registration() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::{attestation_object, SoftAuthenticator};
+    use std::collections::BTreeMap;
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?.build()?;
+
+    let aaguid = Uuid::new_v4();
+
+    // The simulated authenticator, with flags (UP | BE | AT).
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::EDDSA)?
+        .with_aaguid(aaguid)
+        .with_flags(0x49);
+
+    let attestation_object =
+        attestation_object("none", BTreeMap::new(), authenticator.auth_data(rp_id)?)?;
+
+    // The challenge and origin are not checked.
+    let client_data_json = r#"{"type":"webauthn.create","challenge":"AAAA","origin":"https://example.net","crossOrigin":false}"#;
+
+    let mut reg = authenticator.registration(&attestation_object, client_data_json)?;
+    reg.response.transports = Some(vec![
+        AuthenticatorTransport::Usb,
+        AuthenticatorTransport::Nfc,
+    ]);
+
+    let inspection = webauthn.inspect_registration(&reg)?;
+    assert_eq!(inspection.aaguid, aaguid);
+    assert_eq!(inspection.algorithm, COSEAlgorithm::EDDSA);
+    assert_eq!(inspection.attestation_format, AttestationFormat::None);
+    assert!(inspection.user_present);
+    assert!(!inspection.user_verified);
+    assert!(inspection.backup_eligible);
+    assert!(!inspection.backup_state);
+    assert_eq!(
+        inspection.transports,
+        Some(vec![
+            AuthenticatorTransport::Usb,
+            AuthenticatorTransport::Nfc
+        ])
+    );
+
+    Ok(())
+}
+
+#[test]
+/// Test that the uvm extension is only requested when enabled.
+fn test_request_uvm() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?.build()?;
+    let (ccr, _) = webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+    assert_eq!(
+        serde_json::to_value(&ccr)?["publicKey"]["extensions"]["uvm"],
+        true
+    );
+
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?
+        .request_uvm(false)
+        .build()?;
+    let (ccr, _) = webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+    let extensions = serde_json::to_value(&ccr)?["publicKey"]["extensions"].clone();
+    assert!(extensions.get("uvm").is_none());
+    // The other extensions are still requested.
+    assert_eq!(extensions["credProps"], true);
+
+    let (ccr, _) = webauthn.start_securitykey_registration(
+        Uuid::new_v4(),
+        "claire",
+        "Claire",
+        None,
+        None,
+        None,
+    )?;
+    assert!(ccr
+        .public_key
+        .extensions
+        .as_ref()
+        .and_then(|e| e.uvm)
+        .is_none());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "testing")]
+/// Test that the payloads of the testing helpers complete a registration.
+fn test_testing_soft_passkey() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::testing::{example_passkey_registration, SoftPasskey};
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?.build()?;
+
+    let (_, reg_state, reg) = example_passkey_registration(&webauthn, &rp_origin)?;
+    let passkey = webauthn.finish_passkey_registration(&reg, &reg_state)?;
+    assert_eq!(passkey.cred_id(), &reg.raw_id);
+
+    // Security keys verify the packed self attestation.
+    let authenticator = SoftPasskey::new()?;
+    let (ccr, reg_state) = webauthn.start_securitykey_registration(
+        Uuid::new_v4(),
+        "claire",
+        "Claire",
+        None,
+        None,
+        None,
+    )?;
+    let reg = authenticator.register(&ccr, &rp_origin)?;
+    let security_key = webauthn.finish_securitykey_registration(&reg, &reg_state)?;
+    assert_eq!(security_key.cred_id(), &authenticator.cred_id());
+    assert_eq!(security_key.attestation_type(), AttestationType::Self_);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "testing")]
+/// Test that a registration started with a fixed challenge verifies a response captured for
+/// that challenge, and that an ordinary registration rejects it.
+fn test_passkey_registration_with_challenge() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+
+    let rp_id = "localhost";
+    let rp_origin = Url::parse("https://localhost:8443")?;
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?.build()?;
+
+    // Generated using navigator.credentials.create on Chrome Version 77.0.3865.120 using
+    // Touch ID on MacBook running MacOS 10.15
+    let challenge = vec![
+        148, 254, 166, 88, 208, 45, 27, 239, 213, 191, 94, 98, 51, 185, 91, 253, 116, 100, 117, 99,
+        47, 85, 15, 165, 79, 34, 176, 102, 227, 160, 213, 90,
+    ];
+    let reg: RegisterPublicKeyCredential = serde_json::from_str(
+        r#"{
+            "id":"ATk_7QKbi_ntSdp16LXeU6RDf9YnRLIDTCqEjJFzc6rKBhbqoSYccxNa",
+            "rawId":"ATk_7QKbi_ntSdp16LXeU6RDf9YnRLIDTCqEjJFzc6rKBhbqoSYccxNa",
+            "response":{
+                "attestationObject":"o2NmbXRmcGFja2VkZ2F0dFN0bXSiY2FsZyZjc2lnWEcwRQIgLXPjBtVEhBH3KdUDFFk3LAd9EtHogllIf48vjX4wgfECIQCXOymmfg12FPMXEdwpSjjtmrvki4K8y0uYxqWN5Bw6DGhhdXRoRGF0YViuSZYN5YgOjGh0NBcPZHZgW4_krrmihjLHmVzzuoMdl2NFXaqejq3OAAI1vMYKZIsLJfHwVQMAKgE5P-0Cm4v57Unadei13lOkQ3_WJ0SyA0wqhIyRc3OqygYW6qEmHHMTWqUBAgMmIAEhWCDNRS_Gw52ow5PNrC9OdFTFNudDmZO6Y3wmM9N8e0tJICJYIC09iIH5_RrT5tbS0PIw3srdAxYDMGao7yWgu0JFIEzT",
+                "clientDataJSON":"eyJjaGFsbGVuZ2UiOiJsUDZtV05BdEctX1Z2MTVpTTdsYl9YUmtkV012VlEtbFR5S3dadU9nMVZvIiwiZXh0cmFfa2V5c19tYXlfYmVfYWRkZWRfaGVyZSI6ImRvIG5vdCBjb21wYXJlIGNsaWVudERhdGFKU09OIGFnYWluc3QgYSB0ZW1wbGF0ZS4gU2VlIGh0dHBzOi8vZ29vLmdsL3lhYlBleCIsIm9yaWdpbiI6Imh0dHBzOi8vbG9jYWxob3N0Ojg0NDMiLCJ0eXBlIjoid2ViYXV0aG4uY3JlYXRlIn0"
+            },
+            "type":"public-key"
+        }"#,
+    )?;
+
+    let (ccr, reg_state) = webauthn.start_passkey_registration_with_challenge(
+        challenge.clone(),
+        Uuid::new_v4(),
+        "claire",
+        "Claire",
+        None,
+    )?;
+    assert_eq!(ccr.public_key.challenge.as_ref(), challenge.as_slice());
+    assert_eq!(reg_state.rs.challenge(), challenge.as_slice());
+
+    let passkey = webauthn.finish_passkey_registration(&reg, &reg_state)?;
+    assert_eq!(passkey.cred_id(), &reg.raw_id);
+
+    // A freshly generated challenge does not match the captured response.
+    let (_, reg_state) =
+        webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+    assert!(matches!(
+        webauthn.finish_passkey_registration(&reg, &reg_state),
+        Err(WebauthnError::MismatchedChallenge)
+    ));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "preview-features")]
+/// Test that the credential ids of all types of credential are collected for exclusion, and
+/// that an id registered through multiple flows is only returned once.
+fn test_all_credential_ids() {
+    use crate::prelude::*;
+
+    let cred = |cred_id: &[u8]| Credential {
+        cred_id: cred_id.to_vec().into(),
+        cred: COSEKey {
+            type_: COSEAlgorithm::ES256,
+            key: COSEKeyType::EC_EC2(COSEEC2Key {
+                curve: ECDSACurve::SECP256R1,
+                x: vec![1; 32].into(),
+                y: vec![2; 32].into(),
+            }),
+        },
+        counter: 0,
+        transports: None,
+        user_verified: true,
+        backup_eligible: false,
+        backup_state: false,
+        registration_policy: UserVerificationPolicy::Required,
+        extensions: webauthn_rs_core::proto::RegisteredExtensions::none(),
+        attestation: ParsedAttestation {
+            data: ParsedAttestationData::None,
+            metadata: AttestationMetadata::None,
+        },
+        attestation_format: AttestationFormat::None,
+        registered_at: None,
+    };
+
+    let passkeys = [Passkey { cred: cred(&[1]) }, Passkey { cred: cred(&[2]) }];
+    let security_keys = [SecurityKey { cred: cred(&[3]) }];
+    // This physical key was also registered as a security key.
+    let attested = [
+        AttestedPasskey {
+            cred: cred(&[3]),
+            recovery: false,
+        },
+        AttestedPasskey {
+            cred: cred(&[4]),
+            recovery: true,
+        },
+    ];
+
+    assert_eq!(
+        Webauthn::all_credential_ids(&passkeys, &security_keys, &attested),
+        vec![
+            CredentialID::from(vec![1]),
+            CredentialID::from(vec![2]),
+            CredentialID::from(vec![3]),
+            CredentialID::from(vec![4]),
+        ]
+    );
+    assert!(Webauthn::all_credential_ids(&[], &[], &[]).is_empty());
+}
+
+#[test]
+/// Test that aaguids are described by their name when one is set, and that names do not affect
+/// verification.
+fn test_aaguid_names() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use webauthn_rs_core::proto::RegisteredExtensions;
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+
+    let named = Uuid::new_v4();
+    let unnamed = Uuid::new_v4();
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?
+        .aaguid_name(named, "Feitian BioPass")
+        .build()?;
+
+    assert_eq!(
+        webauthn.describe_aaguid(named),
+        format!("Feitian BioPass (aaguid {named})")
+    );
+    assert_eq!(
+        webauthn.describe_aaguid(unnamed),
+        format!("aaguid {unnamed}")
+    );
+
+    // A named aaguid is still rejected when it is not the expected one.
+    let cred = Credential {
+        cred_id: vec![0xde, 0xad, 0xbe, 0xef].into(),
+        cred: COSEKey {
+            type_: COSEAlgorithm::ES256,
+            key: COSEKeyType::EC_EC2(COSEEC2Key {
+                curve: ECDSACurve::SECP256R1,
+                x: vec![1; 32].into(),
+                y: vec![2; 32].into(),
+            }),
+        },
+        counter: 1,
+        transports: None,
+        user_verified: true,
+        backup_eligible: false,
+        backup_state: false,
+        registration_policy: UserVerificationPolicy::Required,
+        extensions: RegisteredExtensions::none(),
+        attestation: ParsedAttestation {
+            data: ParsedAttestationData::None,
+            metadata: AttestationMetadata::Packed {
+                aaguid: named,
+                authenticator_options: None,
+            },
+        },
+        attestation_format: AttestationFormat::Packed,
+        registered_at: None,
+    };
+    assert_eq!(
+        webauthn.check_expected_aaguid(&cred, Some(unnamed)),
+        Err(WebauthnError::AttestationAaguidMismatch)
+    );
+    assert!(webauthn.check_expected_aaguid(&cred, Some(named)).is_ok());
+
+    Ok(())
+}
+
+#[test]
+/// Test that the preferred passkey is listed first in the allowed credentials, without
+/// excluding the others.
+fn test_passkey_authentication_preferring() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use webauthn_rs_core::proto::RegisteredExtensions;
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?.build()?;
+
+    let passkey = |cred_id: u8| Passkey {
+        cred: Credential {
+            cred_id: vec![cred_id; 16].into(),
+            cred: COSEKey {
+                type_: COSEAlgorithm::ES256,
+                key: COSEKeyType::EC_EC2(COSEEC2Key {
+                    curve: ECDSACurve::SECP256R1,
+                    x: vec![1; 32].into(),
+                    y: vec![2; 32].into(),
+                }),
+            },
+            counter: 0,
+            transports: None,
+            user_verified: true,
+            backup_eligible: true,
+            backup_state: true,
+            registration_policy: UserVerificationPolicy::Required,
+            extensions: RegisteredExtensions::none(),
+            attestation: ParsedAttestation {
+                data: ParsedAttestationData::None,
+                metadata: AttestationMetadata::None,
+            },
+            attestation_format: AttestationFormat::None,
+            registered_at: None,
+        },
+    };
+    let passkeys = [passkey(1), passkey(2), passkey(3)];
+
+    let (rcr, _) =
+        webauthn.start_passkey_authentication_preferring(&passkeys, passkeys[2].cred_id())?;
+    let allowed: Vec<_> = rcr
+        .public_key
+        .allow_credentials
+        .iter()
+        .map(|ac| ac.id.clone())
+        .collect();
+    assert_eq!(
+        allowed,
+        vec![
+            passkeys[2].cred_id().clone(),
+            passkeys[0].cred_id().clone(),
+            passkeys[1].cred_id().clone(),
+        ]
+    );
+
+    let unknown = passkey(4);
+    assert_eq!(
+        webauthn
+            .start_passkey_authentication_preferring(&passkeys, unknown.cred_id())
+            .unwrap_err(),
+        WebauthnError::CredentialNotFound
+    );
+
+    Ok(())
+}
+
+#[test]
+/// Test that challenges report the user verification policy they requested.
+fn test_challenge_user_verification() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?.build()?;
+    let (ccr, _) = webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+    assert_eq!(
+        ccr.user_verification(),
+        Some(UserVerificationPolicy::Required)
+    );
+
+    let (rcr, _) = webauthn.start_securitykey_authentication_from_ids(&[AllowCredentials {
+        type_: "public-key".to_string(),
+        id: Base64UrlSafeData(vec![0; 16]),
+        transports: None,
+    }])?;
+    assert_eq!(rcr.user_verification(), UserVerificationPolicy::Preferred);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "danger-user-presence-only-security-keys")]
+/// Test that challenges of presence-only security keys report that user verification is
+/// discouraged.
+fn test_challenge_user_verification_presence_only() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?
+        .danger_set_user_presence_only_security_keys(true)
+        .build()?;
+    let (ccr, _) = webauthn.start_securitykey_registration(
+        Uuid::new_v4(),
+        "claire",
+        "Claire",
+        None,
+        None,
+        None,
+    )?;
+    assert_eq!(
+        ccr.user_verification(),
+        Some(UserVerificationPolicy::Discouraged_DO_NOT_USE)
+    );
+
+    let (rcr, _) = webauthn.start_securitykey_authentication_from_ids(&[AllowCredentials {
+        type_: "public-key".to_string(),
+        id: Base64UrlSafeData(vec![0; 16]),
+        transports: None,
+    }])?;
+    assert_eq!(
+        rcr.user_verification(),
+        UserVerificationPolicy::Discouraged_DO_NOT_USE
+    );
+    assert_eq!(
+        serde_json::to_value(&rcr)?["publicKey"]["userVerification"],
+        "discouraged"
+    );
+
+    Ok(())
+}
+
+#[test]
+/// Test that registering a credential with a recognised but unsupported algorithm names the
+/// algorithm in the error.
+fn test_passkey_registration_ed448_unsupported() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::{attestation_object, client_data, SoftAuthenticator};
+    use serde_cbor_2::Value;
+    use std::collections::BTreeMap;
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?
+        .enable_eddsa(true)
+        .build()?;
+    let (ccr, reg_state) =
+        webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+
+    // The simulated authenticator, which creates an EdDSA credential on the Ed448 curve.
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::EDDSA)?;
+
+    let cose_key = serde_cbor_2::to_vec(&Value::Map(BTreeMap::from([
+        (Value::Integer(1), Value::Integer(1)),
+        (Value::Integer(3), Value::Integer(-8)),
+        (Value::Integer(-1), Value::Integer(7)),
+        (Value::Integer(-2), Value::Bytes(vec![0x42; 57])),
+    ])))?;
+
+    let auth_data = authenticator.auth_data_with(rp_id, &cose_key, None)?;
+    let attestation_object = attestation_object("none", BTreeMap::new(), auth_data)?;
+    let client_data_json = client_data("webauthn.create", &ccr.public_key.challenge);
+    let reg = authenticator.registration(&attestation_object, &client_data_json)?;
+
+    let err = webauthn
+        .finish_passkey_registration(&reg, &reg_state)
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        WebauthnError::CredentialAlgorithmNotSupported { ref alg } if alg == "Ed448"
+    ));
+    assert!(err.to_string().contains("Ed448"));
+
+    Ok(())
+}
+
+#[test]
+/// Test that the processed extensions reflect the extension outputs present in the responses.
+fn test_processed_extensions() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::SoftAuthenticator;
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?
+        .enable_eddsa(true)
+        .build()?;
+    let (ccr, reg_state) =
+        webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+
+    // The simulated authenticator.
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::EDDSA)?;
+
+    let mut reg = authenticator.register(&ccr)?;
+    reg.extensions = serde_json::from_value(serde_json::json!({
+        "credProps": { "rk": true },
+    }))?;
+
+    let passkey = webauthn.finish_passkey_registration(&reg, &reg_state)?;
+    assert_eq!(passkey.processed_extensions(), vec!["credProps"]);
+
+    let (rcr, auth_state) = webauthn.start_passkey_authentication(&[passkey])?;
+
+    // flags (UP | UV)
+    let mut reg = authenticator.authenticate(&rcr, 0x05, 1)?;
+    reg.extensions = serde_json::from_value(serde_json::json!({
+        "appid": false,
+        "largeBlob": { "written": true },
+    }))?;
+
+    let result = webauthn.finish_passkey_authentication(&reg, &auth_state)?;
+    assert_eq!(result.processed_extensions(), vec!["appid", "largeBlob"]);
+
+    Ok(())
+}
+
+#[test]
+fn test_user_verification_capable() {
+    use crate::prelude::*;
+    use webauthn_rs_core::proto::{ExtnState, RegisteredExtensions, UserVerificationPolicy};
+
+    let credential = |user_verified: bool, extensions: RegisteredExtensions| Credential {
+        cred_id: vec![0xde, 0xad, 0xbe, 0xef].into(),
+        cred: COSEKey {
+            type_: COSEAlgorithm::ES256,
+            key: COSEKeyType::EC_EC2(COSEEC2Key {
+                curve: ECDSACurve::SECP256R1,
+                x: vec![1; 32].into(),
+                y: vec![2; 32].into(),
+            }),
+        },
+        counter: 1,
+        transports: None,
+        user_verified,
+        backup_eligible: false,
+        backup_state: false,
+        registration_policy: UserVerificationPolicy::Preferred,
+        extensions,
+        attestation: ParsedAttestation {
+            data: ParsedAttestationData::None,
+            metadata: AttestationMetadata::None,
+        },
+        attestation_format: AttestationFormat::None,
+        registered_at: None,
+    };
+
+    // A security key that only tested user presence, and has no credProtect policy.
+    let presence_only = SecurityKey {
+        cred: credential(false, RegisteredExtensions::none()),
+    };
+    assert!(!presence_only.is_user_verification_capable());
+
+    // The authenticator applied credProtect UV required, so the credential can never be used
+    // without user verification, even though registration itself did not verify the user.
+    let cred_protected = SecurityKey {
+        cred: credential(
+            false,
+            RegisteredExtensions {
+                cred_protect: ExtnState::Set(CredentialProtectionPolicy::UserVerificationRequired),
+                cred_protect_requested: Some(CredentialProtectionPolicy::UserVerificationRequired),
+                ..RegisteredExtensions::none()
+            },
+        ),
+    };
+    assert!(cred_protected.is_user_verification_capable());
+
+    // A weaker policy alone does not show that the authenticator can verify the user.
+    let optional_with_list = SecurityKey {
+        cred: credential(
+            false,
+            RegisteredExtensions {
+                cred_protect: ExtnState::Set(
+                    CredentialProtectionPolicy::UserVerificationOptionalWithCredentialIDList,
+                ),
+                ..RegisteredExtensions::none()
+            },
+        ),
+    };
+    assert!(!optional_with_list.is_user_verification_capable());
+
+    let passkey = Passkey {
+        cred: credential(true, RegisteredExtensions::none()),
+    };
+    assert!(passkey.is_user_verification_capable());
+}
+
+#[test]
+fn test_summarize_credentials() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use webauthn_rs_core::proto::{
+        CredProps, ExtnState, RegisteredExtensions, UserVerificationPolicy,
+    };
+
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let webauthn = WebauthnBuilder::new("idm.example.com", &rp_origin)?.build()?;
+
+    let passkey = |type_: COSEAlgorithm,
+                   key: COSEKeyType,
+                   user_verified: bool,
+                   backup_state: bool,
+                   extensions: RegisteredExtensions| Passkey {
+        cred: Credential {
+            cred_id: Uuid::new_v4().as_bytes().to_vec().into(),
+            cred: COSEKey { type_, key },
+            counter: 0,
+            transports: None,
+            user_verified,
+            backup_eligible: backup_state,
+            backup_state,
+            registration_policy: UserVerificationPolicy::Preferred,
+            extensions,
+            attestation: ParsedAttestation {
+                data: ParsedAttestationData::None,
+                metadata: AttestationMetadata::None,
+            },
+            attestation_format: AttestationFormat::None,
+            registered_at: None,
+        },
+    };
+    let ec2 = || {
+        COSEKeyType::EC_EC2(COSEEC2Key {
+            curve: ECDSACurve::SECP256R1,
+            x: vec![1; 32].into(),
+            y: vec![2; 32].into(),
+        })
+    };
+
+    // No credentials summarize to nothing.
+    assert_eq!(
+        webauthn.summarize_credentials(&[]),
+        CredentialSummary::default()
+    );
+
+    // A presence-only security key, which is neither synced nor discoverable.
+    let security_key = passkey(
+        COSEAlgorithm::ES256,
+        ec2(),
+        false,
+        false,
+        RegisteredExtensions::none(),
+    );
+    assert_eq!(
+        webauthn.summarize_credentials(std::slice::from_ref(&security_key)),
+        CredentialSummary {
+            count: 1,
+            algorithms_present: vec![COSEAlgorithm::ES256],
+            has_synced: false,
+            has_discoverable: false,
+            has_uv_capable: false,
+        }
+    );
+
+    // A synced, discoverable passkey that verified the user.
+    let synced = passkey(
+        COSEAlgorithm::ES256,
+        ec2(),
+        true,
+        true,
+        RegisteredExtensions {
+            cred_props: ExtnState::Unsigned(serde_json::from_str::<CredProps>(r#"{"rk":true}"#)?),
+            ..RegisteredExtensions::none()
+        },
+    );
+    // A device-bound Ed25519 key that reported it is not discoverable.
+    let ed25519 = passkey(
+        COSEAlgorithm::EDDSA,
+        COSEKeyType::EC_OKP(COSEOKPKey {
+            curve: EDDSACurve::ED25519,
+            x: [3; 32],
+        }),
+        false,
+        false,
+        RegisteredExtensions {
+            cred_props: ExtnState::Unsigned(serde_json::from_str::<CredProps>(r#"{"rk":false}"#)?),
+            ..RegisteredExtensions::none()
+        },
+    );
+
+    assert_eq!(
+        webauthn.summarize_credentials(&[security_key, synced, ed25519]),
+        CredentialSummary {
+            count: 3,
+            algorithms_present: vec![COSEAlgorithm::ES256, COSEAlgorithm::EDDSA],
+            has_synced: true,
+            has_discoverable: true,
+            has_uv_capable: true,
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_passkey_authentication_user_not_verified() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::SoftAuthenticator;
+    use webauthn_rs_core::proto::{RegisteredExtensions, UserVerificationPolicy};
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?.build()?;
+
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::ES256)?;
+
+    let passkey = Passkey {
+        cred: Credential {
+            cred_id: authenticator.cred_id().to_vec().into(),
+            cred: authenticator.credential_key()?,
+            counter: 0,
+            transports: None,
+            user_verified: true,
+            backup_eligible: false,
+            backup_state: false,
+            registration_policy: UserVerificationPolicy::Required,
+            extensions: RegisteredExtensions::none(),
+            attestation: ParsedAttestation {
+                data: ParsedAttestationData::None,
+                metadata: AttestationMetadata::None,
+            },
+            attestation_format: AttestationFormat::None,
+            registered_at: None,
+        },
+    };
+
+    let authenticate = |flags: u8| -> WebauthnResult<_> {
+        let (rcr, auth_state) = webauthn.start_passkey_authentication(&[passkey.clone()])?;
+        assert_eq!(rcr.user_verification(), UserVerificationPolicy::Required);
+
+        let assertion = authenticator.authenticate(&rcr, flags, 1)?;
+
+        Ok(webauthn.finish_passkey_authentication(&assertion, &auth_state))
+    };
+
+    // The user was only present (UP=1, UV=0) even though verification was required. The
+    // signature is valid, so the rejection must be specifically that the user was not verified.
+    assert!(matches!(
+        authenticate(0x01)?,
+        Err(WebauthnError::UserNotVerified)
+    ));
+
+    // Once the user completes verification (UP=1, UV=1), the retry succeeds.
+    let result = authenticate(0x05)??;
+    assert!(result.user_verified());
+
+    Ok(())
+}
+
+#[test]
+fn test_creation_challenge_rp_name_override() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let webauthn = WebauthnBuilder::new("example.com", &rp_origin)?
+        .rp_name("Example")
+        .build()?;
+
+    let (ccr, _) = webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+    assert_eq!(ccr.public_key.rp.name, "Example");
+
+    let ccr = ccr.with_rp_name("Tenant Brand");
+    assert_eq!(ccr.public_key.rp.name, "Tenant Brand");
+    assert_eq!(ccr.public_key.rp.id, "example.com");
+
+    let json = serde_json::to_value(&ccr)?;
+    assert_eq!(json["publicKey"]["rp"]["name"], "Tenant Brand");
+    assert_eq!(json["publicKey"]["rp"]["id"], "example.com");
+
+    // The same applies to the other registration flows.
+    let (ccr, _) = webauthn.start_securitykey_registration(
+        Uuid::new_v4(),
+        "claire",
+        "Claire",
+        None,
+        None,
+        None,
+    )?;
+    let ccr = ccr.with_rp_name("Tenant Brand");
+    assert_eq!(ccr.public_key.rp.name, "Tenant Brand");
+    assert_eq!(ccr.public_key.rp.id, "example.com");
+
+    Ok(())
+}
+
+#[test]
+/// Test that an android-key attestation is only accepted when the key description of its
+/// certificate binds the clientDataHash, and describes a key generated for signing.
+fn test_securitykey_android_key_attestation() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::{
+        android_key_certificate, attestation_object, client_data, SoftAuthenticator,
+    };
+    use openssl::sha;
+    use serde_cbor_2::Value;
+    use std::collections::BTreeMap;
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?.build()?;
+
+    // The simulated authenticator. For android-key, the attestation certificate is issued for
+    // the credential key itself.
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::ES256)?;
+
+    let register = |wrong_challenge: bool,
+                    purpose: u8,
+                    all_applications: bool|
+     -> Result<WebauthnResult<SecurityKey>, Box<dyn std::error::Error>> {
+        let (ccr, reg_state) = webauthn.start_securitykey_registration(
+            Uuid::new_v4(),
+            "claire",
+            "Claire",
+            None,
+            None,
+            None,
+        )?;
+
+        let client_data_json = client_data("webauthn.create", &ccr.public_key.challenge);
+        let client_data_hash = sha::sha256(client_data_json.as_bytes());
+        let attestation_challenge = if wrong_challenge {
+            sha::sha256(b"another ceremony")
+        } else {
+            client_data_hash
+        };
+
+        let att_cert = android_key_certificate(
+            authenticator.key(),
+            &attestation_challenge,
+            purpose,
+            all_applications,
+        )?
+        .to_der()?;
+
+        let auth_data = authenticator.auth_data(rp_id)?;
+        let att_stmt = BTreeMap::from([
+            (Value::Text("alg".to_string()), Value::Integer(-7)),
+            (
+                Value::Text("sig".to_string()),
+                Value::Bytes(authenticator.sign(&auth_data, &client_data_json)?),
+            ),
+            (
+                Value::Text("x5c".to_string()),
+                Value::Array(vec![Value::Bytes(att_cert)]),
+            ),
+        ]);
+        let attestation_object = attestation_object("android-key", att_stmt, auth_data)?;
+        let reg = authenticator.registration(&attestation_object, &client_data_json)?;
+
+        Ok(webauthn.finish_securitykey_registration(&reg, &reg_state))
+    };
+
+    // A key generated in the TEE for signing, bound to this ceremony.
+    let security_key = register(false, 2, false)??;
+    assert_eq!(
+        security_key.telemetry().attestation_format,
+        AttestationFormat::AndroidKey
+    );
+    assert!(matches!(
+        security_key.attestation().metadata,
+        AttestationMetadata::AndroidKey {
+            is_km_tee: true,
+            is_attest_tee: false,
+        }
+    ));
+
+    // The attestation challenge is not the clientDataHash of this ceremony.
+    assert_eq!(
+        register(true, 2, false)?.unwrap_err(),
+        WebauthnError::AttestationStatementAndroidKeyInvalid
+    );
+
+    // The key is for a purpose other than signing.
+    assert_eq!(
+        register(false, 3, false)?.unwrap_err(),
+        WebauthnError::AttestationStatementAndroidKeyInvalid
+    );
+
+    // The key is not scoped to the relying party.
+    assert_eq!(
+        register(false, 2, true)?.unwrap_err(),
+        WebauthnError::AttestationStatementAndroidKeyInvalid
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_validate_configuration() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let webauthn = WebauthnBuilder::new("example.com", &rp_origin)?
+        .append_allowed_origin(&Url::parse("https://au.example.com:8443")?)
+        .build()?;
+    assert!(webauthn.validate_configuration().is_ok());
+
+    // Native app origins have no domain, and are not checked against the rp_id.
+    let webauthn = WebauthnBuilder::new("example.com", &rp_origin)?
+        .append_allowed_origin(&Url::parse(
+            "android:apk-key-hash:Z5dua9pXG2AxWyw6u2SPUO8NfhIlX5jMzPzmGjnbwrQ",
+        )?)
+        .append_allowed_origin(&Url::parse("ios:bundle-id:com.example.app")?)
+        .build()?;
+    assert!(webauthn.validate_configuration().is_ok());
+
+    // Appended origins are not checked against the rp_id by the builder.
+    let webauthn = WebauthnBuilder::new("example.com", &rp_origin)?
+        .append_allowed_origin(&Url::parse("https://idm.myexample.com")?)
+        .build()?;
+    assert_eq!(
+        webauthn.validate_configuration().unwrap_err(),
+        WebauthnError::ConfigurationRpIdNotEffectiveDomain {
+            rp_id: "example.com".to_string(),
+            origin: "https://idm.myexample.com/".to_string(),
+        }
+    );
+
+    // An origin without a domain can never match the rp_id.
+    let webauthn = WebauthnBuilder::new("example.com", &rp_origin)?
+        .append_allowed_origin(&Url::parse("https://127.0.0.1")?)
+        .build()?;
+    assert_eq!(
+        webauthn.validate_configuration().unwrap_err(),
+        WebauthnError::ConfigurationRpIdNotEffectiveDomain {
+            rp_id: "example.com".to_string(),
+            origin: "https://127.0.0.1/".to_string(),
+        }
+    );
+
+    let webauthn = WebauthnBuilder::new("example.com", &rp_origin)?
+        .append_allowed_origin(&Url::parse("https://*.example.com")?)
+        .build()?;
+    assert_eq!(
+        webauthn.validate_configuration().unwrap_err(),
+        WebauthnError::ConfigurationWildcardOrigin {
+            origin: "https://*.example.com/".to_string(),
+        }
+    );
+
+    // The builder rejects bad algorithms, so set them directly to check they are found again.
+    let mut webauthn = WebauthnBuilder::new("example.com", &rp_origin)?.build()?;
+    webauthn.algorithms = vec![COSEAlgorithm::ES256, COSEAlgorithm::INSECURE_RS1];
+    assert_eq!(
+        webauthn.validate_configuration().unwrap_err(),
+        WebauthnError::ConfigurationInsecureAlgorithm {
+            alg: "INSECURE_RS1".to_string(),
+        }
+    );
+
+    webauthn.algorithms = Vec::new();
+    assert_eq!(
+        webauthn.validate_configuration().unwrap_err(),
+        WebauthnError::ConfigurationNoSecureAlgorithm
+    );
+
+    webauthn.algorithms = vec![COSEAlgorithm::PS256];
+    assert_eq!(
+        webauthn.validate_configuration().unwrap_err(),
+        WebauthnError::ConfigurationNoSecureAlgorithm
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "preview-features")]
+/// Test that credentials sharing a batch certificate can not be distinguished, but those with
+/// different batch certificates or models are reported as different devices.
+fn test_attested_passkey_same_device_as() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::{attestation_certificate, es256_key, PACKED_SUBJECT_OU};
+    use webauthn_rs_core::proto::{RegisteredExtensions, UserVerificationPolicy};
+
+    // A batch attestation certificate, which is distinct for each new key.
+    let batch_cert = || -> WebauthnResult<_> {
+        attestation_certificate(&es256_key()?, PACKED_SUBJECT_OU, None, None)
+    };
+
+    let credential = |cred_id: u8, aaguid: Uuid, data: ParsedAttestationData| AttestedPasskey {
+        cred: Credential {
+            cred_id: vec![cred_id; 16].into(),
+            cred: COSEKey {
+                type_: COSEAlgorithm::ES256,
+                key: COSEKeyType::EC_EC2(COSEEC2Key {
+                    curve: ECDSACurve::SECP256R1,
+                    x: vec![1; 32].into(),
+                    y: vec![2; 32].into(),
+                }),
+            },
+            counter: 1,
+            transports: None,
+            user_verified: true,
+            backup_eligible: false,
+            backup_state: false,
+            registration_policy: UserVerificationPolicy::Required,
+            extensions: RegisteredExtensions::none(),
+            attestation: ParsedAttestation {
+                data,
+                metadata: AttestationMetadata::Packed {
+                    aaguid,
+                    authenticator_options: None,
+                },
+            },
+            attestation_format: AttestationFormat::Packed,
+            registered_at: None,
+        },
+        recovery: false,
+    };
+
+    let aaguid = Uuid::new_v4();
+    let batch = batch_cert()?;
+    let other_batch = batch_cert()?;
+
+    // Two registrations with the same batch certificate may be from any device of the batch.
+    let first = credential(1, aaguid, ParsedAttestationData::Basic(vec![batch.clone()]));
+    let second = credential(2, aaguid, ParsedAttestationData::Basic(vec![batch.clone()]));
+    assert_eq!(first.same_device_as(&second), None);
+
+    let other_device = credential(3, aaguid, ParsedAttestationData::Basic(vec![other_batch]));
+    assert_eq!(first.same_device_as(&other_device), Some(false));
+
+    let other_model = credential(
+        4,
+        Uuid::new_v4(),
+        ParsedAttestationData::Basic(vec![batch.clone()]),
+    );
+    assert_eq!(first.same_device_as(&other_model), Some(false));
+
+    // A certificate issued per credential says nothing about the device.
+    let att_ca = credential(5, aaguid, ParsedAttestationData::AttCa(vec![batch]));
+    assert_eq!(first.same_device_as(&att_ca), None);
+
+    Ok(())
+}
+
+#[test]
+fn test_require_https_origins() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let http_origin = Url::parse("http://idm.example.com")?;
+
+    // An http origin is rejected by default, even when it is only appended.
+    assert_eq!(
+        WebauthnBuilder::new("example.com", &http_origin)?
+            .build()
+            .unwrap_err(),
+        WebauthnError::Configuration
+    );
+    assert_eq!(
+        WebauthnBuilder::new("example.com", &rp_origin)?
+            .append_allowed_origin(&http_origin)
+            .allow_any_port(true)
+            .build()
+            .unwrap_err(),
+        WebauthnError::Configuration
+    );
+
+    assert!(WebauthnBuilder::new("example.com", &rp_origin)?
+        .append_allowed_origin(&http_origin)
+        .require_https_origins(false)
+        .build()
+        .is_ok());
+
+    // The scheme is checked independently of the port.
+    assert!(WebauthnBuilder::new("example.com", &rp_origin)?
+        .append_allowed_origin(&Url::parse("https://idm.example.com:8443")?)
+        .build()
+        .is_ok());
+
+    // Local development over plain http is only allowed when explicitly enabled.
+    let localhost = Url::parse("http://localhost:8080")?;
+    assert_eq!(
+        WebauthnBuilder::new("localhost", &localhost)?
+            .build()
+            .unwrap_err(),
+        WebauthnError::Configuration
+    );
+    assert!(WebauthnBuilder::new("localhost", &localhost)?
+        .allow_insecure_localhost(true)
+        .build()
+        .is_ok());
+    assert!(
+        WebauthnBuilder::new("localhost", &Url::parse("https://localhost")?)?
+            .append_allowed_origin(&Url::parse("http://127.0.0.1:8080")?)
+            .append_allowed_origin(&Url::parse("http://[::1]:8080")?)
+            .allow_insecure_localhost(true)
+            .build()
+            .is_ok()
+    );
+
+    // This does not extend to other hosts.
+    assert_eq!(
+        WebauthnBuilder::new("example.com", &rp_origin)?
+            .append_allowed_origin(&http_origin)
+            .allow_insecure_localhost(true)
+            .build()
+            .unwrap_err(),
+        WebauthnError::Configuration
+    );
+
+    // Browser extension and native app origins are not web origins, and are not rejected.
+    let extension_id = "2114c9f524d0cbd74dbe846a51c3e5b34b83ac02c5220ec5cdff751096fa25a5";
+    assert!(WebauthnBuilder::new(
+        extension_id,
+        &Url::parse(&format!("chrome-extension://{extension_id}"))?
+    )?
+    .build()
+    .is_ok());
+    assert!(WebauthnBuilder::new("example.com", &rp_origin)?
+        .append_allowed_origin(&Url::parse(
+            "android:apk-key-hash:Z5dua9pXG2AxWyw6u2SPUO8NfhIlX5jMzPzmGjnbwrQ"
+        )?)
+        .append_allowed_origin(&Url::parse("ios:bundle-id:com.example.app")?)
+        .build()
+        .is_ok());
+
+    Ok(())
+}
+
+#[test]
+fn test_offered_algorithms() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let webauthn = WebauthnBuilder::new("example.com", &rp_origin)?.build()?;
+    let (ccr, _) = webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+    assert_eq!(ccr.offered_algorithms(), webauthn.algorithms);
+
+    let algorithms = [COSEAlgorithm::ES384, COSEAlgorithm::ES256];
+    let webauthn = WebauthnBuilder::new("example.com", &rp_origin)?
+        .with_algorithms(&algorithms)
+        .build()?;
+    let (ccr, _) = webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+    assert_eq!(ccr.offered_algorithms(), algorithms);
+
+    Ok(())
+}
+
+#[test]
+fn test_passkey_authentication_accessible() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::prelude::*;
+    use crate::test_authenticator::SoftAuthenticator;
+    use webauthn_rs_core::proto::{RegisteredExtensions, UserVerificationPolicy};
+
+    let rp_id = "example.com";
+    let rp_origin = Url::parse("https://idm.example.com")?;
+    let webauthn = WebauthnBuilder::new(rp_id, &rp_origin)?.build()?;
+
+    let authenticator = SoftAuthenticator::new(COSEAlgorithm::ES256)?;
+
+    let passkey = Passkey {
+        cred: Credential {
+            cred_id: authenticator.cred_id().to_vec().into(),
+            cred: authenticator.credential_key()?,
+            counter: 0,
+            transports: None,
+            user_verified: true,
+            backup_eligible: false,
+            backup_state: false,
+            registration_policy: UserVerificationPolicy::Required,
+            extensions: RegisteredExtensions::none(),
+            attestation: ParsedAttestation {
+                data: ParsedAttestationData::None,
+                metadata: AttestationMetadata::None,
+            },
+            attestation_format: AttestationFormat::None,
+            registered_at: None,
+        },
+    };
+
+    let authenticate = |flags: u8| -> WebauthnResult<_> {
+        let (rcr, auth_state) =
+            webauthn.start_passkey_authentication_accessible(&[passkey.clone()])?;
+        assert_eq!(rcr.user_verification(), UserVerificationPolicy::Preferred);
+
+        let assertion = authenticator.authenticate(&rcr, flags, 1)?;
+
+        Ok(webauthn.finish_passkey_authentication(&assertion, &auth_state))
+    };
+
+    // A presence-only assertion (UP=1, UV=0) is accepted, and reported as not verified.
+    let result = authenticate(0x01)??;
+    assert!(!result.user_verified());
+
+    // A user who can verify (UP=1, UV=1) still does so.
+    let result = authenticate(0x05)??;
+    assert!(result.user_verified());
+
+    Ok(())
+}
@@ -0,0 +1,454 @@
+//! A software authenticator for the tests of this crate.
+//!
+//! This builds the registration and authentication responses that the tests pass through a
+//! real [Webauthn] instance, with the algorithm, flags, aaguid and attestation that each test
+//! needs. Tests that exercise a malformed response can use the individual pieces, such as
+//! [SoftAuthenticator::auth_data_with] and [attestation_object], to alter only the part they
+//! are interested in.
+
+use openssl::{asn1, bn, ec, hash, nid, pkey, rsa, sha, sign, x509};
+use serde_cbor_2::Value;
+use std::collections::BTreeMap;
+
+use crate::prelude::*;
+
+/// The origin that the tests serve the relying party `example.com` from.
+pub(crate) const ORIGIN: &str = "https://idm.example.com";
+
+/// The Subject-OU that packed attestation certificates must have.
+pub(crate) const PACKED_SUBJECT_OU: &str = "Authenticator Attestation";
+
+/// Encode `data` as a client would in a JSON response.
+pub(crate) fn b64(data: &[u8]) -> String {
+    Base64UrlSafeData(data.to_vec()).to_string()
+}
+
+/// The clientDataJSON of a ceremony of `type_`, either `webauthn.create` or `webauthn.get`,
+/// as produced by a client visiting [ORIGIN].
+pub(crate) fn client_data(type_: &str, challenge: &Base64UrlSafeData) -> String {
+    format!(
+        r#"{{"type":"{type_}","challenge":"{challenge}","origin":"{ORIGIN}","crossOrigin":false}}"#
+    )
+}
+
+/// The authenticator data of an assertion: the rpIdHash, flags and counter.
+pub(crate) fn assertion_auth_data(rp_id: &str, flags: u8, counter: u32) -> Vec<u8> {
+    let mut auth_data = sha::sha256(rp_id.as_bytes()).to_vec();
+    auth_data.push(flags);
+    auth_data.extend_from_slice(&counter.to_be_bytes());
+    auth_data
+}
+
+/// An attestation object, with the attestation statement in the given format.
+pub(crate) fn attestation_object(
+    fmt: &str,
+    att_stmt: BTreeMap<Value, Value>,
+    auth_data: Vec<u8>,
+) -> WebauthnResult<Vec<u8>> {
+    Ok(serde_cbor_2::to_vec(&Value::Map(BTreeMap::from([
+        (Value::Text("fmt".to_string()), Value::Text(fmt.to_string())),
+        (Value::Text("attStmt".to_string()), Value::Map(att_stmt)),
+        (Value::Text("authData".to_string()), Value::Bytes(auth_data)),
+    ])))?)
+}
+
+/// A packed attestation statement signed by the ES256 `att_key`. This is self attestation if
+/// `x5c` is empty, in which case `att_key` must be the credential key.
+pub(crate) fn packed_statement(
+    att_key: &pkey::PKeyRef<pkey::Private>,
+    auth_data: &[u8],
+    client_data_json: &str,
+    x5c: &[&[u8]],
+) -> WebauthnResult<BTreeMap<Value, Value>> {
+    let mut signer = sign::Signer::new(hash::MessageDigest::sha256(), att_key)?;
+    signer.update(auth_data)?;
+    signer.update(&sha::sha256(client_data_json.as_bytes()))?;
+
+    let mut att_stmt = BTreeMap::from([
+        (Value::Text("alg".to_string()), Value::Integer(-7)),
+        (
+            Value::Text("sig".to_string()),
+            Value::Bytes(signer.sign_to_vec()?),
+        ),
+    ]);
+    if !x5c.is_empty() {
+        att_stmt.insert(
+            Value::Text("x5c".to_string()),
+            Value::Array(x5c.iter().map(|c| Value::Bytes(c.to_vec())).collect()),
+        );
+    }
+    Ok(att_stmt)
+}
+
+/// A new P-256 key, as used for attestation keys and CAs.
+pub(crate) fn es256_key() -> WebauthnResult<pkey::PKey<pkey::Private>> {
+    let group = ec::EcGroup::from_curve_name(nid::Nid::X9_62_PRIME256V1)?;
+    Ok(pkey::PKey::from_ec_key(ec::EcKey::generate(&group)?)?)
+}
+
+/// A root CA for attestation certificates, and its key.
+pub(crate) fn attestation_ca() -> WebauthnResult<(pkey::PKey<pkey::Private>, x509::X509)> {
+    let ca_key = es256_key()?;
+    let mut ca_name = x509::X509NameBuilder::new()?;
+    ca_name.append_entry_by_nid(nid::Nid::COMMONNAME, "Example Attestation Root")?;
+    let ca_name = ca_name.build();
+
+    let mut ca_cert = x509::X509Builder::new()?;
+    ca_cert.set_version(2)?;
+    ca_cert.set_serial_number(&bn::BigNum::from_u32(1)?.to_asn1_integer()?)?;
+    ca_cert.set_subject_name(&ca_name)?;
+    ca_cert.set_issuer_name(&ca_name)?;
+    ca_cert.set_pubkey(&ca_key)?;
+    ca_cert.set_not_before(&asn1::Asn1Time::days_from_now(0)?)?;
+    ca_cert.set_not_after(&asn1::Asn1Time::days_from_now(1)?)?;
+    ca_cert.append_extension(
+        x509::extension::BasicConstraints::new()
+            .critical()
+            .ca()
+            .build()?,
+    )?;
+    ca_cert.append_extension(
+        x509::extension::KeyUsage::new()
+            .critical()
+            .key_cert_sign()
+            .build()?,
+    )?;
+    ca_cert.sign(&ca_key, hash::MessageDigest::sha256())?;
+    Ok((ca_key, ca_cert.build()))
+}
+
+/// A packed attestation certificate for `att_key`, with the given Subject-OU. If `aaguid` is
+/// given it is embedded in the id-fido-gen-ce-aaguid extension. The certificate is self signed,
+/// unless it is issued by the CA certificate and key in `issuer`.
+pub(crate) fn attestation_certificate(
+    att_key: &pkey::PKeyRef<pkey::Private>,
+    subject_ou: &str,
+    aaguid: Option<Uuid>,
+    issuer: Option<(&x509::X509, &pkey::PKey<pkey::Private>)>,
+) -> WebauthnResult<x509::X509> {
+    let mut name = x509::X509NameBuilder::new()?;
+    name.append_entry_by_nid(nid::Nid::COUNTRYNAME, "SE")?;
+    name.append_entry_by_nid(nid::Nid::ORGANIZATIONNAME, "Example")?;
+    name.append_entry_by_nid(nid::Nid::ORGANIZATIONALUNITNAME, subject_ou)?;
+    name.append_entry_by_nid(nid::Nid::COMMONNAME, "Example Attestation")?;
+    let name = name.build();
+
+    let mut cert = x509::X509Builder::new()?;
+    cert.set_version(2)?;
+    cert.set_serial_number(&bn::BigNum::from_u32(2)?.to_asn1_integer()?)?;
+    cert.set_subject_name(&name)?;
+    cert.set_issuer_name(match issuer {
+        Some((ca_cert, _)) => ca_cert.subject_name(),
+        None => &name,
+    })?;
+    cert.set_pubkey(att_key)?;
+    cert.set_not_before(&asn1::Asn1Time::days_from_now(0)?)?;
+    cert.set_not_after(&asn1::Asn1Time::days_from_now(1)?)?;
+    cert.append_extension(x509::extension::BasicConstraints::new().build()?)?;
+    if let Some(aaguid) = aaguid {
+        // The extension value is the aaguid as a DER encoded 16 byte OCTET STRING.
+        let mut aaguid_ext = vec![0x04, 0x10];
+        aaguid_ext.extend_from_slice(aaguid.as_bytes());
+        cert.append_extension(x509::X509Extension::new_from_der(
+            &asn1::Asn1Object::from_str("1.3.6.1.4.1.45724.1.1.4")?,
+            false,
+            &asn1::Asn1OctetString::new_from_bytes(&aaguid_ext)?,
+        )?)?;
+    }
+    let signing_key: &pkey::PKeyRef<pkey::Private> = match issuer {
+        Some((_, ca_key)) => ca_key,
+        None => att_key,
+    };
+    cert.sign(signing_key, hash::MessageDigest::sha256())?;
+    Ok(cert.build())
+}
+
+/// A DER tag, length and value. This only supports the short values used here.
+fn tlv(tag: &[u8], value: &[u8]) -> Vec<u8> {
+    [tag, &[value.len() as u8], value].concat()
+}
+
+/// An android-key attestation certificate, which is issued for the credential `key` itself.
+/// Its KeyDescription is for a key generated in a TEE keymaster with the `purpose` and
+/// `attestation_challenge`, that is usable by all applications if `all_applications` is set.
+pub(crate) fn android_key_certificate(
+    key: &pkey::PKeyRef<pkey::Private>,
+    attestation_challenge: &[u8],
+    purpose: u8,
+    all_applications: bool,
+) -> WebauthnResult<x509::X509> {
+    // The teeEnforced AuthorizationList, with the purpose [1], allApplications [600] and
+    // origin [702] of the key.
+    let mut tee_enforced = tlv(&[0xa1], &tlv(&[0x31], &tlv(&[0x02], &[purpose])));
+    if all_applications {
+        tee_enforced.extend(tlv(&[0xbf, 0x84, 0x58], &tlv(&[0x05], &[])));
+    }
+    tee_enforced.extend(tlv(&[0xbf, 0x85, 0x3e], &tlv(&[0x02], &[0])));
+
+    // The KeyDescription, with a software attestation and a TEE keymaster.
+    let key_description = tlv(
+        &[0x30],
+        &[
+            tlv(&[0x02], &[3]),
+            tlv(&[0x0a], &[0]),
+            tlv(&[0x02], &[4]),
+            tlv(&[0x0a], &[1]),
+            tlv(&[0x04], attestation_challenge),
+            tlv(&[0x04], &[]),
+            tlv(&[0x30], &[]),
+            tlv(&[0x30], &tee_enforced),
+        ]
+        .concat(),
+    );
+
+    let mut name = x509::X509NameBuilder::new()?;
+    name.append_entry_by_nid(nid::Nid::COMMONNAME, "Android Keystore Key")?;
+    let name = name.build();
+
+    let mut cert = x509::X509Builder::new()?;
+    cert.set_version(2)?;
+    cert.set_serial_number(&bn::BigNum::from_u32(1)?.to_asn1_integer()?)?;
+    cert.set_subject_name(&name)?;
+    cert.set_issuer_name(&name)?;
+    cert.set_pubkey(key)?;
+    cert.set_not_before(&asn1::Asn1Time::days_from_now(0)?)?;
+    cert.set_not_after(&asn1::Asn1Time::days_from_now(1)?)?;
+    cert.append_extension(x509::X509Extension::new_from_der(
+        &asn1::Asn1Object::from_str("1.3.6.1.4.1.11129.2.1.17")?,
+        false,
+        &asn1::Asn1OctetString::new_from_bytes(&key_description)?,
+    )?)?;
+    cert.sign(key, hash::MessageDigest::sha256())?;
+    Ok(cert.build())
+}
+
+/// A software authenticator holding a single credential.
+///
+/// By default the credential id is the bytes `0..16`, the aaguid is all zeros, and the flags
+/// of a registration are UP | UV | AT.
+#[derive(Debug, Clone)]
+pub(crate) struct SoftAuthenticator {
+    key: pkey::PKey<pkey::Private>,
+    alg: COSEAlgorithm,
+    cred_id: Vec<u8>,
+    aaguid: Uuid,
+    flags: u8,
+}
+
+impl SoftAuthenticator {
+    /// Create an authenticator with a new credential for `alg`, which is one of ES256, EDDSA
+    /// (on Ed25519), or RS256 (with a 2048 bit key).
+    pub(crate) fn new(alg: COSEAlgorithm) -> WebauthnResult<Self> {
+        let key = match alg {
+            COSEAlgorithm::ES256 => es256_key()?,
+            COSEAlgorithm::EDDSA => pkey::PKey::generate_ed25519()?,
+            COSEAlgorithm::RS256 => pkey::PKey::from_rsa(rsa::Rsa::generate(2048)?)?,
+            _ => return Err(WebauthnError::COSEKeyInvalidAlgorithm),
+        };
+        Ok(SoftAuthenticator {
+            key,
+            alg,
+            cred_id: (0..16).collect(),
+            aaguid: Uuid::nil(),
+            flags: 0x45,
+        })
+    }
+
+    /// Use `cred_id` as the id of the credential.
+    pub(crate) fn with_cred_id(mut self, cred_id: Vec<u8>) -> Self {
+        self.cred_id = cred_id;
+        self
+    }
+
+    /// Report `aaguid` in the attested credential data.
+    pub(crate) fn with_aaguid(mut self, aaguid: Uuid) -> Self {
+        self.aaguid = aaguid;
+        self
+    }
+
+    /// Set `flags` in the authenticator data of a registration.
+    pub(crate) fn with_flags(mut self, flags: u8) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// The id of the credential.
+    pub(crate) fn cred_id(&self) -> &[u8] {
+        &self.cred_id
+    }
+
+    /// The private key of the credential.
+    pub(crate) fn key(&self) -> &pkey::PKeyRef<pkey::Private> {
+        &self.key
+    }
+
+    /// The CBOR encoded COSE public key of the credential.
+    pub(crate) fn cose_key(&self) -> WebauthnResult<Vec<u8>> {
+        let cose_key = match self.alg {
+            COSEAlgorithm::ES256 => {
+                let ec_key = self.key.ec_key()?;
+                let mut ctx = bn::BigNumContext::new()?;
+                let mut x = bn::BigNum::new()?;
+                let mut y = bn::BigNum::new()?;
+                ec_key.public_key().affine_coordinates_gfp(
+                    ec_key.group(),
+                    &mut x,
+                    &mut y,
+                    &mut ctx,
+                )?;
+                BTreeMap::from([
+                    (Value::Integer(1), Value::Integer(2)),
+                    (Value::Integer(3), Value::Integer(-7)),
+                    (Value::Integer(-1), Value::Integer(1)),
+                    (Value::Integer(-2), Value::Bytes(x.to_vec_padded(32)?)),
+                    (Value::Integer(-3), Value::Bytes(y.to_vec_padded(32)?)),
+                ])
+            }
+            COSEAlgorithm::EDDSA => BTreeMap::from([
+                (Value::Integer(1), Value::Integer(1)),
+                (Value::Integer(3), Value::Integer(-8)),
+                (Value::Integer(-1), Value::Integer(6)),
+                (Value::Integer(-2), Value::Bytes(self.key.raw_public_key()?)),
+            ]),
+            _ => {
+                let rsa_key = self.key.rsa()?;
+                BTreeMap::from([
+                    (Value::Integer(1), Value::Integer(3)),
+                    (Value::Integer(3), Value::Integer(-257)),
+                    (
+                        Value::Integer(-1),
+                        Value::Bytes(rsa_key.n().to_vec_padded(256)?),
+                    ),
+                    (
+                        Value::Integer(-2),
+                        Value::Bytes(rsa_key.e().to_vec_padded(3)?),
+                    ),
+                ])
+            }
+        };
+        Ok(serde_cbor_2::to_vec(&Value::Map(cose_key))?)
+    }
+
+    /// The public key of the credential, as it is stored in a registered credential.
+    pub(crate) fn credential_key(&self) -> WebauthnResult<COSEKey> {
+        COSEKey::try_from(&serde_cbor_2::from_slice::<Value>(&self.cose_key()?)?)
+    }
+
+    /// The authenticator data of a registration for `rp_id`.
+    pub(crate) fn auth_data(&self, rp_id: &str) -> WebauthnResult<Vec<u8>> {
+        self.auth_data_with(rp_id, &self.cose_key()?, None)
+    }
+
+    /// The authenticator data of a registration for `rp_id`, with the CBOR encoded `cose_key`
+    /// in place of the public key of the credential. The ED flag is set if `extensions` are
+    /// given.
+    pub(crate) fn auth_data_with(
+        &self,
+        rp_id: &str,
+        cose_key: &[u8],
+        extensions: Option<&Value>,
+    ) -> WebauthnResult<Vec<u8>> {
+        // rpIdHash, flags, counter, aaguid, credential id, public key and extensions
+        let mut auth_data = sha::sha256(rp_id.as_bytes()).to_vec();
+        auth_data.push(if extensions.is_some() {
+            self.flags | 0x80
+        } else {
+            self.flags
+        });
+        auth_data.extend_from_slice(&0u32.to_be_bytes());
+        auth_data.extend_from_slice(self.aaguid.as_bytes());
+        auth_data.extend_from_slice(&(self.cred_id.len() as u16).to_be_bytes());
+        auth_data.extend_from_slice(&self.cred_id);
+        auth_data.extend_from_slice(cose_key);
+        if let Some(extensions) = extensions {
+            auth_data.extend_from_slice(&serde_cbor_2::to_vec(extensions)?);
+        }
+        Ok(auth_data)
+    }
+
+    /// Sign `auth_data` and the hash of `client_data_json` with the credential key.
+    pub(crate) fn sign(&self, auth_data: &[u8], client_data_json: &str) -> WebauthnResult<Vec<u8>> {
+        let mut signed_data = auth_data.to_vec();
+        signed_data.extend_from_slice(&sha::sha256(client_data_json.as_bytes()));
+        let mut signer = match self.alg {
+            COSEAlgorithm::EDDSA => sign::Signer::new_without_digest(&self.key)?,
+            _ => sign::Signer::new(hash::MessageDigest::sha256(), &self.key)?,
+        };
+        Ok(signer.sign_oneshot_to_vec(&signed_data)?)
+    }
+
+    /// A registration response carrying `attestation_object` and `client_data_json`.
+    pub(crate) fn registration(
+        &self,
+        attestation_object: &[u8],
+        client_data_json: &str,
+    ) -> WebauthnResult<RegisterPublicKeyCredential> {
+        Ok(serde_json::from_value(serde_json::json!({
+            "id": b64(&self.cred_id),
+            "rawId": b64(&self.cred_id),
+            "response": {
+                "attestationObject": b64(attestation_object),
+                "clientDataJSON": b64(client_data_json.as_bytes()),
+            },
+            "type": "public-key",
+        }))?)
+    }
+
+    /// Respond to a registration challenge without attestation.
+    pub(crate) fn register(
+        &self,
+        ccr: &CreationChallengeResponse,
+    ) -> WebauthnResult<RegisterPublicKeyCredential> {
+        let auth_data = self.auth_data(&ccr.public_key.rp.id)?;
+        let client_data_json = client_data("webauthn.create", &ccr.public_key.challenge);
+        let attestation_object = attestation_object("none", BTreeMap::new(), auth_data)?;
+        self.registration(&attestation_object, &client_data_json)
+    }
+
+    /// Respond to a registration challenge with a packed attestation by `att_key`, which is
+    /// self attestation if `x5c` is empty.
+    pub(crate) fn register_packed(
+        &self,
+        ccr: &CreationChallengeResponse,
+        att_key: &pkey::PKeyRef<pkey::Private>,
+        x5c: &[&[u8]],
+    ) -> WebauthnResult<RegisterPublicKeyCredential> {
+        let auth_data = self.auth_data(&ccr.public_key.rp.id)?;
+        let client_data_json = client_data("webauthn.create", &ccr.public_key.challenge);
+        let att_stmt = packed_statement(att_key, &auth_data, &client_data_json, x5c)?;
+        let attestation_object = attestation_object("packed", att_stmt, auth_data)?;
+        self.registration(&attestation_object, &client_data_json)
+    }
+
+    /// An authentication response signing `auth_data` and `client_data_json`.
+    pub(crate) fn assertion(
+        &self,
+        auth_data: &[u8],
+        client_data_json: &str,
+    ) -> WebauthnResult<PublicKeyCredential> {
+        Ok(serde_json::from_value(serde_json::json!({
+            "id": b64(&self.cred_id),
+            "rawId": b64(&self.cred_id),
+            "response": {
+                "authenticatorData": b64(auth_data),
+                "clientDataJSON": b64(client_data_json.as_bytes()),
+                "signature": b64(&self.sign(auth_data, client_data_json)?),
+                "userHandle": null,
+            },
+            "type": "public-key",
+        }))?)
+    }
+
+    /// Respond to an authentication challenge with `flags` and `counter` in the authenticator
+    /// data.
+    pub(crate) fn authenticate(
+        &self,
+        rcr: &RequestChallengeResponse,
+        flags: u8,
+        counter: u32,
+    ) -> WebauthnResult<PublicKeyCredential> {
+        let auth_data = assertion_auth_data(&rcr.public_key.rp_id, flags, counter);
+        let client_data_json = client_data("webauthn.get", &rcr.public_key.challenge);
+        self.assertion(&auth_data, &client_data_json)
+    }
+}
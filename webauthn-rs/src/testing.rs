@@ -0,0 +1,130 @@
+//! Helpers for writing tests of code that uses webauthn-rs, such as the registration handlers
+//! of a web server.
+//!
+//! These produce ceremony payloads that are structurally valid and correctly signed by a
+//! software key, so they can be passed through a real [Webauthn] instance. They do NOT come
+//! from a genuine authenticator, and MUST NOT be used outside of tests. For a more complete
+//! software authenticator, see the `webauthn-authenticator-rs` crate.
+//!
+//! *requires feature testing*
+
+use openssl::{bn, ec, hash, nid, pkey, sha, sign};
+use serde_cbor_2::Value;
+use std::collections::BTreeMap;
+
+use crate::prelude::*;
+use webauthn_rs_core::proto::{
+    AuthenticatorAttestationResponseRaw, RegistrationExtensionsClientOutputs,
+};
+
+/// A minimal software authenticator, holding a single ES256 credential.
+#[derive(Debug)]
+pub struct SoftPasskey {
+    key: pkey::PKey<pkey::Private>,
+    cred_id: Vec<u8>,
+}
+
+impl SoftPasskey {
+    /// Create a new software authenticator with a freshly generated credential.
+    pub fn new() -> WebauthnResult<Self> {
+        let group = ec::EcGroup::from_curve_name(nid::Nid::X9_62_PRIME256V1)?;
+        let key = pkey::PKey::from_ec_key(ec::EcKey::generate(&group)?)?;
+        let cred_id = Uuid::new_v4().as_bytes().to_vec();
+        Ok(SoftPasskey { key, cred_id })
+    }
+
+    /// The id of the credential held by this authenticator.
+    pub fn cred_id(&self) -> CredentialID {
+        Base64UrlSafeData(self.cred_id.clone())
+    }
+
+    /// Respond to a registration challenge as a client visiting `origin` would, with the
+    /// credential self attested in the `packed` format.
+    pub fn register(
+        &self,
+        ccr: &CreationChallengeResponse,
+        origin: &Url,
+    ) -> WebauthnResult<RegisterPublicKeyCredential> {
+        let ec_key = self.key.ec_key()?;
+        let mut ctx = bn::BigNumContext::new()?;
+        let mut x = bn::BigNum::new()?;
+        let mut y = bn::BigNum::new()?;
+        ec_key
+            .public_key()
+            .affine_coordinates_gfp(ec_key.group(), &mut x, &mut y, &mut ctx)?;
+
+        let cose_key = serde_cbor_2::to_vec(&Value::Map(BTreeMap::from([
+            (Value::Integer(1), Value::Integer(2)),
+            (Value::Integer(3), Value::Integer(-7)),
+            (Value::Integer(-1), Value::Integer(1)),
+            (Value::Integer(-2), Value::Bytes(x.to_vec_padded(32)?)),
+            (Value::Integer(-3), Value::Bytes(y.to_vec_padded(32)?)),
+        ])))?;
+
+        // rpIdHash, flags (UP | UV | AT), counter, aaguid, credential id and public key
+        let mut auth_data = sha::sha256(ccr.public_key.rp.id.as_bytes()).to_vec();
+        auth_data.push(0x45);
+        auth_data.extend_from_slice(&0u32.to_be_bytes());
+        auth_data.extend_from_slice(&[0; 16]);
+        auth_data.extend_from_slice(&(self.cred_id.len() as u16).to_be_bytes());
+        auth_data.extend_from_slice(&self.cred_id);
+        auth_data.extend_from_slice(&cose_key);
+
+        let client_data_json = format!(
+            r#"{{"type":"webauthn.create","challenge":"{}","origin":"{}","crossOrigin":false}}"#,
+            ccr.public_key.challenge,
+            origin.origin().ascii_serialization()
+        );
+
+        let mut signer = sign::Signer::new(hash::MessageDigest::sha256(), &self.key)?;
+        signer.update(&auth_data)?;
+        signer.update(&sha::sha256(client_data_json.as_bytes()))?;
+        let sig = signer.sign_to_vec()?;
+
+        let attestation_object = serde_cbor_2::to_vec(&Value::Map(BTreeMap::from([
+            (
+                Value::Text("fmt".to_string()),
+                Value::Text("packed".to_string()),
+            ),
+            (
+                Value::Text("attStmt".to_string()),
+                Value::Map(BTreeMap::from([
+                    (Value::Text("alg".to_string()), Value::Integer(-7)),
+                    (Value::Text("sig".to_string()), Value::Bytes(sig)),
+                ])),
+            ),
+            (Value::Text("authData".to_string()), Value::Bytes(auth_data)),
+        ])))?;
+
+        let raw_id = self.cred_id();
+        Ok(RegisterPublicKeyCredential {
+            id: raw_id.to_string(),
+            raw_id,
+            response: AuthenticatorAttestationResponseRaw {
+                attestation_object: Base64UrlSafeData(attestation_object),
+                client_data_json: Base64UrlSafeData(client_data_json.into_bytes()),
+                transports: None,
+            },
+            type_: "public-key".to_string(),
+            extensions: RegistrationExtensionsClientOutputs::default(),
+            authenticator_attachment: None,
+        })
+    }
+}
+
+/// Start a passkey registration, and produce the response of a new [SoftPasskey] to it as a
+/// client visiting `origin` would. The response can be completed with
+/// [`finish_passkey_registration`](Webauthn::finish_passkey_registration).
+pub fn example_passkey_registration(
+    webauthn: &Webauthn,
+    origin: &Url,
+) -> WebauthnResult<(
+    CreationChallengeResponse,
+    PasskeyRegistration,
+    RegisterPublicKeyCredential,
+)> {
+    let (ccr, reg_state) =
+        webauthn.start_passkey_registration(Uuid::new_v4(), "claire", "Claire", None)?;
+    let reg = SoftPasskey::new()?.register(&ccr, origin)?;
+    Ok((ccr, reg_state, reg))
+}